@@ -0,0 +1,207 @@
+//! Transport-agnostic ceremony core
+//!
+//! A `Ceremony` is the coordinator's state machine for one DKG, signing or
+//! rotation run. It opens rounds by emitting messages, takes participant
+//! replies one at a time and reports when the next round opens or the
+//! ceremony is done. It does no I/O and keeps no clock: the driver decides
+//! when a round has run out of time and calls `expire`.
+//!
+//! `run_in_memory` drives a ceremony against in-process participants;
+//! `runtime::Coordinator` drives it over a network link.
+
+use crate::message::{CeremonyId, CoordinatorMessage, Envelope, ParticipantMessage};
+use crate::participant::CeremonyParticipant;
+use crate::{CoordinatorError, CoordinatorResult, Misbehaviour};
+use frost_core::ParticipantId;
+use rand::{CryptoRng, RngCore};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// What a participant reply did to the ceremony
+#[derive(Debug)]
+pub enum Progress<T> {
+    /// The current round is still open
+    Waiting,
+    /// The next round opened; send these messages
+    Round(Vec<Envelope<CoordinatorMessage>>),
+    /// The ceremony finished; send these messages and return the output
+    Done(T, Vec<Envelope<CoordinatorMessage>>),
+}
+
+/// Coordinator side of one ceremony
+pub trait Ceremony: Send {
+    /// Result of a successful ceremony
+    type Output: Send;
+
+    /// Ceremony ID carried by every message
+    fn id(&self) -> CeremonyId;
+
+    /// Participants taking part, told to abort if the ceremony fails
+    fn participants(&self) -> Vec<ParticipantId>;
+
+    /// Open the first round
+    fn start(&mut self) -> Vec<Envelope<CoordinatorMessage>>;
+
+    /// Take one reply from `from`
+    ///
+    /// Replies from participants the current round is not waiting for are
+    /// ignored. An error fails the ceremony.
+    fn receive(&mut self, from: ParticipantId, message: ParticipantMessage) -> CoordinatorResult<Progress<Self::Output>>;
+
+    /// The current round ran out of time; the error that fails the ceremony
+    fn expire(&mut self) -> CoordinatorError;
+}
+
+/// Fresh random ceremony ID
+pub fn new_ceremony_id<R: RngCore + CryptoRng>(rng: &mut R) -> CeremonyId {
+    let mut id = [0u8; 32];
+    rng.fill_bytes(&mut id);
+    id
+}
+
+/// The same message to each of `participants`
+pub(crate) fn to_all(
+    ceremony_id: CeremonyId,
+    participants: &[ParticipantId],
+    message: CoordinatorMessage,
+) -> Vec<Envelope<CoordinatorMessage>> {
+    participants.iter()
+        .map(|&participant_id| Envelope { ceremony_id, participant_id, message: message.clone() })
+        .collect()
+}
+
+/// Abort messages for every participant of a failed ceremony
+pub(crate) fn abort_all<C: Ceremony + ?Sized>(ceremony: &C, error: &CoordinatorError) -> Vec<Envelope<CoordinatorMessage>> {
+    to_all(ceremony.id(), &ceremony.participants(), CoordinatorMessage::Abort { reason: error.to_string() })
+}
+
+/// Fail the ceremony, blaming `participant_id`
+pub(crate) fn misbehaved(participant_id: ParticipantId, reason: impl Into<String>) -> CoordinatorError {
+    CoordinatorError::Misbehaviour(vec![Misbehaviour::new(participant_id, reason)])
+}
+
+/// Drop a reply the current round is not waiting for
+pub(crate) fn ignored<T>(from: ParticipantId, message: &ParticipantMessage) -> Progress<T> {
+    log::warn!("Ignoring {} from participant {}", message.kind(), from.as_u32());
+    Progress::Waiting
+}
+
+/// Error for a reply of the wrong kind from a participant the round waits for
+pub(crate) fn unexpected(from: ParticipantId, message: ParticipantMessage, round: &str) -> CoordinatorError {
+    match message {
+        ParticipantMessage::Refused(reason) => CoordinatorError::Refused(from, reason),
+        other => misbehaved(from, format!("sent a {} during {}", other.kind(), round)),
+    }
+}
+
+/// Replies of one round, one per expected participant
+pub(crate) struct RoundCollector<T> {
+    expected: BTreeSet<ParticipantId>,
+    received: BTreeMap<ParticipantId, T>,
+}
+
+impl<T> RoundCollector<T> {
+    /// Wait for a reply from each of `expected`
+    pub(crate) fn new(expected: impl IntoIterator<Item = ParticipantId>) -> Self {
+        RoundCollector { expected: expected.into_iter().collect(), received: BTreeMap::new() }
+    }
+
+    /// Whether a reply from `participant_id` is still outstanding
+    pub(crate) fn awaits(&self, participant_id: ParticipantId) -> bool {
+        self.expected.contains(&participant_id) && !self.received.contains_key(&participant_id)
+    }
+
+    /// Record the reply from `participant_id`
+    pub(crate) fn insert(&mut self, participant_id: ParticipantId, reply: T) {
+        if self.awaits(participant_id) {
+            self.received.insert(participant_id, reply);
+        }
+    }
+
+    /// Stop waiting for `participant_id`
+    pub(crate) fn release(&mut self, participant_id: ParticipantId) {
+        if !self.received.contains_key(&participant_id) {
+            self.expected.remove(&participant_id);
+        }
+    }
+
+    /// Every expected reply is in
+    pub(crate) fn is_complete(&self) -> bool {
+        self.received.len() == self.expected.len()
+    }
+
+    /// Participants that have not replied
+    pub(crate) fn missing(&self) -> Vec<ParticipantId> {
+        self.expected.iter().filter(|p| !self.received.contains_key(p)).copied().collect()
+    }
+
+    /// Replies so far, by participant
+    pub(crate) fn received(&self) -> &BTreeMap<ParticipantId, T> {
+        &self.received
+    }
+
+    /// Take the replies, leaving the collector empty
+    pub(crate) fn take(&mut self) -> BTreeMap<ParticipantId, T> {
+        self.expected.clear();
+        std::mem::take(&mut self.received)
+    }
+}
+
+/// Drive `ceremony` against in-process participants
+///
+/// Participants missing from `participants` never answer, as if offline;
+/// a round that cannot complete without them expires.
+pub fn run_in_memory<C: Ceremony>(
+    ceremony: C,
+    participants: &mut [CeremonyParticipant],
+) -> CoordinatorResult<C::Output> {
+    drive(ceremony, participants, |_| {})
+}
+
+/// `run_in_memory` with a hook that may alter each participant reply
+pub(crate) fn drive<C: Ceremony>(
+    mut ceremony: C,
+    participants: &mut [CeremonyParticipant],
+    mut tamper: impl FnMut(&mut Envelope<ParticipantMessage>),
+) -> CoordinatorResult<C::Output> {
+    let mut outbox: VecDeque<_> = ceremony.start().into();
+
+    loop {
+        let progress = match outbox.pop_front() {
+            Some(envelope) => match deliver(participants, envelope) {
+                Some(mut reply) => {
+                    tamper(&mut reply);
+                    ceremony.receive(reply.participant_id, reply.message)
+                }
+                None => continue,
+            },
+            None => Err(ceremony.expire()),
+        };
+
+        match progress {
+            Ok(Progress::Waiting) => {}
+            Ok(Progress::Round(messages)) => outbox.extend(messages),
+            Ok(Progress::Done(output, messages)) => {
+                for message in messages {
+                    deliver(participants, message);
+                }
+                return Ok(output);
+            }
+            Err(e) => {
+                for message in abort_all(&ceremony, &e) {
+                    deliver(participants, message);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+fn deliver(
+    participants: &mut [CeremonyParticipant],
+    envelope: Envelope<CoordinatorMessage>,
+) -> Option<Envelope<ParticipantMessage>> {
+    let participant = participants.iter_mut()
+        .find(|p| p.participant_id() == envelope.participant_id)?;
+    let message = participant.handle(envelope.ceremony_id, envelope.message)?;
+    Some(Envelope { ceremony_id: envelope.ceremony_id, participant_id: envelope.participant_id, message })
+}
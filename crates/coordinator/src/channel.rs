@@ -0,0 +1,139 @@
+//! Encrypted point-to-point channel for DKG and rotation shares
+//!
+//! Each participant publishes a fresh channel key E = e * G per ceremony in
+//! its round-1 broadcast. A share from i to j is sealed with
+//! ChaCha20-Poly1305 under HKDF-SHA256(e_i * E_j, ceremony || i || j), so
+//! the coordinator relays ciphertexts it cannot read. Each key seals exactly
+//! one share, which is why the nonce is fixed.
+//!
+//! A participant that receives a bad share reveals e_j in a complaint; the
+//! coordinator then opens the disputed share itself.
+
+use crate::message::{CeremonyId, EncryptedShare};
+use crate::{CoordinatorError, CoordinatorResult};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use frost_core::{ParticipantId, SecretScalar};
+use hkdf::Hkdf;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A participant's channel key for one ceremony
+pub struct ChannelKey {
+    secret: SecretScalar,
+}
+
+impl ChannelKey {
+    /// Fresh key for a new ceremony
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        ChannelKey { secret: SecretScalar::new(Scalar::random(rng)) }
+    }
+
+    /// Public key E = e * G, published in the round-1 broadcast
+    pub fn public_key(&self) -> CompressedRistretto {
+        (self.secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress()
+    }
+
+    /// Secret e, revealed in a complaint once the ceremony is lost anyway
+    pub fn reveal(&self) -> Scalar {
+        *self.secret.as_scalar()
+    }
+
+    /// Seal `plaintext` from `sender` to `recipient`
+    pub fn seal(
+        &self,
+        ceremony_id: &CeremonyId,
+        sender: ParticipantId,
+        recipient: ParticipantId,
+        recipient_key: &CompressedRistretto,
+        plaintext: &[u8],
+    ) -> CoordinatorResult<EncryptedShare> {
+        let recipient_key = recipient_key.decompress()
+            .ok_or_else(|| CoordinatorError::Protocol(format!("Invalid channel key for participant {}", recipient.as_u32())))?;
+
+        let (cipher, aad) = channel_cipher(&(self.secret.as_scalar() * recipient_key), ceremony_id, sender, recipient);
+        let ciphertext = cipher
+            .encrypt(&Nonce::default(), Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| CoordinatorError::Protocol("Share encryption failed".to_string()))?;
+
+        Ok(EncryptedShare { sender_id: sender, recipient_id: recipient, ciphertext })
+    }
+
+    /// Open a share addressed to this key, or None if it does not authenticate
+    pub fn open(
+        &self,
+        ceremony_id: &CeremonyId,
+        share: &EncryptedShare,
+        sender_key: &CompressedRistretto,
+    ) -> Option<Zeroizing<Vec<u8>>> {
+        open_with(self.secret.as_scalar(), ceremony_id, share, sender_key)
+    }
+}
+
+/// Open a share with the recipient's (possibly revealed) channel secret
+pub(crate) fn open_with(
+    secret: &Scalar,
+    ceremony_id: &CeremonyId,
+    share: &EncryptedShare,
+    sender_key: &CompressedRistretto,
+) -> Option<Zeroizing<Vec<u8>>> {
+    let sender_key = sender_key.decompress()?;
+    let (cipher, aad) = channel_cipher(&(secret * sender_key), ceremony_id, share.sender_id, share.recipient_id);
+    cipher
+        .decrypt(&Nonce::default(), Payload { msg: &share.ciphertext, aad: &aad })
+        .ok()
+        .map(Zeroizing::new)
+}
+
+/// Cipher and associated data for one sender → recipient share
+fn channel_cipher(
+    shared_secret: &RistrettoPoint,
+    ceremony_id: &CeremonyId,
+    sender: ParticipantId,
+    recipient: ParticipantId,
+) -> (ChaCha20Poly1305, Vec<u8>) {
+    let mut context = Vec::with_capacity(32 + 4 + 4);
+    context.extend_from_slice(ceremony_id);
+    context.extend_from_slice(&sender.as_u32().to_le_bytes());
+    context.extend_from_slice(&recipient.as_u32().to_le_bytes());
+
+    let hkdf = Hkdf::<Sha256>::new(Some(b"FROST-CEREMONY-CHANNEL-v1"), shared_secret.compress().as_bytes());
+    let mut key = [0u8; 32];
+    hkdf.expand(&context, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    key.zeroize();
+    (cipher, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_seal_and_open() {
+        let ceremony_id = [5u8; 32];
+        let (alice, bob, eve) = (ChannelKey::generate(&mut OsRng), ChannelKey::generate(&mut OsRng), ChannelKey::generate(&mut OsRng));
+        let (p1, p2) = (ParticipantId(1), ParticipantId(2));
+
+        let share = alice.seal(&ceremony_id, p1, p2, &bob.public_key(), b"share").unwrap();
+        assert_eq!(bob.open(&ceremony_id, &share, &alice.public_key()).unwrap().as_slice(), b"share");
+
+        // Revealing Bob's secret lets a third party open it; nothing else does
+        assert!(open_with(&bob.reveal(), &ceremony_id, &share, &alice.public_key()).is_some());
+        assert!(eve.open(&ceremony_id, &share, &alice.public_key()).is_none());
+        assert!(bob.open(&[6u8; 32], &share, &alice.public_key()).is_none());
+
+        // Relabelling the share breaks authentication
+        let relabelled = EncryptedShare { recipient_id: ParticipantId(3), ..share };
+        assert!(bob.open(&ceremony_id, &relabelled, &alice.public_key()).is_none());
+    }
+}
@@ -0,0 +1,304 @@
+//! Distributed key generation ceremony
+//!
+//! Runs frost-core's Pedersen DKG across remote participants:
+//!
+//! 1. `DkgStart` → every participant broadcasts its commitments and channel key
+//! 2. `DkgShares` → every participant seals one share for each peer
+//! 3. `DkgVerify` → every participant opens and verifies the shares addressed
+//!    to it, then reports its key or complains
+//!
+//! The coordinator checks the broadcasts, the share addressing and the
+//! reported keys, and settles complaints by opening the disputed shares, so
+//! a failed ceremony names the participants at fault. On success every
+//! participant is told to `Commit` the new share.
+
+use crate::ceremony::{ignored, misbehaved, to_all, unexpected, Ceremony, Progress, RoundCollector};
+use crate::message::{
+    CeremonyId, Complaint, CoordinatorMessage, EncryptedShare, Envelope, KeyGenBroadcast, ParticipantMessage,
+    Verification,
+};
+use crate::vss::{
+    check_dkg_broadcast, check_shares, dkg_group_key, expected_verification, resolve_complaint, route_shares,
+    verify_dkg_share,
+};
+use crate::{CoordinatorError, CoordinatorResult, Misbehaviour};
+use frost_core::transcript::DkgTranscript;
+use frost_core::{FrostError, GroupPublicKey, ParticipantId};
+use std::collections::BTreeMap;
+
+/// Result of a successful DKG
+#[derive(Debug, Clone)]
+pub struct DkgOutcome {
+    /// The new group key with every participant's verification share
+    pub group_public_key: GroupPublicKey,
+    /// Transcript of the round-1 broadcasts
+    pub transcript: DkgTranscript,
+}
+
+enum DkgRound {
+    Broadcast(RoundCollector<KeyGenBroadcast>),
+    Shares(RoundCollector<Vec<EncryptedShare>>),
+    Verify(RoundCollector<Result<Verification, Complaint>>),
+    Finished,
+}
+
+/// Coordinator side of a DKG
+pub struct DkgCeremony {
+    id: CeremonyId,
+    threshold: u32,
+    participants: Vec<ParticipantId>,
+    timestamp: u64,
+    broadcasts: BTreeMap<ParticipantId, KeyGenBroadcast>,
+    shares: BTreeMap<ParticipantId, Vec<EncryptedShare>>,
+    round: DkgRound,
+}
+
+impl DkgCeremony {
+    /// Generate a `threshold`-of-`num_participants` key among participants 1..=n
+    ///
+    /// `timestamp` (Unix epoch seconds) is recorded in the transcript.
+    pub fn new(id: CeremonyId, threshold: u32, num_participants: u32, timestamp: u64) -> CoordinatorResult<Self> {
+        if threshold == 0 || threshold > num_participants {
+            return Err(FrostError::InvalidThreshold(threshold, num_participants).into());
+        }
+        let participants: Vec<_> = (1..=num_participants).map(ParticipantId).collect();
+
+        Ok(DkgCeremony {
+            id,
+            threshold,
+            round: DkgRound::Broadcast(RoundCollector::new(participants.clone())),
+            participants,
+            timestamp,
+            broadcasts: BTreeMap::new(),
+            shares: BTreeMap::new(),
+        })
+    }
+
+    /// Check the reported keys and settle complaints
+    fn finish(&self, replies: BTreeMap<ParticipantId, Result<Verification, Complaint>>) -> CoordinatorResult<DkgOutcome> {
+        let round1: Vec<_> = self.broadcasts.values().map(|b| b.round1.clone()).collect();
+        let group_public_key = dkg_group_key(&round1, self.threshold, &self.participants)
+            .ok_or_else(|| CoordinatorError::Protocol("Broadcasts do not define a group key".to_string()))?;
+        let channel_keys: BTreeMap<_, _> = self.broadcasts.iter().map(|(&p, b)| (p, b.channel_key)).collect();
+
+        let mut found = Vec::new();
+        for (participant_id, reply) in replies {
+            match reply {
+                Ok(verification) => {
+                    if Some(verification) != expected_verification(&group_public_key, participant_id) {
+                        found.push(Misbehaviour::new(participant_id, "reported a key the broadcasts do not define"));
+                    }
+                }
+                Err(complaint) => found.extend(resolve_complaint(
+                    &self.id,
+                    participant_id,
+                    &complaint,
+                    &channel_keys,
+                    &self.shares,
+                    |sender, plaintext| verify_dkg_share(&self.broadcasts[&sender].round1, participant_id, plaintext).is_some(),
+                )),
+            }
+        }
+        if !found.is_empty() {
+            return Err(CoordinatorError::Misbehaviour(found));
+        }
+
+        let broadcasts = round1.iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CoordinatorError::Protocol(e.to_string()))?;
+        let transcript = DkgTranscript::new(self.timestamp, self.threshold, self.participants.len() as u32, broadcasts);
+
+        Ok(DkgOutcome { group_public_key, transcript })
+    }
+}
+
+impl Ceremony for DkgCeremony {
+    type Output = DkgOutcome;
+
+    fn id(&self) -> CeremonyId {
+        self.id
+    }
+
+    fn participants(&self) -> Vec<ParticipantId> {
+        self.participants.clone()
+    }
+
+    fn start(&mut self) -> Vec<Envelope<CoordinatorMessage>> {
+        to_all(self.id, &self.participants, CoordinatorMessage::DkgStart {
+            threshold: self.threshold,
+            num_participants: self.participants.len() as u32,
+        })
+    }
+
+    fn receive(&mut self, from: ParticipantId, message: ParticipantMessage) -> CoordinatorResult<Progress<DkgOutcome>> {
+        match &mut self.round {
+            DkgRound::Broadcast(collector) if collector.awaits(from) => {
+                let ParticipantMessage::DkgBroadcast(broadcast) = message else {
+                    return Err(unexpected(from, message, "DKG round 1"));
+                };
+                check_dkg_broadcast(&broadcast, from, self.threshold).map_err(|reason| misbehaved(from, reason))?;
+                collector.insert(from, broadcast);
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                self.broadcasts = collector.take();
+                self.round = DkgRound::Shares(RoundCollector::new(self.participants.clone()));
+                Ok(Progress::Round(to_all(self.id, &self.participants, CoordinatorMessage::DkgShares {
+                    broadcasts: self.broadcasts.values().cloned().collect(),
+                })))
+            }
+            DkgRound::Shares(collector) if collector.awaits(from) => {
+                let ParticipantMessage::Shares(shares) = message else {
+                    return Err(unexpected(from, message, "DKG round 2"));
+                };
+                check_shares(&shares, from, &self.participants).map_err(|reason| misbehaved(from, reason))?;
+                collector.insert(from, shares);
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                self.shares = collector.take();
+                self.round = DkgRound::Verify(RoundCollector::new(self.participants.clone()));
+                Ok(Progress::Round(route_shares(self.id, &self.shares, &self.participants, |shares| {
+                    CoordinatorMessage::DkgVerify { shares }
+                })))
+            }
+            DkgRound::Verify(collector) if collector.awaits(from) => {
+                match message {
+                    ParticipantMessage::Verified(verification) => collector.insert(from, Ok(verification)),
+                    ParticipantMessage::Complaint(complaint) => collector.insert(from, Err(complaint)),
+                    other => return Err(unexpected(from, other, "DKG round 3")),
+                }
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                let replies = collector.take();
+                self.round = DkgRound::Finished;
+                let outcome = self.finish(replies)?;
+                log::info!("DKG complete: {}-of-{} key {}", self.threshold, self.participants.len(),
+                    hex_prefix(outcome.group_public_key.public_key.as_bytes()));
+                Ok(Progress::Done(outcome, to_all(self.id, &self.participants, CoordinatorMessage::Commit)))
+            }
+            _ => Ok(ignored(from, &message)),
+        }
+    }
+
+    fn expire(&mut self) -> CoordinatorError {
+        CoordinatorError::Timeout(match &self.round {
+            DkgRound::Broadcast(collector) => collector.missing(),
+            DkgRound::Shares(collector) => collector.missing(),
+            DkgRound::Verify(collector) => collector.missing(),
+            DkgRound::Finished => Vec::new(),
+        })
+    }
+}
+
+/// First bytes of a key, for logs
+pub(crate) fn hex_prefix(bytes: &[u8]) -> String {
+    bytes.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ceremony::{drive, run_in_memory};
+    use crate::channel::ChannelKey;
+    use crate::participant::CeremonyParticipant;
+    use rand::rngs::OsRng;
+
+    fn participants(n: u32) -> Vec<CeremonyParticipant> {
+        (1..=n).map(|i| CeremonyParticipant::new(ParticipantId(i))).collect()
+    }
+
+    fn blamed(result: CoordinatorResult<DkgOutcome>) -> Vec<u32> {
+        match result {
+            Err(CoordinatorError::Misbehaviour(found)) => {
+                let blamed: std::collections::BTreeSet<_> = found.iter().map(|m| m.participant_id.as_u32()).collect();
+                blamed.into_iter().collect()
+            }
+            other => panic!("expected misbehaviour, got {:?}", other.map(|o| o.group_public_key)),
+        }
+    }
+
+    #[test]
+    fn test_dkg_across_participants() {
+        let mut nodes = participants(3);
+        let ceremony = DkgCeremony::new([1u8; 32], 2, 3, 1_700_000_000).unwrap();
+        let outcome = run_in_memory(ceremony, &mut nodes).unwrap();
+
+        assert!(outcome.transcript.verify());
+        assert_eq!(outcome.transcript.round1_broadcasts.len(), 3);
+        for node in &nodes {
+            let key = node.group_public_key().unwrap();
+            assert_eq!(key.public_key, outcome.group_public_key.public_key);
+            assert_eq!(
+                expected_verification(key, node.participant_id()),
+                expected_verification(&outcome.group_public_key, node.participant_id()),
+            );
+        }
+
+        assert!(DkgCeremony::new([1u8; 32], 4, 3, 0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_share_identifies_sender() {
+        let mut nodes = participants(3);
+        let ceremony = DkgCeremony::new([2u8; 32], 2, 3, 0).unwrap();
+        let result = drive(ceremony, &mut nodes, |reply| {
+            if let (2, ParticipantMessage::Shares(shares)) = (reply.participant_id.as_u32(), &mut reply.message) {
+                let to_3 = shares.iter_mut().find(|s| s.recipient_id == ParticipantId(3)).unwrap();
+                to_3.ciphertext[0] ^= 1;
+            }
+        });
+
+        assert_eq!(blamed(result), vec![2]);
+        assert!(nodes.iter().all(|n| n.group_public_key().is_none()));
+    }
+
+    #[test]
+    fn test_false_complaint_identifies_complainant() {
+        // Participant 3 rightly accuses participant 2, then adds participant 1
+        // whose share verifies under the revealed key
+        let mut nodes = participants(3);
+        let ceremony = DkgCeremony::new([3u8; 32], 2, 3, 0).unwrap();
+        let result = drive(ceremony, &mut nodes, |reply| match (reply.participant_id.as_u32(), &mut reply.message) {
+            (2, ParticipantMessage::Shares(shares)) => {
+                let to_3 = shares.iter_mut().find(|s| s.recipient_id == ParticipantId(3)).unwrap();
+                to_3.ciphertext[0] ^= 1;
+            }
+            (3, ParticipantMessage::Complaint(complaint)) => complaint.accused.push(ParticipantId(1)),
+            _ => {}
+        });
+        assert_eq!(blamed(result), vec![2, 3]);
+
+        // A complaint with a key the complainant never published convicts it too
+        let key = ChannelKey::generate(&mut OsRng);
+        let mut nodes = participants(3);
+        let ceremony = DkgCeremony::new([4u8; 32], 2, 3, 0).unwrap();
+        let result = drive(ceremony, &mut nodes, |reply| {
+            if reply.participant_id == ParticipantId(2) {
+                if let ParticipantMessage::Verified(_) = reply.message {
+                    reply.message = ParticipantMessage::Complaint(Complaint {
+                        accused: vec![ParticipantId(1)],
+                        channel_secret: key.reveal(),
+                    });
+                }
+            }
+        });
+        assert_eq!(blamed(result), vec![2]);
+    }
+
+    #[test]
+    fn test_offline_participant_times_out() {
+        let mut nodes = participants(3);
+        nodes.remove(1);
+        let ceremony = DkgCeremony::new([5u8; 32], 2, 3, 0).unwrap();
+        assert!(matches!(
+            run_in_memory(ceremony, &mut nodes),
+            Err(CoordinatorError::Timeout(missing)) if missing == vec![ParticipantId(2)]
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Ceremony coordinator for FROST RoT
+//!
+//! Runs DKG, signing and share rotation across remote participants. The
+//! coordinator relays and checks messages but never sees a share: DKG and
+//! rotation shares travel sealed to their recipient.
+//!
+//! ```text
+//! runtime::Coordinator ──► ceremony::Ceremony (DkgCeremony, SigningCeremony, RotationCeremony)
+//!        │                    sans-IO state machines: rounds, checks, blame
+//!        └── ParticipantLink ──► participant::CeremonyParticipant (one per share holder)
+//! ```
+//!
+//! A failed ceremony names the participants at fault where the protocol
+//! allows: malformed broadcasts, invalid shares (settled by complaints that
+//! reveal the complainant's channel key), inconsistent key reports and
+//! invalid partial signatures. New shares are only installed once every
+//! participant has verified them and the coordinator sends `Commit`.
+
+#![warn(missing_docs)]
+
+pub mod message;
+pub mod channel;
+pub mod ceremony;
+pub mod dkg;
+pub mod signing;
+pub mod rotation;
+pub mod participant;
+pub mod runtime;
+mod vss;
+
+pub use message::{CeremonyId, Envelope, CoordinatorMessage, ParticipantMessage};
+pub use ceremony::{Ceremony, Progress, new_ceremony_id, run_in_memory};
+pub use dkg::{DkgCeremony, DkgOutcome};
+pub use signing::{SigningCeremony, SigningOutcome};
+pub use rotation::{RotationCeremony, RotationOutcome};
+pub use participant::CeremonyParticipant;
+pub use runtime::{Coordinator, CoordinatorConfig, ParticipantLink, ChannelLink};
+
+use frost_core::{FrostError, ParticipantId};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// A participant caught deviating from the protocol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Misbehaviour {
+    /// Participant at fault
+    pub participant_id: ParticipantId,
+    /// What it did, e.g. "sent an invalid partial signature"
+    pub reason: String,
+}
+
+impl Misbehaviour {
+    /// Blame `participant_id` for `reason`
+    pub fn new(participant_id: ParticipantId, reason: impl Into<String>) -> Self {
+        Misbehaviour { participant_id, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for Misbehaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "participant {} {}", self.participant_id.as_u32(), self.reason)
+    }
+}
+
+/// Coordinator errors
+#[derive(Error, Debug)]
+pub enum CoordinatorError {
+    /// FROST protocol error
+    #[error("FROST error: {0}")]
+    Frost(#[from] FrostError),
+
+    /// Participants did not answer before the round timed out
+    #[error("Timed out waiting for participants {}", ids(.0))]
+    Timeout(Vec<ParticipantId>),
+
+    /// A participant declined to take part
+    #[error("Participant {} refused: {}", .0.as_u32(), .1)]
+    Refused(ParticipantId, String),
+
+    /// Participants deviated from the protocol
+    #[error("Misbehaviour: {}", blame(.0))]
+    Misbehaviour(Vec<Misbehaviour>),
+
+    /// Not enough participants to reach the threshold
+    #[error("Insufficient participants: {0} available, {1} required")]
+    InsufficientParticipants(usize, u32),
+
+    /// The ceremony was aborted
+    #[error("Ceremony aborted: {0}")]
+    Aborted(String),
+
+    /// Participant link failure
+    #[error("Transport error: {0}")]
+    Transport(String),
+
+    /// Malformed or out-of-order message
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+fn ids(participants: &[ParticipantId]) -> String {
+    participants.iter().map(|p| p.as_u32().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn blame(found: &[Misbehaviour]) -> String {
+    found.iter().map(|m| m.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+/// Result type for coordinator operations
+pub type CoordinatorResult<T> = Result<T, CoordinatorError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_names_participants() {
+        let timeout = CoordinatorError::Timeout(vec![ParticipantId(2), ParticipantId(5)]);
+        assert_eq!(timeout.to_string(), "Timed out waiting for participants 2, 5");
+
+        let found = CoordinatorError::Misbehaviour(vec![
+            Misbehaviour::new(ParticipantId(3), "sent an invalid partial signature"),
+        ]);
+        assert_eq!(found.to_string(), "Misbehaviour: participant 3 sent an invalid partial signature");
+    }
+}
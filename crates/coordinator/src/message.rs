@@ -0,0 +1,167 @@
+//! Messages between the coordinator and participants
+//!
+//! Every message travels in an `Envelope` naming the ceremony and the
+//! participant: the recipient for coordinator messages, the sender for
+//! participant messages. The transport is responsible for authenticating
+//! the sender (e.g. mutual TLS); the coordinator trusts the envelope.
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use frost_core::dkg::DkgRound1Broadcast;
+use frost_core::rotation::RotationCommitment;
+use frost_core::signing::{PartialSignature, SigningCommitment};
+use frost_core::ParticipantId;
+use serde::{Serialize, Deserialize};
+
+/// Random identifier of one ceremony
+pub type CeremonyId = [u8; 32];
+
+/// A message addressed to or received from one participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<M> {
+    /// Ceremony the message belongs to
+    pub ceremony_id: CeremonyId,
+    /// Recipient (coordinator messages) or sender (participant messages)
+    pub participant_id: ParticipantId,
+    /// The message
+    pub message: M,
+}
+
+/// DKG round-1 broadcast with the sender's channel key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyGenBroadcast {
+    /// Pedersen and Feldman commitments to the sender's polynomial
+    pub round1: DkgRound1Broadcast,
+    /// Key that shares to the sender are encrypted to
+    pub channel_key: CompressedRistretto,
+}
+
+/// Rotation round-1 broadcast with the sender's channel key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationBroadcast {
+    /// Commitment to the sender's zero-sum polynomial
+    pub commitment: RotationCommitment,
+    /// Key that delta shares to the sender are encrypted to
+    pub channel_key: CompressedRistretto,
+}
+
+/// Share sealed for one recipient; opaque to the coordinator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedShare {
+    /// Participant that evaluated its polynomial
+    pub sender_id: ParticipantId,
+    /// Participant the share is for
+    pub recipient_id: ParticipantId,
+    /// ChaCha20-Poly1305 ciphertext and tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// Key material a participant derived from verified shares
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verification {
+    /// Group public key PK
+    pub group_public_key: CompressedRistretto,
+    /// The participant's verification share Y_i = s_i * G
+    pub verification_share: CompressedRistretto,
+}
+
+/// Accusation that shares failed to decrypt or verify
+///
+/// Reveals the complainant's channel key for the ceremony so the
+/// coordinator can open the disputed shares and decide who lied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Complaint {
+    /// Senders of the bad shares
+    pub accused: Vec<ParticipantId>,
+    /// Complainant's channel secret for this ceremony
+    pub channel_secret: Scalar,
+}
+
+/// Coordinator → participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CoordinatorMessage {
+    /// DKG round 1: commit to a polynomial for a t-of-n key
+    DkgStart {
+        /// Threshold t
+        threshold: u32,
+        /// Number of participants n
+        num_participants: u32,
+    },
+    /// DKG round 2: everyone's broadcasts; send encrypted shares
+    DkgShares {
+        /// Round-1 broadcasts of all participants
+        broadcasts: Vec<KeyGenBroadcast>,
+    },
+    /// DKG round 3: the shares addressed to the recipient; verify them
+    DkgVerify {
+        /// Encrypted shares from every other participant
+        shares: Vec<EncryptedShare>,
+    },
+    /// Rotation round 1: commit to a zero-sum polynomial
+    RotationStart,
+    /// Rotation round 2: everyone's broadcasts; send encrypted delta shares
+    RotationShares {
+        /// Round-1 broadcasts of all participants
+        broadcasts: Vec<RotationBroadcast>,
+    },
+    /// Rotation round 3: the delta shares addressed to the recipient
+    RotationVerify {
+        /// Encrypted delta shares from every other participant
+        shares: Vec<EncryptedShare>,
+    },
+    /// Install the key material of the finished ceremony
+    Commit,
+    /// The ceremony failed; discard its state
+    Abort {
+        /// Why the ceremony was abandoned
+        reason: String,
+    },
+    /// Signing round 1: commit to nonces for the message with this hash
+    SigningCommit {
+        /// SHA-256 of the message
+        message_hash: [u8; 32],
+    },
+    /// Signing round 2: sign with the selected signers' commitments
+    SigningShare {
+        /// Message to sign
+        message: Vec<u8>,
+        /// Commitments of the signing set
+        commitments: Vec<SigningCommitment>,
+    },
+}
+
+/// Participant → coordinator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParticipantMessage {
+    /// DKG round-1 broadcast
+    DkgBroadcast(KeyGenBroadcast),
+    /// Rotation round-1 broadcast
+    RotationBroadcast(RotationBroadcast),
+    /// Encrypted shares for every other participant
+    Shares(Vec<EncryptedShare>),
+    /// All received shares verified
+    Verified(Verification),
+    /// Some received shares were bad
+    Complaint(Complaint),
+    /// Signing round-1 commitment
+    Commitment(SigningCommitment),
+    /// Signing round-2 partial signature
+    Partial(PartialSignature),
+    /// The participant will not take part
+    Refused(String),
+}
+
+impl ParticipantMessage {
+    /// Short name for logs and errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParticipantMessage::DkgBroadcast(_) => "DKG broadcast",
+            ParticipantMessage::RotationBroadcast(_) => "rotation broadcast",
+            ParticipantMessage::Shares(_) => "shares",
+            ParticipantMessage::Verified(_) => "verification",
+            ParticipantMessage::Complaint(_) => "complaint",
+            ParticipantMessage::Commitment(_) => "signing commitment",
+            ParticipantMessage::Partial(_) => "partial signature",
+            ParticipantMessage::Refused(_) => "refusal",
+        }
+    }
+}
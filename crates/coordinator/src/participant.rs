@@ -0,0 +1,439 @@
+//! Participant side of the ceremonies
+//!
+//! `CeremonyParticipant` answers coordinator messages for one share holder.
+//! State is kept per ceremony ID. Key material from a DKG or rotation stays
+//! pending until the coordinator sends `Commit`, so a ceremony that fails
+//! late leaves the old share in place. Signing nonces are spent at most
+//! once: round 2 consumes the round-1 state whatever the outcome.
+
+use crate::channel::ChannelKey;
+use crate::message::{
+    CeremonyId, Complaint, CoordinatorMessage, EncryptedShare, KeyGenBroadcast, ParticipantMessage,
+    RotationBroadcast, Verification,
+};
+use crate::vss::{
+    check_dkg_broadcast, check_rotation_broadcast, expected_verification, rotated_group_key, verify_dkg_share,
+    verify_rotation_share,
+};
+use crate::{CoordinatorError, CoordinatorResult};
+use frost_core::dkg::DkgParticipant;
+use frost_core::rotation::ShareRotation;
+use frost_core::signing::{SigningCommitment, SigningRound1};
+use frost_core::transport::message_hash;
+use frost_core::{GroupPublicKey, ParticipantId, SecretShare};
+use rand::rngs::OsRng;
+use std::collections::{BTreeSet, HashMap};
+use zeroize::Zeroizing;
+
+/// State of one ceremony
+enum Session {
+    Dkg {
+        participant: DkgParticipant,
+        channel: ChannelKey,
+        threshold: u32,
+        num_participants: u32,
+        broadcasts: Vec<KeyGenBroadcast>,
+    },
+    Rotation {
+        rotation: ShareRotation,
+        channel: ChannelKey,
+        broadcasts: Vec<RotationBroadcast>,
+    },
+    Pending {
+        share: SecretShare,
+        group_public_key: GroupPublicKey,
+    },
+    Signing {
+        message_hash: [u8; 32],
+        round1: SigningRound1,
+    },
+}
+
+/// One share holder taking part in coordinated ceremonies
+pub struct CeremonyParticipant {
+    participant_id: ParticipantId,
+    key: Option<(SecretShare, GroupPublicKey)>,
+    sessions: HashMap<CeremonyId, Session>,
+}
+
+impl CeremonyParticipant {
+    /// Participant without a key, ready for a DKG
+    pub fn new(participant_id: ParticipantId) -> Self {
+        CeremonyParticipant { participant_id, key: None, sessions: HashMap::new() }
+    }
+
+    /// Participant holding `share` of `group_public_key`
+    pub fn with_key(share: SecretShare, group_public_key: GroupPublicKey) -> Self {
+        CeremonyParticipant {
+            participant_id: share.participant_id,
+            key: Some((share, group_public_key)),
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// This participant's ID
+    pub fn participant_id(&self) -> ParticipantId {
+        self.participant_id
+    }
+
+    /// Group key of the committed share, if any
+    pub fn group_public_key(&self) -> Option<&GroupPublicKey> {
+        self.key.as_ref().map(|(_, group_public_key)| group_public_key)
+    }
+
+    /// The committed share, if any
+    pub fn share(&self) -> Option<&SecretShare> {
+        self.key.as_ref().map(|(share, _)| share)
+    }
+
+    /// Answer one coordinator message
+    ///
+    /// `Commit` and `Abort` get no reply; anything this participant will not
+    /// do is answered with `Refused` and drops the ceremony's state.
+    pub fn handle(&mut self, ceremony_id: CeremonyId, message: CoordinatorMessage) -> Option<ParticipantMessage> {
+        match message {
+            CoordinatorMessage::Commit => {
+                self.commit(&ceremony_id);
+                None
+            }
+            CoordinatorMessage::Abort { reason } => {
+                if self.sessions.remove(&ceremony_id).is_some() {
+                    log::info!("Participant {}: ceremony aborted: {}", self.participant_id.as_u32(), reason);
+                }
+                None
+            }
+            message => Some(self.respond(&ceremony_id, message).unwrap_or_else(|e| {
+                self.sessions.remove(&ceremony_id);
+                ParticipantMessage::Refused(e.to_string())
+            })),
+        }
+    }
+
+    fn respond(&mut self, ceremony_id: &CeremonyId, message: CoordinatorMessage) -> CoordinatorResult<ParticipantMessage> {
+        match message {
+            CoordinatorMessage::DkgStart { threshold, num_participants } => self.dkg_start(ceremony_id, threshold, num_participants),
+            CoordinatorMessage::DkgShares { broadcasts } => self.dkg_shares(ceremony_id, broadcasts),
+            CoordinatorMessage::DkgVerify { shares } => self.dkg_verify(ceremony_id, shares),
+            CoordinatorMessage::RotationStart => self.rotation_start(ceremony_id),
+            CoordinatorMessage::RotationShares { broadcasts } => self.rotation_shares(ceremony_id, broadcasts),
+            CoordinatorMessage::RotationVerify { shares } => self.rotation_verify(ceremony_id, shares),
+            CoordinatorMessage::SigningCommit { message_hash } => self.signing_commit(ceremony_id, message_hash),
+            CoordinatorMessage::SigningShare { message, commitments } => self.signing_share(ceremony_id, &message, &commitments),
+            CoordinatorMessage::Commit | CoordinatorMessage::Abort { .. } => {
+                Err(CoordinatorError::Protocol("Message takes no reply".to_string()))
+            }
+        }
+    }
+
+    fn open_session(&mut self, ceremony_id: &CeremonyId, session: Session) -> CoordinatorResult<()> {
+        if self.sessions.contains_key(ceremony_id) {
+            return Err(CoordinatorError::Protocol("Ceremony already started".to_string()));
+        }
+        self.sessions.insert(*ceremony_id, session);
+        Ok(())
+    }
+
+    fn commit(&mut self, ceremony_id: &CeremonyId) {
+        match self.sessions.remove(ceremony_id) {
+            Some(Session::Pending { share, group_public_key }) => {
+                log::info!("Participant {}: committed new share", self.participant_id.as_u32());
+                self.key = Some((share, group_public_key));
+            }
+            Some(session) => {
+                self.sessions.insert(*ceremony_id, session);
+            }
+            None => {}
+        }
+    }
+
+    fn dkg_start(&mut self, ceremony_id: &CeremonyId, threshold: u32, num_participants: u32) -> CoordinatorResult<ParticipantMessage> {
+        let participant = DkgParticipant::new(self.participant_id, threshold, num_participants, &mut OsRng)?;
+        let channel = ChannelKey::generate(&mut OsRng);
+        let broadcast = KeyGenBroadcast {
+            round1: participant.round1_broadcast(),
+            channel_key: channel.public_key(),
+        };
+
+        self.open_session(ceremony_id, Session::Dkg {
+            participant,
+            channel,
+            threshold,
+            num_participants,
+            broadcasts: Vec::new(),
+        })?;
+        Ok(ParticipantMessage::DkgBroadcast(broadcast))
+    }
+
+    fn dkg_shares(&mut self, ceremony_id: &CeremonyId, received: Vec<KeyGenBroadcast>) -> CoordinatorResult<ParticipantMessage> {
+        let my_id = self.participant_id;
+        let Some(Session::Dkg { participant, channel, threshold, num_participants, broadcasts }) = self.sessions.get_mut(ceremony_id) else {
+            return Err(no_session("DKG"));
+        };
+        if !broadcasts.is_empty() {
+            return Err(CoordinatorError::Protocol("Shares already sent".to_string()));
+        }
+
+        check_roster(received.iter().map(|b| b.round1.sender_id), &(1..=*num_participants).map(ParticipantId).collect::<Vec<_>>())?;
+        for broadcast in &received {
+            check_dkg_broadcast(broadcast, broadcast.round1.sender_id, *threshold).map_err(CoordinatorError::Protocol)?;
+        }
+        let mine = received.iter().find(|b| b.round1.sender_id == my_id);
+        if mine.map(|b| (&b.round1.public_commitments, b.channel_key)) != Some((&participant.round1_broadcast().public_commitments, channel.public_key())) {
+            return Err(CoordinatorError::Protocol("Relayed broadcast differs from ours".to_string()));
+        }
+
+        let mut shares = Vec::with_capacity(received.len() - 1);
+        for message in participant.round2_secret_shares() {
+            let recipient = received.iter().find(|b| b.round1.sender_id == message.recipient_id)
+                .ok_or_else(|| no_broadcast(message.recipient_id))?;
+            let mut plaintext = Zeroizing::new([0u8; 64]);
+            plaintext[..32].copy_from_slice(message.secret_share.as_scalar().as_bytes());
+            plaintext[32..].copy_from_slice(message.blinding_share.as_scalar().as_bytes());
+            shares.push(channel.seal(ceremony_id, my_id, message.recipient_id, &recipient.channel_key, plaintext.as_slice())?);
+        }
+
+        *broadcasts = received;
+        Ok(ParticipantMessage::Shares(shares))
+    }
+
+    fn dkg_verify(&mut self, ceremony_id: &CeremonyId, shares: Vec<EncryptedShare>) -> CoordinatorResult<ParticipantMessage> {
+        let my_id = self.participant_id;
+        let Some(Session::Dkg { participant, channel, broadcasts, .. }) = self.sessions.remove(ceremony_id) else {
+            return Err(no_session("DKG"));
+        };
+        if broadcasts.is_empty() {
+            return Err(CoordinatorError::Protocol("Shares received before broadcasts".to_string()));
+        }
+
+        let mut accused = Vec::new();
+        let mut verified = Vec::with_capacity(broadcasts.len() - 1);
+        for broadcast in broadcasts.iter().filter(|b| b.round1.sender_id != my_id) {
+            let sender = broadcast.round1.sender_id;
+            let share = single_share(&shares, sender)
+                .and_then(|share| channel.open(ceremony_id, share, &broadcast.channel_key))
+                .and_then(|plaintext| verify_dkg_share(&broadcast.round1, my_id, &plaintext));
+            match share {
+                Some(share) => verified.push(share),
+                None => accused.push(sender),
+            }
+        }
+        if !accused.is_empty() {
+            return Ok(complaint(accused, &channel));
+        }
+
+        let round1: Vec<_> = broadcasts.iter().map(|b| b.round1.clone()).collect();
+        let output = participant.finalize(&round1, &verified)?;
+        let verification = report(&output.group_public_key, my_id)?;
+
+        self.sessions.insert(*ceremony_id, Session::Pending {
+            share: output.secret_share.clone(),
+            group_public_key: output.group_public_key.clone(),
+        });
+        Ok(ParticipantMessage::Verified(verification))
+    }
+
+    fn rotation_start(&mut self, ceremony_id: &CeremonyId) -> CoordinatorResult<ParticipantMessage> {
+        let (share, group_public_key) = self.key.as_ref()
+            .ok_or_else(|| CoordinatorError::Protocol("No share to rotate".to_string()))?;
+        let rotation = ShareRotation::new(
+            self.participant_id,
+            share,
+            group_public_key.threshold,
+            group_public_key.num_participants,
+            &mut OsRng,
+        )?;
+        let channel = ChannelKey::generate(&mut OsRng);
+        let broadcast = RotationBroadcast {
+            commitment: rotation.generate_commitments(),
+            channel_key: channel.public_key(),
+        };
+
+        self.open_session(ceremony_id, Session::Rotation { rotation, channel, broadcasts: Vec::new() })?;
+        Ok(ParticipantMessage::RotationBroadcast(broadcast))
+    }
+
+    fn rotation_shares(&mut self, ceremony_id: &CeremonyId, received: Vec<RotationBroadcast>) -> CoordinatorResult<ParticipantMessage> {
+        let my_id = self.participant_id;
+        let group_public_key = self.group_public_key()
+            .ok_or_else(|| CoordinatorError::Protocol("No share to rotate".to_string()))?
+            .clone();
+        let Some(Session::Rotation { rotation, channel, broadcasts }) = self.sessions.get_mut(ceremony_id) else {
+            return Err(no_session("rotation"));
+        };
+        if !broadcasts.is_empty() {
+            return Err(CoordinatorError::Protocol("Shares already sent".to_string()));
+        }
+
+        let roster: Vec<_> = group_public_key.participant_shares.iter().map(|s| s.participant_id).collect();
+        check_roster(received.iter().map(|b| b.commitment.sender_id), &roster)?;
+        for broadcast in &received {
+            check_rotation_broadcast(broadcast, broadcast.commitment.sender_id, group_public_key.threshold)
+                .map_err(CoordinatorError::Protocol)?;
+        }
+        let mine = received.iter().find(|b| b.commitment.sender_id == my_id);
+        if mine.map(|b| (&b.commitment.commitment.commitments, b.channel_key))
+            != Some((&rotation.generate_commitments().commitment.commitments, channel.public_key()))
+        {
+            return Err(CoordinatorError::Protocol("Relayed broadcast differs from ours".to_string()));
+        }
+
+        let mut shares = Vec::with_capacity(received.len() - 1);
+        for delta in rotation.generate_delta_shares() {
+            let recipient = received.iter().find(|b| b.commitment.sender_id == delta.recipient_id)
+                .ok_or_else(|| no_broadcast(delta.recipient_id))?;
+            let plaintext = Zeroizing::new(delta.delta_share.as_scalar().to_bytes());
+            shares.push(channel.seal(ceremony_id, my_id, delta.recipient_id, &recipient.channel_key, plaintext.as_slice())?);
+        }
+
+        *broadcasts = received;
+        Ok(ParticipantMessage::Shares(shares))
+    }
+
+    fn rotation_verify(&mut self, ceremony_id: &CeremonyId, shares: Vec<EncryptedShare>) -> CoordinatorResult<ParticipantMessage> {
+        let my_id = self.participant_id;
+        let group_public_key = self.group_public_key()
+            .ok_or_else(|| CoordinatorError::Protocol("No share to rotate".to_string()))?
+            .clone();
+        let Some(Session::Rotation { rotation, channel, broadcasts }) = self.sessions.remove(ceremony_id) else {
+            return Err(no_session("rotation"));
+        };
+        if broadcasts.is_empty() {
+            return Err(CoordinatorError::Protocol("Shares received before broadcasts".to_string()));
+        }
+
+        let mut accused = Vec::new();
+        let mut verified = Vec::with_capacity(broadcasts.len() - 1);
+        for broadcast in broadcasts.iter().filter(|b| b.commitment.sender_id != my_id) {
+            let sender = broadcast.commitment.sender_id;
+            let share = single_share(&shares, sender)
+                .and_then(|share| channel.open(ceremony_id, share, &broadcast.channel_key))
+                .and_then(|plaintext| verify_rotation_share(&broadcast.commitment, my_id, &plaintext));
+            match share {
+                Some(share) => verified.push(share),
+                None => accused.push(sender),
+            }
+        }
+        if !accused.is_empty() {
+            return Ok(complaint(accused, &channel));
+        }
+
+        let commitments: Vec<_> = broadcasts.iter().map(|b| b.commitment.clone()).collect();
+        let share = rotation.finalize(&verified, &commitments)?;
+        let rotated = rotated_group_key(&group_public_key, &commitments)
+            .ok_or_else(|| CoordinatorError::Protocol("Commitments do not define rotated shares".to_string()))?;
+        let verification = report(&rotated, my_id)?;
+
+        self.sessions.insert(*ceremony_id, Session::Pending { share, group_public_key: rotated });
+        Ok(ParticipantMessage::Verified(verification))
+    }
+
+    fn signing_commit(&mut self, ceremony_id: &CeremonyId, message_hash: [u8; 32]) -> CoordinatorResult<ParticipantMessage> {
+        let (share, _) = self.key.as_ref()
+            .ok_or_else(|| CoordinatorError::Protocol("No share to sign with".to_string()))?;
+        let round1 = SigningRound1::new(self.participant_id, share, &mut OsRng);
+        let commitment = round1.commitment();
+
+        self.open_session(ceremony_id, Session::Signing { message_hash, round1 })?;
+        Ok(ParticipantMessage::Commitment(commitment))
+    }
+
+    fn signing_share(
+        &mut self,
+        ceremony_id: &CeremonyId,
+        message: &[u8],
+        commitments: &[SigningCommitment],
+    ) -> CoordinatorResult<ParticipantMessage> {
+        // Removing the session spends the nonces, whatever happens next
+        let Some(Session::Signing { message_hash: committed_hash, round1 }) = self.sessions.remove(ceremony_id) else {
+            return Err(no_session("signing"));
+        };
+        if message_hash(message) != committed_hash {
+            return Err(CoordinatorError::Protocol("Message does not match the committed hash".to_string()));
+        }
+        if !commitments.contains(&round1.commitment()) {
+            return Err(CoordinatorError::Protocol("Signing set does not carry our commitment".to_string()));
+        }
+
+        let round2 = round1.into_round2(message, commitments)?;
+        Ok(ParticipantMessage::Partial(round2.partial_signature()))
+    }
+}
+
+fn no_session(kind: &str) -> CoordinatorError {
+    CoordinatorError::Protocol(format!("No {} in progress for this ceremony", kind))
+}
+
+fn no_broadcast(participant_id: ParticipantId) -> CoordinatorError {
+    CoordinatorError::Protocol(format!("No broadcast from participant {}", participant_id.as_u32()))
+}
+
+/// Check that the relayed broadcasts come from exactly the expected participants
+fn check_roster(senders: impl Iterator<Item = ParticipantId>, expected: &[ParticipantId]) -> CoordinatorResult<()> {
+    let senders: Vec<_> = senders.map(|p| p.as_u32()).collect();
+    let unique: BTreeSet<_> = senders.iter().copied().collect();
+    let expected: BTreeSet<_> = expected.iter().map(|p| p.as_u32()).collect();
+    if unique != expected || senders.len() != expected.len() {
+        return Err(CoordinatorError::Protocol("Broadcasts do not match the participants".to_string()));
+    }
+    Ok(())
+}
+
+/// The one share from `sender`, or None if it sent none or several
+fn single_share(shares: &[EncryptedShare], sender: ParticipantId) -> Option<&EncryptedShare> {
+    let mut from_sender = shares.iter().filter(|s| s.sender_id == sender);
+    match (from_sender.next(), from_sender.next()) {
+        (Some(share), None) => Some(share),
+        _ => None,
+    }
+}
+
+fn complaint(accused: Vec<ParticipantId>, channel: &ChannelKey) -> ParticipantMessage {
+    ParticipantMessage::Complaint(Complaint { accused, channel_secret: channel.reveal() })
+}
+
+fn report(group_public_key: &GroupPublicKey, participant_id: ParticipantId) -> CoordinatorResult<Verification> {
+    expected_verification(group_public_key, participant_id)
+        .ok_or_else(|| CoordinatorError::Protocol("No verification share for this participant".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frost_core::dkg::DkgCoordinator;
+
+    #[test]
+    fn test_signing_nonces_are_single_use() {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let mut node = CeremonyParticipant::with_key(outputs[0].secret_share.clone(), outputs[0].group_public_key.clone());
+        let other = SigningRound1::new(ParticipantId(2), &outputs[1].secret_share, &mut OsRng).commitment();
+        let message = b"approve".to_vec();
+        let commit = CoordinatorMessage::SigningCommit { message_hash: message_hash(&message) };
+        let committed = |node: &mut CeremonyParticipant, id: CeremonyId| {
+            let Some(ParticipantMessage::Commitment(mine)) = node.handle(id, commit.clone()) else { panic!("no commitment") };
+            vec![mine, other.clone()]
+        };
+        let share = |message: &[u8], commitments: &[SigningCommitment]| {
+            CoordinatorMessage::SigningShare { message: message.to_vec(), commitments: commitments.to_vec() }
+        };
+
+        // Signs once per ceremony
+        let commitments = committed(&mut node, [1u8; 32]);
+        assert!(matches!(node.handle([1u8; 32], share(&message, &commitments)), Some(ParticipantMessage::Partial(_))));
+        assert!(matches!(node.handle([1u8; 32], share(&message, &commitments)), Some(ParticipantMessage::Refused(_))));
+
+        // A message other than the committed one is refused and spends the nonces
+        let commitments = committed(&mut node, [2u8; 32]);
+        assert!(matches!(node.handle([2u8; 32], share(b"transfer", &commitments)), Some(ParticipantMessage::Refused(_))));
+        assert!(matches!(node.handle([2u8; 32], share(&message, &commitments)), Some(ParticipantMessage::Refused(_))));
+
+        // So does a second commit request for the same ceremony
+        let commitments = committed(&mut node, [3u8; 32]);
+        assert!(matches!(node.handle([3u8; 32], commit.clone()), Some(ParticipantMessage::Refused(_))));
+        assert!(matches!(node.handle([3u8; 32], share(&message, &commitments)), Some(ParticipantMessage::Refused(_))));
+
+        // Aborting drops pending nonces
+        let commitments = committed(&mut node, [4u8; 32]);
+        assert!(node.handle([4u8; 32], CoordinatorMessage::Abort { reason: "test".to_string() }).is_none());
+        assert!(matches!(node.handle([4u8; 32], share(&message, &commitments)), Some(ParticipantMessage::Refused(_))));
+    }
+}
@@ -0,0 +1,268 @@
+//! Proactive share rotation ceremony
+//!
+//! Runs frost-core's `ShareRotation` across all n share holders. The rounds
+//! mirror the DKG: broadcast commitments to a zero-sum polynomial, seal one
+//! delta share per peer, verify and report. The group public key never
+//! changes; every verification share does, and the coordinator checks that
+//! each participant's new share matches the commitments before it sends
+//! `Commit`.
+
+use crate::ceremony::{ignored, misbehaved, to_all, unexpected, Ceremony, Progress, RoundCollector};
+use crate::dkg::hex_prefix;
+use crate::message::{
+    CeremonyId, Complaint, CoordinatorMessage, EncryptedShare, Envelope, ParticipantMessage, RotationBroadcast,
+    Verification,
+};
+use crate::vss::{
+    check_rotation_broadcast, check_shares, expected_verification, resolve_complaint, rotated_group_key,
+    route_shares, verify_rotation_share,
+};
+use crate::{CoordinatorError, CoordinatorResult, Misbehaviour};
+use frost_core::transcript::RotationTranscript;
+use frost_core::{FrostError, GroupPublicKey, ParticipantId};
+use std::collections::BTreeMap;
+
+/// Result of a successful rotation
+#[derive(Debug, Clone)]
+pub struct RotationOutcome {
+    /// The same group key with the rotated verification shares
+    pub group_public_key: GroupPublicKey,
+    /// Transcript of the rotation commitments, chained to the previous one
+    pub transcript: RotationTranscript,
+}
+
+enum RotationRound {
+    Broadcast(RoundCollector<RotationBroadcast>),
+    Shares(RoundCollector<Vec<EncryptedShare>>),
+    Verify(RoundCollector<Result<Verification, Complaint>>),
+    Finished,
+}
+
+/// Coordinator side of a share rotation
+pub struct RotationCeremony {
+    id: CeremonyId,
+    group_public_key: GroupPublicKey,
+    participants: Vec<ParticipantId>,
+    previous_hash: [u8; 32],
+    timestamp: u64,
+    broadcasts: BTreeMap<ParticipantId, RotationBroadcast>,
+    shares: BTreeMap<ParticipantId, Vec<EncryptedShare>>,
+    round: RotationRound,
+}
+
+impl RotationCeremony {
+    /// Rotate the shares of `group_public_key`
+    ///
+    /// `previous_hash` is the hash of the DKG or rotation transcript this
+    /// rotation follows; `timestamp` (Unix epoch seconds) is recorded with it.
+    pub fn new(
+        id: CeremonyId,
+        group_public_key: GroupPublicKey,
+        previous_hash: [u8; 32],
+        timestamp: u64,
+    ) -> CoordinatorResult<Self> {
+        let mut participants: Vec<_> = group_public_key.participant_shares.iter().map(|s| s.participant_id).collect();
+        participants.sort_by_key(|p| p.as_u32());
+        participants.dedup();
+        if participants.len() != group_public_key.num_participants as usize {
+            return Err(FrostError::InsufficientParticipants(participants.len(), group_public_key.num_participants).into());
+        }
+
+        Ok(RotationCeremony {
+            id,
+            round: RotationRound::Broadcast(RoundCollector::new(participants.clone())),
+            group_public_key,
+            participants,
+            previous_hash,
+            timestamp,
+            broadcasts: BTreeMap::new(),
+            shares: BTreeMap::new(),
+        })
+    }
+
+    /// Check the reported shares and settle complaints
+    fn finish(&self, replies: BTreeMap<ParticipantId, Result<Verification, Complaint>>) -> CoordinatorResult<RotationOutcome> {
+        let commitments: Vec<_> = self.broadcasts.values().map(|b| b.commitment.clone()).collect();
+        let group_public_key = rotated_group_key(&self.group_public_key, &commitments)
+            .ok_or_else(|| CoordinatorError::Protocol("Commitments do not define rotated shares".to_string()))?;
+        let channel_keys: BTreeMap<_, _> = self.broadcasts.iter().map(|(&p, b)| (p, b.channel_key)).collect();
+
+        let mut found = Vec::new();
+        for (participant_id, reply) in replies {
+            match reply {
+                Ok(verification) => {
+                    if Some(verification) != expected_verification(&group_public_key, participant_id) {
+                        found.push(Misbehaviour::new(participant_id, "reported a share the commitments do not define"));
+                    }
+                }
+                Err(complaint) => found.extend(resolve_complaint(
+                    &self.id,
+                    participant_id,
+                    &complaint,
+                    &channel_keys,
+                    &self.shares,
+                    |sender, plaintext| {
+                        verify_rotation_share(&self.broadcasts[&sender].commitment, participant_id, plaintext).is_some()
+                    },
+                )),
+            }
+        }
+        if !found.is_empty() {
+            return Err(CoordinatorError::Misbehaviour(found));
+        }
+
+        let commitments = commitments.iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CoordinatorError::Protocol(e.to_string()))?;
+        let transcript = RotationTranscript::new(self.timestamp, self.previous_hash, commitments);
+
+        Ok(RotationOutcome { group_public_key, transcript })
+    }
+}
+
+impl Ceremony for RotationCeremony {
+    type Output = RotationOutcome;
+
+    fn id(&self) -> CeremonyId {
+        self.id
+    }
+
+    fn participants(&self) -> Vec<ParticipantId> {
+        self.participants.clone()
+    }
+
+    fn start(&mut self) -> Vec<Envelope<CoordinatorMessage>> {
+        to_all(self.id, &self.participants, CoordinatorMessage::RotationStart)
+    }
+
+    fn receive(&mut self, from: ParticipantId, message: ParticipantMessage) -> CoordinatorResult<Progress<RotationOutcome>> {
+        match &mut self.round {
+            RotationRound::Broadcast(collector) if collector.awaits(from) => {
+                let ParticipantMessage::RotationBroadcast(broadcast) = message else {
+                    return Err(unexpected(from, message, "rotation round 1"));
+                };
+                check_rotation_broadcast(&broadcast, from, self.group_public_key.threshold)
+                    .map_err(|reason| misbehaved(from, reason))?;
+                collector.insert(from, broadcast);
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                self.broadcasts = collector.take();
+                self.round = RotationRound::Shares(RoundCollector::new(self.participants.clone()));
+                Ok(Progress::Round(to_all(self.id, &self.participants, CoordinatorMessage::RotationShares {
+                    broadcasts: self.broadcasts.values().cloned().collect(),
+                })))
+            }
+            RotationRound::Shares(collector) if collector.awaits(from) => {
+                let ParticipantMessage::Shares(shares) = message else {
+                    return Err(unexpected(from, message, "rotation round 2"));
+                };
+                check_shares(&shares, from, &self.participants).map_err(|reason| misbehaved(from, reason))?;
+                collector.insert(from, shares);
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                self.shares = collector.take();
+                self.round = RotationRound::Verify(RoundCollector::new(self.participants.clone()));
+                Ok(Progress::Round(route_shares(self.id, &self.shares, &self.participants, |shares| {
+                    CoordinatorMessage::RotationVerify { shares }
+                })))
+            }
+            RotationRound::Verify(collector) if collector.awaits(from) => {
+                match message {
+                    ParticipantMessage::Verified(verification) => collector.insert(from, Ok(verification)),
+                    ParticipantMessage::Complaint(complaint) => collector.insert(from, Err(complaint)),
+                    other => return Err(unexpected(from, other, "rotation round 3")),
+                }
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                let replies = collector.take();
+                self.round = RotationRound::Finished;
+                let outcome = self.finish(replies)?;
+                log::info!("Rotated shares of key {}", hex_prefix(outcome.group_public_key.public_key.as_bytes()));
+                Ok(Progress::Done(outcome, to_all(self.id, &self.participants, CoordinatorMessage::Commit)))
+            }
+            _ => Ok(ignored(from, &message)),
+        }
+    }
+
+    fn expire(&mut self) -> CoordinatorError {
+        CoordinatorError::Timeout(match &self.round {
+            RotationRound::Broadcast(collector) => collector.missing(),
+            RotationRound::Shares(collector) => collector.missing(),
+            RotationRound::Verify(collector) => collector.missing(),
+            RotationRound::Finished => Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ceremony::{drive, run_in_memory};
+    use crate::participant::CeremonyParticipant;
+    use crate::signing::SigningCeremony;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use frost_core::dkg::DkgCoordinator;
+    use rand::rngs::OsRng;
+
+    fn holders() -> Vec<CeremonyParticipant> {
+        DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap()
+            .into_iter()
+            .map(|o| CeremonyParticipant::with_key(o.secret_share.clone(), o.group_public_key.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation_keeps_group_key() {
+        let mut nodes = holders();
+        let before = nodes[0].group_public_key().unwrap().clone();
+
+        let ceremony = RotationCeremony::new([1u8; 32], before.clone(), [9u8; 32], 1_700_000_000).unwrap();
+        let outcome = run_in_memory(ceremony, &mut nodes).unwrap();
+
+        assert_eq!(outcome.group_public_key.public_key, before.public_key);
+        assert_eq!(outcome.transcript.previous_hash, [9u8; 32]);
+        assert!(outcome.transcript.verify());
+        for node in &nodes {
+            let id = node.participant_id();
+            let after = expected_verification(node.group_public_key().unwrap(), id);
+            assert_eq!(after, expected_verification(&outcome.group_public_key, id));
+            assert_ne!(after, expected_verification(&before, id));
+        }
+
+        // The rotated shares still sign for the group key
+        let message = b"signed after rotation";
+        let ceremony = SigningCeremony::new([2u8; 32], outcome.group_public_key.clone(), message.to_vec()).unwrap();
+        let signed = run_in_memory(ceremony, &mut nodes).unwrap();
+        assert!(before.verify_signature(message, &signed.signature));
+    }
+
+    #[test]
+    fn test_key_changing_rotation_identified() {
+        let mut nodes = holders();
+        let before = nodes[0].group_public_key().unwrap().clone();
+
+        let ceremony = RotationCeremony::new([3u8; 32], before.clone(), [0u8; 32], 0).unwrap();
+        let result = drive(ceremony, &mut nodes, |reply| {
+            if let (3, ParticipantMessage::RotationBroadcast(broadcast)) = (reply.participant_id.as_u32(), &mut reply.message) {
+                broadcast.commitment.commitment.commitments[0] = RISTRETTO_BASEPOINT_POINT.compress();
+            }
+        });
+
+        let Err(CoordinatorError::Misbehaviour(found)) = result else { panic!("rotation should fail") };
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].participant_id, ParticipantId(3));
+
+        // Everyone keeps the old shares
+        for node in &nodes {
+            let id = node.participant_id();
+            assert_eq!(expected_verification(node.group_public_key().unwrap(), id), expected_verification(&before, id));
+        }
+    }
+}
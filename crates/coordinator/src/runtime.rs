@@ -0,0 +1,241 @@
+//! Tokio driver for ceremonies over a network link
+//!
+//! `Coordinator` runs one ceremony at a time over a `ParticipantLink`: it
+//! sends each round's messages, collects replies until the round completes
+//! or its timeout passes, and tells every participant to abort when the
+//! ceremony fails. `ChannelLink` connects it to in-process participants,
+//! each served from its own task.
+
+use crate::ceremony::{abort_all, new_ceremony_id, Ceremony, Progress};
+use crate::dkg::{DkgCeremony, DkgOutcome};
+use crate::message::{CoordinatorMessage, Envelope, ParticipantMessage};
+use crate::participant::CeremonyParticipant;
+use crate::rotation::{RotationCeremony, RotationOutcome};
+use crate::signing::{SigningCeremony, SigningOutcome};
+use crate::{CoordinatorError, CoordinatorResult};
+use async_trait::async_trait;
+use frost_core::{GroupPublicKey, ParticipantId};
+use rand::rngs::OsRng;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout_at, Instant};
+
+/// Authenticated message path between the coordinator and the participants
+#[async_trait]
+pub trait ParticipantLink: Send {
+    /// Deliver `envelope` to the participant it is addressed to
+    async fn send(&mut self, envelope: Envelope<CoordinatorMessage>) -> CoordinatorResult<()>;
+
+    /// Next reply from any participant, or None once the link is closed
+    ///
+    /// The envelope's participant ID must be the authenticated sender.
+    async fn recv(&mut self) -> Option<Envelope<ParticipantMessage>>;
+}
+
+/// Coordinator settings
+#[derive(Debug, Clone)]
+pub struct CoordinatorConfig {
+    /// How long a round waits for replies before the ceremony times out
+    pub round_timeout: Duration,
+}
+
+impl Default for CoordinatorConfig {
+    fn default() -> Self {
+        CoordinatorConfig { round_timeout: Duration::from_secs(30) }
+    }
+}
+
+/// Runs ceremonies over a participant link
+pub struct Coordinator<L> {
+    link: L,
+    config: CoordinatorConfig,
+}
+
+impl<L: ParticipantLink> Coordinator<L> {
+    /// Coordinator talking to participants over `link`
+    pub fn new(link: L, config: CoordinatorConfig) -> Self {
+        Coordinator { link, config }
+    }
+
+    /// Give back the link
+    pub fn into_link(self) -> L {
+        self.link
+    }
+
+    /// Run `ceremony` to completion, aborting it everywhere if it fails
+    pub async fn run<C: Ceremony>(&mut self, mut ceremony: C) -> CoordinatorResult<C::Output> {
+        let result = self.drive(&mut ceremony).await;
+        if let Err(e) = &result {
+            log::warn!("Ceremony failed: {}", e);
+            self.send_all(abort_all(&ceremony, e)).await;
+        }
+        result
+    }
+
+    /// Generate a new t-of-n group key
+    pub async fn generate_key(&mut self, threshold: u32, num_participants: u32) -> CoordinatorResult<DkgOutcome> {
+        let ceremony = DkgCeremony::new(new_ceremony_id(&mut OsRng), threshold, num_participants, unix_time())?;
+        self.run(ceremony).await
+    }
+
+    /// Sign `message` with the first t share holders of `group_public_key` to answer
+    pub async fn sign(&mut self, group_public_key: &GroupPublicKey, message: &[u8]) -> CoordinatorResult<SigningOutcome> {
+        let ceremony = SigningCeremony::new(new_ceremony_id(&mut OsRng), group_public_key.clone(), message.to_vec())?;
+        self.run(ceremony).await
+    }
+
+    /// Rotate the shares of `group_public_key`, chaining to the transcript `previous_hash`
+    pub async fn rotate(
+        &mut self,
+        group_public_key: &GroupPublicKey,
+        previous_hash: [u8; 32],
+    ) -> CoordinatorResult<RotationOutcome> {
+        let ceremony = RotationCeremony::new(
+            new_ceremony_id(&mut OsRng),
+            group_public_key.clone(),
+            previous_hash,
+            unix_time(),
+        )?;
+        self.run(ceremony).await
+    }
+
+    async fn drive<C: Ceremony>(&mut self, ceremony: &mut C) -> CoordinatorResult<C::Output> {
+        self.send_all(ceremony.start()).await;
+        let mut deadline = Instant::now() + self.config.round_timeout;
+
+        loop {
+            let progress = match timeout_at(deadline, self.link.recv()).await {
+                Err(_) => return Err(ceremony.expire()),
+                Ok(None) => return Err(CoordinatorError::Transport("Participant link closed".to_string())),
+                Ok(Some(reply)) if reply.ceremony_id != ceremony.id() => {
+                    log::debug!("Ignoring {} for another ceremony", reply.message.kind());
+                    continue;
+                }
+                Ok(Some(reply)) => ceremony.receive(reply.participant_id, reply.message)?,
+            };
+
+            match progress {
+                Progress::Waiting => {}
+                Progress::Round(messages) => {
+                    self.send_all(messages).await;
+                    deadline = Instant::now() + self.config.round_timeout;
+                }
+                Progress::Done(output, messages) => {
+                    self.send_all(messages).await;
+                    return Ok(output);
+                }
+            }
+        }
+    }
+
+    /// Send every message; an unreachable participant shows up as a timeout
+    async fn send_all(&mut self, messages: Vec<Envelope<CoordinatorMessage>>) {
+        for envelope in messages {
+            let participant_id = envelope.participant_id;
+            if let Err(e) = self.link.send(envelope).await {
+                log::warn!("Could not reach participant {}: {}", participant_id.as_u32(), e);
+            }
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Link to in-process participants, each served from its own task
+pub struct ChannelLink {
+    participants: HashMap<ParticipantId, mpsc::UnboundedSender<Envelope<CoordinatorMessage>>>,
+    replies: mpsc::UnboundedReceiver<Envelope<ParticipantMessage>>,
+}
+
+impl ChannelLink {
+    /// Spawn a task per participant
+    ///
+    /// Each task hands its participant back once the link is dropped.
+    pub fn spawn(participants: Vec<CeremonyParticipant>) -> (Self, Vec<JoinHandle<CeremonyParticipant>>) {
+        let (reply_tx, replies) = mpsc::unbounded_channel();
+        let mut links = HashMap::new();
+        let mut handles = Vec::with_capacity(participants.len());
+
+        for mut participant in participants {
+            let (tx, mut rx) = mpsc::unbounded_channel::<Envelope<CoordinatorMessage>>();
+            let reply_tx = reply_tx.clone();
+            links.insert(participant.participant_id(), tx);
+
+            handles.push(tokio::spawn(async move {
+                while let Some(envelope) = rx.recv().await {
+                    if let Some(message) = participant.handle(envelope.ceremony_id, envelope.message) {
+                        let reply = Envelope {
+                            ceremony_id: envelope.ceremony_id,
+                            participant_id: participant.participant_id(),
+                            message,
+                        };
+                        if reply_tx.send(reply).is_err() {
+                            break;
+                        }
+                    }
+                }
+                participant
+            }));
+        }
+
+        (ChannelLink { participants: links, replies }, handles)
+    }
+}
+
+#[async_trait]
+impl ParticipantLink for ChannelLink {
+    async fn send(&mut self, envelope: Envelope<CoordinatorMessage>) -> CoordinatorResult<()> {
+        let participant_id = envelope.participant_id;
+        self.participants.get(&participant_id)
+            .ok_or_else(|| CoordinatorError::Transport(format!("No link to participant {}", participant_id.as_u32())))?
+            .send(envelope)
+            .map_err(|_| CoordinatorError::Transport(format!("Participant {} has stopped", participant_id.as_u32())))
+    }
+
+    async fn recv(&mut self) -> Option<Envelope<ParticipantMessage>> {
+        self.replies.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ceremonies_over_link() {
+        let nodes = (1..=3).map(|i| CeremonyParticipant::new(ParticipantId(i))).collect();
+        let (link, handles) = ChannelLink::spawn(nodes);
+        let mut coordinator = Coordinator::new(link, CoordinatorConfig::default());
+
+        let dkg = coordinator.generate_key(2, 3).await.unwrap();
+        let group = dkg.group_public_key;
+        let signed = coordinator.sign(&group, b"first").await.unwrap();
+        assert!(group.verify_signature(b"first", &signed.signature));
+
+        let rotated = coordinator.rotate(&group, dkg.transcript.transcript_hash).await.unwrap();
+        assert_eq!(rotated.group_public_key.public_key, group.public_key);
+        let signed = coordinator.sign(&rotated.group_public_key, b"second").await.unwrap();
+        assert!(group.verify_signature(b"second", &signed.signature));
+
+        drop(coordinator);
+        for handle in handles {
+            let node = handle.await.unwrap();
+            let shares = |key: &GroupPublicKey| key.participant_shares.iter().map(|s| s.public_key).collect::<Vec<_>>();
+            assert_eq!(shares(node.group_public_key().unwrap()), shares(&rotated.group_public_key));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unreachable_participant_times_out() {
+        let nodes = (1..=2).map(|i| CeremonyParticipant::new(ParticipantId(i))).collect();
+        let (link, _handles) = ChannelLink::spawn(nodes);
+        let mut coordinator = Coordinator::new(link, CoordinatorConfig::default());
+
+        let result = coordinator.generate_key(2, 3).await;
+        assert!(matches!(result, Err(CoordinatorError::Timeout(missing)) if missing == vec![ParticipantId(3)]));
+    }
+}
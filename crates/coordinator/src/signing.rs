@@ -0,0 +1,258 @@
+//! Threshold signing ceremony
+//!
+//! Round 1 asks every candidate to commit to nonces for the message hash
+//! and takes the first t to answer as the signing set; the rest are told to
+//! abort so they drop their nonces. Round 2 sends the message and the set's
+//! commitments to the selected signers. Each partial signature is checked
+//! against its signer's verification share before aggregation, so a bad
+//! partial names its signer rather than yielding an invalid signature.
+
+use crate::ceremony::{ignored, misbehaved, to_all, unexpected, Ceremony, Progress, RoundCollector};
+use crate::message::{CeremonyId, CoordinatorMessage, Envelope, ParticipantMessage};
+use crate::{CoordinatorError, CoordinatorResult, Misbehaviour};
+use frost_core::signing::{
+    aggregate_signatures, group_commitment, verify_partial_signature, PartialSignature, SigningCommitment,
+};
+use frost_core::transport::message_hash;
+use frost_core::{FrostError, GroupPublicKey, ParticipantId, SchnorrSignature};
+
+/// Result of a successful signing ceremony
+#[derive(Debug, Clone)]
+pub struct SigningOutcome {
+    /// Aggregated signature, verified against the group key
+    pub signature: SchnorrSignature,
+    /// Participants whose shares signed
+    pub signers: Vec<ParticipantId>,
+}
+
+enum SigningRound {
+    Commit(RoundCollector<SigningCommitment>),
+    Sign {
+        commitments: Vec<SigningCommitment>,
+        collector: RoundCollector<PartialSignature>,
+    },
+    Finished,
+}
+
+/// Coordinator side of a signing ceremony
+pub struct SigningCeremony {
+    id: CeremonyId,
+    group_public_key: GroupPublicKey,
+    message: Vec<u8>,
+    candidates: Vec<ParticipantId>,
+    round: SigningRound,
+}
+
+impl SigningCeremony {
+    /// Sign `message` with any t of the group's participants
+    pub fn new(id: CeremonyId, group_public_key: GroupPublicKey, message: Vec<u8>) -> CoordinatorResult<Self> {
+        let candidates: Vec<_> = group_public_key.participant_shares.iter().map(|s| s.participant_id).collect();
+        SigningCeremony { id, group_public_key, message, candidates: Vec::new(), round: SigningRound::Finished }
+            .with_candidates(&candidates)
+    }
+
+    /// Only invite `candidates`, e.g. the share holders currently reachable
+    pub fn with_candidates(mut self, candidates: &[ParticipantId]) -> CoordinatorResult<Self> {
+        let mut selected = Vec::with_capacity(candidates.len());
+        for &candidate in candidates {
+            if !self.group_public_key.participant_shares.iter().any(|s| s.participant_id == candidate) {
+                return Err(FrostError::InvalidParticipantIndex(candidate.as_u32()).into());
+            }
+            if !selected.contains(&candidate) {
+                selected.push(candidate);
+            }
+        }
+        if selected.len() < self.group_public_key.threshold as usize {
+            return Err(CoordinatorError::InsufficientParticipants(selected.len(), self.group_public_key.threshold));
+        }
+
+        self.round = SigningRound::Commit(RoundCollector::new(selected.clone()));
+        self.candidates = selected;
+        Ok(self)
+    }
+
+    /// Verify every partial and aggregate them
+    fn finish(
+        &self,
+        commitments: &[SigningCommitment],
+        partials: Vec<PartialSignature>,
+    ) -> CoordinatorResult<SigningOutcome> {
+        let r = group_commitment(&self.message, commitments)?;
+
+        let mut found = Vec::new();
+        for partial in &partials {
+            let valid = self.group_public_key.participant_shares.iter()
+                .find(|s| s.participant_id == partial.participant_id)
+                .is_some_and(|share| {
+                    verify_partial_signature(&self.message, commitments, partial, share, &r, &self.group_public_key.public_key).unwrap_or(false)
+                });
+            if !valid {
+                found.push(Misbehaviour::new(partial.participant_id, "sent an invalid partial signature"));
+            }
+        }
+        if !found.is_empty() {
+            return Err(CoordinatorError::Misbehaviour(found));
+        }
+
+        let signature = aggregate_signatures(&r.compress(), &partials)?;
+        if !self.group_public_key.verify_signature(&self.message, &signature) {
+            return Err(FrostError::AggregationFailed.into());
+        }
+
+        Ok(SigningOutcome {
+            signature,
+            signers: partials.iter().map(|p| p.participant_id).collect(),
+        })
+    }
+}
+
+impl Ceremony for SigningCeremony {
+    type Output = SigningOutcome;
+
+    fn id(&self) -> CeremonyId {
+        self.id
+    }
+
+    fn participants(&self) -> Vec<ParticipantId> {
+        self.candidates.clone()
+    }
+
+    fn start(&mut self) -> Vec<Envelope<CoordinatorMessage>> {
+        to_all(self.id, &self.candidates, CoordinatorMessage::SigningCommit {
+            message_hash: message_hash(&self.message),
+        })
+    }
+
+    fn receive(&mut self, from: ParticipantId, message: ParticipantMessage) -> CoordinatorResult<Progress<SigningOutcome>> {
+        let threshold = self.group_public_key.threshold;
+
+        match &mut self.round {
+            SigningRound::Commit(collector) if collector.awaits(from) => {
+                match message {
+                    ParticipantMessage::Commitment(commitment) => {
+                        if commitment.participant_id != from
+                            || commitment.hiding.decompress().is_none()
+                            || commitment.binding.decompress().is_none()
+                        {
+                            return Err(misbehaved(from, "sent an invalid signing commitment"));
+                        }
+                        collector.insert(from, commitment);
+                    }
+                    ParticipantMessage::Refused(reason) => {
+                        // Another candidate may still make up the signing set
+                        log::warn!("Participant {} declined to sign: {}", from.as_u32(), reason);
+                        collector.release(from);
+                        let possible = collector.received().len() + collector.missing().len();
+                        if possible < threshold as usize {
+                            return Err(CoordinatorError::InsufficientParticipants(possible, threshold));
+                        }
+                    }
+                    other => return Err(unexpected(from, other, "signing round 1")),
+                }
+                if collector.received().len() < threshold as usize {
+                    return Ok(Progress::Waiting);
+                }
+
+                // The first t to commit sign; the rest drop their nonces
+                let commitments: Vec<_> = collector.take().into_values().collect();
+                let signers: Vec<_> = commitments.iter().map(|c| c.participant_id).collect();
+                let others: Vec<_> = self.candidates.iter().filter(|c| !signers.contains(c)).copied().collect();
+
+                let mut messages = to_all(self.id, &signers, CoordinatorMessage::SigningShare {
+                    message: self.message.clone(),
+                    commitments: commitments.clone(),
+                });
+                messages.extend(to_all(self.id, &others, CoordinatorMessage::Abort {
+                    reason: "Not selected for signing".to_string(),
+                }));
+
+                self.round = SigningRound::Sign { commitments, collector: RoundCollector::new(signers) };
+                Ok(Progress::Round(messages))
+            }
+            SigningRound::Sign { collector, .. } if collector.awaits(from) => {
+                let ParticipantMessage::Partial(partial) = message else {
+                    return Err(unexpected(from, message, "signing round 2"));
+                };
+                if partial.participant_id != from {
+                    return Err(misbehaved(from, "sent a partial signature for another participant"));
+                }
+                collector.insert(from, partial);
+                if !collector.is_complete() {
+                    return Ok(Progress::Waiting);
+                }
+
+                let SigningRound::Sign { commitments, mut collector } = std::mem::replace(&mut self.round, SigningRound::Finished) else {
+                    unreachable!("matched the signing round above");
+                };
+                let outcome = self.finish(&commitments, collector.take().into_values().collect())?;
+                Ok(Progress::Done(outcome, Vec::new()))
+            }
+            _ => Ok(ignored(from, &message)),
+        }
+    }
+
+    fn expire(&mut self) -> CoordinatorError {
+        CoordinatorError::Timeout(match &self.round {
+            SigningRound::Commit(collector) => collector.missing(),
+            SigningRound::Sign { collector, .. } => collector.missing(),
+            SigningRound::Finished => Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ceremony::{drive, run_in_memory};
+    use crate::participant::CeremonyParticipant;
+    use curve25519_dalek::scalar::Scalar;
+    use frost_core::dkg::DkgCoordinator;
+    use rand::rngs::OsRng;
+
+    fn holders(threshold: u32, n: u32) -> Vec<CeremonyParticipant> {
+        DkgCoordinator::new(threshold, n).unwrap().run_dkg(&mut OsRng).unwrap()
+            .into_iter()
+            .map(|o| CeremonyParticipant::with_key(o.secret_share.clone(), o.group_public_key.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_first_t_responders_sign() {
+        let mut nodes = holders(2, 3);
+        let group = nodes[0].group_public_key().unwrap().clone();
+        let message = b"coordinated signature".to_vec();
+
+        let ceremony = SigningCeremony::new([1u8; 32], group.clone(), message.clone()).unwrap();
+        let outcome = run_in_memory(ceremony, &mut nodes).unwrap();
+        assert!(group.verify_signature(&message, &outcome.signature));
+        assert_eq!(outcome.signers, vec![ParticipantId(1), ParticipantId(2)]);
+
+        // With participant 1 offline the next candidates make up the set
+        let mut online = nodes.split_off(1);
+        let ceremony = SigningCeremony::new([2u8; 32], group.clone(), message.clone()).unwrap();
+        let outcome = run_in_memory(ceremony, &mut online).unwrap();
+        assert_eq!(outcome.signers, vec![ParticipantId(2), ParticipantId(3)]);
+
+        // Too few candidates never starts; too few online times out
+        let ceremony = SigningCeremony::new([3u8; 32], group.clone(), message.clone()).unwrap();
+        assert!(ceremony.with_candidates(&[ParticipantId(1)]).is_err());
+        let ceremony = SigningCeremony::new([4u8; 32], group, message).unwrap();
+        assert!(matches!(run_in_memory(ceremony, &mut online[..1]), Err(CoordinatorError::Timeout(_))));
+    }
+
+    #[test]
+    fn test_invalid_partial_identifies_signer() {
+        let mut nodes = holders(2, 3);
+        let group = nodes[0].group_public_key().unwrap().clone();
+
+        let ceremony = SigningCeremony::new([5u8; 32], group, b"message".to_vec()).unwrap();
+        let result = drive(ceremony, &mut nodes, |reply| {
+            if let (2, ParticipantMessage::Partial(partial)) = (reply.participant_id.as_u32(), &mut reply.message) {
+                partial.z += Scalar::ONE;
+            }
+        });
+
+        let Err(CoordinatorError::Misbehaviour(found)) = result else { panic!("signing should fail") };
+        assert_eq!(found, vec![Misbehaviour::new(ParticipantId(2), "sent an invalid partial signature")]);
+    }
+}
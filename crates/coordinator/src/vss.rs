@@ -0,0 +1,285 @@
+//! Verifiable secret sharing checks shared by DKG and rotation
+//!
+//! Participants run these on the shares they receive; the coordinator runs
+//! the same checks on round-1 broadcasts and, when a complaint reveals a
+//! channel key, on the disputed shares.
+
+use crate::channel::open_with;
+use crate::message::{
+    CeremonyId, Complaint, CoordinatorMessage, EncryptedShare, Envelope, KeyGenBroadcast, RotationBroadcast,
+    Verification,
+};
+use crate::Misbehaviour;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use frost_core::dkg::{pedersen_h_generator, DkgRound1Broadcast, DkgRound2P2PMessage};
+use frost_core::rotation::{RotationCommitment, RotationShare};
+use frost_core::{GroupPublicKey, ParticipantId, PublicKeyShare, SecretScalar};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Evaluate Σ x^k * C_k at participant x, or None if a point is invalid
+pub(crate) fn evaluate_commitments(
+    commitments: &[CompressedRistretto],
+    participant_id: ParticipantId,
+) -> Option<RistrettoPoint> {
+    let x = participant_id.as_scalar();
+    let mut x_power = Scalar::ONE;
+    let mut result = RistrettoPoint::identity();
+
+    for commitment in commitments {
+        result += x_power * commitment.decompress()?;
+        x_power *= x;
+    }
+
+    Some(result)
+}
+
+fn all_valid(points: &[CompressedRistretto]) -> bool {
+    points.iter().all(|p| p.decompress().is_some())
+}
+
+fn scalar(bytes: &[u8]) -> Option<Scalar> {
+    Option::from(Scalar::from_canonical_bytes(bytes.try_into().ok()?))
+}
+
+/// Check that a DKG broadcast commits to a degree t-1 polynomial
+pub(crate) fn check_dkg_broadcast(
+    broadcast: &KeyGenBroadcast,
+    sender: ParticipantId,
+    threshold: u32,
+) -> Result<(), String> {
+    let round1 = &broadcast.round1;
+    if round1.sender_id != sender {
+        return Err(format!("broadcast claims to be from participant {}", round1.sender_id.as_u32()));
+    }
+    if round1.public_commitments.len() != threshold as usize
+        || round1.commitment.commitments.len() != threshold as usize
+    {
+        return Err("commitments are not for a degree t-1 polynomial".to_string());
+    }
+    if !all_valid(&round1.public_commitments)
+        || !all_valid(&round1.commitment.commitments)
+        || broadcast.channel_key.decompress().is_none()
+    {
+        return Err("broadcast contains invalid points".to_string());
+    }
+    Ok(())
+}
+
+/// Check that a rotation broadcast commits to a zero-sum polynomial of degree t-1
+pub(crate) fn check_rotation_broadcast(
+    broadcast: &RotationBroadcast,
+    sender: ParticipantId,
+    threshold: u32,
+) -> Result<(), String> {
+    let commitments = &broadcast.commitment.commitment.commitments;
+    if broadcast.commitment.sender_id != sender {
+        return Err(format!("broadcast claims to be from participant {}", broadcast.commitment.sender_id.as_u32()));
+    }
+    if commitments.len() != threshold as usize {
+        return Err("commitments are not for a degree t-1 polynomial".to_string());
+    }
+    if !all_valid(commitments) || broadcast.channel_key.decompress().is_none() {
+        return Err("broadcast contains invalid points".to_string());
+    }
+    // δ(0) must be zero, or the rotation would change the group key
+    if commitments[0] != RistrettoPoint::identity().compress() {
+        return Err("rotation polynomial does not preserve the group key".to_string());
+    }
+    Ok(())
+}
+
+/// Check that a participant sealed exactly one share for every other participant
+pub(crate) fn check_shares(
+    shares: &[EncryptedShare],
+    sender: ParticipantId,
+    participants: &[ParticipantId],
+) -> Result<(), String> {
+    let recipients: BTreeSet<_> = shares.iter().map(|s| s.recipient_id.as_u32()).collect();
+    let expected: BTreeSet<_> = participants.iter()
+        .filter(|&&p| p != sender)
+        .map(|p| p.as_u32())
+        .collect();
+
+    if shares.iter().any(|s| s.sender_id != sender) {
+        return Err("shares claim a different sender".to_string());
+    }
+    if recipients != expected || shares.len() != expected.len() {
+        return Err("did not send exactly one share to every other participant".to_string());
+    }
+    Ok(())
+}
+
+/// Decode a DKG share `f(j) || g(j)` and verify it against the sender's commitments
+pub(crate) fn verify_dkg_share(
+    broadcast: &DkgRound1Broadcast,
+    recipient: ParticipantId,
+    plaintext: &[u8],
+) -> Option<DkgRound2P2PMessage> {
+    if plaintext.len() != 64 {
+        return None;
+    }
+    let secret = scalar(&plaintext[..32])?;
+    let blinding = scalar(&plaintext[32..])?;
+
+    let g = RISTRETTO_BASEPOINT_POINT;
+    let pedersen_ok = broadcast.commitment.verify_share(recipient, &secret, &blinding, &g, &pedersen_h_generator());
+    let feldman_ok = evaluate_commitments(&broadcast.public_commitments, recipient) == Some(secret * g);
+    if !pedersen_ok || !feldman_ok {
+        return None;
+    }
+
+    Some(DkgRound2P2PMessage {
+        sender_id: broadcast.sender_id,
+        recipient_id: recipient,
+        secret_share: SecretScalar::new(secret),
+        blinding_share: SecretScalar::new(blinding),
+    })
+}
+
+/// Decode a rotation delta share δ(j) and verify it against the sender's commitment
+pub(crate) fn verify_rotation_share(
+    commitment: &RotationCommitment,
+    recipient: ParticipantId,
+    plaintext: &[u8],
+) -> Option<RotationShare> {
+    let delta = scalar(plaintext)?;
+    let ok = commitment.commitment.verify_share(
+        recipient,
+        &delta,
+        &Scalar::ZERO,
+        &RISTRETTO_BASEPOINT_POINT,
+        &pedersen_h_generator(),
+    );
+
+    ok.then(|| RotationShare {
+        sender_id: commitment.sender_id,
+        recipient_id: recipient,
+        delta_share: SecretScalar::new(delta),
+    })
+}
+
+/// Group key defined by the DKG broadcasts
+///
+/// PK = Σ_i A_{i,0} and Y_j = Σ_i f_i(j) * G, from the Feldman commitments.
+pub(crate) fn dkg_group_key(
+    broadcasts: &[DkgRound1Broadcast],
+    threshold: u32,
+    participants: &[ParticipantId],
+) -> Option<GroupPublicKey> {
+    let mut public_key = RistrettoPoint::identity();
+    for broadcast in broadcasts {
+        public_key += broadcast.public_commitments.first()?.decompress()?;
+    }
+
+    let mut participant_shares = Vec::with_capacity(participants.len());
+    for &participant_id in participants {
+        let mut share = RistrettoPoint::identity();
+        for broadcast in broadcasts {
+            share += evaluate_commitments(&broadcast.public_commitments, participant_id)?;
+        }
+        participant_shares.push(PublicKeyShare { participant_id, public_key: share.compress() });
+    }
+
+    Some(GroupPublicKey {
+        public_key: public_key.compress(),
+        participant_shares,
+        threshold,
+        num_participants: participants.len() as u32,
+    })
+}
+
+/// Group key after applying the rotation commitments
+///
+/// PK is unchanged; Y'_j = Y_j + Σ_i δ_i(j) * G.
+pub(crate) fn rotated_group_key(
+    group_public_key: &GroupPublicKey,
+    commitments: &[RotationCommitment],
+) -> Option<GroupPublicKey> {
+    let mut rotated = group_public_key.clone();
+    for share in &mut rotated.participant_shares {
+        let mut point = share.public_key.decompress()?;
+        for commitment in commitments {
+            point += evaluate_commitments(&commitment.commitment.commitments, share.participant_id)?;
+        }
+        share.public_key = point.compress();
+    }
+    Some(rotated)
+}
+
+/// Decide a complaint by opening the disputed shares with the revealed key
+///
+/// A share that opens and verifies convicts the complainant of a false
+/// accusation; one that does not convicts its sender.
+pub(crate) fn resolve_complaint(
+    ceremony_id: &CeremonyId,
+    complainant: ParticipantId,
+    complaint: &Complaint,
+    channel_keys: &BTreeMap<ParticipantId, CompressedRistretto>,
+    shares: &BTreeMap<ParticipantId, Vec<EncryptedShare>>,
+    share_is_valid: impl Fn(ParticipantId, &[u8]) -> bool,
+) -> Vec<Misbehaviour> {
+    let revealed = (complaint.channel_secret * RISTRETTO_BASEPOINT_POINT).compress();
+    if channel_keys.get(&complainant) != Some(&revealed) {
+        return vec![Misbehaviour::new(complainant, "revealed a channel secret that does not match its key")];
+    }
+    if complaint.accused.is_empty() {
+        return vec![Misbehaviour::new(complainant, "complained without accusing anyone")];
+    }
+
+    let mut found = Vec::new();
+    for accused in complaint.accused.iter().copied().collect::<BTreeSet<_>>() {
+        let share = shares.get(&accused)
+            .and_then(|sent| sent.iter().find(|s| s.recipient_id == complainant));
+        let (Some(share), Some(sender_key)) = (share, channel_keys.get(&accused)) else {
+            found.push(Misbehaviour::new(complainant, format!("accused unknown participant {}", accused.as_u32())));
+            continue;
+        };
+
+        let valid = open_with(&complaint.channel_secret, ceremony_id, share, sender_key)
+            .is_some_and(|plaintext| share_is_valid(accused, &plaintext));
+        if valid {
+            found.push(Misbehaviour::new(complainant, format!("falsely accused participant {}", accused.as_u32())));
+        } else {
+            found.push(Misbehaviour::new(accused, format!("sent participant {} an invalid share", complainant.as_u32())));
+        }
+    }
+    found
+}
+
+/// Route each sealed share to its recipient
+pub(crate) fn route_shares(
+    ceremony_id: CeremonyId,
+    shares: &BTreeMap<ParticipantId, Vec<EncryptedShare>>,
+    participants: &[ParticipantId],
+    wrap: impl Fn(Vec<EncryptedShare>) -> CoordinatorMessage,
+) -> Vec<Envelope<CoordinatorMessage>> {
+    participants.iter()
+        .map(|&participant_id| Envelope {
+            ceremony_id,
+            participant_id,
+            message: wrap(shares.values()
+                .flatten()
+                .filter(|s| s.recipient_id == participant_id)
+                .cloned()
+                .collect()),
+        })
+        .collect()
+}
+
+/// Key material `participant_id` should report for `group_public_key`
+pub(crate) fn expected_verification(
+    group_public_key: &GroupPublicKey,
+    participant_id: ParticipantId,
+) -> Option<Verification> {
+    group_public_key.participant_shares.iter()
+        .find(|s| s.participant_id == participant_id)
+        .map(|s| Verification {
+            group_public_key: group_public_key.public_key,
+            verification_share: s.public_key,
+        })
+}
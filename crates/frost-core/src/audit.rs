@@ -0,0 +1,335 @@
+//! Signed, hash-chained audit trail of session token use
+//!
+//! Every attempt to use a token, allowed or denied, appends an entry
+//! signed by the token's holder key:
+//!
+//! ```text
+//! entry_i.hash = H(tag || token_id || entry_{i-1}.hash || seq || timestamp || operation digest || outcome)
+//! entry_i.sig  = Schnorr(holder key, entry_i.hash)
+//! ```
+//!
+//! The log is bounded: once `capacity` entries are awaiting upload the
+//! token refuses further use rather than drop history. On reconnect the
+//! device uploads `SessionToken::export_audit` to the transparency log and
+//! prunes what was accepted with `AuditLog::acknowledge`. A verifier that
+//! remembers the last head it accepted detects missing, reordered or
+//! rewritten entries with `AuditExport::verify`.
+
+use crate::nonce::{hedged_nonce, DefaultEntropy};
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+
+/// Entries a token holds before it must upload its log
+pub const AUDIT_LOG_CAPACITY: usize = 256;
+
+/// Result of one token use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditOutcome {
+    /// The operation was authorized and signed
+    Allowed,
+    /// The token refused the operation
+    Denied,
+}
+
+impl AuditOutcome {
+    fn to_byte(self) -> u8 {
+        match self {
+            AuditOutcome::Allowed => 1,
+            AuditOutcome::Denied => 0,
+        }
+    }
+}
+
+/// One recorded token use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log (1-based, never reused)
+    pub sequence: u64,
+    /// Time of the attempt (Unix epoch seconds)
+    pub timestamp: u64,
+    /// Operation hash; for allowed uses, `TokenSignature::operation_hash`
+    pub operation_digest: [u8; 32],
+    /// Whether the operation was allowed
+    pub outcome: AuditOutcome,
+    /// Hash of this entry, chained from the previous one
+    pub hash: [u8; 32],
+    /// Holder-key signature over `hash`
+    pub signature: SchnorrSignature,
+}
+
+impl AuditEntry {
+    fn compute_hash(
+        token_id: &[u8; 16],
+        prev_hash: &[u8; 32],
+        sequence: u64,
+        timestamp: u64,
+        operation_digest: &[u8; 32],
+        outcome: AuditOutcome,
+    ) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"FROST-TOKEN-AUDIT-v1");
+        hasher.update(token_id);
+        hasher.update(prev_hash);
+        hasher.update(sequence.to_le_bytes());
+        hasher.update(timestamp.to_le_bytes());
+        hasher.update(operation_digest);
+        hasher.update([outcome.to_byte()]);
+        hasher.finalize().into()
+    }
+}
+
+/// Bounded audit log of one token holder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    capacity: usize,
+    /// Sequence of the last acknowledged entry
+    anchor_sequence: u64,
+    /// Hash of the last acknowledged entry
+    anchor_hash: [u8; 32],
+    /// Entries not yet acknowledged, oldest first
+    entries: Vec<AuditEntry>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog::new(AUDIT_LOG_CAPACITY)
+    }
+}
+
+impl AuditLog {
+    /// Empty log holding up to `capacity` unacknowledged entries
+    pub fn new(capacity: usize) -> Self {
+        AuditLog {
+            capacity,
+            anchor_sequence: 0,
+            anchor_hash: [0u8; 32],
+            entries: Vec::new(),
+        }
+    }
+
+    /// Entries not yet acknowledged
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Whether the log must be uploaded before the token can be used again
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.capacity
+    }
+
+    /// Sequence and hash of the newest entry
+    pub fn head(&self) -> (u64, [u8; 32]) {
+        self.entries.last()
+            .map(|e| (e.sequence, e.hash))
+            .unwrap_or((self.anchor_sequence, self.anchor_hash))
+    }
+
+    /// Append an entry signed by `holder_key`
+    pub(crate) fn append(
+        &mut self,
+        token_id: &[u8; 16],
+        holder_key: &SecretScalar,
+        holder_public: &CompressedRistretto,
+        timestamp: u64,
+        operation_digest: [u8; 32],
+        outcome: AuditOutcome,
+    ) -> FrostResult<()> {
+        if self.is_full() {
+            return Err(FrostError::StorageError("Token audit log is full; upload it before further use".to_string()));
+        }
+
+        let (head, prev_hash) = self.head();
+        let sequence = head + 1;
+        let hash = AuditEntry::compute_hash(token_id, &prev_hash, sequence, timestamp, &operation_digest, outcome);
+        let nonce = hedged_nonce(b"token-audit", holder_key.as_scalar(), holder_public, &hash, &DefaultEntropy)?;
+
+        self.entries.push(AuditEntry {
+            sequence,
+            timestamp,
+            operation_digest,
+            outcome,
+            hash,
+            signature: SchnorrSignature::sign_with_nonce(holder_key.as_scalar(), nonce.as_scalar(), &hash),
+        });
+        Ok(())
+    }
+
+    /// Export the unacknowledged entries for upload
+    pub fn export(&self, token_id: [u8; 16], holder_public: CompressedRistretto) -> AuditExport {
+        AuditExport {
+            token_id,
+            holder_public,
+            anchor_sequence: self.anchor_sequence,
+            anchor_hash: self.anchor_hash,
+            entries: self.entries.clone(),
+        }
+    }
+
+    /// Drop entries up to `sequence` once the transparency log has them
+    pub fn acknowledge(&mut self, sequence: u64) {
+        let keep_from = self.entries.iter()
+            .position(|e| e.sequence > sequence)
+            .unwrap_or(self.entries.len());
+
+        if let Some(last) = keep_from.checked_sub(1).map(|i| &self.entries[i]) {
+            self.anchor_sequence = last.sequence;
+            self.anchor_hash = last.hash;
+        }
+        self.entries.drain(..keep_from);
+    }
+}
+
+/// Audit entries of one token holder, as uploaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExport {
+    /// Token the entries were recorded under
+    pub token_id: [u8; 16],
+    /// Key every entry is signed with
+    pub holder_public: CompressedRistretto,
+    /// Sequence the entries chain from
+    pub anchor_sequence: u64,
+    /// Hash the entries chain from
+    pub anchor_hash: [u8; 32],
+    /// Entries, oldest first
+    pub entries: Vec<AuditEntry>,
+}
+
+impl AuditExport {
+    /// Check the chain and signatures, returning the new head
+    ///
+    /// `previous_head` is the head of the last export accepted for this
+    /// holder (`None` for the first), so entries dropped before the anchor
+    /// are caught as well as gaps, reordering and rewrites within it.
+    pub fn verify(&self, previous_head: Option<(u64, [u8; 32])>) -> FrostResult<(u64, [u8; 32])> {
+        let expected_anchor = previous_head.unwrap_or((0, [0u8; 32]));
+        if (self.anchor_sequence, self.anchor_hash) != expected_anchor {
+            return Err(FrostError::RollbackDetected(format!(
+                "Audit export chains from entry {} but entry {} was the last accepted",
+                self.anchor_sequence, expected_anchor.0,
+            )));
+        }
+
+        let mut sequence = self.anchor_sequence;
+        let mut hash = self.anchor_hash;
+        for entry in &self.entries {
+            if entry.sequence != sequence + 1 {
+                return Err(FrostError::RollbackDetected(format!(
+                    "Audit entry {} follows entry {}",
+                    entry.sequence, sequence,
+                )));
+            }
+            let expected = AuditEntry::compute_hash(
+                &self.token_id,
+                &hash,
+                entry.sequence,
+                entry.timestamp,
+                &entry.operation_digest,
+                entry.outcome,
+            );
+            if entry.hash != expected {
+                return Err(FrostError::RollbackDetected(format!("Audit entry {} breaks the hash chain", entry.sequence)));
+            }
+            if !entry.signature.verify(SignatureScheme::Schnorr, &entry.hash, &self.holder_public) {
+                return Err(FrostError::CryptoError(format!("Audit entry {} is not signed by the holder", entry.sequence)));
+            }
+            sequence = entry.sequence;
+            hash = entry.hash;
+        }
+        Ok((sequence, hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn holder() -> (SecretScalar, CompressedRistretto) {
+        let key = SecretScalar::new(Scalar::random(&mut rand::thread_rng()));
+        let public = (key.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        (key, public)
+    }
+
+    fn filled(entries: u8) -> (AuditLog, CompressedRistretto) {
+        let (key, public) = holder();
+        let mut log = AuditLog::new(8);
+        for i in 0..entries {
+            let outcome = if i % 3 == 2 { AuditOutcome::Denied } else { AuditOutcome::Allowed };
+            log.append(&[1u8; 16], &key, &public, 1000 + i as u64, [i; 32], outcome).unwrap();
+        }
+        (log, public)
+    }
+
+    #[test]
+    fn test_export_verifies_across_acknowledgements() {
+        let (mut log, public) = filled(4);
+        let head = log.export([1u8; 16], public).verify(None).unwrap();
+        assert_eq!(head, log.head());
+        assert_eq!(head.0, 4);
+
+        log.acknowledge(head.0);
+        assert!(log.entries().is_empty());
+        let (key, _) = holder();
+        assert!(log.append(&[1u8; 16], &key, &public, 2000, [9u8; 32], AuditOutcome::Allowed).is_ok());
+
+        // The next export chains from the accepted head, not from genesis
+        let next = log.export([1u8; 16], public);
+        assert_eq!(next.anchor_sequence, 4);
+        assert!(next.verify(None).is_err());
+        // The entry above was signed by a different key
+        assert!(matches!(next.verify(Some(head)), Err(FrostError::CryptoError(_))));
+    }
+
+    #[test]
+    fn test_missing_reordered_and_rewritten_entries_detected() {
+        let (log, public) = filled(5);
+        let export = log.export([1u8; 16], public);
+        assert!(export.verify(None).is_ok());
+
+        let mut missing = export.clone();
+        missing.entries.remove(2);
+        assert!(matches!(missing.verify(None), Err(FrostError::RollbackDetected(_))));
+
+        let mut dropped_head = export.clone();
+        dropped_head.entries.remove(0);
+        dropped_head.anchor_sequence = 1;
+        assert!(dropped_head.verify(None).is_err());
+
+        let mut reordered = export.clone();
+        reordered.entries.swap(1, 2);
+        assert!(reordered.verify(None).is_err());
+
+        let mut renumbered = reordered.clone();
+        renumbered.entries[1].sequence = 2;
+        renumbered.entries[2].sequence = 3;
+        assert!(renumbered.verify(None).is_err());
+
+        let mut flipped = export.clone();
+        flipped.entries[2].outcome = AuditOutcome::Allowed;
+        assert!(flipped.verify(None).is_err());
+
+        let mut other_token = export.clone();
+        other_token.token_id = [2u8; 16];
+        assert!(other_token.verify(None).is_err());
+    }
+
+    #[test]
+    fn test_full_log_refuses_appends() {
+        let (mut log, public) = filled(8);
+        let (key, _) = holder();
+        assert!(log.is_full());
+        assert!(matches!(
+            log.append(&[1u8; 16], &key, &public, 3000, [0u8; 32], AuditOutcome::Allowed),
+            Err(FrostError::StorageError(_)),
+        ));
+
+        log.acknowledge(3);
+        assert!(!log.is_full());
+        assert_eq!(log.entries().len(), 5);
+        assert_eq!(log.export([1u8; 16], public).anchor_sequence, 3);
+    }
+}
@@ -0,0 +1,715 @@
+//! CWT (RFC 8392) / COSE_Sign1 (RFC 9052) encoding of session tokens
+//!
+//! The token body is a CWT claims set and the FROST group signs the
+//! COSE_Sign1 `Sig_structure` over it; `SessionToken::to_signing_data`
+//! *is* that structure, so the FROST signature is the COSE signature and
+//! every issued token has a CWT form.
+//!
+//! ```text
+//! COSE_Sign1 = 18([protected: bstr .cbor {1: -65537}, unprotected: {}, payload: bstr .cbor claims, signature: R(32) || z(32)])
+//! claims     = {4: exp, 6: iat, 7: cti, -65537: device id, -65538: token key,
+//!               -65539: unlock, -65540: keychain, ?-65541: [per tx, per day, reset at],
+//!               -65542: code signing, -65543: FileVault, -65544: [custom...], ?-65545: policy}
+//! ```
+//!
+//! Algorithm -65537 (private use) is FROST(ristretto255, SHA-512): a
+//! consumer accepts the token if `z·B == R + c·PK`, where
+//! `c = SHA-512("FROST-RISTRETTO255-SHA512-v1-challenge" || R || Sig_structure)`
+//! reduced mod ℓ and `PK` is the group public key. All CBOR is in the
+//! deterministic encoding of RFC 8949 §4.2.1; decoding rejects anything else.
+
+use crate::audit::AuditLog;
+use crate::policy::Rule;
+use crate::session_token::{Capabilities, KeychainAccessLevel, PaymentLimits, SessionToken, UsageTracker};
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+
+/// COSE algorithm ID for FROST(ristretto255, SHA-512) signatures (private use)
+pub const COSE_ALG_FROST_RISTRETTO255_SHA512: i64 = -65537;
+
+/// CBOR tag of a COSE_Sign1 structure
+pub const COSE_SIGN1_TAG: u64 = 18;
+
+/// CBOR tag of a CWT
+pub const CWT_TAG: u64 = 61;
+
+/// Registered claim: expiration time
+pub const CLAIM_EXP: i64 = 4;
+/// Registered claim: issued at
+pub const CLAIM_IAT: i64 = 6;
+/// Registered claim: CWT ID (the token ID)
+pub const CLAIM_CTI: i64 = 7;
+/// Private claim: device the token is bound to
+pub const CLAIM_DEVICE_ID: i64 = -65537;
+/// Private claim: ephemeral public key operations are signed with
+pub const CLAIM_TOKEN_KEY: i64 = -65538;
+/// Private claim: device unlock allowed
+pub const CLAIM_DEVICE_UNLOCK: i64 = -65539;
+/// Private claim: keychain access level (0-3)
+pub const CLAIM_KEYCHAIN_ACCESS: i64 = -65540;
+/// Private claim: payment limits `[max per transaction, max per day, daily reset at]`
+pub const CLAIM_PAYMENT_LIMITS: i64 = -65541;
+/// Private claim: code signing allowed
+pub const CLAIM_CODE_SIGNING: i64 = -65542;
+/// Private claim: FileVault decryption allowed
+pub const CLAIM_FILEVAULT_DECRYPT: i64 = -65543;
+/// Private claim: custom operations, sorted and unique
+pub const CLAIM_CUSTOM: i64 = -65544;
+/// Private claim: policy rule tree (`Rule::to_canonical_bytes`)
+pub const CLAIM_POLICY: i64 = -65545;
+/// Private claim: message signing allowed (present only when granted)
+pub const CLAIM_SIGN_MESSAGE: i64 = -65546;
+
+/// Start of every COSE `Sig_structure` signed with this profile:
+/// `array(4)`, then the text string `"Signature1"`
+pub const SIG_STRUCTURE_PREFIX: &[u8] = b"\x84\x6aSignature1";
+
+/// Length of the COSE signature: commitment R || response z
+const COSE_SIGNATURE_LEN: usize = 64;
+
+impl SessionToken {
+    /// Encode as a tagged COSE_Sign1 CWT
+    ///
+    /// Caveats are holder-signed, not group-signed, and have no CWT form.
+    pub fn to_cwt(&self) -> FrostResult<Vec<u8>> {
+        if !self.caveats.is_empty() {
+            return Err(FrostError::SerializationError("Attenuated tokens have no CWT encoding".to_string()));
+        }
+
+        let mut signature = Vec::with_capacity(COSE_SIGNATURE_LEN);
+        signature.extend_from_slice(self.frost_signature.commitment.as_bytes());
+        signature.extend_from_slice(&self.frost_signature.z);
+
+        let mut out = Vec::new();
+        head(&mut out, MAJOR_TAG, COSE_SIGN1_TAG);
+        head(&mut out, MAJOR_ARRAY, 4);
+        bstr(&mut out, &protected_header());
+        head(&mut out, MAJOR_MAP, 0);
+        bstr(&mut out, &claims(self));
+        bstr(&mut out, &signature);
+        Ok(out)
+    }
+
+    /// Decode a COSE_Sign1 CWT without checking its signature
+    ///
+    /// Use `verify_cwt` for tokens from untrusted sources. The decoded
+    /// token carries no ephemeral key.
+    pub fn from_cwt(bytes: &[u8]) -> FrostResult<Self> {
+        Ok(parse_sign1(bytes)?.token)
+    }
+}
+
+/// Verify a CWT against the group public key and decode it
+///
+/// Checks the COSE signature over the received bytes, that the claims are
+/// canonical, and that the algorithm is FROST(ristretto255, SHA-512). Does
+/// not check the validity window; see `SessionToken::is_valid`.
+pub fn verify_cwt(bytes: &[u8], group_public_key: &GroupPublicKey) -> FrostResult<SessionToken> {
+    let parsed = parse_sign1(bytes)?;
+    let signing_data = sig_structure(parsed.protected, parsed.payload);
+    if !parsed.token.frost_signature.verify(SignatureScheme::Threshold, &signing_data, &group_public_key.public_key) {
+        return Err(FrostError::CryptoError("CWT is not signed by the FROST group".to_string()));
+    }
+    Ok(parsed.token)
+}
+
+/// Decode the token a COSE `Sig_structure` is for, as sent to be co-signed
+///
+/// Accepts only what `SessionToken::to_signing_data` produces, so the
+/// decoded token signs to exactly `bytes`. The token carries no signature
+/// and no ephemeral key.
+pub fn token_from_sig_structure(bytes: &[u8]) -> FrostResult<SessionToken> {
+    let mut decoder = Decoder { bytes };
+    if decoder.expect(MAJOR_ARRAY)? != 4 || decoder.tstr()? != "Signature1" {
+        return Err(cbor_error("not a COSE_Sign1 Sig_structure"));
+    }
+    if decoder.bstr()? != protected_header().as_slice() {
+        return Err(FrostError::CryptoError("Unsupported COSE algorithm".to_string()));
+    }
+    if !decoder.bstr()?.is_empty() {
+        return Err(cbor_error("external AAD must be empty"));
+    }
+    let payload = decoder.bstr()?;
+    if !decoder.bytes.is_empty() {
+        return Err(cbor_error("trailing bytes"));
+    }
+
+    let token = parse_claims(payload)?;
+    if claims(&token) != payload {
+        return Err(cbor_error("claims are not canonically encoded"));
+    }
+    Ok(token)
+}
+
+/// COSE `Sig_structure` for `token`: what the FROST group signs
+pub(crate) fn token_sig_structure(token: &SessionToken) -> Vec<u8> {
+    sig_structure(&protected_header(), &claims(token))
+}
+
+/// `["Signature1", protected, external_aad = h'', payload]`
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + protected.len() + payload.len());
+    head(&mut out, MAJOR_ARRAY, 4);
+    tstr(&mut out, "Signature1");
+    bstr(&mut out, protected);
+    bstr(&mut out, &[]);
+    bstr(&mut out, payload);
+    out
+}
+
+/// `{1 (alg): -65537}`
+fn protected_header() -> Vec<u8> {
+    let mut out = Vec::new();
+    head(&mut out, MAJOR_MAP, 1);
+    int(&mut out, 1);
+    int(&mut out, COSE_ALG_FROST_RISTRETTO255_SHA512);
+    out
+}
+
+/// Claims set, keys in deterministic (encoded byte) order
+fn claims(token: &SessionToken) -> Vec<u8> {
+    let capabilities = &token.capabilities;
+    let mut custom: Vec<&String> = capabilities.custom.iter().collect();
+    custom.sort();
+    custom.dedup();
+
+    let entries = 10
+        + capabilities.payment_limits.is_some() as u64
+        + capabilities.policy.is_some() as u64
+        + capabilities.sign_message as u64;
+    let mut out = Vec::with_capacity(256);
+    head(&mut out, MAJOR_MAP, entries);
+
+    int(&mut out, CLAIM_EXP);
+    head(&mut out, MAJOR_UINT, token.expires_at);
+    int(&mut out, CLAIM_IAT);
+    head(&mut out, MAJOR_UINT, token.issued_at);
+    int(&mut out, CLAIM_CTI);
+    bstr(&mut out, &token.token_id);
+    int(&mut out, CLAIM_DEVICE_ID);
+    bstr(&mut out, &token.device_id);
+    int(&mut out, CLAIM_TOKEN_KEY);
+    bstr(&mut out, token.ephemeral_public.as_bytes());
+    int(&mut out, CLAIM_DEVICE_UNLOCK);
+    boolean(&mut out, capabilities.device_unlock);
+    int(&mut out, CLAIM_KEYCHAIN_ACCESS);
+    head(&mut out, MAJOR_UINT, capabilities.keychain_access.to_byte() as u64);
+    if let Some(limits) = &capabilities.payment_limits {
+        int(&mut out, CLAIM_PAYMENT_LIMITS);
+        head(&mut out, MAJOR_ARRAY, 3);
+        head(&mut out, MAJOR_UINT, limits.max_per_transaction);
+        head(&mut out, MAJOR_UINT, limits.max_per_day);
+        head(&mut out, MAJOR_UINT, limits.daily_reset_at);
+    }
+    int(&mut out, CLAIM_CODE_SIGNING);
+    boolean(&mut out, capabilities.code_signing);
+    int(&mut out, CLAIM_FILEVAULT_DECRYPT);
+    boolean(&mut out, capabilities.filevault_decrypt);
+    int(&mut out, CLAIM_CUSTOM);
+    head(&mut out, MAJOR_ARRAY, custom.len() as u64);
+    for flag in custom {
+        tstr(&mut out, flag);
+    }
+    if let Some(policy) = &capabilities.policy {
+        int(&mut out, CLAIM_POLICY);
+        bstr(&mut out, &policy.to_canonical_bytes());
+    }
+    if capabilities.sign_message {
+        int(&mut out, CLAIM_SIGN_MESSAGE);
+        boolean(&mut out, true);
+    }
+    out
+}
+
+/// A decoded COSE_Sign1 and the byte ranges its signature covers
+struct ParsedSign1<'a> {
+    protected: &'a [u8],
+    payload: &'a [u8],
+    token: SessionToken,
+}
+
+fn parse_sign1(bytes: &[u8]) -> FrostResult<ParsedSign1<'_>> {
+    let mut decoder = Decoder { bytes };
+    let mut tag = decoder.expect(MAJOR_TAG)?;
+    if tag == CWT_TAG {
+        tag = decoder.expect(MAJOR_TAG)?;
+    }
+    if tag != COSE_SIGN1_TAG {
+        return Err(cbor_error("not a COSE_Sign1"));
+    }
+    if decoder.expect(MAJOR_ARRAY)? != 4 {
+        return Err(cbor_error("COSE_Sign1 must have 4 elements"));
+    }
+
+    let protected = decoder.bstr()?;
+    if protected != protected_header().as_slice() {
+        return Err(FrostError::CryptoError("Unsupported COSE algorithm".to_string()));
+    }
+    if decoder.expect(MAJOR_MAP)? != 0 {
+        return Err(cbor_error("unprotected header must be empty"));
+    }
+    let payload = decoder.bstr()?;
+    let signature = decoder.bstr()?;
+    if !decoder.bytes.is_empty() {
+        return Err(cbor_error("trailing bytes"));
+    }
+    if signature.len() != COSE_SIGNATURE_LEN {
+        return Err(cbor_error("signature must be 64 bytes"));
+    }
+
+    let mut token = parse_claims(payload)?;
+    token.frost_signature = SchnorrSignature {
+        z: signature[32..].try_into().expect("32-byte slice"),
+        commitment: CompressedRistretto::from_slice(&signature[..32]).expect("32-byte slice"),
+        scheme: SignatureScheme::Threshold,
+    };
+
+    // The Rust side signs `to_signing_data`, so only canonical claims are accepted
+    if claims(&token) != payload {
+        return Err(cbor_error("claims are not canonically encoded"));
+    }
+
+    Ok(ParsedSign1 { protected, payload, token })
+}
+
+fn parse_claims(payload: &[u8]) -> FrostResult<SessionToken> {
+    let mut claims = ClaimReader::new(payload)?;
+
+    let expires_at = claims.require(CLAIM_EXP)?.expect(MAJOR_UINT)?;
+    let issued_at = claims.require(CLAIM_IAT)?.expect(MAJOR_UINT)?;
+    let token_id: [u8; 16] = claims.require(CLAIM_CTI)?.bstr()?
+        .try_into().map_err(|_| cbor_error("cti must be 16 bytes"))?;
+    let device_id: [u8; 32] = claims.require(CLAIM_DEVICE_ID)?.bstr()?
+        .try_into().map_err(|_| cbor_error("device ID must be 32 bytes"))?;
+    let ephemeral_public = CompressedRistretto::from_slice(claims.require(CLAIM_TOKEN_KEY)?.bstr()?)
+        .map_err(|_| cbor_error("token key must be 32 bytes"))?;
+    let device_unlock = claims.require(CLAIM_DEVICE_UNLOCK)?.boolean()?;
+    let keychain_level = claims.require(CLAIM_KEYCHAIN_ACCESS)?.expect(MAJOR_UINT)?;
+    let keychain_access = KeychainAccessLevel::from_byte(
+        u8::try_from(keychain_level).map_err(|_| cbor_error("keychain level out of range"))?,
+    )?;
+
+    let payment_limits = match claims.optional(CLAIM_PAYMENT_LIMITS)? {
+        Some(d) => {
+            if d.expect(MAJOR_ARRAY)? != 3 {
+                return Err(cbor_error("payment limits must have 3 elements"));
+            }
+            let max_per_transaction = d.expect(MAJOR_UINT)?;
+            let max_per_day = d.expect(MAJOR_UINT)?;
+            let daily_reset_at = d.expect(MAJOR_UINT)?;
+            Some(PaymentLimits { max_per_transaction, max_per_day, remaining_today: max_per_day, daily_reset_at })
+        }
+        None => None,
+    };
+
+    let code_signing = claims.require(CLAIM_CODE_SIGNING)?.boolean()?;
+    let filevault_decrypt = claims.require(CLAIM_FILEVAULT_DECRYPT)?.boolean()?;
+
+    let d = claims.require(CLAIM_CUSTOM)?;
+    let count = d.expect(MAJOR_ARRAY)?;
+    let custom = (0..count)
+        .map(|_| d.tstr().map(str::to_string))
+        .collect::<FrostResult<Vec<_>>>()?;
+
+    let policy = match claims.optional(CLAIM_POLICY)? {
+        Some(d) => Some(Rule::from_canonical_bytes(d.bstr()?)?),
+        None => None,
+    };
+    let sign_message = match claims.optional(CLAIM_SIGN_MESSAGE)? {
+        Some(d) => d.boolean()?,
+        None => false,
+    };
+
+    claims.finish()?;
+
+    Ok(SessionToken {
+        token_id,
+        issued_at,
+        expires_at,
+        device_id,
+        capabilities: Capabilities {
+            device_unlock,
+            keychain_access,
+            payment_limits,
+            code_signing,
+            filevault_decrypt,
+            sign_message,
+            custom,
+            policy,
+        },
+        usage: UsageTracker {
+            use_count: 0,
+            last_used_at: None,
+            audit: AuditLog::default(),
+        },
+        ephemeral_public,
+        frost_signature: SchnorrSignature {
+            z: [0u8; 32],
+            commitment: CompressedRistretto::default(),
+            scheme: SignatureScheme::Threshold,
+        },
+        caveats: Vec::new(),
+        ephemeral_key: None,
+    })
+}
+
+/// Walks a claims map in key order
+struct ClaimReader<'a> {
+    decoder: Decoder<'a>,
+    remaining: u64,
+    /// Label of the next claim, once read
+    next: Option<Option<i64>>,
+}
+
+impl<'a> ClaimReader<'a> {
+    fn new(payload: &'a [u8]) -> FrostResult<Self> {
+        let mut decoder = Decoder { bytes: payload };
+        let remaining = decoder.expect(MAJOR_MAP)?;
+        Ok(ClaimReader { decoder, remaining, next: None })
+    }
+
+    /// Label of the next claim, or `None` at the end of the map
+    fn peek(&mut self) -> FrostResult<Option<i64>> {
+        if self.next.is_none() {
+            let label = if self.remaining == 0 {
+                None
+            } else {
+                self.remaining -= 1;
+                Some(self.decoder.int()?)
+            };
+            self.next = Some(label);
+        }
+        Ok(self.next.flatten())
+    }
+
+    /// Decoder positioned at the value of `label`, if it is the next claim
+    fn optional(&mut self, label: i64) -> FrostResult<Option<&mut Decoder<'a>>> {
+        if self.peek()? != Some(label) {
+            return Ok(None);
+        }
+        self.next = None;
+        Ok(Some(&mut self.decoder))
+    }
+
+    fn require(&mut self, label: i64) -> FrostResult<&mut Decoder<'a>> {
+        self.optional(label)?
+            .ok_or_else(|| cbor_error(&format!("missing or misplaced claim {}", label)))
+    }
+
+    fn finish(mut self) -> FrostResult<()> {
+        if self.peek()?.is_some() || !self.decoder.bytes.is_empty() {
+            return Err(cbor_error("unexpected claims"));
+        }
+        Ok(())
+    }
+}
+
+fn cbor_error(reason: &str) -> FrostError {
+    FrostError::SerializationError(format!("Invalid CWT: {}", reason))
+}
+
+const MAJOR_UINT: u8 = 0;
+const MAJOR_NINT: u8 = 1;
+const MAJOR_BSTR: u8 = 2;
+const MAJOR_TSTR: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+/// Initial byte(s) with the shortest argument encoding
+fn head(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+fn int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        head(out, MAJOR_UINT, value as u64);
+    } else {
+        head(out, MAJOR_NINT, !value as u64);
+    }
+}
+
+fn bstr(out: &mut Vec<u8>, bytes: &[u8]) {
+    head(out, MAJOR_BSTR, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn tstr(out: &mut Vec<u8>, text: &str) {
+    head(out, MAJOR_TSTR, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn boolean(out: &mut Vec<u8>, value: bool) {
+    out.push(MAJOR_SIMPLE << 5 | if value { 21 } else { 20 });
+}
+
+/// Cursor over deterministic CBOR
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> FrostResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(cbor_error("truncated"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Major type and argument, rejecting non-shortest and indefinite forms
+    fn head(&mut self) -> FrostResult<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let (argument, minimum) = match info {
+            0..=23 => (info as u64, 0),
+            24 => (self.take(1)?[0] as u64, 24),
+            25 => (u16::from_be_bytes(self.take(2)?.try_into().expect("2-byte slice")) as u64, 0x100),
+            26 => (u32::from_be_bytes(self.take(4)?.try_into().expect("4-byte slice")) as u64, 0x1_0000),
+            27 => (u64::from_be_bytes(self.take(8)?.try_into().expect("8-byte slice")), 0x1_0000_0000),
+            _ => return Err(cbor_error("indefinite or reserved length")),
+        };
+        if argument < minimum {
+            return Err(cbor_error("non-shortest integer encoding"));
+        }
+        Ok((major, argument))
+    }
+
+    fn expect(&mut self, major: u8) -> FrostResult<u64> {
+        match self.head()? {
+            (m, argument) if m == major => Ok(argument),
+            _ => Err(cbor_error("unexpected item type")),
+        }
+    }
+
+    fn int(&mut self) -> FrostResult<i64> {
+        match self.head()? {
+            (MAJOR_UINT, value) => i64::try_from(value).map_err(|_| cbor_error("integer out of range")),
+            (MAJOR_NINT, value) => i64::try_from(value).map(|v| !v).map_err(|_| cbor_error("integer out of range")),
+            _ => Err(cbor_error("expected integer")),
+        }
+    }
+
+    fn bstr(&mut self) -> FrostResult<&'a [u8]> {
+        let len = self.expect(MAJOR_BSTR)?;
+        self.take(usize::try_from(len).map_err(|_| cbor_error("length out of range"))?)
+    }
+
+    fn tstr(&mut self) -> FrostResult<&'a str> {
+        let len = self.expect(MAJOR_TSTR)?;
+        let bytes = self.take(usize::try_from(len).map_err(|_| cbor_error("length out of range"))?)?;
+        core::str::from_utf8(bytes).map_err(|_| cbor_error("text is not UTF-8"))
+    }
+
+    fn boolean(&mut self) -> FrostResult<bool> {
+        match self.head()? {
+            (MAJOR_SIMPLE, 20) => Ok(false),
+            (MAJOR_SIMPLE, 21) => Ok(true),
+            _ => Err(cbor_error("expected boolean")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{group, issue, now_context};
+    use crate::session_token::TokenRequest;
+    use curve25519_dalek::scalar::Scalar;
+    use std::time::Duration;
+
+    fn capabilities() -> Capabilities {
+        Capabilities {
+            device_unlock: true,
+            keychain_access: KeychainAccessLevel::MediumSecurity,
+            payment_limits: Some(PaymentLimits {
+                max_per_transaction: 10_000,
+                max_per_day: 50_000,
+                remaining_today: 50_000,
+                daily_reset_at: 1_700_000_000,
+            }),
+            code_signing: false,
+            filevault_decrypt: true,
+            sign_message: true,
+            custom: vec!["ssh".to_string(), "gpg".to_string()],
+            policy: Some(Rule::AmountAtMost(5_000)),
+        }
+    }
+
+    fn issued(group_secret: &Scalar) -> SessionToken {
+        let mut token = SessionToken::new([7u8; 32], capabilities(), Duration::from_secs(3600));
+        issue(&mut token, group_secret);
+        token
+    }
+
+    #[test]
+    fn test_cwt_round_trip_verifies_with_group_key() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let token = issued(&secret);
+        let cwt = token.to_cwt().unwrap();
+
+        let decoded = verify_cwt(&cwt, &group(&secret)).unwrap();
+        assert_eq!(decoded.token_id, token.token_id);
+        assert_eq!(decoded.expires_at, token.expires_at);
+        assert_eq!(decoded.ephemeral_public, token.ephemeral_public);
+        assert_eq!(decoded.capabilities.custom, vec!["gpg".to_string(), "ssh".to_string()]);
+        assert_eq!(decoded.capabilities.to_canonical_bytes(), token.capabilities.to_canonical_bytes());
+        assert!(decoded.verify_frost_signature(&group(&secret)));
+        assert!(decoded.allows_operation(&TokenRequest::Payment { amount: 4_000 }, &now_context()));
+        assert!(!decoded.allows_operation(&TokenRequest::Payment { amount: 6_000 }, &now_context()));
+
+        // Outer CWT tag is accepted
+        let mut tagged = vec![0xd8, 0x3d];
+        tagged.extend_from_slice(&cwt);
+        assert!(verify_cwt(&tagged, &group(&secret)).is_ok());
+
+        let other = Scalar::random(&mut rand::thread_rng());
+        assert!(verify_cwt(&cwt, &group(&other)).is_err());
+
+        // Flip the device unlock claim (0xf5 true -> 0xf4 false)
+        let unlock = cwt.windows(5).position(|w| w == [0x3a, 0x00, 0x01, 0x00, 0x02]).unwrap();
+        let mut tampered = cwt.clone();
+        tampered[unlock + 5] = 0xf4;
+        assert!(SessionToken::from_cwt(&tampered).is_ok());
+        assert!(verify_cwt(&tampered, &group(&secret)).is_err());
+    }
+
+    #[test]
+    fn test_cwt_known_encoding() {
+        let nothing = Capabilities {
+            device_unlock: false,
+            keychain_access: KeychainAccessLevel::None,
+            payment_limits: None,
+            code_signing: false,
+            filevault_decrypt: false,
+            sign_message: false,
+            custom: Vec::new(),
+            policy: None,
+        };
+        let mut token = SessionToken::new([0xaa; 32], nothing, Duration::ZERO);
+        token.token_id = [0x11; 16];
+        token.issued_at = 1_000;
+        token.expires_at = 4_600;
+        token.ephemeral_public = CompressedRistretto([0xbb; 32]);
+
+        assert_eq!(protected_header(), [0xa1, 0x01, 0x3a, 0x00, 0x01, 0x00, 0x00]);
+
+        let mut expected = vec![0xaa];
+        expected.extend_from_slice(&[0x04, 0x19, 0x11, 0xf8]);
+        expected.extend_from_slice(&[0x06, 0x19, 0x03, 0xe8]);
+        expected.extend_from_slice(&[0x07, 0x50]);
+        expected.extend_from_slice(&[0x11; 16]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x00, 0x58, 0x20]);
+        expected.extend_from_slice(&[0xaa; 32]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x01, 0x58, 0x20]);
+        expected.extend_from_slice(&[0xbb; 32]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x02, 0xf4]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x03, 0x00]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x05, 0xf4]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x06, 0xf4]);
+        expected.extend_from_slice(&[0x3a, 0x00, 0x01, 0x00, 0x07, 0x80]);
+        assert_eq!(claims(&token), expected);
+
+        let signing_data = token.to_signing_data();
+        assert!(signing_data.starts_with(b"\x84\x6aSignature1\x47\xa1\x01\x3a\x00\x01\x00\x00\x40\x58"));
+        assert!(signing_data.ends_with(&expected));
+    }
+
+    #[test]
+    fn test_non_canonical_cwt_rejected() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let cwt = issued(&secret).to_cwt().unwrap();
+
+        // Indefinite-length outer array
+        let mut indefinite = cwt.clone();
+        indefinite[1] = 0x9f;
+        assert!(SessionToken::from_cwt(&indefinite).is_err());
+
+        // Trailing bytes
+        let mut trailing = cwt.clone();
+        trailing.push(0x00);
+        assert!(SessionToken::from_cwt(&trailing).is_err());
+
+        // Non-shortest integer head, and claims out of order
+        let mut long_form = Vec::new();
+        head(&mut long_form, MAJOR_MAP, 0);
+        long_form.extend_from_slice(&[0xb8, 0x00]);
+        assert!(parse_claims(&long_form[1..]).is_err());
+        assert!(parse_claims(&[0xa2, 0x06, 0x01, 0x04, 0x02]).is_err());
+
+        // Duplicate custom operations are not canonical
+        let mut token = issued(&secret);
+        token.capabilities.custom = vec!["gpg".to_string()];
+        let payload = claims(&token);
+        let at = payload.windows(5).position(|w| w == [0x81, 0x63, b'g', b'p', b'g']).unwrap();
+        let mut duplicated = payload[..at].to_vec();
+        duplicated.extend_from_slice(&[0x82, 0x63, b'g', b'p', b'g', 0x63, b'g', b'p', b'g']);
+        duplicated.extend_from_slice(&payload[at + 5..]);
+        assert!(parse_claims(&duplicated).is_ok());
+        assert!(SessionToken::from_cwt(&sign1(&payload)).is_ok());
+        assert!(SessionToken::from_cwt(&sign1(&duplicated)).is_err());
+    }
+
+    fn sign1(payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        head(&mut out, MAJOR_TAG, COSE_SIGN1_TAG);
+        head(&mut out, MAJOR_ARRAY, 4);
+        bstr(&mut out, &protected_header());
+        head(&mut out, MAJOR_MAP, 0);
+        bstr(&mut out, payload);
+        bstr(&mut out, &[0u8; 64]);
+        out
+    }
+
+    #[test]
+    fn test_token_decoded_from_sig_structure() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let token = issued(&secret);
+        let signing_data = token.to_signing_data();
+
+        let decoded = token_from_sig_structure(&signing_data).unwrap();
+        assert_eq!((decoded.token_id, decoded.device_id), (token.token_id, token.device_id));
+        assert_eq!(decoded.ephemeral_public, token.ephemeral_public);
+        assert_eq!(decoded.to_signing_data(), signing_data);
+
+        // A COSE_Sign1, trailing bytes, or external AAD are not token bodies
+        assert!(token_from_sig_structure(&token.to_cwt().unwrap()).is_err());
+        let mut trailing = signing_data.clone();
+        trailing.push(0x00);
+        assert!(token_from_sig_structure(&trailing).is_err());
+        let mut with_aad = Vec::new();
+        head(&mut with_aad, MAJOR_ARRAY, 4);
+        tstr(&mut with_aad, "Signature1");
+        bstr(&mut with_aad, &protected_header());
+        bstr(&mut with_aad, b"aad");
+        bstr(&mut with_aad, &claims(&token));
+        assert!(token_from_sig_structure(&with_aad).is_err());
+    }
+
+    #[test]
+    fn test_attenuated_token_has_no_cwt() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let token = issued(&secret);
+        let attenuated = token.attenuate(Capabilities::default(), None).unwrap();
+        assert!(attenuated.to_cwt().is_err());
+    }
+}
@@ -23,13 +23,22 @@
 //! - ✓ Manufacturing process is open source and auditable
 
 use crate::types::*;
-use crate::dkg::DkgCoordinator;
+use crate::signing::compute_lagrange_coefficient;
+use crate::merkle::{self, SignedTreeHead, WitnessPolicy};
+use crate::nonce::{hedged_nonce, DefaultEntropy, EntropySource};
 use crate::{FrostError, FrostResult};
 use serde::{Serialize, Deserialize};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
-use sha2::{Sha512, Digest};
+use curve25519_dalek::traits::Identity;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305, XNonce, KeyInit, Nonce};
+use chacha20poly1305::aead::{Aead, Payload};
+use hkdf::Hkdf;
+use rand_core::{RngCore, CryptoRng};
+use sha2::{Sha256, Sha512, Digest};
+use std::collections::{HashMap, HashSet};
 
 /// Device master key derived from FROST DKG
 #[derive(Zeroize, ZeroizeOnDrop)]
@@ -37,14 +46,15 @@ pub struct DerivedDeviceKey {
     /// Device unique identifier (from PUF + TRNG)
     device_id: [u8; 32],
 
-    /// Master secret key (derived from FROST DKG)
-    #[zeroize(skip)]
+    /// Master secret key (threshold PRF output, never the group secret)
     master_secret: SecretScalar,
 
     /// Public key (for verification)
+    #[zeroize(skip)]
     pub master_public: GroupPublicKey,
 
     /// Derivation proof (proves this key came from FROST ceremony)
+    #[zeroize(skip)]
     pub derivation_proof: DerivationProof,
 
     /// Re-key version (increments on each re-key)
@@ -60,11 +70,14 @@ pub struct DerivationProof {
     /// Hash of DKG transcript
     pub dkg_transcript_hash: [u8; 32],
 
+    /// Index of the derivation statement in the transparency log
+    pub log_index: u64,
+
     /// Merkle proof of inclusion in transparency log
     pub merkle_proof: Vec<[u8; 32]>,
 
-    /// Signatures from all 3 FROST participants
-    pub participant_signatures: Vec<SchnorrSignature>,
+    /// Signatures from the FROST participants over the derivation statement
+    pub participant_signatures: Vec<ParticipantSignature>,
 
     /// Device attestation at time of derivation
     pub device_attestation: DeviceAttestation,
@@ -86,219 +99,1032 @@ pub struct DeviceAttestation {
     pub boot_measurements: Vec<[u8; 32]>,
 }
 
-impl DerivedDeviceKey {
-    /// Derive device key from FROST DKG ceremony
+/// Statement the FROST participants sign once a device key is derived
+///
+/// Binds the device to its public key, key version, firmware and the DKG
+/// ceremony whose shares evaluated the PRF.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivationStatement {
+    /// Device unique identifier
+    pub device_id: [u8; 32],
+    /// Derived device public key
+    pub public_key: CompressedRistretto,
+    /// Key version
+    pub version: u32,
+    /// Firmware hash from the device attestation
+    pub firmware_hash: [u8; 32],
+    /// Hash of the DKG transcript that produced the participants' shares
+    pub dkg_transcript_hash: [u8; 32],
+}
+
+impl DerivationStatement {
+    /// Canonical encoding that participants sign
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(29 + 32 * 4 + 4);
+        bytes.extend_from_slice(b"FROST-DERIVATION-STATEMENT-v1");
+        bytes.extend_from_slice(&self.device_id);
+        bytes.extend_from_slice(self.public_key.as_bytes());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.firmware_hash);
+        bytes.extend_from_slice(&self.dkg_transcript_hash);
+        bytes
+    }
+
+    /// Parse the encoding produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(&b"FROST-DERIVATION-STATEMENT-v1"[..])?;
+        if rest.len() != 32 * 4 + 4 {
+            return None;
+        }
+        let bytes32 = |offset: usize| -> [u8; 32] { rest[offset..offset + 32].try_into().expect("32 bytes") };
+        Some(DerivationStatement {
+            device_id: bytes32(0),
+            public_key: CompressedRistretto(bytes32(32)),
+            version: u32::from_le_bytes(rest[64..68].try_into().expect("4 bytes")),
+            firmware_hash: bytes32(68),
+            dkg_transcript_hash: bytes32(100),
+        })
+    }
+
+    /// Transparency log leaf hash for this statement
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        merkle::leaf_hash(&self.to_bytes())
+    }
+
+    /// Message signed by a `PossessionProof` for this statement
     ///
-    /// This happens once during manufacturing:
-    /// 1. Device generates local randomness (PUF + TRNG)
-    /// 2. Initiates DKG with 3 remote FROST participants
-    /// 3. DKG produces group secret (2-of-3 threshold)
-    /// 4. Device derives its key from group secret + device ID
-    /// 5. Remote participants sign derivation proof
-    /// 6. Device stores key locally (encrypted to PUF)
-    /// 7. Public key logged to transparency tree
-    pub fn derive_from_dkg(
-        device_id: [u8; 32],
-        dkg_output: &crate::dkg::DkgOutput,
-        participant_signatures: Vec<SchnorrSignature>,
-        device_attestation: DeviceAttestation,
+    /// Binds the statement to the transport key the partial evaluations were
+    /// encrypted to, so the proof only answers the derivation it came from.
+    pub fn possession_message(&self, transport_public: &CompressedRistretto) -> Vec<u8> {
+        let statement = self.to_bytes();
+        let mut message = Vec::with_capacity(30 + statement.len() + 32);
+        message.extend_from_slice(b"FROST-DERIVATION-POSSESSION-v1");
+        message.extend_from_slice(&statement);
+        message.extend_from_slice(transport_public.as_bytes());
+        message
+    }
+}
+
+/// What a verifier trusts when checking a derivation proof
+#[derive(Debug, Clone)]
+pub struct DerivationPolicy {
+    /// Group public key of the ceremony whose participants sign statements
+    pub ceremony_key: GroupPublicKey,
+    /// Latest tree head the verifier accepts
+    pub tree_head: SignedTreeHead,
+    /// Key that signs tree heads
+    pub log_public_key: CompressedRistretto,
+    /// Witnesses that must have cosigned the tree head
+    pub witnesses: WitnessPolicy,
+    /// Firmware hashes of approved reproducible builds
+    pub allowed_firmware: Vec<[u8; 32]>,
+}
+
+/// A single failed derivation proof check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DerivationCheckFailure {
+    /// Signature does not verify under the participant's verification share
+    InvalidParticipantSignature(ParticipantId),
+    /// Signer is not a participant of the ceremony
+    UnknownParticipant(ParticipantId),
+    /// Participant signed more than once
+    DuplicateParticipant(ParticipantId),
+    /// Fewer valid participant signatures than the ceremony threshold
+    InsufficientSignatures {
+        /// Valid signatures found
+        valid: usize,
+        /// Ceremony threshold
+        required: u32,
+    },
+    /// Tree head is not signed by the log key
+    InvalidTreeHeadSignature,
+    /// Fewer trusted witnesses cosigned the tree head than the policy requires
+    InsufficientCosignatures {
+        /// Valid cosignatures from distinct trusted witnesses
+        valid: usize,
+        /// Witness threshold
+        required: usize,
+    },
+    /// Statement is not included in the tree at the claimed index
+    InclusionProofInvalid {
+        /// Claimed log index
+        log_index: u64,
+        /// Size of the tree head checked against
+        tree_size: u64,
+    },
+    /// Firmware hash is not on the allow-list
+    FirmwareNotAllowed([u8; 32]),
+    /// Tamper sensors reported an event at derivation time
+    TamperDetected(u8),
+    /// Attested hardware ID differs from the device ID
+    HardwareIdMismatch,
+}
+
+impl std::fmt::Display for DerivationCheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidParticipantSignature(id) => {
+                write!(f, "signature from participant {} is invalid", id.as_u32())
+            }
+            Self::UnknownParticipant(id) => {
+                write!(f, "participant {} is not part of the ceremony", id.as_u32())
+            }
+            Self::DuplicateParticipant(id) => {
+                write!(f, "participant {} signed more than once", id.as_u32())
+            }
+            Self::InsufficientSignatures { valid, required } => {
+                write!(f, "{} valid participant signatures, need {}", valid, required)
+            }
+            Self::InvalidTreeHeadSignature => write!(f, "tree head signature is invalid"),
+            Self::InsufficientCosignatures { valid, required } => {
+                write!(f, "{} valid witness cosignatures, need {}", valid, required)
+            }
+            Self::InclusionProofInvalid { log_index, tree_size } => {
+                write!(f, "not included at index {} of tree size {}", log_index, tree_size)
+            }
+            Self::FirmwareNotAllowed(hash) => {
+                write!(f, "firmware hash {:02x?} is not allowed", &hash[..8])
+            }
+            Self::TamperDetected(status) => write!(f, "tamper status {} at derivation", status),
+            Self::HardwareIdMismatch => write!(f, "attested hardware ID does not match device ID"),
+        }
+    }
+}
+
+/// Result of checking a derivation proof: every check that failed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivationVerdict {
+    /// Failed checks (empty if the proof is valid)
+    pub failures: Vec<DerivationCheckFailure>,
+}
+
+impl DerivationVerdict {
+    /// True if every check passed
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl DerivationProof {
+    /// Check this proof for the given statement
+    ///
+    /// Does not stop at the first failure, so the verdict explains every
+    /// reason a device would be rejected.
+    pub fn verify(&self, statement: &DerivationStatement, policy: &DerivationPolicy) -> DerivationVerdict {
+        let mut failures = Vec::new();
+
+        // 1. Participant signatures over the canonical statement
+        let statement_bytes = statement.to_bytes();
+        let mut signers = HashSet::new();
+        let mut valid = 0usize;
+        for signature in &self.participant_signatures {
+            let participant_id = signature.participant_id;
+            if !signers.insert(participant_id) {
+                failures.push(DerivationCheckFailure::DuplicateParticipant(participant_id));
+                continue;
+            }
+
+            match verification_share(&policy.ceremony_key, participant_id) {
+                Ok(y_i) if signature.signature.verify(SignatureScheme::Schnorr, &statement_bytes, &y_i.compress()) => valid += 1,
+                Ok(_) => failures.push(DerivationCheckFailure::InvalidParticipantSignature(participant_id)),
+                Err(_) => failures.push(DerivationCheckFailure::UnknownParticipant(participant_id)),
+            }
+        }
+        if valid < policy.ceremony_key.threshold as usize {
+            failures.push(DerivationCheckFailure::InsufficientSignatures {
+                valid,
+                required: policy.ceremony_key.threshold,
+            });
+        }
+
+        // 2. Inclusion under a signed tree head
+        if !policy.tree_head.verify(&policy.log_public_key) {
+            failures.push(DerivationCheckFailure::InvalidTreeHeadSignature);
+        }
+        let cosigned = policy.witnesses.count_cosignatures(&policy.log_public_key, &policy.tree_head);
+        if cosigned < policy.witnesses.threshold {
+            failures.push(DerivationCheckFailure::InsufficientCosignatures {
+                valid: cosigned,
+                required: policy.witnesses.threshold,
+            });
+        }
+        if !policy.tree_head.verify_inclusion(&statement.leaf_hash(), self.log_index, &self.merkle_proof) {
+            failures.push(DerivationCheckFailure::InclusionProofInvalid {
+                log_index: self.log_index,
+                tree_size: policy.tree_head.tree_size,
+            });
+        }
+
+        // 3. Device attestation at derivation time
+        let attestation = &self.device_attestation;
+        if attestation.firmware_hash != statement.firmware_hash
+            || !policy.allowed_firmware.contains(&attestation.firmware_hash)
+        {
+            failures.push(DerivationCheckFailure::FirmwareNotAllowed(attestation.firmware_hash));
+        }
+        if attestation.tamper_status != 0 {
+            failures.push(DerivationCheckFailure::TamperDetected(attestation.tamper_status));
+        }
+        if attestation.hardware_id != statement.device_id {
+            failures.push(DerivationCheckFailure::HardwareIdMismatch);
+        }
+
+        DerivationVerdict { failures }
+    }
+}
+
+/// Signature by one FROST participant, made with its secret share
+///
+/// Verifies against the participant's verification share in the
+/// ceremony's `GroupPublicKey`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticipantSignature {
+    /// Signing participant
+    pub participant_id: ParticipantId,
+    /// Schnorr signature over `DerivationStatement::to_bytes`
+    pub signature: SchnorrSignature,
+}
+
+/// Device's proof that it completed a derivation and holds the derived key
+///
+/// Both signatures cover `DerivationStatement::possession_message`: one by
+/// the derivation's transport key, which only the device that decrypted the
+/// partial evaluations holds, and one by the statement's public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PossessionProof {
+    /// Signature by the derivation's transport key
+    pub transport_signature: SchnorrSignature,
+    /// Signature by the derived device key
+    pub key_signature: SchnorrSignature,
+}
+
+/// Device → participants: request a partial PRF evaluation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivationRequest {
+    /// Device unique identifier (PRF input)
+    pub device_id: [u8; 32],
+    /// Key version being derived (PRF input)
+    pub version: u32,
+    /// Device's ephemeral key for the encrypted return channel
+    pub transport_public: CompressedRistretto,
+    /// Device attestation, checked by each participant before evaluating
+    pub device_attestation: DeviceAttestation,
+}
+
+/// Participant → device: partial PRF evaluation, encrypted to the device
+///
+/// The plaintext is `E_i || c || z`, where `E_i = s_i * H(device_id, version)`
+/// and `(c, z)` is a Chaum-Pedersen proof that `E_i` uses the same share as
+/// the participant's verification share `Y_i = s_i * G`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedPartialEvaluation {
+    /// Evaluating participant
+    pub participant_id: ParticipantId,
+    /// Participant's ephemeral channel key
+    pub ephemeral_public: CompressedRistretto,
+    /// ChaCha20-Poly1305 ciphertext and tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// Decrypted partial evaluation with its DLEQ proof
+struct PartialEvaluation {
+    participant_id: ParticipantId,
+    evaluation: RistrettoPoint,
+    challenge: Scalar,
+    response: Scalar,
+}
+
+const PARTIAL_EVALUATION_LEN: usize = 96;
+
+impl PartialEvaluation {
+    fn to_bytes(&self) -> [u8; PARTIAL_EVALUATION_LEN] {
+        let mut bytes = [0u8; PARTIAL_EVALUATION_LEN];
+        bytes[..32].copy_from_slice(self.evaluation.compress().as_bytes());
+        bytes[32..64].copy_from_slice(self.challenge.as_bytes());
+        bytes[64..].copy_from_slice(self.response.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(participant_id: ParticipantId, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PARTIAL_EVALUATION_LEN {
+            return None;
+        }
+
+        let evaluation = CompressedRistretto::from_slice(&bytes[..32]).ok()?.decompress()?;
+        let challenge = Option::from(Scalar::from_canonical_bytes(bytes[32..64].try_into().ok()?))?;
+        let response = Option::from(Scalar::from_canonical_bytes(bytes[64..].try_into().ok()?))?;
+
+        Some(PartialEvaluation { participant_id, evaluation, challenge, response })
+    }
+
+    /// Check z*G == A1 + c*Y_i and z*P == A2 + c*E_i by recomputing c
+    fn verify(&self, verification_share: &RistrettoPoint, prf_input: &RistrettoPoint) -> bool {
+        let a1 = self.response * RISTRETTO_BASEPOINT_POINT - self.challenge * verification_share;
+        let a2 = self.response * prf_input - self.challenge * self.evaluation;
+
+        let expected = dleq_challenge(verification_share, prf_input, &self.evaluation, &a1, &a2);
+        expected == self.challenge
+    }
+}
+
+/// PRF input point H(device_id, version)
+fn prf_input(device_id: &[u8; 32], version: u32) -> RistrettoPoint {
+    let mut input = Vec::with_capacity(23 + 32 + 4);
+    input.extend_from_slice(b"FROST-DEVICE-KEY-PRF-v1");
+    input.extend_from_slice(device_id);
+    input.extend_from_slice(&version.to_le_bytes());
+    RistrettoPoint::hash_from_bytes::<Sha512>(&input)
+}
+
+/// Fiat-Shamir challenge for the Chaum-Pedersen DLEQ proof
+fn dleq_challenge(
+    verification_share: &RistrettoPoint,
+    prf_input: &RistrettoPoint,
+    evaluation: &RistrettoPoint,
+    a1: &RistrettoPoint,
+    a2: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"FROST-DEVICE-KEY-DLEQ-v1");
+    for point in [verification_share, prf_input, evaluation, a1, a2] {
+        hasher.update(point.compress().as_bytes());
+    }
+    Scalar::from_hash(hasher)
+}
+
+/// Channel cipher and associated data for one participant → device message
+fn channel_cipher(
+    shared_secret: &RistrettoPoint,
+    ephemeral_public: &CompressedRistretto,
+    participant_id: ParticipantId,
+    device_id: &[u8; 32],
+    version: u32,
+) -> (ChaCha20Poly1305, Vec<u8>) {
+    let mut context = Vec::with_capacity(32 + 4 + 32 + 4);
+    context.extend_from_slice(device_id);
+    context.extend_from_slice(&version.to_le_bytes());
+    context.extend_from_slice(ephemeral_public.as_bytes());
+    context.extend_from_slice(&participant_id.as_u32().to_le_bytes());
+
+    let hkdf = Hkdf::<Sha256>::new(
+        Some(b"FROST-DERIVATION-CHANNEL-v1"),
+        shared_secret.compress().as_bytes(),
+    );
+    let mut key = [0u8; 32];
+    hkdf.expand(&context, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    key.zeroize();
+    (cipher, context)
+}
+
+/// Find participant i's verification share Y_i
+fn verification_share(
+    group_public_key: &GroupPublicKey,
+    participant_id: ParticipantId,
+) -> FrostResult<RistrettoPoint> {
+    group_public_key.participant_shares.iter()
+        .find(|s| s.participant_id == participant_id)
+        .and_then(|s| s.public_key.decompress())
+        .ok_or(FrostError::InvalidParticipantIndex(participant_id.as_u32()))
+}
+
+/// Evaluation a participant has served and not yet signed a statement for
+struct EvaluatedDerivation {
+    /// Device transport key the partial evaluation was encrypted to
+    transport_public: CompressedRistretto,
+    /// Firmware hash from the attestation the participant checked
+    firmware_hash: [u8; 32],
+}
+
+/// FROST participant's side of device key derivation
+///
+/// Holds one DKG share. The share only ever evaluates the PRF on the
+/// requesting device's input; neither it nor the group secret leaves here.
+/// Each evaluation is remembered until the device returns its statement, and
+/// a statement is only signed once, for a derivation this participant served.
+pub struct DerivationParticipant {
+    share: SecretShare,
+    verification_share: RistrettoPoint,
+    dkg_transcript_hash: [u8; 32],
+    evaluated: HashMap<([u8; 32], u32), EvaluatedDerivation>,
+}
+
+impl DerivationParticipant {
+    /// Create from this participant's DKG output
+    pub fn new(
+        share: SecretShare,
+        group_public_key: &GroupPublicKey,
+        dkg_transcript_hash: [u8; 32],
     ) -> FrostResult<Self> {
-        // Use FROST group secret as key derivation seed
-        let group_secret = dkg_output.secret_share.value.as_scalar();
+        let verification_share = verification_share(group_public_key, share.participant_id)?;
 
-        // Derive device-specific key: KDF(group_secret, device_id)
-        let master_secret = Self::kdf(group_secret, &device_id);
+        if share.value.as_scalar() * RISTRETTO_BASEPOINT_POINT != verification_share {
+            return Err(FrostError::InvalidSignatureShare(share.participant_id.as_u32()));
+        }
 
-        // Compute public key
-        let master_public_point = master_secret * RISTRETTO_BASEPOINT_POINT;
-        let master_public = GroupPublicKey {
-            public_key: master_public_point.compress(),
+        Ok(DerivationParticipant {
+            share,
+            verification_share,
+            dkg_transcript_hash,
+            evaluated: HashMap::new(),
+        })
+    }
+
+    /// Participant ID
+    pub fn participant_id(&self) -> ParticipantId {
+        self.share.participant_id
+    }
+
+    /// Evaluate the PRF on the device's input and encrypt the result to it
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &mut self,
+        request: &DerivationRequest,
+        rng: &mut R,
+    ) -> FrostResult<EncryptedPartialEvaluation> {
+        let attestation = &request.device_attestation;
+        if attestation.tamper_status != 0 || attestation.hardware_id != request.device_id {
+            return Err(FrostError::CryptoError("Device attestation rejected".to_string()));
+        }
+
+        let transport_public = request.transport_public.decompress()
+            .ok_or(FrostError::CryptoError("Invalid device transport key".to_string()))?;
+
+        let secret = self.share.value.as_scalar();
+        let input = prf_input(&request.device_id, request.version);
+        let evaluation = secret * input;
+
+        // Chaum-Pedersen proof that log_G(Y_i) == log_P(E_i)
+        let k = SecretScalar::new(Scalar::random(rng));
+        let a1 = k.as_scalar() * RISTRETTO_BASEPOINT_POINT;
+        let a2 = k.as_scalar() * input;
+        let challenge = dleq_challenge(&self.verification_share, &input, &evaluation, &a1, &a2);
+        let response = k.as_scalar() + challenge * secret;
+
+        let partial = PartialEvaluation {
+            participant_id: self.share.participant_id,
+            evaluation,
+            challenge,
+            response,
         };
 
-        // Create derivation proof
+        // Encrypt to the device under an ephemeral DH key
+        let ephemeral = SecretScalar::new(Scalar::random(rng));
+        let ephemeral_public = (ephemeral.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        let shared_secret = ephemeral.as_scalar() * transport_public;
+
+        let (cipher, aad) = channel_cipher(
+            &shared_secret,
+            &ephemeral_public,
+            self.share.participant_id,
+            &request.device_id,
+            request.version,
+        );
+        let mut plaintext = partial.to_bytes();
+        let ciphertext = cipher
+            .encrypt(&Nonce::default(), Payload { msg: &plaintext, aad: &aad })
+            .map_err(|_| FrostError::CryptoError("Partial evaluation encryption failed".to_string()))?;
+        plaintext.zeroize();
+
+        self.evaluated.insert(
+            (request.device_id, request.version),
+            EvaluatedDerivation {
+                transport_public: request.transport_public,
+                firmware_hash: attestation.firmware_hash,
+            },
+        );
+
+        Ok(EncryptedPartialEvaluation {
+            participant_id: self.share.participant_id,
+            ephemeral_public,
+            ciphertext,
+        })
+    }
+
+    /// Sign the device's derivation statement with this participant's share
+    ///
+    /// The statement must name a derivation this participant evaluated, with
+    /// the firmware it attested, and come with a `PossessionProof` from that
+    /// derivation's transport key and the statement's public key. The
+    /// derivation is then forgotten, so each evaluation yields at most one
+    /// signed statement.
+    pub fn sign_statement<R: RngCore + CryptoRng>(
+        &mut self,
+        statement: &DerivationStatement,
+        proof: &PossessionProof,
+        rng: &mut R,
+    ) -> FrostResult<ParticipantSignature> {
+        if statement.dkg_transcript_hash != self.dkg_transcript_hash {
+            return Err(FrostError::CryptoError("Statement names a different DKG transcript".to_string()));
+        }
+
+        let key = (statement.device_id, statement.version);
+        let evaluated = self.evaluated.get(&key)
+            .ok_or(FrostError::CryptoError("Statement names no derivation this participant evaluated".to_string()))?;
+        if statement.firmware_hash != evaluated.firmware_hash {
+            return Err(FrostError::CryptoError("Statement firmware differs from the attested firmware".to_string()));
+        }
+        let possession = statement.possession_message(&evaluated.transport_public);
+        if !proof.transport_signature.verify(SignatureScheme::Schnorr, &possession, &evaluated.transport_public)
+            || !proof.key_signature.verify(SignatureScheme::Derived, &possession, &statement.public_key)
+        {
+            return Err(FrostError::CryptoError("Proof of possession of the derived key failed".to_string()));
+        }
+        self.evaluated.remove(&key);
+
+        let nonce = SecretScalar::new(Scalar::random(rng));
+        let signature = SchnorrSignature::sign_with_nonce(
+            self.share.value.as_scalar(),
+            nonce.as_scalar(),
+            &statement.to_bytes(),
+        );
+
+        Ok(ParticipantSignature {
+            participant_id: self.share.participant_id,
+            signature,
+        })
+    }
+}
+
+/// Device's side of device key derivation
+///
+/// Holds the ephemeral key that partial evaluations are encrypted to.
+pub struct DerivationSession {
+    device_id: [u8; 32],
+    version: u32,
+    device_attestation: DeviceAttestation,
+    transport_secret: SecretScalar,
+}
+
+impl DerivationSession {
+    /// Start deriving key `version` for this device
+    pub fn new<R: RngCore + CryptoRng>(
+        device_id: [u8; 32],
+        version: u32,
+        device_attestation: DeviceAttestation,
+        rng: &mut R,
+    ) -> Self {
+        DerivationSession {
+            device_id,
+            version,
+            device_attestation,
+            transport_secret: SecretScalar::new(Scalar::random(rng)),
+        }
+    }
+
+    /// Request to broadcast to the FROST participants
+    pub fn request(&self) -> DerivationRequest {
+        DerivationRequest {
+            device_id: self.device_id,
+            version: self.version,
+            transport_public: (self.transport_secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress(),
+            device_attestation: self.device_attestation.clone(),
+        }
+    }
+
+    /// Combine at least `t` verified partial evaluations into the device key
+    ///
+    /// E = Σ λ_i * E_i = s * H(device_id, version), so the device learns the
+    /// PRF output without anyone reconstructing s.
+    pub fn combine(
+        &self,
+        group_public_key: &GroupPublicKey,
+        dkg_transcript_hash: [u8; 32],
+        partials: &[EncryptedPartialEvaluation],
+    ) -> FrostResult<DerivedDeviceKey> {
+        let input = prf_input(&self.device_id, self.version);
+        let mut seen = HashSet::new();
+        let mut evaluations = Vec::with_capacity(partials.len());
+
+        for encrypted in partials {
+            let participant_id = encrypted.participant_id;
+            if !seen.insert(participant_id) {
+                return Err(FrostError::AggregationFailed);
+            }
+
+            let y_i = verification_share(group_public_key, participant_id)?;
+            let ephemeral = encrypted.ephemeral_public.decompress()
+                .ok_or(FrostError::InvalidSignatureShare(participant_id.as_u32()))?;
+            let shared_secret = self.transport_secret.as_scalar() * ephemeral;
+
+            let (cipher, aad) = channel_cipher(
+                &shared_secret,
+                &encrypted.ephemeral_public,
+                participant_id,
+                &self.device_id,
+                self.version,
+            );
+            let mut plaintext = cipher
+                .decrypt(&Nonce::default(), Payload { msg: &encrypted.ciphertext, aad: &aad })
+                .map_err(|_| FrostError::InvalidSignatureShare(participant_id.as_u32()))?;
+            let partial = PartialEvaluation::from_bytes(participant_id, &plaintext);
+            plaintext.zeroize();
+
+            let partial = partial
+                .filter(|p| p.verify(&y_i, &input))
+                .ok_or(FrostError::InvalidSignatureShare(participant_id.as_u32()))?;
+            evaluations.push(partial);
+        }
+
+        if evaluations.len() < group_public_key.threshold as usize {
+            return Err(FrostError::InsufficientParticipants(
+                evaluations.len(),
+                group_public_key.threshold,
+            ));
+        }
+
+        let participants: Vec<_> = evaluations.iter().map(|e| e.participant_id).collect();
+        let mut prf_output = RistrettoPoint::identity();
+        for evaluation in &evaluations {
+            let lambda = compute_lagrange_coefficient(evaluation.participant_id, &participants);
+            prf_output += lambda * evaluation.evaluation;
+        }
+
+        let master_secret = DerivedDeviceKey::kdf(&prf_output.compress(), &self.device_id, self.version);
+
         let derivation_proof = DerivationProof {
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            dkg_transcript_hash: [0u8; 32], // TODO: actual transcript hash
-            merkle_proof: Vec::new(),       // TODO: actual Merkle proof
-            participant_signatures,
-            device_attestation,
+            dkg_transcript_hash,
+            log_index: 0,
+            merkle_proof: Vec::new(),  // Filled in once the key is logged
+            participant_signatures: Vec::new(),
+            device_attestation: self.device_attestation.clone(),
+        };
+
+        Ok(DerivedDeviceKey::from_secret(
+            self.device_id,
+            master_secret,
+            derivation_proof,
+            self.version,
+        ))
+    }
+
+    /// Prove to the participants that this session produced `device_key`
+    ///
+    /// Participants require the proof before signing the key's
+    /// `derivation_statement`.
+    pub fn prove_possession<R: RngCore + CryptoRng>(
+        &self,
+        device_key: &DerivedDeviceKey,
+        rng: &mut R,
+    ) -> FrostResult<PossessionProof> {
+        if device_key.device_id != self.device_id || device_key.version != self.version {
+            return Err(FrostError::CryptoError("Key was not derived in this session".to_string()));
+        }
+
+        let transport_public = (self.transport_secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        let message = device_key.derivation_statement().possession_message(&transport_public);
+        let nonce = SecretScalar::new(Scalar::random(rng));
+        Ok(PossessionProof {
+            transport_signature: SchnorrSignature::sign_with_nonce(
+                self.transport_secret.as_scalar(),
+                nonce.as_scalar(),
+                &message,
+            ),
+            key_signature: device_key.sign(&message)?,
+        })
+    }
+}
+
+impl DerivedDeviceKey {
+    /// Assemble a key from its secret; the public key is recomputed
+    pub(crate) fn from_secret(
+        device_id: [u8; 32],
+        master_secret: Scalar,
+        derivation_proof: DerivationProof,
+        version: u32,
+    ) -> Self {
+        let master_public_point = master_secret * RISTRETTO_BASEPOINT_POINT;
+        let master_public = GroupPublicKey {
+            public_key: master_public_point.compress(),
+            participant_shares: Vec::new(),
+            threshold: 1,
+            num_participants: 1,
         };
 
-        Ok(DerivedDeviceKey {
+        DerivedDeviceKey {
             device_id,
             master_secret: SecretScalar::new(master_secret),
             master_public,
             derivation_proof,
-            version: 1,
-        })
+            version,
+        }
     }
 
-    /// Key derivation function: KDF(group_secret, device_id) -> device_key
-    fn kdf(group_secret: &Scalar, device_id: &[u8; 32]) -> Scalar {
+    /// Key derivation function: KDF(prf_output, device_id, version) -> device_key
+    fn kdf(prf_output: &CompressedRistretto, device_id: &[u8; 32], version: u32) -> Scalar {
         let mut hasher = Sha512::new();
-        hasher.update(b"FROST-DEVICE-KEY-DERIVATION-v1");
-        hasher.update(group_secret.as_bytes());
+        hasher.update(b"FROST-DEVICE-KEY-DERIVATION-v2");
+        hasher.update(prf_output.as_bytes());
         hasher.update(device_id);
+        hasher.update(version.to_le_bytes());
         Scalar::from_hash(hasher)
     }
 
+    /// HKDF-SHA512 expansion of the device key, used for the subkey hierarchy
+    pub(crate) fn expand_subkey_material(&self, info: &[u8], okm: &mut [u8]) {
+        let hkdf = Hkdf::<Sha512>::new(Some(b"FROST-DEVICE-SUBKEY-v1"), self.master_secret.as_scalar().as_bytes());
+        hkdf.expand(info, okm)
+            .expect("subkey material length is a valid HKDF-SHA512 output length");
+    }
+
+    /// Device unique identifier
+    pub fn device_id(&self) -> &[u8; 32] {
+        &self.device_id
+    }
+
+    /// Statement the FROST participants sign for this key
+    pub fn derivation_statement(&self) -> DerivationStatement {
+        DerivationStatement {
+            device_id: self.device_id,
+            public_key: self.master_public.public_key,
+            version: self.version,
+            firmware_hash: self.derivation_proof.device_attestation.firmware_hash,
+            dkg_transcript_hash: self.derivation_proof.dkg_transcript_hash,
+        }
+    }
+
+    /// Attach participant signatures over `derivation_statement`
+    ///
+    /// Each signature is checked against the signer's verification share in
+    /// the ceremony's group public key before it is stored.
+    pub fn attach_participant_signatures(
+        &mut self,
+        group_public_key: &GroupPublicKey,
+        signatures: Vec<ParticipantSignature>,
+    ) -> FrostResult<()> {
+        let statement = self.derivation_statement().to_bytes();
+
+        for signature in &signatures {
+            let y_i = verification_share(group_public_key, signature.participant_id)?;
+            if !signature.signature.verify(SignatureScheme::Schnorr, &statement, &y_i.compress()) {
+                return Err(FrostError::InvalidSignatureShare(signature.participant_id.as_u32()));
+            }
+        }
+
+        self.derivation_proof.participant_signatures.extend(signatures);
+        Ok(())
+    }
+
     /// Sign a message with the derived device key (fully offline)
+    ///
+    /// Nonce randomness comes from `DefaultEntropy`, the secure element TRNG
+    /// once the hardware layer has installed it.
     pub fn sign(&self, message: &[u8]) -> FrostResult<SchnorrSignature> {
+        self.sign_with_entropy(message, &DefaultEntropy)
+    }
+
+    /// Sign with a hedged nonce drawing fresh randomness from `entropy`
+    pub fn sign_with_entropy(&self, message: &[u8], entropy: &dyn EntropySource) -> FrostResult<SchnorrSignature> {
         // Standard Schnorr signature (non-threshold)
-        let mut rng = rand::thread_rng();
-        let nonce = Scalar::random(&mut rng);
-        let r = nonce * RISTRETTO_BASEPOINT_POINT;
+        let nonce = hedged_nonce(
+            b"derived-device-key",
+            self.master_secret.as_scalar(),
+            &self.master_public.public_key,
+            message,
+            entropy,
+        )?;
+        Ok(SchnorrSignature::sign_with_scheme(
+            SignatureScheme::Derived,
+            self.master_secret.as_scalar(),
+            nonce.as_scalar(),
+            message,
+        ))
+    }
 
-        // Challenge: H(R || PK || message)
-        let mut hasher = Sha512::new();
-        hasher.update(b"FROST-DERIVED-SIGNATURE-v1");
-        hasher.update(r.compress().as_bytes());
-        hasher.update(self.master_public.public_key.as_bytes());
-        hasher.update(message);
-        let challenge = Scalar::from_hash(hasher);
-
-        // Response: z = nonce + challenge * secret
-        let z = nonce + challenge * self.master_secret.as_scalar();
-
-        Ok(SchnorrSignature {
-            z: z.to_bytes(),
-            commitment: r.compress(),
-        })
+    /// Record where the derivation statement landed in the transparency log
+    pub fn attach_inclusion_proof(&mut self, log_index: u64, merkle_proof: Vec<[u8; 32]>) {
+        self.derivation_proof.log_index = log_index;
+        self.derivation_proof.merkle_proof = merkle_proof;
     }
 
     /// Verify derivation proof (ensures key came from FROST ceremony)
-    pub fn verify_derivation_proof(&self) -> bool {
-        // In production:
-        // 1. Verify participant signatures on derivation event
-        // 2. Verify Merkle proof against transparency log root
-        // 3. Verify device attestation was valid at derivation time
-        // 4. Verify timestamp is reasonable
+    pub fn verify_derivation_proof(&self, policy: &DerivationPolicy) -> DerivationVerdict {
+        self.derivation_proof.verify(&self.derivation_statement(), policy)
+    }
 
-        // For now, just check we have required components
-        self.derivation_proof.participant_signatures.len() >= 2
+    /// Start a re-key derivation for the next key version
+    pub fn begin_rekey<R: RngCore + CryptoRng>(
+        &self,
+        device_attestation: DeviceAttestation,
+        rng: &mut R,
+    ) -> DerivationSession {
+        DerivationSession::new(self.device_id, self.version + 1, device_attestation, rng)
     }
 
     /// Re-key this device (requires FROST threshold again)
     ///
     /// Use case: Device compromised, need to rotate key
     /// Process:
-    /// 1. Device initiates re-key request to FROST participants
+    /// 1. Device starts a `begin_rekey` session with the FROST participants
     /// 2. Participants verify device identity + re-key authorization
-    /// 3. Run new DKG to derive fresh key
+    /// 3. Participants evaluate the PRF for the next version
     /// 4. Device zeroizes old key, adopts new key
-    /// 5. Increment version number
-    pub fn rekey(
-        &mut self,
-        new_dkg_output: &crate::dkg::DkgOutput,
-        participant_signatures: Vec<SchnorrSignature>,
-        device_attestation: DeviceAttestation,
-    ) -> FrostResult<()> {
-        // Derive new key
-        let new_key = Self::derive_from_dkg(
-            self.device_id,
-            new_dkg_output,
-            participant_signatures,
-            device_attestation,
-        )?;
+    pub fn rekey(&mut self, new_key: DerivedDeviceKey) -> FrostResult<()> {
+        if new_key.device_id != self.device_id {
+            return Err(FrostError::CryptoError("Re-key for a different device".to_string()));
+        }
 
-        // Zeroize old key
-        self.master_secret.zeroize();
+        if new_key.version <= self.version {
+            return Err(FrostError::CryptoError("Re-key must increase the key version".to_string()));
+        }
 
-        // Update to new key
-        self.master_secret = new_key.master_secret;
-        self.master_public = new_key.master_public;
-        self.derivation_proof = new_key.derivation_proof;
-        self.version += 1;
+        // Old secret is zeroized when it is dropped by the assignment
+        self.master_secret = new_key.master_secret.clone();
+        self.master_public = new_key.master_public.clone();
+        self.derivation_proof = new_key.derivation_proof.clone();
+        self.version = new_key.version;
 
         Ok(())
     }
 
     /// Encrypt key to hardware PUF (for persistent storage)
-    pub fn encrypt_to_puf(&self, puf_key: &[u8; 32]) -> Vec<u8> {
-        // In production: Use authenticated encryption (AES-256-GCM)
-        // Key = PUF-derived key (unique to this physical device)
-        // Plaintext = self.master_secret
-        // Returns ciphertext + tag
+    ///
+    /// Returns a versioned key blob; see `KeyBlobHeader` for the layout.
+    pub fn encrypt_to_puf(&self, puf_key: &[u8; 32]) -> FrostResult<Vec<u8>> {
+        use rand::RngCore;
 
-        // Placeholder
-        self.master_secret.as_scalar().as_bytes().to_vec()
+        let mut nonce = [0u8; KEY_BLOB_NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        self.seal_with_nonce(puf_key, nonce)
+    }
+
+    fn seal_with_nonce(&self, puf_key: &[u8; 32], nonce: [u8; KEY_BLOB_NONCE_LEN]) -> FrostResult<Vec<u8>> {
+        let proof = serde_json::to_vec(&self.derivation_proof)
+            .map_err(|e| FrostError::SerializationError(e.to_string()))?;
+
+        let mut plaintext = Vec::with_capacity(32 + proof.len());
+        plaintext.extend_from_slice(self.master_secret.as_scalar().as_bytes());
+        plaintext.extend_from_slice(&proof);
+
+        let header = KeyBlobHeader {
+            format_version: KEY_BLOB_FORMAT_VERSION,
+            algorithm: KEY_BLOB_ALG_XCHACHA20_POLY1305,
+            key_version: self.version,
+            public_key: self.master_public.public_key,
+            nonce,
+        };
+        let blob = seal_key_blob(puf_key, &self.device_id, &header, &plaintext);
+        plaintext.zeroize();
+        blob
     }
 
     /// Decrypt key from PUF-encrypted storage
+    ///
+    /// Fails unless the blob authenticates under this PUF key and device ID
+    /// and the decrypted secret matches the public key in the header.
     pub fn decrypt_from_puf(ciphertext: &[u8], puf_key: &[u8; 32], device_id: [u8; 32]) -> FrostResult<Self> {
-        // In production: AES-256-GCM decrypt
-        // Verify authentication tag
-        // Return decrypted key
+        let header = KeyBlobHeader::parse(ciphertext)?;
+
+        let cipher = key_blob_cipher(puf_key, &device_id);
+        let aad = header.associated_data(&device_id);
+        let mut plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&header.nonce),
+                Payload { msg: &ciphertext[KEY_BLOB_HEADER_LEN..], aad: &aad },
+            )
+            .map_err(|_| FrostError::CryptoError("Key blob authentication failed".to_string()))?;
+
+        let result = Self::open_plaintext(&plaintext, &header, device_id);
+        plaintext.zeroize();
+        result
+    }
+
+    fn open_plaintext(plaintext: &[u8], header: &KeyBlobHeader, device_id: [u8; 32]) -> FrostResult<Self> {
+        if plaintext.len() < 32 {
+            return Err(FrostError::SerializationError("Key blob payload too short".to_string()));
+        }
+
+        let mut secret_bytes = [0u8; 32];
+        secret_bytes.copy_from_slice(&plaintext[..32]);
+        let secret = Option::<Scalar>::from(Scalar::from_canonical_bytes(secret_bytes));
+        secret_bytes.zeroize();
+        let secret = secret
+            .ok_or(FrostError::CryptoError("Key blob secret is not a canonical scalar".to_string()))?;
+
+        let derivation_proof = serde_json::from_slice(&plaintext[32..])
+            .map_err(|e| FrostError::SerializationError(e.to_string()))?;
+
+        let key = Self::from_secret(device_id, secret, derivation_proof, header.key_version);
+        if key.master_public.public_key != header.public_key {
+            return Err(FrostError::CryptoError("Key blob secret does not match its public key".to_string()));
+        }
 
-        Err(FrostError::CryptoError("Not implemented".to_string()))
+        Ok(key)
     }
 }
 
-/// Manufacturing protocol for derived key provisioning
-pub struct ManufacturingProvisioner {
-    /// Factory device ID
-    device_id: [u8; 32],
+/// Magic bytes at the start of a PUF-sealed key blob
+const KEY_BLOB_MAGIC: &[u8; 4] = b"FRDK";
 
-    /// Connection to remote FROST participants
-    remote_endpoints: Vec<String>,
-}
+/// Current key blob format version
+const KEY_BLOB_FORMAT_VERSION: u8 = 1;
 
-impl ManufacturingProvisioner {
-    /// Factory provisioning flow
-    ///
-    /// Step 1: Device generates entropy (PUF + TRNG)
-    /// Step 2: Device initiates DKG with remote participants
-    /// Step 3: Remote participants verify device attestation
-    /// Step 4: DKG ceremony produces group secret
-    /// Step 5: Device derives key from group secret + device ID
-    /// Step 6: Device encrypts key to PUF, stores in flash
-    /// Step 7: Public key logged to transparency tree
-    /// Step 8: Device erases DKG state, keeps only derived key
-    pub async fn provision_device(&self) -> FrostResult<DerivedDeviceKey> {
-        // Step 1: Generate device attestation
-        let attestation = self.generate_attestation()?;
-
-        // Step 2: Initiate DKG with remote participants
-        log::info!("Initiating DKG ceremony for device {:?}", &self.device_id[..8]);
-
-        // In production: Network calls to remote_endpoints
-        // For simulation: Use local DKG
-        let mut rng = rand::thread_rng();
-        let coordinator = DkgCoordinator::new(2, 3)?;
-        let dkg_outputs = coordinator.run_dkg(&mut rng)?;
-
-        // Step 3: Derive device key
-        let participant_sigs = vec![]; // TODO: collect from remotes
-        let device_key = DerivedDeviceKey::derive_from_dkg(
-            self.device_id,
-            &dkg_outputs[0],
-            participant_sigs,
-            attestation,
-        )?;
+/// Algorithm ID: XChaCha20-Poly1305 under an HKDF-SHA256 derived key
+const KEY_BLOB_ALG_XCHACHA20_POLY1305: u8 = 1;
 
-        log::info!("Device key derived successfully, version {}", device_key.version);
-        log::info!("Public key: {:?}", &device_key.master_public.public_key.as_bytes()[..8]);
+const KEY_BLOB_NONCE_LEN: usize = 24;
 
-        // Step 4: Log to transparency tree (not implemented here)
+/// magic(4) || format(1) || alg(1) || key version(4) || public key(32) || nonce(24)
+const KEY_BLOB_HEADER_LEN: usize = 4 + 1 + 1 + 4 + 32 + KEY_BLOB_NONCE_LEN;
 
-        Ok(device_key)
+/// Cleartext header of a PUF-sealed key blob
+///
+/// The whole header plus the device ID is the AEAD associated data, so the
+/// blob cannot be moved to another device, key version or public key. The
+/// header is followed by the ciphertext of `secret(32) || derivation proof`
+/// and the 16-byte tag.
+struct KeyBlobHeader {
+    format_version: u8,
+    algorithm: u8,
+    key_version: u32,
+    public_key: CompressedRistretto,
+    nonce: [u8; KEY_BLOB_NONCE_LEN],
+}
+
+impl KeyBlobHeader {
+    fn to_bytes(&self) -> [u8; KEY_BLOB_HEADER_LEN] {
+        let mut bytes = [0u8; KEY_BLOB_HEADER_LEN];
+        bytes[..4].copy_from_slice(KEY_BLOB_MAGIC);
+        bytes[4] = self.format_version;
+        bytes[5] = self.algorithm;
+        bytes[6..10].copy_from_slice(&self.key_version.to_le_bytes());
+        bytes[10..42].copy_from_slice(self.public_key.as_bytes());
+        bytes[42..].copy_from_slice(&self.nonce);
+        bytes
     }
 
-    fn generate_attestation(&self) -> FrostResult<DeviceAttestation> {
-        // In production: Read from hardware
-        Ok(DeviceAttestation {
-            firmware_hash: [0u8; 32], // SHA-256 of firmware
-            hardware_id: self.device_id,
-            tamper_status: 0,         // 0 = intact
-            boot_measurements: vec![],
+    fn parse(blob: &[u8]) -> FrostResult<Self> {
+        if blob.len() < KEY_BLOB_HEADER_LEN || &blob[..4] != KEY_BLOB_MAGIC {
+            return Err(FrostError::SerializationError("Not a FROST key blob".to_string()));
+        }
+
+        if blob[4] != KEY_BLOB_FORMAT_VERSION {
+            return Err(FrostError::SerializationError(
+                format!("Unsupported key blob format version {}", blob[4]),
+            ));
+        }
+
+        if blob[5] != KEY_BLOB_ALG_XCHACHA20_POLY1305 {
+            return Err(FrostError::SerializationError(
+                format!("Unsupported key blob algorithm {}", blob[5]),
+            ));
+        }
+
+        let mut nonce = [0u8; KEY_BLOB_NONCE_LEN];
+        nonce.copy_from_slice(&blob[42..KEY_BLOB_HEADER_LEN]);
+
+        Ok(KeyBlobHeader {
+            format_version: blob[4],
+            algorithm: blob[5],
+            key_version: u32::from_le_bytes(blob[6..10].try_into().unwrap()),
+            public_key: CompressedRistretto::from_slice(&blob[10..42]).unwrap(),
+            nonce,
         })
     }
+
+    fn associated_data(&self, device_id: &[u8; 32]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(KEY_BLOB_HEADER_LEN + 32);
+        aad.extend_from_slice(&self.to_bytes());
+        aad.extend_from_slice(device_id);
+        aad
+    }
+}
+
+/// Wrapping cipher: key = HKDF-SHA256(PUF key, device ID)
+fn key_blob_cipher(puf_key: &[u8; 32], device_id: &[u8; 32]) -> XChaCha20Poly1305 {
+    let hkdf = Hkdf::<Sha256>::new(Some(b"FROST-PUF-KEY-WRAP-v1"), puf_key);
+    let mut key = [0u8; 32];
+    hkdf.expand(device_id, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    key.zeroize();
+    cipher
+}
+
+fn seal_key_blob(
+    puf_key: &[u8; 32],
+    device_id: &[u8; 32],
+    header: &KeyBlobHeader,
+    plaintext: &[u8],
+) -> FrostResult<Vec<u8>> {
+    let cipher = key_blob_cipher(puf_key, device_id);
+    let aad = header.associated_data(device_id);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&header.nonce), Payload { msg: plaintext, aad: &aad })
+        .map_err(|_| FrostError::CryptoError("Key blob encryption failed".to_string()))?;
+
+    let mut blob = Vec::with_capacity(KEY_BLOB_HEADER_LEN + ciphertext.len());
+    blob.extend_from_slice(&header.to_bytes());
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
 }
 
-/// Comparison with alternatives
 pub mod comparison {
     //! Security comparison: Derived keys vs alternatives
     //!
@@ -334,39 +1160,73 @@ pub mod comparison {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dkg::DkgCoordinator;
+    use crate::dkg::{DkgCoordinator, DkgOutput};
+    use crate::merkle::WitnessKey;
     use rand::rngs::OsRng;
+    use crate::nonce::DeterministicEntropy;
+
+    const TRANSCRIPT_HASH: [u8; 32] = [7u8; 32];
+
+    fn attestation(device_id: [u8; 32]) -> DeviceAttestation {
+        DeviceAttestation {
+            firmware_hash: [0u8; 32],
+            hardware_id: device_id,
+            tamper_status: 0,
+            boot_measurements: vec![],
+        }
+    }
+
+    fn participants(dkg_outputs: &[DkgOutput]) -> Vec<DerivationParticipant> {
+        dkg_outputs.iter()
+            .map(|o| DerivationParticipant::new(
+                o.secret_share.clone(),
+                &o.group_public_key,
+                TRANSCRIPT_HASH,
+            ).unwrap())
+            .collect()
+    }
+
+    fn derive_with(
+        session: &DerivationSession,
+        participants: &mut [DerivationParticipant],
+        group_public_key: &GroupPublicKey,
+        signers: &[usize],
+    ) -> FrostResult<DerivedDeviceKey> {
+        let mut rng = OsRng;
+        let request = session.request();
+        let partials: Vec<_> = signers.iter()
+            .map(|&i| participants[i].evaluate(&request, &mut rng).unwrap())
+            .collect();
+        session.combine(group_public_key, TRANSCRIPT_HASH, &partials)
+    }
+
+    fn derive(
+        session: DerivationSession,
+        dkg_outputs: &[DkgOutput],
+        signers: &[usize],
+    ) -> FrostResult<DerivedDeviceKey> {
+        derive_with(&session, &mut participants(dkg_outputs), &dkg_outputs[0].group_public_key, signers)
+    }
 
     #[test]
     fn test_derived_key_signing() {
         let mut rng = OsRng;
 
-        // Run DKG to get group secret
+        // Run DKG to get participant shares
         let coordinator = DkgCoordinator::new(2, 3).unwrap();
         let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
 
         // Derive device key
         let device_id = [42u8; 32];
-        let attestation = DeviceAttestation {
-            firmware_hash: [0u8; 32],
-            hardware_id: device_id,
-            tamper_status: 0,
-            boot_measurements: vec![],
-        };
-
-        let device_key = DerivedDeviceKey::derive_from_dkg(
-            device_id,
-            &dkg_outputs[0],
-            vec![],
-            attestation,
-        ).unwrap();
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let device_key = derive(session, &dkg_outputs, &[0, 1]).unwrap();
 
         // Sign a message
         let message = b"Test message for derived key signing";
         let signature = device_key.sign(message).unwrap();
 
         // Verify signature
-        assert!(device_key.master_public.verify_signature(message, &signature));
+        assert!(signature.verify(SignatureScheme::Derived, message, &device_key.master_public.public_key));
     }
 
     #[test]
@@ -380,33 +1240,11 @@ mod tests {
         let device1_id = [1u8; 32];
         let device2_id = [2u8; 32];
 
-        let attestation1 = DeviceAttestation {
-            firmware_hash: [0u8; 32],
-            hardware_id: device1_id,
-            tamper_status: 0,
-            boot_measurements: vec![],
-        };
-
-        let attestation2 = DeviceAttestation {
-            firmware_hash: [0u8; 32],
-            hardware_id: device2_id,
-            tamper_status: 0,
-            boot_measurements: vec![],
-        };
-
-        let key1 = DerivedDeviceKey::derive_from_dkg(
-            device1_id,
-            &dkg_outputs[0],
-            vec![],
-            attestation1,
-        ).unwrap();
+        let session1 = DerivationSession::new(device1_id, 1, attestation(device1_id), &mut rng);
+        let session2 = DerivationSession::new(device2_id, 1, attestation(device2_id), &mut rng);
 
-        let key2 = DerivedDeviceKey::derive_from_dkg(
-            device2_id,
-            &dkg_outputs[0],
-            vec![],
-            attestation2,
-        ).unwrap();
+        let key1 = derive(session1, &dkg_outputs, &[0, 1]).unwrap();
+        let key2 = derive(session2, &dkg_outputs, &[0, 1]).unwrap();
 
         // Keys should be different
         assert_ne!(
@@ -415,37 +1253,408 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_any_quorum_derives_same_key() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let device_id = [9u8; 32];
+        let key_a = derive(
+            DerivationSession::new(device_id, 1, attestation(device_id), &mut rng),
+            &dkg_outputs,
+            &[0, 1],
+        ).unwrap();
+        let key_b = derive(
+            DerivationSession::new(device_id, 1, attestation(device_id), &mut rng),
+            &dkg_outputs,
+            &[1, 2],
+        ).unwrap();
+
+        assert_eq!(key_a.master_public.public_key, key_b.master_public.public_key);
+    }
+
+    #[test]
+    fn test_key_is_prf_of_group_secret_not_a_share() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let device_id = [5u8; 32];
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let device_key = derive(session, &dkg_outputs, &[0, 2]).unwrap();
+
+        // Reconstruct s here only to check the construction
+        let ids = [dkg_outputs[0].participant_id, dkg_outputs[1].participant_id];
+        let group_secret: Scalar = dkg_outputs[..2].iter()
+            .map(|o| compute_lagrange_coefficient(o.participant_id, &ids)
+                * o.secret_share.value.as_scalar())
+            .sum();
+
+        let expected = DerivedDeviceKey::kdf(
+            &(group_secret * prf_input(&device_id, 1)).compress(),
+            &device_id,
+            1,
+        );
+        assert_eq!(device_key.master_secret.as_scalar(), &expected);
+
+        for output in &dkg_outputs {
+            let from_share = DerivedDeviceKey::kdf(
+                &(output.secret_share.value.as_scalar() * prf_input(&device_id, 1)).compress(),
+                &device_id,
+                1,
+            );
+            assert_ne!(device_key.master_secret.as_scalar(), &from_share);
+        }
+    }
+
+    #[test]
+    fn test_insufficient_partials_rejected() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let device_id = [3u8; 32];
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        assert!(matches!(
+            derive(session, &dkg_outputs, &[0]),
+            Err(FrostError::InsufficientParticipants(1, 2))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_partial_rejected() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let mut participants = participants(&dkg_outputs);
+
+        let device_id = [4u8; 32];
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let request = session.request();
+
+        let mut partials: Vec<_> = participants.iter_mut()
+            .take(2)
+            .map(|p| p.evaluate(&request, &mut rng).unwrap())
+            .collect();
+        partials[1].ciphertext[0] ^= 1;
+
+        assert!(matches!(
+            session.combine(&dkg_outputs[0].group_public_key, TRANSCRIPT_HASH, &partials),
+            Err(FrostError::InvalidSignatureShare(2))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_device_not_served() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let mut participants = participants(&dkg_outputs);
+
+        let device_id = [6u8; 32];
+        let mut tampered = attestation(device_id);
+        tampered.tamper_status = 1;
+        let session = DerivationSession::new(device_id, 1, tampered, &mut rng);
+
+        assert!(participants[0].evaluate(&session.request(), &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_participant_signatures() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let mut participants = participants(&dkg_outputs);
+        let group_public_key = &dkg_outputs[0].group_public_key;
+
+        let device_id = [8u8; 32];
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let mut device_key = derive_with(&session, &mut participants, group_public_key, &[0, 1]).unwrap();
+
+        let statement = device_key.derivation_statement();
+        assert_eq!(statement.dkg_transcript_hash, TRANSCRIPT_HASH);
+        assert_eq!(statement.public_key, device_key.master_public.public_key);
+        assert_eq!(DerivationStatement::from_bytes(&statement.to_bytes()), Some(statement.clone()));
+        assert_eq!(DerivationStatement::from_bytes(&statement.to_bytes()[1..]), None);
+        let proof = session.prove_possession(&device_key, &mut rng).unwrap();
+
+        // Only participants that served the derivation sign, and only for the
+        // key that derivation produced
+        assert!(participants[2].sign_statement(&statement, &proof, &mut rng).is_err());
+        let other_id = [9u8; 32];
+        let other_session = DerivationSession::new(other_id, 1, attestation(other_id), &mut rng);
+        let other_key = derive(other_session, &dkg_outputs, &[0, 1]).unwrap();
+        let mut substituted = statement.clone();
+        substituted.public_key = other_key.master_public.public_key;
+        let forged = PossessionProof {
+            transport_signature: proof.transport_signature.clone(),
+            key_signature: other_key.sign(&substituted.possession_message(&session.request().transport_public)).unwrap(),
+        };
+        assert!(participants[0].sign_statement(&substituted, &forged, &mut rng).is_err());
+        assert!(participants[0].sign_statement(&substituted, &proof, &mut rng).is_err());
+        let mut other_firmware = statement.clone();
+        other_firmware.firmware_hash = [1u8; 32];
+        assert!(participants[0].sign_statement(&other_firmware, &proof, &mut rng).is_err());
+
+        let signatures: Vec<_> = participants[..2].iter_mut()
+            .map(|p| p.sign_statement(&statement, &proof, &mut rng).unwrap())
+            .collect();
+
+        // Each evaluation yields one signed statement
+        assert!(participants[0].sign_statement(&statement, &proof, &mut rng).is_err());
+
+        // A signature attributed to the wrong participant is refused
+        let mut misattributed = signatures[0].clone();
+        misattributed.participant_id = ParticipantId::new(2).unwrap();
+        assert!(device_key
+            .attach_participant_signatures(group_public_key, vec![misattributed])
+            .is_err());
+
+        device_key.attach_participant_signatures(group_public_key, signatures).unwrap();
+        assert_eq!(device_key.derivation_proof.participant_signatures.len(), 2);
+    }
+
+    /// Fully provisioned key logged at index 1 of a two-entry tree
+    fn logged_key(dkg_outputs: &[DkgOutput]) -> (DerivedDeviceKey, DerivationPolicy) {
+        let mut rng = OsRng;
+        let mut participants = participants(dkg_outputs);
+        let group_public_key = dkg_outputs[0].group_public_key.clone();
+
+        let device_id = [11u8; 32];
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let mut device_key = derive_with(&session, &mut participants, &group_public_key, &[0, 1]).unwrap();
+
+        let statement = device_key.derivation_statement();
+        let proof = session.prove_possession(&device_key, &mut rng).unwrap();
+        let signatures: Vec<_> = participants[..2].iter_mut()
+            .map(|p| p.sign_statement(&statement, &proof, &mut rng).unwrap())
+            .collect();
+        device_key.attach_participant_signatures(&group_public_key, signatures).unwrap();
+
+        let other_leaf = merkle::leaf_hash(b"another device");
+        let root_hash = merkle::node_hash(&other_leaf, &statement.leaf_hash());
+        device_key.attach_inclusion_proof(1, vec![other_leaf]);
+
+        let log_key = Scalar::random(&mut rng);
+        let data = SignedTreeHead::to_signing_data(2, 1704196800, &root_hash);
+        let mut tree_head = SignedTreeHead {
+            tree_size: 2,
+            timestamp: 1704196800,
+            root_hash,
+            signature: SchnorrSignature::sign_with_scheme(SignatureScheme::Threshold, &log_key, &Scalar::random(&mut rng), &data),
+            cosignatures: Vec::new(),
+        };
+
+        let log_public_key = (log_key * RISTRETTO_BASEPOINT_POINT).compress();
+        let witness = WitnessKey::generate(&mut rng);
+        tree_head.cosignatures.push(witness.cosign(&log_public_key, &tree_head, 1704196860, &mut rng));
+
+        let policy = DerivationPolicy {
+            ceremony_key: group_public_key,
+            tree_head,
+            log_public_key,
+            witnesses: WitnessPolicy { witnesses: vec![witness.public_key()], threshold: 1 },
+            allowed_firmware: vec![[0u8; 32]],
+        };
+
+        (device_key, policy)
+    }
+
+    #[test]
+    fn test_verify_derivation_proof() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let (device_key, policy) = logged_key(&dkg_outputs);
+        let verdict = device_key.verify_derivation_proof(&policy);
+        assert!(verdict.is_valid(), "{:?}", verdict.failures);
+    }
+
+    #[test]
+    fn test_verdict_lists_every_failure() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let (mut device_key, mut policy) = logged_key(&dkg_outputs);
+        policy.allowed_firmware = vec![[1u8; 32]];
+        policy.tree_head.timestamp += 1;
+        device_key.derivation_proof.participant_signatures[1].signature.z[0] ^= 1;
+        device_key.derivation_proof.log_index = 0;
+
+        let verdict = device_key.verify_derivation_proof(&policy);
+        let participant_2 = ParticipantId::new(2).unwrap();
+        assert_eq!(verdict.failures, vec![
+            DerivationCheckFailure::InvalidParticipantSignature(participant_2),
+            DerivationCheckFailure::InsufficientSignatures { valid: 1, required: 2 },
+            DerivationCheckFailure::InvalidTreeHeadSignature,
+            DerivationCheckFailure::InclusionProofInvalid { log_index: 0, tree_size: 2 },
+            DerivationCheckFailure::FirmwareNotAllowed([0u8; 32]),
+        ]);
+    }
+
+    #[test]
+    fn test_verdict_requires_witness_cosignatures() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        // A tree head only the log signed could be one of several views
+        let (device_key, mut policy) = logged_key(&dkg_outputs);
+        policy.tree_head.cosignatures.clear();
+        let verdict = device_key.verify_derivation_proof(&policy);
+        assert_eq!(verdict.failures, vec![
+            DerivationCheckFailure::InsufficientCosignatures { valid: 0, required: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_verdict_rejects_foreign_ceremony() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let other_outputs = coordinator.run_dkg(&mut rng).unwrap();
+
+        let (device_key, mut policy) = logged_key(&dkg_outputs);
+        policy.ceremony_key = other_outputs[0].group_public_key.clone();
+
+        let verdict = device_key.verify_derivation_proof(&policy);
+        assert!(!verdict.is_valid());
+        assert!(verdict.failures.contains(
+            &DerivationCheckFailure::InsufficientSignatures { valid: 0, required: 2 }
+        ));
+    }
+
+    fn known_answer_key() -> DerivedDeviceKey {
+        let device_id = [0x11u8; 32];
+        let proof = DerivationProof {
+            timestamp: 1704196800,
+            dkg_transcript_hash: [0u8; 32],
+            log_index: 0,
+            merkle_proof: vec![],
+            participant_signatures: vec![],
+            device_attestation: attestation(device_id),
+        };
+        DerivedDeviceKey::from_secret(device_id, Scalar::from_bytes_mod_order([0x22u8; 32]), proof, 1)
+    }
+
+    #[test]
+    fn test_hedged_signing_known_answer() {
+        let key = known_answer_key();
+        let message = b"hedged nonce known answer";
+
+        let signature = key.sign_with_entropy(message, &DeterministicEntropy([0u8; 32])).unwrap();
+        assert_eq!(hex::encode(signature.commitment.as_bytes()), "b6c7db97f12ab8897cc757245d0eb0d801198224f8e457886f6898e1d1c59278");
+
+        // Deterministic in (key, message, randomness); fresh randomness changes R
+        let again = key.sign_with_entropy(message, &DeterministicEntropy([0u8; 32])).unwrap();
+        assert_eq!(again.commitment, signature.commitment);
+        assert_eq!(again.z, signature.z);
+        let hedged = key.sign_with_entropy(message, &DeterministicEntropy([1u8; 32])).unwrap();
+        assert_ne!(hedged.commitment, signature.commitment);
+    }
+
+    #[test]
+    fn test_puf_blob_known_answer() {
+        let key = known_answer_key();
+        let blob = key.seal_with_nonce(&[0x33u8; 32], [0x44u8; 24]).unwrap();
+
+        assert_eq!(
+            hex::encode(&blob[..KEY_BLOB_HEADER_LEN]),
+            concat!(
+                "4652444b", "01", "01", "01000000",
+                "363787233dd9edefbf2c3ed4e3c33caece7f91fe85ed7565d0b2ee2c8610770f",
+                "444444444444444444444444444444444444444444444444",
+            ),
+        );
+        assert_eq!(hex::encode(Sha256::digest(&blob)), "7c3ebe4161440f604a397f3093ef4b46670b13d433b64e8956d6883d7c8d6c80");
+
+        let opened = DerivedDeviceKey::decrypt_from_puf(&blob, &[0x33u8; 32], [0x11u8; 32]).unwrap();
+        assert_eq!(opened.master_secret.as_scalar(), key.master_secret.as_scalar());
+        assert_eq!(opened.master_public.public_key, key.master_public.public_key);
+        assert_eq!(opened.version, 1);
+        assert_eq!(opened.derivation_proof.timestamp, 1704196800);
+    }
+
+    #[test]
+    fn test_puf_blob_round_trip() {
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let (device_key, policy) = logged_key(&dkg_outputs);
+
+        let puf_key = [0x5au8; 32];
+        let blob = device_key.encrypt_to_puf(&puf_key).unwrap();
+        assert_ne!(blob, device_key.encrypt_to_puf(&puf_key).unwrap());
+
+        let opened = DerivedDeviceKey::decrypt_from_puf(&blob, &puf_key, *device_key.device_id()).unwrap();
+        assert_eq!(opened.master_public.public_key, device_key.master_public.public_key);
+        assert_eq!(opened.master_secret.as_scalar(), device_key.master_secret.as_scalar());
+        assert!(opened.verify_derivation_proof(&policy).is_valid());
+    }
+
+    #[test]
+    fn test_puf_blob_tamper_detected() {
+        let key = known_answer_key();
+        let puf_key = [0x33u8; 32];
+        let device_id = [0x11u8; 32];
+        let blob = key.seal_with_nonce(&puf_key, [0x44u8; 24]).unwrap();
+
+        // Every byte is covered: header by AAD, the rest by the tag
+        for i in 0..blob.len() {
+            let mut tampered = blob.clone();
+            tampered[i] ^= 0x01;
+            assert!(DerivedDeviceKey::decrypt_from_puf(&tampered, &puf_key, device_id).is_err(), "byte {}", i);
+        }
+
+        assert!(DerivedDeviceKey::decrypt_from_puf(&blob[..blob.len() - 1], &puf_key, device_id).is_err());
+        assert!(DerivedDeviceKey::decrypt_from_puf(&blob[..KEY_BLOB_HEADER_LEN - 1], &puf_key, device_id).is_err());
+        assert!(DerivedDeviceKey::decrypt_from_puf(&blob, &[0x34u8; 32], device_id).is_err());
+        assert!(DerivedDeviceKey::decrypt_from_puf(&blob, &puf_key, [0x12u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_puf_blob_rejects_mismatched_public_key() {
+        let key = known_answer_key();
+        let puf_key = [0x33u8; 32];
+        let device_id = [0x11u8; 32];
+
+        // Correctly authenticated, but the secret is not the header's key
+        let header = KeyBlobHeader {
+            format_version: KEY_BLOB_FORMAT_VERSION,
+            algorithm: KEY_BLOB_ALG_XCHACHA20_POLY1305,
+            key_version: 1,
+            public_key: RISTRETTO_BASEPOINT_POINT.compress(),
+            nonce: [0u8; 24],
+        };
+        let mut plaintext = key.master_secret.as_scalar().as_bytes().to_vec();
+        plaintext.extend_from_slice(&serde_json::to_vec(&key.derivation_proof).unwrap());
+        let blob = seal_key_blob(&puf_key, &device_id, &header, &plaintext).unwrap();
+
+        assert!(DerivedDeviceKey::decrypt_from_puf(&blob, &puf_key, device_id).is_err());
+    }
+
     #[test]
     fn test_rekey() {
         let mut rng = OsRng;
 
         // Initial key derivation
-        let coordinator1 = DkgCoordinator::new(2, 3).unwrap();
-        let dkg_outputs1 = coordinator1.run_dkg(&mut rng).unwrap();
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
 
         let device_id = [42u8; 32];
-        let attestation = DeviceAttestation {
-            firmware_hash: [0u8; 32],
-            hardware_id: device_id,
-            tamper_status: 0,
-            boot_measurements: vec![],
-        };
-
-        let mut device_key = DerivedDeviceKey::derive_from_dkg(
-            device_id,
-            &dkg_outputs1[0],
-            vec![],
-            attestation.clone(),
-        ).unwrap();
+        let session = DerivationSession::new(device_id, 1, attestation(device_id), &mut rng);
+        let mut device_key = derive(session, &dkg_outputs, &[0, 1]).unwrap();
 
         let old_public_key = device_key.master_public.clone();
         assert_eq!(device_key.version, 1);
 
         // Re-key
-        let coordinator2 = DkgCoordinator::new(2, 3).unwrap();
-        let dkg_outputs2 = coordinator2.run_dkg(&mut rng).unwrap();
-
-        device_key.rekey(&dkg_outputs2[0], vec![], attestation).unwrap();
+        let session = device_key.begin_rekey(attestation(device_id), &mut rng);
+        let new_key = derive(session, &dkg_outputs, &[1, 2]).unwrap();
+        device_key.rekey(new_key).unwrap();
 
         // Version incremented, key changed
         assert_eq!(device_key.version, 2);
@@ -453,5 +1662,13 @@ mod tests {
             old_public_key.public_key.as_bytes(),
             device_key.master_public.public_key.as_bytes()
         );
+
+        // Re-keying back to an older version is refused
+        let stale = derive(
+            DerivationSession::new(device_id, 1, attestation(device_id), &mut rng),
+            &dkg_outputs,
+            &[0, 1],
+        ).unwrap();
+        assert!(device_key.rekey(stale).is_err());
     }
 }
@@ -99,6 +99,10 @@ impl DkgParticipant {
                 &self.generator_g,
                 &self.generator_h,
             ),
+            public_commitments: self.secret_poly.coefficients
+                .iter()
+                .map(|a| (a.as_scalar() * self.generator_g).compress())
+                .collect(),
         }
     }
 
@@ -150,24 +154,26 @@ impl DkgParticipant {
             ));
         }
 
-        // Verify each received share against the sender's commitment
+        // Verify each received share against the sender's commitments
         for share_msg in round2_shares {
             // Find commitment from this sender
-            let commitment = round1_broadcasts
+            let broadcast = round1_broadcasts
                 .iter()
                 .find(|b| b.sender_id == share_msg.sender_id)
-                .ok_or(FrostError::InvalidParticipantIndex(share_msg.sender_id.as_u32()))?
-                .commitment
-                .clone();
+                .ok_or(FrostError::InvalidParticipantIndex(share_msg.sender_id.as_u32()))?;
 
-            // Verify share
-            if !commitment.verify_share(
+            // Verify share against both the Pedersen and Feldman commitments
+            let pedersen_ok = broadcast.commitment.verify_share(
                 self.my_id,
                 share_msg.secret_share.as_scalar(),
                 share_msg.blinding_share.as_scalar(),
                 &self.generator_g,
                 &self.generator_h,
-            ) {
+            );
+            let feldman_ok = evaluate_public_commitments(&broadcast.public_commitments, self.my_id)
+                == Some(share_msg.secret_share.as_scalar() * self.generator_g);
+
+            if !pedersen_ok || !feldman_ok {
                 return Err(FrostError::CommitmentVerificationFailed(
                     share_msg.sender_id.as_u32(),
                 ));
@@ -181,31 +187,25 @@ impl DkgParticipant {
             aggregated_secret += share_msg.secret_share.as_scalar();
         }
 
-        // Compute group public key from commitments
+        // Compute group public key PK = Σ_j A_{j,0} from the Feldman commitments
+        // (the Pedersen commitments carry blinding terms in H)
         let mut group_public_key = RistrettoPoint::identity();
         for broadcast in round1_broadcasts {
-            if let Some(commitment) = broadcast.commitment.commitments.first() {
-                if let Some(point) = commitment.decompress() {
-                    group_public_key += point;
-                }
-            }
+            let a0 = broadcast.public_commitments.first()
+                .and_then(|c| c.decompress())
+                .ok_or(FrostError::CommitmentVerificationFailed(broadcast.sender_id.as_u32()))?;
+            group_public_key += a0;
         }
 
-        // Compute verification shares for each participant
+        // Compute verification shares Y_i = Σ_j f_j(i) * G for each participant
         let mut verification_shares = Vec::new();
         for i in 1..=self.num_participants {
             let participant_id = ParticipantId::new(i).unwrap();
-            let x = participant_id.as_scalar();
-            let mut x_power = Scalar::ONE;
             let mut vss_point = RistrettoPoint::identity();
 
             for broadcast in round1_broadcasts {
-                for commitment in &broadcast.commitment.commitments {
-                    if let Some(point) = commitment.decompress() {
-                        vss_point += x_power * point;
-                        x_power *= x;
-                    }
-                }
+                vss_point += evaluate_public_commitments(&broadcast.public_commitments, participant_id)
+                    .ok_or(FrostError::CommitmentVerificationFailed(broadcast.sender_id.as_u32()))?;
             }
 
             verification_shares.push(PublicKeyShare {
@@ -220,6 +220,7 @@ impl DkgParticipant {
                 participant_id: self.my_id,
                 value: SecretScalar::new(aggregated_secret),
                 blinding: SecretScalar::new(Scalar::ZERO), // Not needed post-DKG
+                group_public_key: group_public_key.compress(),
             },
             group_public_key: GroupPublicKey {
                 public_key: group_public_key.compress(),
@@ -238,6 +239,25 @@ pub struct DkgRound1Broadcast {
     pub sender_id: ParticipantId,
     /// Pedersen commitment to polynomial
     pub commitment: PedersenCommitment,
+    /// Feldman commitments A_k = a_k * G to the secret polynomial
+    pub public_commitments: Vec<CompressedRistretto>,
+}
+
+/// Evaluate Σ x^k * A_k at participant x, or None if a point is invalid
+fn evaluate_public_commitments(
+    commitments: &[CompressedRistretto],
+    participant_id: ParticipantId,
+) -> Option<RistrettoPoint> {
+    let x = participant_id.as_scalar();
+    let mut x_power = Scalar::ONE;
+    let mut result = RistrettoPoint::identity();
+
+    for commitment in commitments {
+        result += x_power * commitment.decompress()?;
+        x_power *= x;
+    }
+
+    Some(result)
 }
 
 /// Round 2 point-to-point message (secret shares)
@@ -307,7 +327,7 @@ impl DkgCoordinator {
 
         // Finalize for each participant
         let mut outputs = Vec::new();
-        for (i, participant) in participants.iter().enumerate() {
+        for participant in participants.iter() {
             // Collect shares destined for this participant
             let shares_for_me: Vec<_> = round2_messages
                 .iter()
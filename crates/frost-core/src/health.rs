@@ -0,0 +1,368 @@
+//! Health of remote shares
+//!
+//! `HealthTracker` records the outcome of every probe and signing attempt
+//! per remote: a moving-average latency, and a circuit breaker that opens
+//! after `failure_threshold` consecutive failures. An open breaker keeps
+//! the remote out of selection for `open_for`; after that it is half-open,
+//! and the next request decides whether it closes or opens again.
+//!
+//! `rank` orders the usable remotes for a signing attempt. Latency is
+//! weighed against jurisdiction diversity: a remote in a jurisdiction not
+//! yet ranked is credited `diversity_bonus`, so a fallback after a failure
+//! prefers another country unless it is much slower. Each failure since a
+//! remote's last success adds `failure_penalty`, so a remote that just
+//! failed drops behind healthy ones before its breaker opens.
+//!
+//! `spawn_health_probes` probes in the background against a shared
+//! `HybridFROSTDevice`.
+
+use crate::hybrid::{HybridFROSTDevice, RemoteShareEndpoint};
+use crate::transport::RemoteShareTransport;
+use crate::types::ParticipantId;
+use crate::FrostError;
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Health tracking parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthPolicy {
+    /// Interval between background probes
+    pub probe_interval: Duration,
+    /// Weight of the newest sample in the latency average (0..=1)
+    pub latency_smoothing: f64,
+    /// Consecutive failures that open a remote's breaker
+    pub failure_threshold: u32,
+    /// How long an open breaker keeps a remote out
+    pub open_for: Duration,
+    /// Latency a remote in a new jurisdiction is worth
+    pub diversity_bonus: Duration,
+    /// Latency added per consecutive failure when ranking
+    pub failure_penalty: Duration,
+    /// Deadline `sign` gives the remote signing paths
+    pub sign_deadline: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        HealthPolicy {
+            probe_interval: Duration::from_secs(60),
+            latency_smoothing: 0.3,
+            failure_threshold: 3,
+            open_for: Duration::from_secs(120),
+            diversity_bonus: Duration::from_millis(150),
+            failure_penalty: Duration::from_millis(250),
+            sign_deadline: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Too many failures; the remote is skipped
+    Open,
+    /// Cool-down over; the next request is a trial
+    HalfOpen,
+}
+
+/// Health of one remote, for monitoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteHealthStats {
+    /// Moving-average latency (ms), once measured
+    pub avg_latency_ms: Option<u64>,
+    /// Breaker state
+    pub circuit: CircuitState,
+    /// Failures since the last success
+    pub consecutive_failures: u32,
+    /// Successful requests so far
+    pub successes: u64,
+    /// Failed requests so far
+    pub failures: u64,
+    /// Error of the last failure
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RemoteRecord {
+    avg_latency_ms: Option<f64>,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    successes: u64,
+    failures: u64,
+    last_error: Option<String>,
+}
+
+/// Per-remote latency and circuit breakers
+#[derive(Debug, Clone, Default)]
+pub struct HealthTracker {
+    policy: HealthPolicy,
+    remotes: HashMap<ParticipantId, RemoteRecord>,
+}
+
+impl HealthTracker {
+    /// Tracker for `policy`
+    pub fn new(policy: HealthPolicy) -> Self {
+        HealthTracker { policy, remotes: HashMap::new() }
+    }
+
+    /// Tracking parameters
+    pub fn policy(&self) -> &HealthPolicy {
+        &self.policy
+    }
+
+    /// Record a successful request that took `latency`; closes the breaker
+    pub fn record_success(&mut self, participant_id: ParticipantId, latency: Duration) {
+        let alpha = self.policy.latency_smoothing.clamp(0.0, 1.0);
+        let record = self.remotes.entry(participant_id).or_default();
+        let sample = latency.as_secs_f64() * 1000.0;
+        record.avg_latency_ms = Some(match record.avg_latency_ms {
+            Some(avg) => alpha * sample + (1.0 - alpha) * avg,
+            None => sample,
+        });
+        record.consecutive_failures = 0;
+        record.opened_at = None;
+        record.successes += 1;
+    }
+
+    /// Record a failed request, opening the breaker at the threshold
+    ///
+    /// A failed trial while half-open opens it again straight away.
+    pub fn record_failure(&mut self, participant_id: ParticipantId, error: &FrostError, now: Instant) {
+        let threshold = self.policy.failure_threshold.max(1);
+        let record = self.remotes.entry(participant_id).or_default();
+        record.consecutive_failures = record.consecutive_failures.saturating_add(1);
+        record.failures += 1;
+        record.last_error = Some(error.to_string());
+        if record.consecutive_failures >= threshold {
+            if record.opened_at.is_none() {
+                log::warn!("Opening circuit to participant {} after {} failures", participant_id.as_u32(), record.consecutive_failures);
+            }
+            record.opened_at = Some(now);
+        }
+    }
+
+    /// Breaker state of `participant_id` at `now`
+    pub fn circuit(&self, participant_id: ParticipantId, now: Instant) -> CircuitState {
+        match self.remotes.get(&participant_id).and_then(|r| r.opened_at) {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.saturating_duration_since(opened_at) < self.policy.open_for => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether requests to `participant_id` may be sent at `now`
+    pub fn allows(&self, participant_id: ParticipantId, now: Instant) -> bool {
+        self.circuit(participant_id, now) != CircuitState::Open
+    }
+
+    /// Moving-average latency of `participant_id`, once measured
+    pub fn avg_latency(&self, participant_id: ParticipantId) -> Option<Duration> {
+        self.remotes.get(&participant_id)
+            .and_then(|r| r.avg_latency_ms)
+            .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+    }
+
+    /// Health of `participant_id` at `now`
+    pub fn stats(&self, participant_id: ParticipantId, now: Instant) -> RemoteHealthStats {
+        let record = self.remotes.get(&participant_id).cloned().unwrap_or_default();
+        RemoteHealthStats {
+            avg_latency_ms: record.avg_latency_ms.map(|ms| ms.round() as u64),
+            circuit: self.circuit(participant_id, now),
+            consecutive_failures: record.consecutive_failures,
+            successes: record.successes,
+            failures: record.failures,
+            last_error: record.last_error,
+        }
+    }
+
+    /// Usable remotes in the order to try them
+    ///
+    /// Skips remotes marked unavailable or with an open breaker. Each pick
+    /// is the remote with the lowest expected latency, plus
+    /// `failure_penalty` per consecutive failure, less `diversity_bonus` if
+    /// its jurisdiction is not among the earlier picks. Unmeasured remotes
+    /// are expected at their configured `avg_response_time`.
+    pub fn rank<'a>(&self, remotes: &'a [RemoteShareEndpoint], now: Instant) -> Vec<&'a RemoteShareEndpoint> {
+        let penalty = self.policy.failure_penalty.as_secs_f64() * 1000.0;
+        let mut candidates: Vec<(&RemoteShareEndpoint, f64)> = remotes.iter()
+            .filter(|r| r.available && self.allows(r.participant_id, now))
+            .map(|r| {
+                let record = self.remotes.get(&r.participant_id);
+                let latency = record
+                    .and_then(|record| record.avg_latency_ms)
+                    .unwrap_or(r.avg_response_time as f64);
+                let failures = record.map_or(0, |record| record.consecutive_failures);
+                (r, latency + penalty * failures as f64)
+            })
+            .collect();
+
+        let bonus = self.policy.diversity_bonus.as_secs_f64() * 1000.0;
+        let mut seen = HashSet::new();
+        let mut ranked = Vec::with_capacity(candidates.len());
+        while !candidates.is_empty() {
+            let cost = |(remote, latency): &(&RemoteShareEndpoint, f64)| {
+                if seen.contains(remote.jurisdiction()) { *latency } else { latency - bonus }
+            };
+            let best = (0..candidates.len())
+                .min_by(|&a, &b| cost(&candidates[a]).total_cmp(&cost(&candidates[b])))
+                .expect("candidates is not empty");
+            let (remote, _) = candidates.remove(best);
+            seen.insert(remote.jurisdiction());
+            ranked.push(remote);
+        }
+        ranked
+    }
+}
+
+/// Handle to a background probe task
+pub struct HealthProbeTask {
+    handle: JoinHandle<()>,
+}
+
+impl HealthProbeTask {
+    /// Stop the task
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+/// Probe `device`'s remotes every `probe_interval` until stopped
+///
+/// The device lock is held while a round of probes is in flight. Must be
+/// called within a Tokio runtime.
+pub fn spawn_health_probes<T: RemoteShareTransport + 'static>(
+    device: Arc<Mutex<HybridFROSTDevice<T>>>,
+) -> HealthProbeTask {
+    let handle = tokio::spawn(async move {
+        loop {
+            let interval = {
+                let mut device = device.lock().await;
+                device.check_remote_health().await;
+                device.health().policy().probe_interval
+            };
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    HealthProbeTask { handle }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(id: u32, location: &str, avg_response_time: u64) -> RemoteShareEndpoint {
+        RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: location.to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: String::new(),
+            cert_fingerprint: [0u8; 32],
+            available: true,
+            avg_response_time,
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_cools_down_and_closes() {
+        let policy = HealthPolicy { failure_threshold: 2, open_for: Duration::from_secs(60), ..HealthPolicy::default() };
+        let mut tracker = HealthTracker::new(policy);
+        let id = ParticipantId::new(2).unwrap();
+        let error = FrostError::TransportError("connection refused".to_string());
+        let now = Instant::now();
+
+        tracker.record_failure(id, &error, now);
+        assert_eq!(tracker.circuit(id, now), CircuitState::Closed);
+        tracker.record_failure(id, &error, now);
+        assert_eq!(tracker.circuit(id, now), CircuitState::Open);
+        assert!(!tracker.allows(id, now + Duration::from_secs(59)));
+
+        // One failed trial reopens it for a full period
+        let later = now + Duration::from_secs(60);
+        assert_eq!(tracker.circuit(id, later), CircuitState::HalfOpen);
+        tracker.record_failure(id, &error, later);
+        assert_eq!(tracker.circuit(id, later + Duration::from_secs(59)), CircuitState::Open);
+
+        let recovered = later + Duration::from_secs(60);
+        tracker.record_success(id, Duration::from_millis(100));
+        let stats = tracker.stats(id, recovered);
+        assert_eq!(stats.circuit, CircuitState::Closed);
+        assert_eq!((stats.successes, stats.failures, stats.consecutive_failures), (1, 3, 0));
+        assert_eq!(stats.last_error.as_deref(), Some("Transport error: connection refused"));
+    }
+
+    #[test]
+    fn test_latency_moving_average() {
+        let mut tracker = HealthTracker::new(HealthPolicy { latency_smoothing: 0.25, ..HealthPolicy::default() });
+        let id = ParticipantId::new(3).unwrap();
+        assert_eq!(tracker.avg_latency(id), None);
+
+        tracker.record_success(id, Duration::from_millis(100));
+        tracker.record_success(id, Duration::from_millis(500));
+        assert_eq!(tracker.stats(id, Instant::now()).avg_latency_ms, Some(200));
+        tracker.record_success(id, Duration::from_millis(200));
+        assert_eq!(tracker.avg_latency(id), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn test_rank_weighs_latency_against_jurisdiction() {
+        let policy = HealthPolicy { diversity_bonus: Duration::from_millis(100), ..HealthPolicy::default() };
+        let mut tracker = HealthTracker::new(policy);
+        let remotes = vec![
+            remote(2, "Zürich, CH", 50),
+            remote(3, "Geneva, CH", 60),
+            remote(4, "Frankfurt, DE", 120),
+            remote(5, "Tokyo, JP", 400),
+        ];
+        let now = Instant::now();
+        let order = |tracker: &HealthTracker, remotes: &[RemoteShareEndpoint]| -> Vec<u32> {
+            tracker.rank(remotes, now).iter().map(|r| r.participant_id.as_u32()).collect()
+        };
+
+        // Frankfurt beats a second Swiss share that is 60ms faster; Tokyo is
+        // too slow for its jurisdiction bonus to lift it past either
+        assert_eq!(order(&tracker, &remotes), vec![2, 4, 3, 5]);
+
+        // Measured latency replaces the configured estimate
+        tracker.record_success(ParticipantId::new(2).unwrap(), Duration::from_millis(300));
+        assert_eq!(order(&tracker, &remotes), vec![3, 4, 2, 5]);
+
+        // Open breakers and unavailable remotes are skipped
+        let error = FrostError::TransportError("timeout".to_string());
+        for _ in 0..3 {
+            tracker.record_failure(ParticipantId::new(3).unwrap(), &error, now);
+        }
+        let mut remotes = remotes;
+        remotes[3].available = false;
+        assert_eq!(order(&tracker, &remotes), vec![4, 2]);
+    }
+
+    #[test]
+    fn test_rank_penalises_recent_failures() {
+        let policy = HealthPolicy { failure_penalty: Duration::from_millis(100), ..HealthPolicy::default() };
+        let mut tracker = HealthTracker::new(policy);
+        let remotes = vec![remote(2, "Zürich, CH", 50), remote(3, "Frankfurt, DE", 120)];
+        let now = Instant::now();
+        let order = |tracker: &HealthTracker| -> Vec<u32> {
+            tracker.rank(&remotes, now).iter().map(|r| r.participant_id.as_u32()).collect()
+        };
+        let id = ParticipantId::new(2).unwrap();
+        let error = FrostError::TransportError("connection reset".to_string());
+
+        // One failure leaves the breaker closed but the remote is tried later
+        tracker.record_failure(id, &error, now);
+        assert_eq!(tracker.circuit(id, now), CircuitState::Closed);
+        assert_eq!(order(&tracker), vec![3, 2]);
+
+        // A success clears the penalty
+        tracker.record_success(id, Duration::from_millis(50));
+        assert_eq!(order(&tracker), vec![2, 3]);
+    }
+}
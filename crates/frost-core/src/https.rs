@@ -0,0 +1,795 @@
+//! HTTPS transport to remote shares
+//!
+//! Speaks the share API of the integration docs: HTTP/2 over TLS 1.3 with
+//! CBOR bodies.
+//!
+//! ```text
+//! POST /v1/signing/round1   Round1Request      -> Round1Response
+//! POST /v1/signing/round2   Round2Request      -> Round2Response
+//! POST /v1/tokens/issue      Round2Request      -> Round2Response
+//! GET  /v1/health                              -> HealthResponse
+//! POST /v1/attestation      AttestationRequest -> ShareAttestation
+//! POST /v1/revocations      RevocationRequest  -> RevocationList
+//! ```
+//!
+//! Remote certificates are not checked against any CA: each endpoint's
+//! end-entity certificate must hash (SHA-256) to its `cert_fingerprint`.
+//! The device authenticates with a self-signed Ed25519 client certificate
+//! derived from its device key (`TlsIdentity`).
+//!
+//! Round 2 for a session token body (`SessionToken::to_signing_data`) goes
+//! to `/v1/tokens/issue`, where the share checks the token before
+//! co-signing it; round 1 is the same for both.
+//!
+//! Every attempt is bounded by `HttpsConfig::timeout`. Connection failures,
+//! timeouts and 5xx answers are retried with exponential backoff, except
+//! for round 2: a remote spends its nonces on the first round-2 request, so
+//! that one is never repeated.
+
+use crate::cose::SIG_STRUCTURE_PREFIX;
+use crate::derived_key::DerivedDeviceKey;
+use crate::hybrid::RemoteShareEndpoint;
+use crate::revocation::RevocationList;
+use crate::signing::{PartialSignature, SigningCommitment};
+use crate::session_token::SessionToken;
+use crate::transport::{message_hash, RemoteHealth, RemoteShareTransport, SessionId, ShareAttestation};
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use serde::{de::DeserializeOwned, Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use zeroize::Zeroizing;
+
+/// Round-1 commitment request
+pub const ROUND1_PATH: &str = "/v1/signing/round1";
+/// Round-2 partial signature request
+pub const ROUND2_PATH: &str = "/v1/signing/round2";
+/// Round-2 partial signature over a session token body
+pub const ISSUE_TOKEN_PATH: &str = "/v1/tokens/issue";
+/// Health check
+pub const HEALTH_PATH: &str = "/v1/health";
+/// Share attestation request
+pub const ATTESTATION_PATH: &str = "/v1/attestation";
+/// Revocation list request
+pub const REVOCATIONS_PATH: &str = "/v1/revocations";
+
+/// Content type of every request and response body
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// `POST /v1/signing/round1`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round1Request {
+    /// Signing session, chosen by the device
+    pub session_id: SessionId,
+    /// Share the request is for
+    pub participant_id: ParticipantId,
+    /// SHA-256 of the message to be signed
+    pub message_hash: [u8; 32],
+    /// Holder key signature over `share_request_digest(session_id, message_hash)`
+    pub holder_signature: Option<SchnorrSignature>,
+}
+
+/// Answer to `Round1Request`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round1Response {
+    /// The share's nonce commitment
+    pub commitment: SigningCommitment,
+    /// Server time (Unix epoch seconds)
+    pub timestamp: u64,
+}
+
+/// `POST /v1/signing/round2` and `POST /v1/tokens/issue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round2Request {
+    /// Signing session opened in round 1
+    pub session_id: SessionId,
+    /// Share the request is for
+    pub participant_id: ParticipantId,
+    /// Message whose hash was sent in round 1
+    pub message: Vec<u8>,
+    /// Commitments of every signer in the session
+    pub commitments: Vec<SigningCommitment>,
+    /// Holder key signature over `share_request_digest(session_id, message_hash(message))`
+    pub holder_signature: Option<SchnorrSignature>,
+}
+
+/// Answer to `Round2Request`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round2Response {
+    /// The share's partial signature
+    pub partial: PartialSignature,
+}
+
+/// Answer to `GET /v1/health`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    /// Whether the share takes signing requests
+    pub available: bool,
+}
+
+/// `POST /v1/attestation`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationRequest {
+    /// Share the request is for
+    pub participant_id: ParticipantId,
+    /// Fresh challenge to bind the attestation to
+    pub challenge: [u8; 32],
+}
+
+/// `POST /v1/revocations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationRequest {
+    /// Sequence of the list the device holds
+    pub since_sequence: Option<u64>,
+}
+
+/// Body of any non-200 answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// Reason for the failure
+    pub error: String,
+}
+
+/// Encode a request or response body
+pub fn to_cbor<T: Serialize>(value: &T) -> FrostResult<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::into_writer(value, &mut out)
+        .map_err(|e| FrostError::SerializationError(e.to_string()))?;
+    Ok(out)
+}
+
+/// Decode a request or response body
+pub fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> FrostResult<T> {
+    ciborium::from_reader(bytes).map_err(|e| FrostError::SerializationError(e.to_string()))
+}
+
+/// SHA-256 fingerprint of a DER certificate, as pinned in `RemoteShareEndpoint`
+pub fn cert_fingerprint(cert_der: &[u8]) -> [u8; 32] {
+    Sha256::digest(cert_der).into()
+}
+
+/// Device client certificate for mutual TLS
+pub struct TlsIdentity {
+    cert_der: Vec<u8>,
+    key_pkcs8: Zeroizing<Vec<u8>>,
+    public_key: [u8; 32],
+}
+
+/// PKCS#8 v1 prefix of an Ed25519 private key (RFC 8410)
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+impl TlsIdentity {
+    /// Self-signed Ed25519 certificate from the device key's TLS seed
+    ///
+    /// The subject is the hex device ID. The certificate is deterministic,
+    /// so shares can pin its fingerprint at enrollment.
+    pub fn from_device_key(device_key: &DerivedDeviceKey) -> FrostResult<Self> {
+        Self::from_seed(&device_key.tls_client_seed(), device_key.device_id())
+    }
+
+    /// Self-signed Ed25519 certificate from a seed held elsewhere
+    pub fn from_seed(seed: &[u8; 32], device_id: &[u8; 32]) -> FrostResult<Self> {
+        let mut pkcs8 = Zeroizing::new(ED25519_PKCS8_PREFIX.to_vec());
+        pkcs8.extend_from_slice(seed);
+
+        let tls_error = |e: rcgen::Error| FrostError::CryptoError(format!("Client certificate: {}", e));
+        let key_pair = rcgen::KeyPair::from_pkcs8_der_and_sign_algo(
+            &PrivatePkcs8KeyDer::from(pkcs8.as_slice()),
+            &rcgen::PKCS_ED25519,
+        ).map_err(tls_error)?;
+
+        let subject = device_id.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).map_err(tls_error)?;
+        params.distinguished_name.push(rcgen::DnType::CommonName, subject);
+        params.serial_number = Some(rcgen::SerialNumber::from_slice(&device_id[..16]));
+        let cert = params.self_signed(&key_pair).map_err(tls_error)?;
+
+        Ok(TlsIdentity {
+            cert_der: cert.der().to_vec(),
+            public_key: key_pair.public_key_raw().try_into()
+                .map_err(|_| FrostError::CryptoError("Ed25519 public key must be 32 bytes".to_string()))?,
+            key_pkcs8: pkcs8,
+        })
+    }
+
+    /// DER client certificate
+    pub fn cert_der(&self) -> &[u8] {
+        &self.cert_der
+    }
+
+    /// Ed25519 public key in the certificate
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+}
+
+/// Timeouts and retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpsConfig {
+    /// Bound on one attempt: connect, handshake, request and response
+    pub timeout: Duration,
+    /// Further attempts after a transient failure
+    pub retry_attempts: u32,
+    /// Delay before the first retry, doubling after each
+    pub retry_backoff: Duration,
+}
+
+impl Default for HttpsConfig {
+    fn default() -> Self {
+        HttpsConfig {
+            timeout: Duration::from_millis(5000),
+            retry_attempts: 3,
+            retry_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Remote share transport over pinned mutual TLS
+pub struct HttpsTransport {
+    identity: TlsIdentity,
+    config: HttpsConfig,
+    bearer_token: Option<(String, SessionToken)>,
+    provider: Arc<CryptoProvider>,
+}
+
+/// Failure of one attempt
+enum AttemptError {
+    /// Worth retrying: connection failure, timeout, 5xx
+    Transient(FrostError),
+    /// Retrying cannot help
+    Fatal(FrostError),
+}
+
+impl HttpsTransport {
+    /// Transport authenticating as `identity`
+    pub fn new(identity: TlsIdentity, config: HttpsConfig) -> Self {
+        HttpsTransport {
+            identity,
+            config,
+            bearer_token: None,
+            provider: Arc::new(rustls::crypto::ring::default_provider()),
+        }
+    }
+
+    /// Send `bearer` as `Authorization: Bearer` on every request
+    ///
+    /// `bearer` is the encoded CWT of `token`; signing requests are signed
+    /// with `token`'s holder key.
+    pub fn with_bearer_token(mut self, bearer: String, token: SessionToken) -> Self {
+        self.bearer_token = Some((bearer, token));
+        self
+    }
+
+    /// Holder signature for a signing request, if a token is configured
+    fn sign_request(&self, session_id: &SessionId, message_hash: &[u8; 32]) -> FrostResult<Option<SchnorrSignature>> {
+        self.bearer_token.as_ref()
+            .map(|(_, token)| token.sign_share_request(session_id, message_hash))
+            .transpose()
+    }
+
+    fn tls_config(&self, pin: [u8; 32]) -> FrostResult<ClientConfig> {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.identity.key_pkcs8.to_vec()));
+        let mut config = ClientConfig::builder_with_provider(self.provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(|e| FrostError::CryptoError(e.to_string()))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier { pin, provider: self.provider.clone() }))
+            .with_client_auth_cert(vec![CertificateDer::from(self.identity.cert_der.clone())], key)
+            .map_err(|e| FrostError::CryptoError(e.to_string()))?;
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+
+    /// Send a request, retrying transient failures if `idempotent`
+    async fn call<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        remote: &RemoteShareEndpoint,
+        method: Method,
+        path: &str,
+        body: Option<&Req>,
+        idempotent: bool,
+    ) -> FrostResult<Resp> {
+        let body = body.map(to_cbor).transpose()?;
+        let attempts = if idempotent { self.config.retry_attempts + 1 } else { 1 };
+        let mut backoff = self.config.retry_backoff;
+
+        for attempt in 1..=attempts {
+            let result = tokio::time::timeout(self.config.timeout, self.attempt(remote, method.clone(), path, body.clone()))
+                .await
+                .unwrap_or_else(|_| Err(AttemptError::Transient(FrostError::TransportError(format!(
+                    "{} timed out after {:?}", remote.endpoint_url, self.config.timeout,
+                )))));
+
+            match result {
+                Ok(bytes) => return from_cbor(&bytes),
+                Err(AttemptError::Transient(e)) if attempt < attempts => {
+                    log::warn!("Attempt {} to {}{} failed: {}", attempt, remote.endpoint_url, path, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(AttemptError::Transient(e)) | Err(AttemptError::Fatal(e)) => return Err(e),
+            }
+        }
+        unreachable!("at least one attempt is made")
+    }
+
+    async fn attempt(
+        &self,
+        remote: &RemoteShareEndpoint,
+        method: Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<Bytes, AttemptError> {
+        let (host, port) = parse_endpoint(&remote.endpoint_url).map_err(AttemptError::Fatal)?;
+        let server_name = ServerName::try_from(host.clone())
+            .map_err(|e| AttemptError::Fatal(FrostError::TransportError(e.to_string())))?;
+        let connector = TlsConnector::from(Arc::new(self.tls_config(remote.cert_fingerprint).map_err(AttemptError::Fatal)?));
+
+        let transient = |e: String| AttemptError::Transient(FrostError::TransportError(e));
+        let fatal = |e: String| AttemptError::Fatal(FrostError::TransportError(e));
+
+        let tcp = TcpStream::connect((host.as_str(), port)).await
+            .map_err(|e| transient(format!("{}: {}", remote.endpoint_url, e)))?;
+        let tls = connector.connect(server_name, tcp).await
+            .map_err(|e| fatal(format!("TLS with {}: {}", remote.endpoint_url, e)))?;
+
+        let (mut sender, connection) = hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(tls))
+            .await
+            .map_err(|e| transient(e.to_string()))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::debug!("HTTP/2 connection closed: {}", e);
+            }
+        });
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("https://{}:{}{}", host, port, path))
+            .header(hyper::header::CONTENT_TYPE, CBOR_CONTENT_TYPE);
+        if let Some((bearer, _)) = &self.bearer_token {
+            request = request.header(hyper::header::AUTHORIZATION, format!("Bearer {}", bearer));
+        }
+        let request = request.body(Full::new(Bytes::from(body.unwrap_or_default())))
+            .map_err(|e| fatal(e.to_string()))?;
+
+        let response = sender.send_request(request).await.map_err(|e| transient(e.to_string()))?;
+        let status = response.status();
+        let body = response.into_body().collect().await.map_err(|e| transient(e.to_string()))?.to_bytes();
+
+        if status == StatusCode::OK {
+            return Ok(body);
+        }
+        let reason = from_cbor::<ErrorResponse>(&body).map(|e| e.error).unwrap_or_default();
+        let error = FrostError::TransportError(format!("{} answered {}: {}", remote.endpoint_url, status, reason));
+        if status.is_server_error() {
+            Err(AttemptError::Transient(error))
+        } else {
+            Err(AttemptError::Fatal(error))
+        }
+    }
+}
+
+/// Host and port of an `https://host[:port][/...]` URL
+fn parse_endpoint(url: &str) -> FrostResult<(String, u16)> {
+    let invalid = || FrostError::TransportError(format!("Invalid share endpoint URL: {}", url));
+    let authority = url.strip_prefix("https://").ok_or_else(invalid)?
+        .split('/').next().ok_or_else(invalid)?;
+
+    // IPv6 literals are bracketed and contain colons of their own
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid())?,
+        None => 443,
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host.to_string(), port))
+}
+
+/// Accepts exactly the certificate whose SHA-256 fingerprint is pinned
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pin: [u8; 32],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if cert_fingerprint(end_entity) != self.pin {
+            return Err(rustls::Error::InvalidCertificate(rustls::CertificateError::ApplicationVerificationFailure));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[async_trait]
+impl RemoteShareTransport for HttpsTransport {
+    async fn request_commitment(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message_hash: &[u8; 32],
+    ) -> FrostResult<SigningCommitment> {
+        let request = Round1Request {
+            session_id: *session_id,
+            participant_id: remote.participant_id,
+            message_hash: *message_hash,
+            holder_signature: self.sign_request(session_id, message_hash)?,
+        };
+        let response: Round1Response = self.call(remote, Method::POST, ROUND1_PATH, Some(&request), true).await?;
+        Ok(response.commitment)
+    }
+
+    async fn request_partial(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message: &[u8],
+        commitments: &[SigningCommitment],
+    ) -> FrostResult<PartialSignature> {
+        let request = Round2Request {
+            session_id: *session_id,
+            participant_id: remote.participant_id,
+            message: message.to_vec(),
+            commitments: commitments.to_vec(),
+            holder_signature: self.sign_request(session_id, &message_hash(message))?,
+        };
+        // Only session token bodies start with the COSE Sig_structure prefix
+        let path = if message.starts_with(SIG_STRUCTURE_PREFIX) { ISSUE_TOKEN_PATH } else { ROUND2_PATH };
+        let response: Round2Response = self.call(remote, Method::POST, path, Some(&request), false).await?;
+        Ok(response.partial)
+    }
+
+    async fn health_check(&self, remote: &RemoteShareEndpoint) -> FrostResult<RemoteHealth> {
+        let started = Instant::now();
+        let response: HealthResponse = self.call(remote, Method::GET, HEALTH_PATH, None::<&()>, true).await?;
+        Ok(RemoteHealth {
+            available: response.available,
+            response_time_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn fetch_attestation(
+        &self,
+        remote: &RemoteShareEndpoint,
+        challenge: &[u8; 32],
+    ) -> FrostResult<ShareAttestation> {
+        let request = AttestationRequest {
+            participant_id: remote.participant_id,
+            challenge: *challenge,
+        };
+        self.call(remote, Method::POST, ATTESTATION_PATH, Some(&request), true).await
+    }
+
+    async fn fetch_revocation_list(
+        &self,
+        remote: &RemoteShareEndpoint,
+        since_sequence: Option<u64>,
+    ) -> FrostResult<RevocationList> {
+        let request = RevocationRequest { since_sequence };
+        self.call(remote, Method::POST, REVOCATIONS_PATH, Some(&request), true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dkg::DkgCoordinator;
+    use crate::hybrid::HybridFROSTDevice;
+    use crate::session_token::Capabilities;
+    use crate::transport::LoopbackTransport;
+    use hyper::body::Incoming;
+    use hyper::service::service_fn;
+    use hyper::Response;
+    use rand::rngs::OsRng;
+    use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+    use rustls::{DistinguishedName, ServerConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// Requires a client certificate and records it
+    #[derive(Debug)]
+    struct RecordingClientVerifier {
+        provider: Arc<CryptoProvider>,
+        seen: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl ClientCertVerifier for RecordingClientVerifier {
+        fn root_hint_subjects(&self) -> &[DistinguishedName] {
+            &[]
+        }
+
+        fn verify_client_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _now: UnixTime,
+        ) -> Result<ClientCertVerified, rustls::Error> {
+            self.seen.lock().unwrap().push(end_entity.to_vec());
+            Ok(ClientCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.provider.signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    struct TestServer {
+        url: String,
+        fingerprint: [u8; 32],
+        client_certs: Arc<RecordingClientVerifier>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    fn endpoint(id: u32, url: &str, fingerprint: [u8; 32]) -> RemoteShareEndpoint {
+        RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: "test".to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: url.to_string(),
+            cert_fingerprint: fingerprint,
+            available: true,
+            avg_response_time: 0,
+        }
+    }
+
+    fn respond<T: Serialize>(status: StatusCode, body: &T) -> Response<Full<Bytes>> {
+        let mut response = Response::new(Full::new(Bytes::from(to_cbor(body).unwrap())));
+        *response.status_mut() = status;
+        response
+    }
+
+    async fn handle(
+        shares: &LoopbackTransport,
+        request: Request<Incoming>,
+        fail_with: Option<StatusCode>,
+    ) -> Response<Full<Bytes>> {
+        if let Some(status) = fail_with {
+            return respond(status, &ErrorResponse { error: "unavailable".to_string() });
+        }
+        let path = request.uri().path().to_string();
+        let body = request.into_body().collect().await.unwrap().to_bytes();
+        let result = match path.as_str() {
+            ROUND1_PATH => {
+                let req: Round1Request = from_cbor(&body).unwrap();
+                let remote = endpoint(req.participant_id.as_u32(), "", [0u8; 32]);
+                shares.request_commitment(&remote, &req.session_id, &req.message_hash).await
+                    .map(|commitment| to_cbor(&Round1Response { commitment, timestamp: 0 }).unwrap())
+            }
+            ROUND2_PATH => {
+                let req: Round2Request = from_cbor(&body).unwrap();
+                let remote = endpoint(req.participant_id.as_u32(), "", [0u8; 32]);
+                shares.request_partial(&remote, &req.session_id, &req.message, &req.commitments).await
+                    .map(|partial| to_cbor(&Round2Response { partial }).unwrap())
+            }
+            HEALTH_PATH => Ok(to_cbor(&HealthResponse { available: true }).unwrap()),
+            ATTESTATION_PATH => {
+                let req: AttestationRequest = from_cbor(&body).unwrap();
+                let remote = endpoint(req.participant_id.as_u32(), "", [0u8; 32]);
+                shares.fetch_attestation(&remote, &req.challenge).await.map(|a| to_cbor(&a).unwrap())
+            }
+            _ => return respond(StatusCode::NOT_FOUND, &ErrorResponse { error: path }),
+        };
+        match result {
+            Ok(bytes) => Response::new(Full::new(Bytes::from(bytes))),
+            Err(e) => respond(StatusCode::BAD_REQUEST, &ErrorResponse { error: e.to_string() }),
+        }
+    }
+
+    /// HTTP/2 share server on localhost backed by `shares`
+    async fn serve(shares: LoopbackTransport, fail_with: Option<StatusCode>) -> TestServer {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_der = certified.cert.der().to_vec();
+        let client_certs = Arc::new(RecordingClientVerifier { provider: provider.clone(), seen: Mutex::new(Vec::new()) });
+
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_protocol_versions(&[&rustls::version::TLS13]).unwrap()
+            .with_client_cert_verifier(client_certs.clone())
+            .with_single_cert(
+                vec![CertificateDer::from(cert_der.clone())],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der())),
+            ).unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("https://localhost:{}", listener.local_addr().unwrap().port());
+        let shares = Arc::new(shares);
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let (acceptor, shares, counter) = (acceptor.clone(), shares.clone(), counter.clone());
+                tokio::spawn(async move {
+                    let Ok(tls) = acceptor.accept(tcp).await else { return };
+                    let service = service_fn(move |request| {
+                        let shares = shares.clone();
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        async move { Ok::<_, std::convert::Infallible>(handle(&shares, request, fail_with).await) }
+                    });
+                    let _ = hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+                        .serve_connection(TokioIo::new(tls), service)
+                        .await;
+                });
+            }
+        });
+
+        TestServer { url, fingerprint: cert_fingerprint(&cert_der), client_certs, requests }
+    }
+
+    fn fast_config() -> HttpsConfig {
+        HttpsConfig {
+            timeout: Duration::from_millis(500),
+            retry_attempts: 2,
+            retry_backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_signing_over_https() {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let group_pk = outputs[0].group_public_key.clone();
+        let server = serve(
+            LoopbackTransport::new(outputs[1..].iter().map(|o| o.secret_share.clone()).collect()),
+            None,
+        ).await;
+
+        let identity = TlsIdentity::from_seed(&[5u8; 32], &[6u8; 32]).unwrap();
+        let again = TlsIdentity::from_seed(&[5u8; 32], &[6u8; 32]).unwrap();
+        assert_eq!(identity.cert_der(), again.cert_der());
+        let client_cert = identity.cert_der().to_vec();
+
+        let remotes = [2u32, 3].map(|id| endpoint(id, &server.url, server.fingerprint));
+        let token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let mut device = HybridFROSTDevice::with_transport(
+            [1u8; 32],
+            Some(outputs[0].secret_share.clone()),
+            group_pk.clone(),
+            remotes.to_vec(),
+            HttpsTransport::new(identity, fast_config()).with_bearer_token("device-token".to_string(), token),
+        );
+
+        let message = b"hybrid over https";
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert!(group_pk.verify_signature(message, &signature));
+        assert!(device.attest_remote(ParticipantId::new(3).unwrap()).await.is_ok());
+        device.check_remote_health().await;
+        assert_eq!(device.get_current_mode(), crate::hybrid::SigningMode::Hybrid);
+
+        // The share saw the device's pinned client certificate
+        let seen = server.client_certs.seen.lock().unwrap();
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|cert| *cert == client_cert));
+    }
+
+    #[tokio::test]
+    async fn test_pinning_retries_and_timeouts() {
+        let identity = || TlsIdentity::from_seed(&[5u8; 32], &[6u8; 32]).unwrap();
+        let transport = HttpsTransport::new(identity(), fast_config());
+
+        // A certificate other than the pinned one is refused without retrying
+        let server = serve(LoopbackTransport::new(vec![]), None).await;
+        let wrong_pin = endpoint(2, &server.url, [0u8; 32]);
+        assert!(matches!(transport.health_check(&wrong_pin).await, Err(FrostError::TransportError(_))));
+        assert_eq!(server.client_certs.seen.lock().unwrap().len(), 0);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 0);
+
+        // 5xx answers are retried for idempotent calls only
+        let failing = serve(LoopbackTransport::new(vec![]), Some(StatusCode::SERVICE_UNAVAILABLE)).await;
+        let remote = endpoint(2, &failing.url, failing.fingerprint);
+        assert!(transport.health_check(&remote).await.is_err());
+        assert_eq!(failing.requests.load(Ordering::SeqCst), 3);
+        assert!(transport.request_partial(&remote, &[0u8; 32], b"m", &[]).await.is_err());
+        assert_eq!(failing.requests.load(Ordering::SeqCst), 4);
+
+        // 4xx answers are final
+        let remote = endpoint(2, &server.url, server.fingerprint);
+        assert!(transport.request_commitment(&remote, &[0u8; 32], &[0u8; 32]).await.is_err());
+        assert_eq!(server.requests.load(Ordering::SeqCst), 1);
+
+        // A server that never completes the handshake times out
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("https://localhost:{}", silent.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((tcp, _)) = silent.accept().await {
+                held.push(tcp);
+            }
+        });
+        let started = Instant::now();
+        let err = transport.health_check(&endpoint(2, &url, [0u8; 32])).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(parse_endpoint("https://share2.example.com").unwrap(), ("share2.example.com".to_string(), 443));
+        assert_eq!(parse_endpoint("https://10.0.0.2:8443/frost").unwrap(), ("10.0.0.2".to_string(), 8443));
+        assert_eq!(parse_endpoint("https://[::1]:8443").unwrap(), ("::1".to_string(), 8443));
+        assert_eq!(parse_endpoint("https://[::1]").unwrap(), ("::1".to_string(), 443));
+        assert_eq!(parse_endpoint("https://[fd00::2]/frost").unwrap(), ("fd00::2".to_string(), 443));
+        assert!(parse_endpoint("https://[::1]8443").is_err());
+        assert!(parse_endpoint("https://[::1").is_err());
+        assert!(parse_endpoint("http://share2.example.com").is_err());
+        assert!(parse_endpoint("https://:443").is_err());
+    }
+}
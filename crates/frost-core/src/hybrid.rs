@@ -6,9 +6,17 @@
 use crate::types::*;
 use crate::signing::*;
 use crate::session_token::*;
+use crate::nonce::{hedged_nonce, DefaultEntropy, EntropySource};
+use crate::revocation::RevocationList;
+use crate::refresh::RefreshScheduler;
+use crate::health::HealthTracker;
+use crate::policy::RequestContext;
+use crate::transport::{message_hash, DisconnectedTransport, RemoteShareTransport, ShareAttestation};
 use crate::{FrostError, FrostResult};
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use serde::{Serialize, Deserialize};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
 
 /// Hybrid FROST signing modes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,8 +34,40 @@ pub enum SigningMode {
     DegradedLocal,
 }
 
+/// Signature produced by `HybridFROSTDevice::sign`
+///
+/// The session token path does not produce a Schnorr signature over the
+/// message: its ephemeral key signs an operation hash, which only verifies
+/// against the token. It is kept a separate variant so it is never checked
+/// as a group signature.
+#[derive(Debug, Clone)]
+pub enum DeviceSignature {
+    /// Schnorr signature over the message, by the group or, in degraded
+    /// mode, the local share
+    Schnorr(SchnorrSignature),
+    /// Session token's signature; check it with `verify_token_operation`
+    /// against the token and `TokenRequest::sign_message(message)`
+    Token(TokenSignature),
+}
+
+impl DeviceSignature {
+    /// The Schnorr signature, unless this came from a session token
+    pub fn into_schnorr(self) -> Option<SchnorrSignature> {
+        match self {
+            DeviceSignature::Schnorr(signature) => Some(signature),
+            DeviceSignature::Token(_) => None,
+        }
+    }
+}
+
+/// Default bound on revocation staleness before offline tokens are refused
+pub const DEFAULT_REVOCATION_STALENESS: std::time::Duration = std::time::Duration::from_secs(24 * 3600);
+
 /// Hybrid FROST device
-pub struct HybridFROSTDevice {
+pub struct HybridFROSTDevice<T: RemoteShareTransport = DisconnectedTransport> {
+    /// Hardware identity the device's session tokens are bound to
+    device_id: [u8; 32],
+
     /// Local share (encrypted to PUF)
     local_share: Option<SecretShare>,
 
@@ -45,6 +85,19 @@ pub struct HybridFROSTDevice {
 
     /// Allow degraded mode
     allow_degraded: bool,
+
+    /// Randomness for degraded-mode nonces (the backing HAL's TRNG)
+    entropy: Box<dyn EntropySource + Send + Sync>,
+
+    /// Channel to the remote shares
+    transport: T,
+
+    /// Latency and circuit breakers per remote share
+    health: HealthTracker,
+
+    /// Region and network token policies are checked against, if known
+    region: Option<String>,
+    network: Option<String>,
 }
 
 /// Remote share endpoint configuration
@@ -72,68 +125,316 @@ pub struct RemoteShareEndpoint {
     pub avg_response_time: u64,
 }
 
+impl RemoteShareEndpoint {
+    /// Jurisdiction, the last comma-separated part of `location`
+    ///
+    /// "Zürich, CH" is in "CH"; a location without a comma is its own
+    /// jurisdiction.
+    pub fn jurisdiction(&self) -> &str {
+        self.location.rsplit(',').next().unwrap_or_default().trim()
+    }
+}
+
 impl HybridFROSTDevice {
-    /// Create new hybrid device
+    /// Create new hybrid device, with no transport to its remote shares yet
     pub fn new(
+        device_id: [u8; 32],
         local_share: Option<SecretShare>,
         group_public_key: GroupPublicKey,
         remote_shares: Vec<RemoteShareEndpoint>,
     ) -> Self {
+        Self::with_transport(device_id, local_share, group_public_key, remote_shares, DisconnectedTransport)
+    }
+}
+
+impl<T: RemoteShareTransport> HybridFROSTDevice<T> {
+    /// Create new hybrid device reaching its remote shares over `transport`
+    ///
+    /// `device_id` is the hardware identity session tokens are issued to.
+    pub fn with_transport(
+        device_id: [u8; 32],
+        local_share: Option<SecretShare>,
+        group_public_key: GroupPublicKey,
+        remote_shares: Vec<RemoteShareEndpoint>,
+        transport: T,
+    ) -> Self {
+        let mut token_cache = SessionTokenCache::new(20);
+        token_cache.set_staleness_bound(Some(DEFAULT_REVOCATION_STALENESS));
+
         HybridFROSTDevice {
+            device_id,
             local_share,
             group_public_key,
-            token_cache: SessionTokenCache::new(20),
+            token_cache,
             remote_shares,
             preferred_mode: SigningMode::Hybrid,
             allow_degraded: false,
+            entropy: Box::new(DefaultEntropy),
+            transport,
+            health: HealthTracker::default(),
+            region: None,
+            network: None,
         }
     }
 
     /// Sign a message using best available method
-    pub async fn sign(&mut self, message: &[u8]) -> FrostResult<SchnorrSignature> {
+    ///
+    /// Remote signing is bounded by the health policy's `sign_deadline`.
+    pub async fn sign(&mut self, message: &[u8]) -> FrostResult<DeviceSignature> {
+        let deadline = Instant::now() + self.health.policy().sign_deadline;
+        self.sign_with_deadline(message, deadline).await
+    }
+
+    /// Sign a message using best available method, giving up on remote
+    /// shares at `deadline`
+    ///
+    /// Once the deadline passes the offline paths are still tried, except
+    /// in full distributed mode, which never falls back to weaker paths.
+    pub async fn sign_with_deadline(&mut self, message: &[u8], deadline: Instant) -> FrostResult<DeviceSignature> {
         // Try each mode in order of security
 
-        // Mode 1: Hybrid (local + 1 remote)
-        if matches!(self.preferred_mode, SigningMode::Hybrid) {
-            if let Some(_local) = &self.local_share {
-                if let Ok(sig) = self.hybrid_sign(message).await {
-                    return Ok(sig);
-                }
+        // Mode 1: Full distributed (t remotes, local share unused)
+        if matches!(self.preferred_mode, SigningMode::FullDistributed) {
+            return self.distributed_sign(message, deadline).await.map(DeviceSignature::Schnorr);
+        }
+
+        // Mode 2: Hybrid (local + 1 remote)
+        if matches!(self.preferred_mode, SigningMode::Hybrid) && self.local_share.is_some() {
+            match self.hybrid_sign(message, deadline).await {
+                Ok(sig) => return Ok(DeviceSignature::Schnorr(sig)),
+                Err(e) => log::warn!("Hybrid signing failed: {}", e),
             }
         }
 
-        // Mode 2: Session token
-        if let Some(token) = self.token_cache.get_valid_token(&TokenRequest::DeviceUnlock) {
-            if let Ok(token_sig) = token.use_for_operation(TokenRequest::DeviceUnlock) {
-                // Convert token signature to Schnorr signature
-                // (In real impl, token has its own key)
-                return self.token_to_signature(token_sig);
+        // Mode 3: Session token, its ephemeral key signing for the message
+        let request = TokenRequest::sign_message(message);
+        let context = self.request_context();
+        if let Some(token) = self.token_cache.get_valid_token(&request, &context) {
+            if let Ok(token_sig) = token.use_for_operation(request, &context) {
+                return Ok(DeviceSignature::Token(token_sig));
             }
         }
 
-        // Mode 3: Degraded local-only (requires user consent)
+        // Mode 4: Degraded local-only (requires user consent)
         if self.allow_degraded {
             if let Some(local) = &self.local_share {
                 log::warn!("Using degraded local-only signing mode");
-                return self.local_only_sign(message, local);
+                return self.local_only_sign(message, local).map(DeviceSignature::Schnorr);
             }
         }
 
         Err(FrostError::CryptoError("No valid signing path available".to_string()))
     }
 
+    /// Full distributed signing: t remote shares, never the local one
+    ///
+    /// Round 1 goes to every usable remote at once and the first t to
+    /// commit form the signing set. Remotes that fail are dropped and a
+    /// fresh session is started with the rest, until too few are left or
+    /// `deadline` passes. Remotes left out of the set expire their
+    /// commitments on their side.
+    async fn distributed_sign(&mut self, message: &[u8], deadline: Instant) -> FrostResult<SchnorrSignature> {
+        let mut candidates: Vec<RemoteShareEndpoint> = self.health.rank(&self.remote_shares, Instant::now())
+            .into_iter()
+            .cloned()
+            .collect();
+
+        loop {
+            let mut outcomes = Vec::new();
+            let result = self.distributed_sign_with(&candidates, message, deadline, &mut outcomes).await;
+
+            let now = Instant::now();
+            let tried = candidates.len();
+            for (participant_id, outcome) in outcomes {
+                match outcome {
+                    Ok(round_trip) => self.health.record_success(participant_id, round_trip),
+                    Err(e) => {
+                        log::warn!("Distributed signing with participant {} failed: {}", participant_id.as_u32(), e);
+                        self.health.record_failure(participant_id, &e, now);
+                        candidates.retain(|r| r.participant_id != participant_id);
+                    }
+                }
+            }
+
+            match result {
+                Err(_) if candidates.len() < tried && now < deadline => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// One distributed signing session with the first t of `candidates`
+    ///
+    /// Pushes each remote's outcome to `outcomes`: the round trip of its
+    /// commitment if its partial signature verified, or why it failed.
+    async fn distributed_sign_with(
+        &self,
+        candidates: &[RemoteShareEndpoint],
+        message: &[u8],
+        deadline: Instant,
+        outcomes: &mut Vec<(ParticipantId, FrostResult<Duration>)>,
+    ) -> FrostResult<SchnorrSignature> {
+        let threshold = self.group_public_key.threshold;
+        if candidates.len() < threshold as usize {
+            return Err(FrostError::InsufficientParticipants(candidates.len(), threshold));
+        }
+        let stalled = |remote: &RemoteShareEndpoint| {
+            FrostError::TransportError(format!("Deadline passed waiting for {}", remote.operator))
+        };
+
+        let mut session_id = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut session_id);
+        let hash = message_hash(message);
+
+        // Round 1 to every candidate at once
+        let mut pending: FuturesUnordered<_> = candidates.iter()
+            .map(|remote| async move {
+                let started = Instant::now();
+                let result = self.transport.request_commitment(remote, &session_id, &hash).await;
+                (remote, started.elapsed(), result)
+            })
+            .collect();
+
+        let mut answered = Vec::new();
+        let mut signers = Vec::new();
+        while signers.len() < threshold as usize {
+            let (remote, round_trip, result) = match tokio::time::timeout_at(deadline, pending.next()).await {
+                Ok(Some(response)) => response,
+                Ok(None) => return Err(FrostError::InsufficientParticipants(signers.len(), threshold)),
+                Err(_) => {
+                    for remote in candidates.iter().filter(|r| !answered.contains(&r.participant_id)) {
+                        outcomes.push((remote.participant_id, Err(stalled(remote))));
+                    }
+                    return Err(FrostError::TransportError("Deadline passed in round 1".to_string()));
+                }
+            };
+            answered.push(remote.participant_id);
+
+            let id = remote.participant_id;
+            match result {
+                Ok(commitment) if commitment.participant_id != id => {
+                    outcomes.push((id, Err(FrostError::InvalidParticipantIndex(commitment.participant_id.as_u32()))));
+                }
+                Ok(commitment) if commitment.hiding.decompress().is_none() || commitment.binding.decompress().is_none() => {
+                    outcomes.push((id, Err(FrostError::CommitmentVerificationFailed(id.as_u32()))));
+                }
+                Ok(commitment) => signers.push((remote, round_trip, commitment)),
+                Err(e) => outcomes.push((id, Err(e))),
+            }
+        }
+        drop(pending);
+
+        // Round 2 with the signing set only
+        let mut commitments: Vec<SigningCommitment> = signers.iter().map(|(_, _, c)| c.clone()).collect();
+        commitments.sort_by_key(|c| c.participant_id.as_u32());
+        let r = group_commitment(message, &commitments)?;
+
+        let commitments = &commitments;
+        let mut pending: FuturesUnordered<_> = signers.iter()
+            .map(|(remote, round_trip, _)| async move {
+                let result = self.transport.request_partial(remote, &session_id, message, commitments).await;
+                (*remote, *round_trip, result)
+            })
+            .collect();
+
+        let mut answered = Vec::new();
+        let mut partials = Vec::with_capacity(signers.len());
+        loop {
+            let (remote, round_trip, result) = match tokio::time::timeout_at(deadline, pending.next()).await {
+                Ok(Some(response)) => response,
+                Ok(None) => break,
+                Err(_) => {
+                    for (remote, _, _) in signers.iter().filter(|(r, _, _)| !answered.contains(&r.participant_id)) {
+                        outcomes.push((remote.participant_id, Err(stalled(remote))));
+                    }
+                    return Err(FrostError::TransportError("Deadline passed in round 2".to_string()));
+                }
+            };
+            answered.push(remote.participant_id);
+
+            // Check each partial against its share, so a bad one names its signer
+            let id = remote.participant_id;
+            let valid = result.and_then(|partial| {
+                let share = self.group_public_key.participant_shares.iter()
+                    .find(|s| s.participant_id == id)
+                    .ok_or(FrostError::InvalidParticipantIndex(id.as_u32()))?;
+                if partial.participant_id == id && verify_partial_signature(message, commitments, &partial, share, &r, &self.group_public_key.public_key)? {
+                    Ok(partial)
+                } else {
+                    Err(FrostError::InvalidSignatureShare(id.as_u32()))
+                }
+            });
+            match valid {
+                Ok(partial) => {
+                    outcomes.push((id, Ok(round_trip)));
+                    partials.push(partial);
+                }
+                Err(e) => outcomes.push((id, Err(e))),
+            }
+        }
+        if partials.len() < signers.len() {
+            return Err(FrostError::InsufficientParticipants(partials.len(), threshold));
+        }
+
+        let signature = aggregate_signatures(&r.compress(), &partials)?;
+        if !self.group_public_key.verify_signature(message, &signature) {
+            return Err(FrostError::AggregationFailed);
+        }
+
+        Ok(signature)
+    }
+
     /// Hybrid signing: local share + 1 remote
-    async fn hybrid_sign(&self, message: &[u8]) -> FrostResult<SchnorrSignature> {
+    ///
+    /// Remotes are tried in the order the health tracker ranks them until
+    /// one completes. Every attempt starts a fresh round 1, so no local
+    /// nonce is reused after a remote fails mid-protocol; the abandoned
+    /// nonces never left the device in a partial signature.
+    async fn hybrid_sign(&mut self, message: &[u8], deadline: Instant) -> FrostResult<SchnorrSignature> {
+        if self.local_share.is_none() {
+            return Err(FrostError::CryptoError("No local share".to_string()));
+        }
+
+        let candidates: Vec<RemoteShareEndpoint> = self.health.rank(&self.remote_shares, Instant::now())
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut last_error = FrostError::CryptoError("No remote shares available".to_string());
+
+        for remote in &candidates {
+            let error = match tokio::time::timeout_at(deadline, self.hybrid_sign_with(remote, message)).await {
+                Ok(Ok((signature, round_trip))) => {
+                    self.health.record_success(remote.participant_id, round_trip);
+                    return Ok(signature);
+                }
+                Ok(Err(e)) => e,
+                Err(_) => {
+                    let e = FrostError::TransportError(format!("Deadline passed waiting for {}", remote.operator));
+                    self.health.record_failure(remote.participant_id, &e, Instant::now());
+                    return Err(e);
+                }
+            };
+            log::warn!("Signing with {} ({}) failed: {}", remote.operator, remote.location, error);
+            self.health.record_failure(remote.participant_id, &error, Instant::now());
+            last_error = error;
+        }
+
+        Err(last_error)
+    }
+
+    /// One hybrid signing attempt with `remote`
+    ///
+    /// Returns the signature and the round trip of the commitment request.
+    async fn hybrid_sign_with(
+        &self,
+        remote: &RemoteShareEndpoint,
+        message: &[u8],
+    ) -> FrostResult<(SchnorrSignature, Duration)> {
         let local_share = self.local_share.as_ref()
             .ok_or(FrostError::CryptoError("No local share".to_string()))?;
 
-        // Find best available remote share
-        let remote = self.remote_shares.iter()
-            .find(|r| r.available)
-            .ok_or(FrostError::CryptoError("No remote shares available".to_string()))?;
-
         // Start Round 1 locally
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rngs::OsRng;
         let local_round1 = SigningRound1::new(
             local_share.participant_id,
             local_share,
@@ -143,7 +444,15 @@ impl HybridFROSTDevice {
         let local_commitment = local_round1.commitment();
 
         // Request commitment from remote share
-        let remote_commitment = self.request_remote_commitment(remote, message).await?;
+        let mut session_id = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rng, &mut session_id);
+        log::info!("Requesting commitment from {} ({})", remote.operator, remote.location);
+        let started = Instant::now();
+        let remote_commitment = self.transport.request_commitment(remote, &session_id, &message_hash(message)).await?;
+        let round_trip = started.elapsed();
+        if remote_commitment.participant_id != remote.participant_id {
+            return Err(FrostError::InvalidParticipantIndex(remote_commitment.participant_id.as_u32()));
+        }
 
         let commitments = vec![local_commitment.clone(), remote_commitment];
 
@@ -152,16 +461,19 @@ impl HybridFROSTDevice {
         let local_partial = local_round2.partial_signature();
 
         // Get remote partial signature
-        let remote_partial = self.request_remote_partial(
+        let remote_partial = self.transport.request_partial(
             remote,
+            &session_id,
             message,
             &commitments,
         ).await?;
+        if remote_partial.participant_id != remote.participant_id {
+            return Err(FrostError::InvalidSignatureShare(remote_partial.participant_id.as_u32()));
+        }
 
         // Aggregate signatures
         let group_commitment = local_round2.group_commitment();
         let signature = aggregate_signatures(
-            message,
             &group_commitment,
             &[local_partial, remote_partial],
         )?;
@@ -171,39 +483,68 @@ impl HybridFROSTDevice {
             return Err(FrostError::CryptoError("Signature verification failed".to_string()));
         }
 
-        Ok(signature)
+        Ok((signature, round_trip))
     }
 
-    /// Request commitment from remote share (async network call)
-    async fn request_remote_commitment(
-        &self,
-        remote: &RemoteShareEndpoint,
-        message: &[u8],
-    ) -> FrostResult<SigningCommitment> {
-        // In real implementation:
-        // 1. Make HTTPS request to remote.endpoint_url
-        // 2. Send message hash + session ID
-        // 3. Verify TLS cert matches remote.cert_fingerprint
-        // 4. Receive commitment
-        // 5. Verify attestation
-
-        // Placeholder for simulation
-        log::info!("Requesting commitment from {} ({})", remote.operator, remote.location);
+    /// Health-check every remote share, updating availability and latency
+    ///
+    /// Remotes whose circuit is open are left alone until it half-opens.
+    pub async fn check_remote_health(&mut self) {
+        for remote in &mut self.remote_shares {
+            let id = remote.participant_id;
+            if !self.health.allows(id, Instant::now()) {
+                continue;
+            }
+            match self.transport.health_check(remote).await {
+                Ok(health) if health.available => {
+                    self.health.record_success(id, Duration::from_millis(health.response_time_ms));
+                    remote.available = true;
+                }
+                Ok(_) => {
+                    let e = FrostError::TransportError(format!("{} reports itself unavailable", remote.operator));
+                    self.health.record_failure(id, &e, Instant::now());
+                    remote.available = false;
+                }
+                Err(e) => {
+                    log::warn!("Health check of {} failed: {}", remote.operator, e);
+                    self.health.record_failure(id, &e, Instant::now());
+                    remote.available = false;
+                }
+            }
+            if let Some(latency) = self.health.avg_latency(id) {
+                remote.avg_response_time = latency.as_millis() as u64;
+            }
+        }
+    }
 
-        Err(FrostError::CryptoError("Network simulation not implemented".to_string()))
+    /// Remote share health
+    pub fn health(&self) -> &HealthTracker {
+        &self.health
     }
 
-    /// Request partial signature from remote share
-    async fn request_remote_partial(
-        &self,
-        remote: &RemoteShareEndpoint,
-        message: &[u8],
-        commitments: &[SigningCommitment],
-    ) -> FrostResult<PartialSignature> {
-        // Similar to request_remote_commitment
-        log::info!("Requesting partial signature from {}", remote.operator);
+    /// Replace the health tracker, e.g. to change its policy
+    pub fn set_health_tracker(&mut self, health: HealthTracker) {
+        self.health = health;
+    }
 
-        Err(FrostError::CryptoError("Network simulation not implemented".to_string()))
+    /// Fetch and check an attestation from the remote holding `participant_id`
+    pub async fn attest_remote(&self, participant_id: ParticipantId) -> FrostResult<ShareAttestation> {
+        let remote = self.remote_shares.iter()
+            .find(|r| r.participant_id == participant_id)
+            .ok_or(FrostError::InvalidParticipantIndex(participant_id.as_u32()))?;
+
+        let mut challenge = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut challenge);
+        let attestation = self.transport.fetch_attestation(remote, &challenge).await?;
+        if attestation.participant_id != participant_id || !attestation.verify(&self.group_public_key, &challenge) {
+            return Err(FrostError::CryptoError(format!("Attestation from {} does not verify", remote.operator)));
+        }
+        Ok(attestation)
+    }
+
+    /// Remote share transport
+    pub fn transport(&self) -> &T {
+        &self.transport
     }
 
     /// Local-only signing (degraded mode)
@@ -213,74 +554,159 @@ impl HybridFROSTDevice {
         local_share: &SecretShare,
     ) -> FrostResult<SchnorrSignature> {
         use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
-        use curve25519_dalek::scalar::Scalar;
-        use sha2::{Sha512, Digest};
 
         // Single-share signing (NOT threshold!)
         // This is less secure but allows offline operation
 
-        let mut rng = rand::thread_rng();
-        let nonce = Scalar::random(&mut rng);
-        let r = nonce * RISTRETTO_BASEPOINT_POINT;
-
-        // Compute challenge
-        let mut hasher = Sha512::new();
-        hasher.update(b"FROST-DEGRADED-v1");
-        hasher.update(r.compress().as_bytes());
-        hasher.update(message);
-        let challenge = Scalar::from_hash(hasher);
-
-        // Compute signature z = nonce + challenge * secret
-        let z = nonce + challenge * local_share.value.as_scalar();
-
-        let signature = SchnorrSignature {
-            z: z.to_bytes(),
-            commitment: r.compress(),
-        };
+        let secret = local_share.value.as_scalar();
+        let public_key = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+        let nonce = hedged_nonce(b"degraded-local", secret, &public_key, message, self.entropy.as_ref())?;
 
-        Ok(signature)
+        Ok(SchnorrSignature::sign_with_scheme(SignatureScheme::Degraded, secret, nonce.as_scalar(), message))
     }
 
-    /// Convert token signature to Schnorr signature
-    fn token_to_signature(&self, token_sig: TokenSignature) -> FrostResult<SchnorrSignature> {
-        // In real implementation, token has its own ephemeral key
-        // and can produce valid signatures
+    /// Pull the newest revocation list from the remote shares, best ranked first
+    pub async fn refresh_revocations(&mut self) -> FrostResult<()> {
+        let since = self.token_cache.revocation_sequence();
+        let mut last_error = FrostError::CryptoError("No remote shares available".to_string());
+
+        let candidates: Vec<RemoteShareEndpoint> = self.health.rank(&self.remote_shares, Instant::now())
+            .into_iter()
+            .cloned()
+            .collect();
+        for remote in &candidates {
+            match self.transport.fetch_revocation_list(remote, since).await {
+                Ok(list) if since.is_some_and(|s| list.sequence <= s) => {
+                    // Nothing newer: a re-issue of the held list keeps it current
+                    match self.token_cache.confirm_revocation_list(list, &self.group_public_key) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => last_error = e,
+                    }
+                }
+                Ok(list) => match self.token_cache.apply_revocation_list(list, &self.group_public_key) {
+                    Ok(purged) => {
+                        if purged > 0 {
+                            log::warn!("Purged {} revoked session tokens", purged);
+                        }
+                        return Ok(());
+                    }
+                    Err(e) => last_error = e,
+                },
+                Err(e) => last_error = e,
+            }
+        }
 
-        use curve25519_dalek::ristretto::CompressedRistretto;
+        Err(last_error)
+    }
 
-        let mut z = [0u8; 32];
-        z.copy_from_slice(&token_sig.signature[..32]);
+    /// Apply a revocation list received out of band
+    pub fn apply_revocation_list(&mut self, list: RevocationList) -> FrostResult<usize> {
+        self.token_cache.apply_revocation_list(list, &self.group_public_key)
+    }
 
-        Ok(SchnorrSignature {
-            z,
-            commitment: CompressedRistretto::default(),
-        })
+    /// Refuse offline tokens once revocation data is older than `bound`
+    pub fn set_revocation_staleness(&mut self, bound: std::time::Duration) {
+        self.token_cache.set_staleness_bound(Some(bound));
     }
 
     /// Refresh session tokens (call when online)
     pub async fn refresh_tokens(&mut self) -> FrostResult<()> {
+        // Being online is the chance to learn about revoked tokens
+        if let Err(e) = self.refresh_revocations().await {
+            log::warn!("Revocation refresh failed: {}", e);
+        }
+
         if !self.token_cache.needs_refresh() {
             return Ok(());
         }
 
-        // Generate new session token
-        let device_id = [0u8; 32];  // Get from hardware
-        let capabilities = Capabilities::default();
-        let lifetime = std::time::Duration::from_secs(4 * 3600);  // 4 hours
+        let token = self.issue_session_token(std::time::Duration::from_secs(4 * 3600)).await?;
+        self.token_cache.add_token(token)?;
+
+        log::info!("Refreshed session token cache ({} tokens)", self.token_cache.token_count());
+
+        Ok(())
+    }
 
-        let mut token = SessionToken::new(device_id, capabilities, lifetime);
+    /// Top the token cache up to `scheduler`'s target
+    ///
+    /// Returns the number of tokens issued. Outcomes are recorded on the
+    /// scheduler, which decides when to try next. Nothing is fetched when
+    /// no token is needed.
+    pub async fn refresh_with(&mut self, scheduler: &mut RefreshScheduler, now: u64) -> FrostResult<usize> {
+        let needed = scheduler.tokens_needed(&self.token_cache, now);
+        if needed == 0 {
+            scheduler.record_success(now, 0);
+            return Ok(0);
+        }
+
+        if let Err(e) = self.refresh_revocations().await {
+            log::warn!("Revocation refresh failed: {}", e);
+        }
+
+        let lifetime = scheduler.policy().token_lifetime;
+        let mut issued = 0;
+        for _ in 0..needed {
+            let token = match self.issue_session_token(lifetime).await {
+                Ok(token) => token,
+                Err(e) => {
+                    scheduler.record_failure(now, &e);
+                    return Err(e);
+                }
+            };
+            self.token_cache.add_token(token)?;
+            issued += 1;
+        }
+
+        scheduler.record_success(now, issued);
+        Ok(issued)
+    }
 
-        // Sign token with full FROST (requires network)
-        let token_data = token.to_signing_data();
-        let signature = self.sign(&token_data).await?;
+    /// Issue a session token signed by the FROST quorum
+    ///
+    /// Deliberately does not go through `sign`: a token must never be
+    /// vouched for by another token or by the local share alone.
+    async fn issue_session_token(&mut self, lifetime: std::time::Duration) -> FrostResult<SessionToken> {
+        let capabilities = Capabilities { sign_message: true, ..Capabilities::default() };
+        let mut token = SessionToken::new(self.device_id, capabilities, lifetime);
+
+        let deadline = Instant::now() + self.health.policy().sign_deadline;
+        let signature = match self.preferred_mode {
+            SigningMode::FullDistributed => self.distributed_sign(&token.to_signing_data(), deadline).await?,
+            _ => self.hybrid_sign(&token.to_signing_data(), deadline).await?,
+        };
+        if signature.scheme != SignatureScheme::Threshold {
+            return Err(FrostError::CryptoError("Session token must be threshold-signed".to_string()));
+        }
         token.frost_signature = signature;
 
-        // Add to cache
-        self.token_cache.add_token(token)?;
+        Ok(token)
+    }
 
-        log::info!("Refreshed session token cache ({} tokens)", self.token_cache.token_count());
+    /// Hardware identity session tokens are issued to
+    pub fn device_id(&self) -> &[u8; 32] {
+        &self.device_id
+    }
 
-        Ok(())
+    /// Session token cache
+    pub fn token_cache(&self) -> &SessionTokenCache {
+        &self.token_cache
+    }
+
+    /// Choose the threshold mode `sign` tries first
+    ///
+    /// `FullDistributed` signs with t remote shares and never touches the
+    /// local share, nor falls back when they cannot be reached; `Hybrid`
+    /// pairs the local share with one remote. Session tokens and degraded
+    /// mode are fallbacks, not preferences.
+    pub fn set_preferred_mode(&mut self, mode: SigningMode) -> FrostResult<()> {
+        match mode {
+            SigningMode::FullDistributed | SigningMode::Hybrid => {
+                self.preferred_mode = mode;
+                Ok(())
+            }
+            _ => Err(FrostError::CryptoError(format!("{:?} cannot be the preferred mode", mode))),
+        }
     }
 
     /// Enable/disable degraded mode
@@ -288,14 +714,46 @@ impl HybridFROSTDevice {
         self.allow_degraded = allow;
     }
 
+    /// Set the randomness source for degraded-mode nonces
+    pub fn set_entropy_source(&mut self, entropy: Box<dyn EntropySource + Send + Sync>) {
+        self.entropy = entropy;
+    }
+
+    /// Report where the device is, for session token policies
+    ///
+    /// Policies that look at an unknown region or network deny.
+    pub fn set_location(&mut self, region: Option<String>, network: Option<String>) {
+        self.region = region;
+        self.network = network;
+    }
+
+    /// Context session tokens are used in right now
+    fn request_context(&self) -> RequestContext {
+        RequestContext {
+            now: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            // Uses are not tallied here, so use-count policies deny
+            use_count: None,
+            region: self.region.clone(),
+            network: self.network.clone(),
+        }
+    }
+
     /// Get current signing mode capability
     pub fn get_current_mode(&self) -> SigningMode {
         // Check what's currently available
-        if self.local_share.is_some() && self.remote_shares.iter().any(|r| r.available) {
-            return SigningMode::Hybrid;
+        let now = Instant::now();
+        let usable = self.remote_shares.iter()
+            .filter(|r| r.available && self.health.allows(r.participant_id, now))
+            .count();
+        match self.preferred_mode {
+            SigningMode::FullDistributed if usable >= self.group_public_key.threshold as usize => {
+                return SigningMode::FullDistributed;
+            }
+            SigningMode::Hybrid if self.local_share.is_some() && usable > 0 => return SigningMode::Hybrid,
+            _ => {}
         }
 
-        if let Some(_token) = self.token_cache.get_valid_token(&TokenRequest::DeviceUnlock) {
+        if self.token_cache.has_valid_token(&TokenRequest::DeviceUnlock, &self.request_context()) {
             return SigningMode::SessionToken;
         }
 
@@ -378,6 +836,7 @@ mod tests {
         ];
 
         let device = HybridFROSTDevice::new(
+            [1u8; 32],
             Some(local_share),
             group_pk,
             remote_shares,
@@ -385,4 +844,576 @@ mod tests {
 
         assert_eq!(device.get_current_mode(), SigningMode::Hybrid);
     }
+
+    #[test]
+    fn test_every_signing_path_verifies() {
+        use crate::derived_key::{DerivationProof, DerivedDeviceKey, DeviceAttestation};
+        use crate::key_hierarchy::SigningPurpose;
+        use curve25519_dalek::scalar::Scalar;
+
+        let mut rng = OsRng;
+        let coordinator = DkgCoordinator::new(2, 3).unwrap();
+        let dkg_outputs = coordinator.run_dkg(&mut rng).unwrap();
+        let group_pk = dkg_outputs[0].group_public_key.clone();
+        let message = b"one verifier for every path";
+
+        // Threshold: two signers, two rounds
+        let round1: Vec<_> = dkg_outputs[..2].iter()
+            .map(|o| SigningRound1::new(o.participant_id, &o.secret_share, &mut rng))
+            .collect();
+        let commitments: Vec<_> = round1.iter().map(|r| r.commitment()).collect();
+        let round2: Vec<_> = round1.into_iter()
+            .map(|r| r.into_round2(message, &commitments).unwrap())
+            .collect();
+        let partials: Vec<_> = round2.iter().map(|r| r.partial_signature()).collect();
+        let threshold = aggregate_signatures(&round2[0].group_commitment(), &partials).unwrap();
+
+        // Degraded: the local share alone, checked against its verification share
+        let device = HybridFROSTDevice::new([1u8; 32], None, group_pk.clone(), Vec::new());
+        let local_share = &dkg_outputs[0].secret_share;
+        let degraded = device.local_only_sign(message, local_share).unwrap();
+        let share_pk = group_pk.participant_shares.iter()
+            .find(|s| s.participant_id == local_share.participant_id)
+            .unwrap()
+            .public_key;
+
+        // Derived device key and one of its plain Schnorr subkeys
+        let device_id = [9u8; 32];
+        let proof = DerivationProof {
+            timestamp: 0,
+            dkg_transcript_hash: [0u8; 32],
+            log_index: 0,
+            merkle_proof: vec![],
+            participant_signatures: vec![],
+            device_attestation: DeviceAttestation {
+                firmware_hash: [0u8; 32],
+                hardware_id: device_id,
+                tamper_status: 0,
+                boot_measurements: vec![],
+            },
+        };
+        let device_key = DerivedDeviceKey::from_secret(device_id, Scalar::random(&mut rng), proof, 1);
+        let derived = device_key.sign(message).unwrap();
+        let subkey = device_key.signing_subkey(SigningPurpose::Attestation);
+        let plain = subkey.sign(message).unwrap();
+
+        let cases = [
+            (threshold, SignatureScheme::Threshold, group_pk.public_key),
+            (degraded, SignatureScheme::Degraded, share_pk),
+            (derived, SignatureScheme::Derived, device_key.master_public.public_key),
+            (plain, SignatureScheme::Schnorr, subkey.public_key()),
+        ];
+        for (signature, scheme, public_key) in cases {
+            assert_eq!(signature.scheme, scheme);
+            assert!(signature.verify(scheme, message, &public_key));
+            assert!(!signature.verify(scheme, b"another message", &public_key));
+
+            // Neither a relabelled signature nor another expected scheme verifies
+            for other in [SignatureScheme::Schnorr, SignatureScheme::Threshold, SignatureScheme::Derived, SignatureScheme::Degraded] {
+                if other != scheme {
+                    let relabelled = SchnorrSignature { scheme: other, ..signature.clone() };
+                    assert!(!relabelled.verify(other, message, &public_key));
+                    assert!(!relabelled.verify(scheme, message, &public_key));
+                    assert!(!signature.verify(other, message, &public_key));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_degraded_signing_hedged_nonce() {
+        use crate::nonce::DeterministicEntropy;
+        use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+        use curve25519_dalek::scalar::Scalar;
+
+        let secret = Scalar::from_bytes_mod_order([0x55u8; 32]);
+        let public_key = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+        let local_share = SecretShare {
+            participant_id: ParticipantId::new(1).unwrap(),
+            value: SecretScalar::new(secret),
+            blinding: SecretScalar::new(Scalar::ZERO),
+            group_public_key: public_key,
+        };
+        let group_pk = crate::testing::group(&secret);
+
+        let mut device = HybridFROSTDevice::new([1u8; 32], None, group_pk, Vec::new());
+        device.set_entropy_source(Box::new(DeterministicEntropy([0u8; 32])));
+
+        let message = b"degraded mode known answer";
+        let signature = device.local_only_sign(message, &local_share).unwrap();
+        assert_eq!(hex::encode(signature.commitment.as_bytes()), "908cce418192c54468a3e8728a8c75ed13c40e8fe98ab183902d35e334d9ed4d");
+        assert_eq!(device.local_only_sign(message, &local_share).unwrap().z, signature.z);
+
+        device.set_entropy_source(Box::new(DeterministicEntropy([1u8; 32])));
+        let hedged = device.local_only_sign(message, &local_share).unwrap();
+        assert_ne!(hedged.commitment, signature.commitment);
+    }
+
+    /// Device holding a local share, with degraded mode allowed and no remotes
+    fn offline_device() -> HybridFROSTDevice {
+        use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+        use curve25519_dalek::scalar::Scalar;
+
+        let secret = Scalar::random(&mut OsRng);
+        let public_key = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+        let local_share = SecretShare {
+            participant_id: ParticipantId::new(1).unwrap(),
+            value: SecretScalar::new(secret),
+            blinding: SecretScalar::new(Scalar::ZERO),
+            group_public_key: public_key,
+        };
+        let group_pk = crate::testing::group(&secret);
+
+        let mut device = HybridFROSTDevice::new([1u8; 32], Some(local_share), group_pk, Vec::new());
+        device.set_allow_degraded(true);
+        device
+    }
+
+    #[tokio::test]
+    async fn test_refresh_never_issues_tokens_in_degraded_mode() {
+        use crate::refresh::RefreshPolicy;
+
+        let mut device = offline_device();
+        assert_eq!(device.get_current_mode(), SigningMode::DegradedLocal);
+
+        let mut scheduler = RefreshScheduler::new(RefreshPolicy::default());
+        assert!(device.refresh_with(&mut scheduler, 1_700_000_000).await.is_err());
+        assert!(device.refresh_tokens().await.is_err());
+        assert_eq!(device.token_cache().token_count(), 0);
+        assert_eq!(scheduler.health(device.token_cache(), 1_700_000_000).consecutive_failures, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_background_refresh_reports_health() {
+        use crate::refresh::{spawn_token_refresh, RefreshPolicy};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let device = Arc::new(tokio::sync::Mutex::new(offline_device()));
+        let policy = RefreshPolicy {
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(120),
+            ..RefreshPolicy::default()
+        };
+        let task = spawn_token_refresh(device.clone(), policy);
+
+        // Offline: attempts keep failing and back off, the device stays usable
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let health = task.health();
+        assert!(health.failures >= 3, "{:?}", health);
+        assert_eq!(health.consecutive_failures as u64, health.failures);
+        assert_eq!(health.valid_tokens, 0);
+        assert_eq!(health.tokens_needed, 5);
+        assert!(health.last_error.is_some());
+        assert!(device.lock().await.sign(b"still signs").await.is_ok());
+
+        task.stop();
+    }
+
+    /// 2-of-3 device with local share 1 and remotes 2 and 3 over loopback
+    fn loopback_device() -> (HybridFROSTDevice<crate::transport::LoopbackTransport>, GroupPublicKey) {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let group_pk = outputs[0].group_public_key.clone();
+        let remotes = [2u32, 3].map(|id| RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: "loopback".to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: String::new(),
+            cert_fingerprint: [0u8; 32],
+            available: true,
+            avg_response_time: 0,
+        });
+        let transport = crate::transport::LoopbackTransport::new(outputs[1..].iter().map(|o| o.secret_share.clone()).collect());
+        let device = HybridFROSTDevice::with_transport(
+            [1u8; 32],
+            Some(outputs[0].secret_share.clone()),
+            group_pk.clone(),
+            remotes.to_vec(),
+            transport,
+        );
+        (device, group_pk)
+    }
+
+    #[tokio::test]
+    async fn test_background_refresh_renews_inside_jitter_window() {
+        use crate::refresh::{spawn_token_refresh, RefreshPolicy};
+        use std::sync::Arc;
+
+        // A token expiring just past the renewal window, so the first
+        // attempt falls inside the jitter window
+        let (mut device, _) = loopback_device();
+        let policy = RefreshPolicy {
+            target_tokens: 1,
+            token_lifetime: Duration::from_secs(60),
+            renew_before: Duration::from_secs(2),
+            jitter: Duration::from_secs(10),
+            min_interval: Duration::from_secs(1),
+            ..RefreshPolicy::default()
+        };
+        let mut seed = RefreshScheduler::new(RefreshPolicy { token_lifetime: Duration::from_secs(3), ..policy.clone() });
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(device.refresh_with(&mut seed, now).await.unwrap(), 1);
+
+        // The attempt renews once, then sleeps until the new token is due
+        let device = Arc::new(tokio::sync::Mutex::new(device));
+        let task = spawn_token_refresh(device.clone(), policy);
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        let health = task.health();
+        assert_eq!((health.renewals, health.failures), (1, 0), "{:?}", health);
+        assert_eq!(health.tokens_needed, 0);
+        assert_eq!(device.lock().await.token_cache().tokens.iter().filter(|t| t.is_valid()).count(), 1);
+        task.stop();
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_signing_over_loopback() {
+        use crate::refresh::RefreshPolicy;
+        use crate::testing::now_context;
+
+        let (mut device, group_pk) = loopback_device();
+
+        let message = b"hybrid end to end";
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert_eq!(signature.scheme, SignatureScheme::Threshold);
+        assert!(group_pk.verify_signature(message, &signature));
+        assert_eq!(device.transport().open_sessions(), 0);
+        assert!(device.attest_remote(ParticipantId::new(2).unwrap()).await.is_ok());
+
+        // Tokens are issued by the quorum while online
+        let mut scheduler = RefreshScheduler::new(RefreshPolicy { target_tokens: 2, ..RefreshPolicy::default() });
+        assert_eq!(device.refresh_with(&mut scheduler, 1_700_000_000).await.unwrap(), 2);
+        assert!(device.token_cache().tokens.iter().all(|t| t.verify_frost_signature(&group_pk)));
+        assert!(device.token_cache().tokens.iter().all(|t| &t.device_id == device.device_id()));
+
+        // With the first remote down, the health check routes around it
+        device.transport().set_online(ParticipantId::new(2).unwrap(), false);
+        device.check_remote_health().await;
+        assert!(!device.remote_shares[0].available);
+        assert_eq!(device.get_current_mode(), SigningMode::Hybrid);
+        assert!(group_pk.verify_signature(message, &device.sign(message).await.unwrap().into_schnorr().unwrap()));
+
+        // With both down, the device falls back to its session tokens
+        device.transport().set_online(ParticipantId::new(3).unwrap(), false);
+        device.check_remote_health().await;
+        assert_eq!(device.get_current_mode(), SigningMode::SessionToken);
+        let DeviceSignature::Token(token_sig) = device.sign(message).await.unwrap() else {
+            panic!("expected a session token signature");
+        };
+        let token = device.token_cache().tokens.iter().find(|t| t.token_id == token_sig.token_id).unwrap();
+        assert!(verify_token_operation(&group_pk, token, &TokenRequest::sign_message(message), &token_sig, &now_context()).is_ok());
+        assert!(!group_pk.verify_signature(message, &token_sig.signature));
+    }
+
+    /// What goes wrong with the broken remote
+    #[derive(Clone, Copy)]
+    enum Fault {
+        /// Round 2 fails
+        FailPartial,
+        /// Round 2 returns a partial that does not verify
+        CorruptPartial,
+        /// Round 1 hangs
+        Stall(Duration),
+    }
+
+    /// Loopback with one faulty remote, recording what round 2 is sent
+    struct FlakyTransport {
+        inner: crate::transport::LoopbackTransport,
+        broken: ParticipantId,
+        fault: Fault,
+        /// Round 1 latency of the other remotes
+        honest_delay: Duration,
+        sent: std::sync::Mutex<Vec<(u32, Vec<SigningCommitment>)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RemoteShareTransport for FlakyTransport {
+        async fn request_commitment(
+            &self,
+            remote: &RemoteShareEndpoint,
+            session_id: &crate::transport::SessionId,
+            message_hash: &[u8; 32],
+        ) -> FrostResult<SigningCommitment> {
+            match self.fault {
+                Fault::Stall(stall) if remote.participant_id == self.broken => tokio::time::sleep(stall).await,
+                _ if remote.participant_id != self.broken => tokio::time::sleep(self.honest_delay).await,
+                _ => {}
+            }
+            self.inner.request_commitment(remote, session_id, message_hash).await
+        }
+
+        async fn request_partial(
+            &self,
+            remote: &RemoteShareEndpoint,
+            session_id: &crate::transport::SessionId,
+            message: &[u8],
+            commitments: &[SigningCommitment],
+        ) -> FrostResult<PartialSignature> {
+            self.sent.lock().unwrap().push((remote.participant_id.as_u32(), commitments.to_vec()));
+            let mut partial = self.inner.request_partial(remote, session_id, message, commitments).await?;
+            if remote.participant_id == self.broken {
+                match self.fault {
+                    Fault::FailPartial => return Err(FrostError::TransportError("connection reset".to_string())),
+                    Fault::CorruptPartial => partial.z += curve25519_dalek::scalar::Scalar::ONE,
+                    Fault::Stall(_) => {}
+                }
+            }
+            Ok(partial)
+        }
+
+        async fn health_check(&self, remote: &RemoteShareEndpoint) -> FrostResult<crate::transport::RemoteHealth> {
+            self.inner.health_check(remote).await
+        }
+
+        async fn fetch_attestation(
+            &self,
+            remote: &RemoteShareEndpoint,
+            challenge: &[u8; 32],
+        ) -> FrostResult<ShareAttestation> {
+            self.inner.fetch_attestation(remote, challenge).await
+        }
+    }
+
+    impl FlakyTransport {
+        fn signers(&self) -> Vec<u32> {
+            self.sent.lock().unwrap().iter().map(|(id, _)| *id).collect()
+        }
+    }
+
+    /// 2-of-n device with local share 1 and remotes 2..=n, remote 2 faulty
+    fn flaky_device(fault: Fault, participants: u32) -> (HybridFROSTDevice<FlakyTransport>, GroupPublicKey) {
+        let outputs = DkgCoordinator::new(2, participants).unwrap().run_dkg(&mut OsRng).unwrap();
+        let group_pk = outputs[0].group_public_key.clone();
+        let sites = [("Zürich, CH", 50), ("Frankfurt, DE", 80), ("Tokyo, JP", 300)];
+        let remotes = (2..=participants).zip(sites).map(|(id, (location, ms))| RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: location.to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: String::new(),
+            cert_fingerprint: [0u8; 32],
+            available: true,
+            avg_response_time: ms,
+        });
+        let transport = FlakyTransport {
+            inner: crate::transport::LoopbackTransport::new(outputs[1..].iter().map(|o| o.secret_share.clone()).collect()),
+            broken: ParticipantId::new(2).unwrap(),
+            fault,
+            honest_delay: Duration::ZERO,
+            sent: std::sync::Mutex::new(Vec::new()),
+        };
+        let device = HybridFROSTDevice::with_transport(
+            [1u8; 32],
+            Some(outputs[0].secret_share.clone()),
+            group_pk.clone(),
+            remotes.collect(),
+            transport,
+        );
+        (device, group_pk)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failover_after_remote_fails_mid_protocol() {
+        let (mut device, group_pk) = flaky_device(Fault::FailPartial, 3);
+        device.transport.honest_delay = Duration::from_millis(100);
+        let message = b"fail over";
+
+        // The faster remote fails round 2; the device retries with the other
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert!(group_pk.verify_signature(message, &signature));
+
+        assert_eq!(device.transport().signers(), vec![2, 3]);
+        let sent = device.transport().sent.lock().unwrap().clone();
+        let local = |commitments: &[SigningCommitment]| {
+            commitments.iter().find(|c| c.participant_id.as_u32() == 1).unwrap().hiding
+        };
+        assert_ne!(local(&sent[0].1), local(&sent[1].1), "local nonces reused across attempts");
+
+        let now = Instant::now();
+        let stats = device.health().stats(ParticipantId::new(2).unwrap(), now);
+        assert_eq!((stats.failures, stats.consecutive_failures), (1, 1));
+        assert_eq!(device.health().stats(ParticipantId::new(3).unwrap(), now).successes, 1);
+
+        // Slower than the failed remote's 50ms, the working remote is still
+        // tried first while the failure stands
+        device.transport().sent.lock().unwrap().clear();
+        assert!(device.sign(message).await.is_ok());
+        assert_eq!(device.transport().signers(), vec![3]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_signing_stays_within_deadline() {
+        let (mut device, _) = flaky_device(Fault::Stall(Duration::from_secs(30)), 2);
+        let started = Instant::now();
+
+        let result = device.sign_with_deadline(b"in time", started + Duration::from_secs(2)).await;
+        assert!(matches!(result, Err(FrostError::CryptoError(_))), "{:?}", result.map(|_| ()));
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        let stats = device.health().stats(ParticipantId::new(2).unwrap(), Instant::now());
+        assert_eq!(stats.last_error.as_deref(), Some("Transport error: Deadline passed waiting for share 2"));
+
+        // A degraded device still answers once the remotes have run out the clock
+        device.set_allow_degraded(true);
+        let started = Instant::now();
+        let signature = device.sign_with_deadline(b"in time", started + Duration::from_secs(2)).await.unwrap().into_schnorr().unwrap();
+        assert_eq!(signature.scheme, SignatureScheme::Degraded);
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_distributed_takes_first_responders() {
+        let (mut device, group_pk) = flaky_device(Fault::Stall(Duration::from_secs(30)), 4);
+        device.set_preferred_mode(SigningMode::FullDistributed).unwrap();
+        assert!(device.set_preferred_mode(SigningMode::SessionToken).is_err());
+        assert_eq!(device.get_current_mode(), SigningMode::FullDistributed);
+
+        // The stalled remote does not hold up the other two
+        let message = b"full distributed";
+        let started = Instant::now();
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert_eq!(signature.scheme, SignatureScheme::Threshold);
+        assert!(group_pk.verify_signature(message, &signature));
+
+        // Round 2 went to exactly that set, and the local share never committed
+        let mut signers = device.transport().signers();
+        signers.sort();
+        assert_eq!(signers, vec![3, 4]);
+        for (_, commitments) in device.transport().sent.lock().unwrap().iter() {
+            let ids: Vec<u32> = commitments.iter().map(|c| c.participant_id.as_u32()).collect();
+            assert_eq!(ids, vec![3, 4]);
+        }
+
+        // Quorum-issued tokens take the same path
+        let mut scheduler = RefreshScheduler::new(crate::refresh::RefreshPolicy { target_tokens: 1, ..Default::default() });
+        assert_eq!(device.refresh_with(&mut scheduler, 1_700_000_000).await.unwrap(), 1);
+        assert!(device.token_cache().tokens[0].verify_frost_signature(&group_pk));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_distributed_drops_invalid_partial() {
+        let (mut device, group_pk) = flaky_device(Fault::CorruptPartial, 4);
+        device.transport.honest_delay = Duration::from_millis(100);
+        device.set_preferred_mode(SigningMode::FullDistributed).unwrap();
+
+        // The corrupt remote answers first and is caught by its partial, then
+        // a fresh session runs without it
+        let message = b"bad partial";
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert!(group_pk.verify_signature(message, &signature));
+
+        let signers = device.transport().signers();
+        assert_eq!(signers.len(), 4);
+        assert!(signers[..2].contains(&2));
+        let mut retry = signers[2..].to_vec();
+        retry.sort();
+        assert_eq!(retry, vec![3, 4]);
+
+        let stats = device.health().stats(ParticipantId::new(2).unwrap(), Instant::now());
+        assert_eq!(stats.last_error.as_deref(), Some("Invalid signature share from participant 2"));
+
+        // With only the corrupt remote left there is no quorum, and no local
+        // fallback even when degraded signing is allowed
+        for id in [3, 4] {
+            device.transport().inner.set_online(ParticipantId::new(id).unwrap(), false);
+        }
+        device.check_remote_health().await;
+        device.set_allow_degraded(true);
+        assert!(matches!(device.sign(message).await, Err(FrostError::InsufficientParticipants(1, 2))));
+        assert_eq!(device.transport().signers().len(), 4);
+    }
+
+    /// Remotes that only serve revocation lists, one list each
+    struct ListTransport {
+        lists: std::sync::Mutex<std::collections::HashMap<u32, RevocationList>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RemoteShareTransport for ListTransport {
+        async fn request_commitment(
+            &self,
+            _remote: &RemoteShareEndpoint,
+            _session_id: &crate::transport::SessionId,
+            _message_hash: &[u8; 32],
+        ) -> FrostResult<SigningCommitment> {
+            Err(FrostError::TransportError("not signing".to_string()))
+        }
+
+        async fn request_partial(
+            &self,
+            _remote: &RemoteShareEndpoint,
+            _session_id: &crate::transport::SessionId,
+            _message: &[u8],
+            _commitments: &[SigningCommitment],
+        ) -> FrostResult<PartialSignature> {
+            Err(FrostError::TransportError("not signing".to_string()))
+        }
+
+        async fn health_check(&self, _remote: &RemoteShareEndpoint) -> FrostResult<crate::transport::RemoteHealth> {
+            Err(FrostError::TransportError("not serving".to_string()))
+        }
+
+        async fn fetch_attestation(
+            &self,
+            _remote: &RemoteShareEndpoint,
+            _challenge: &[u8; 32],
+        ) -> FrostResult<ShareAttestation> {
+            Err(FrostError::TransportError("not attesting".to_string()))
+        }
+
+        async fn fetch_revocation_list(
+            &self,
+            remote: &RemoteShareEndpoint,
+            _since_sequence: Option<u64>,
+        ) -> FrostResult<RevocationList> {
+            self.lists.lock().unwrap().get(&remote.participant_id.as_u32()).cloned()
+                .ok_or_else(|| FrostError::TransportError("no list".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revocation_refresh_skips_bad_remotes_and_replays() {
+        use crate::revocation::RevokedTokens;
+        use crate::testing::{group, signed_list};
+        use curve25519_dalek::scalar::Scalar;
+
+        let secret = Scalar::random(&mut OsRng);
+        let remotes = [(2u32, 50), (3, 80)].map(|(id, ms)| RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: "lists".to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: String::new(),
+            cert_fingerprint: [0u8; 32],
+            available: true,
+            avg_response_time: ms,
+        });
+        let transport = ListTransport { lists: std::sync::Mutex::new(std::collections::HashMap::new()) };
+        let mut device = HybridFROSTDevice::with_transport([1u8; 32], None, group(&secret), remotes.to_vec(), transport);
+        let serve = |device: &HybridFROSTDevice<ListTransport>, id: u32, list: &RevocationList| {
+            device.transport().lists.lock().unwrap().insert(id, list.clone());
+        };
+
+        // A remote serving a forged list does not stop the next one
+        let list = signed_list(&secret, 2, 1000, RevokedTokens::from_ids(vec![[7u8; 16]]));
+        let forged = signed_list(&Scalar::random(&mut OsRng), 3, 1000, RevokedTokens::from_ids(vec![]));
+        serve(&device, 2, &forged);
+        serve(&device, 3, &list);
+        device.refresh_revocations().await.unwrap();
+        assert_eq!(device.token_cache().revocation_sequence(), Some(2));
+
+        // Replays of the held list are refused; a re-issue is accepted
+        serve(&device, 2, &list);
+        assert!(matches!(device.refresh_revocations().await, Err(FrostError::RollbackDetected(_))));
+        let reissued = signed_list(&secret, 2, 2000, RevokedTokens::from_ids(vec![[7u8; 16]]));
+        serve(&device, 3, &reissued);
+        device.refresh_revocations().await.unwrap();
+        assert_eq!(device.token_cache().revocations.as_ref().unwrap().issued_at, 2000);
+
+        // A remote behind an open breaker is not asked
+        serve(&device, 3, &signed_list(&secret, 3, 3000, RevokedTokens::from_ids(vec![])));
+        let error = FrostError::TransportError("timeout".to_string());
+        for _ in 0..3 {
+            device.health.record_failure(ParticipantId::new(3).unwrap(), &error, Instant::now());
+        }
+        assert!(device.refresh_revocations().await.is_err());
+        assert_eq!(device.token_cache().revocation_sequence(), Some(2));
+    }
 }
@@ -0,0 +1,291 @@
+//! Purpose-specific subkeys under a `DerivedDeviceKey`
+//!
+//! The device key itself only anchors the hierarchy. Every use gets its own
+//! subkey, derived with HKDF-SHA512 and a labelled purpose string:
+//!
+//! ```text
+//! device key ─┬─ attestation           (SigningSubkey)
+//!             ├─ fido/<rp id hash>      (SigningSubkey, from the FIDO credential seed)
+//!             ├─ disk-encryption-kek    (EncryptionSubkey)
+//!             ├─ keychain-wrapping      (EncryptionSubkey)
+//!             ├─ session-token-cache    (EncryptionSubkey)
+//!             └─ tls/client-identity    (Ed25519 seed for mutual TLS)
+//! ```
+//!
+//! Signing and encryption subkeys are different types derived from
+//! different purpose enums, so a KEK cannot sign and an attestation key
+//! cannot wrap keys.
+
+use crate::derived_key::DerivedDeviceKey;
+use crate::nonce::{hedged_nonce, DefaultEntropy, EntropySource};
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use hkdf::Hkdf;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+/// Purposes a signing subkey can be derived for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningPurpose {
+    /// Device attestation statements
+    Attestation,
+    /// FIDO credential for one relying party (SHA-256 of the RP ID)
+    FidoCredential {
+        /// SHA-256 hash of the relying party ID
+        rp_id_hash: [u8; 32],
+    },
+}
+
+impl SigningPurpose {
+    fn label(&self) -> Vec<u8> {
+        match self {
+            SigningPurpose::Attestation => b"attestation".to_vec(),
+            SigningPurpose::FidoCredential { rp_id_hash } => {
+                let mut label = b"fido-credential-seed/".to_vec();
+                label.extend_from_slice(rp_id_hash);
+                label
+            }
+        }
+    }
+}
+
+/// Purposes an encryption subkey can be derived for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionPurpose {
+    /// Key-encryption key for full-disk encryption
+    DiskEncryptionKek,
+    /// Wrapping key for keychain items
+    KeychainWrapping,
+    /// At-rest key for the persisted session token cache
+    TokenCache,
+}
+
+impl EncryptionPurpose {
+    fn label(&self) -> Vec<u8> {
+        match self {
+            EncryptionPurpose::DiskEncryptionKek => b"disk-encryption-kek".to_vec(),
+            EncryptionPurpose::KeychainWrapping => b"keychain-wrapping".to_vec(),
+            EncryptionPurpose::TokenCache => b"session-token-cache".to_vec(),
+        }
+    }
+}
+
+/// Subkey that can only produce signatures
+pub struct SigningSubkey {
+    purpose: SigningPurpose,
+    secret: SecretScalar,
+    public_key: CompressedRistretto,
+}
+
+impl SigningSubkey {
+    /// Purpose this key was derived for
+    pub fn purpose(&self) -> SigningPurpose {
+        self.purpose
+    }
+
+    /// Public key
+    pub fn public_key(&self) -> CompressedRistretto {
+        self.public_key
+    }
+
+    /// Sign a message
+    pub fn sign(&self, message: &[u8]) -> FrostResult<SchnorrSignature> {
+        self.sign_with_entropy(message, &DefaultEntropy)
+    }
+
+    /// Sign with a hedged nonce drawing fresh randomness from `entropy`
+    pub fn sign_with_entropy(&self, message: &[u8], entropy: &dyn EntropySource) -> FrostResult<SchnorrSignature> {
+        let nonce = hedged_nonce(b"signing-subkey", self.secret.as_scalar(), &self.public_key, message, entropy)?;
+        Ok(SchnorrSignature::sign_with_nonce(self.secret.as_scalar(), nonce.as_scalar(), message))
+    }
+}
+
+/// Subkey that can only encrypt, wrap or agree on keys
+pub struct EncryptionSubkey {
+    purpose: EncryptionPurpose,
+    secret: SecretScalar,
+    public_key: CompressedRistretto,
+}
+
+impl EncryptionSubkey {
+    /// Purpose this key was derived for
+    pub fn purpose(&self) -> EncryptionPurpose {
+        self.purpose
+    }
+
+    /// Public ECDH key
+    pub fn public_key(&self) -> CompressedRistretto {
+        self.public_key
+    }
+
+    /// Symmetric key-encryption key for this purpose
+    pub fn symmetric_key(&self) -> Zeroizing<[u8; 32]> {
+        self.expand(b"symmetric", &[])
+    }
+
+    /// ECDH with a peer, returning a 32-byte shared key bound to both public keys
+    pub fn diffie_hellman(&self, peer_public: &CompressedRistretto) -> FrostResult<Zeroizing<[u8; 32]>> {
+        let peer = peer_public.decompress()
+            .ok_or(FrostError::CryptoError("Invalid peer public key".to_string()))?;
+        let shared = (self.secret.as_scalar() * peer).compress();
+
+        let mut context = Vec::with_capacity(96);
+        context.extend_from_slice(shared.as_bytes());
+        context.extend_from_slice(self.public_key.as_bytes());
+        context.extend_from_slice(peer_public.as_bytes());
+        Ok(self.expand(b"ecdh", &context))
+    }
+
+    fn expand(&self, usage: &[u8], context: &[u8]) -> Zeroizing<[u8; 32]> {
+        let hkdf = Hkdf::<Sha512>::new(Some(b"FROST-SUBKEY-ENCRYPTION-v1"), self.secret.as_scalar().as_bytes());
+        let mut info = self.purpose.label();
+        info.push(0);
+        info.extend_from_slice(usage);
+        info.extend_from_slice(context);
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(&info, key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA512 output length");
+        key
+    }
+}
+
+impl DerivedDeviceKey {
+    /// Derive the signing subkey for `purpose`
+    pub fn signing_subkey(&self, purpose: SigningPurpose) -> SigningSubkey {
+        let secret = self.subkey_scalar(b"signing", &purpose.label());
+        let public_key = (secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        SigningSubkey { purpose, secret, public_key }
+    }
+
+    /// Derive the encryption subkey for `purpose`
+    pub fn encryption_subkey(&self, purpose: EncryptionPurpose) -> EncryptionSubkey {
+        let secret = self.subkey_scalar(b"encryption", &purpose.label());
+        let public_key = (secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        EncryptionSubkey { purpose, secret, public_key }
+    }
+
+    /// Ed25519 seed for the device's mutual-TLS client certificate
+    ///
+    /// TLS stacks cannot use ristretto255 keys, so the transport key is a
+    /// separate Ed25519 key bound to this device key and version.
+    pub fn tls_client_seed(&self) -> Zeroizing<[u8; 32]> {
+        let mut info = b"tls/client-identity".to_vec();
+        info.extend_from_slice(self.device_id());
+        info.extend_from_slice(&self.version.to_le_bytes());
+
+        let mut seed = Zeroizing::new([0u8; 32]);
+        self.expand_subkey_material(&info, seed.as_mut());
+        seed
+    }
+
+    /// HKDF(device key, "<class>/<label>" || device_id || version) reduced to a scalar
+    fn subkey_scalar(&self, class: &[u8], label: &[u8]) -> SecretScalar {
+        let mut info = Vec::with_capacity(class.len() + 1 + label.len() + 36);
+        info.extend_from_slice(class);
+        info.push(b'/');
+        info.extend_from_slice(label);
+        info.extend_from_slice(self.device_id());
+        info.extend_from_slice(&self.version.to_le_bytes());
+
+        let mut okm = Zeroizing::new([0u8; 64]);
+        self.expand_subkey_material(&info, okm.as_mut());
+        SecretScalar::new(Scalar::from_bytes_mod_order_wide(&okm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derived_key::{DerivationProof, DeviceAttestation};
+
+    fn device_key(secret: u64) -> DerivedDeviceKey {
+        let device_id = [7u8; 32];
+        let proof = DerivationProof {
+            timestamp: 0,
+            dkg_transcript_hash: [0u8; 32],
+            log_index: 0,
+            merkle_proof: vec![],
+            participant_signatures: vec![],
+            device_attestation: DeviceAttestation {
+                firmware_hash: [0u8; 32],
+                hardware_id: device_id,
+                tamper_status: 0,
+                boot_measurements: vec![],
+            },
+        };
+        DerivedDeviceKey::from_secret(device_id, Scalar::from(secret), proof, 0)
+    }
+
+    #[test]
+    fn test_subkeys_are_distinct_per_purpose() {
+        let key = device_key(1234);
+
+        let attestation = key.signing_subkey(SigningPurpose::Attestation);
+        let fido_a = key.signing_subkey(SigningPurpose::FidoCredential { rp_id_hash: [1u8; 32] });
+        let fido_b = key.signing_subkey(SigningPurpose::FidoCredential { rp_id_hash: [2u8; 32] });
+        let kek = key.encryption_subkey(EncryptionPurpose::DiskEncryptionKek);
+        let keychain = key.encryption_subkey(EncryptionPurpose::KeychainWrapping);
+
+        let publics = [
+            key.master_public.public_key,
+            attestation.public_key(),
+            fido_a.public_key(),
+            fido_b.public_key(),
+            kek.public_key(),
+            keychain.public_key(),
+        ];
+        for (i, a) in publics.iter().enumerate() {
+            for b in &publics[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert_ne!(*kek.symmetric_key(), *keychain.symmetric_key());
+    }
+
+    #[test]
+    fn test_subkeys_are_deterministic() {
+        let key = device_key(1234);
+        let other = device_key(5678);
+
+        assert_eq!(
+            key.signing_subkey(SigningPurpose::Attestation).public_key(),
+            device_key(1234).signing_subkey(SigningPurpose::Attestation).public_key(),
+        );
+        assert_eq!(
+            *key.encryption_subkey(EncryptionPurpose::DiskEncryptionKek).symmetric_key(),
+            *device_key(1234).encryption_subkey(EncryptionPurpose::DiskEncryptionKek).symmetric_key(),
+        );
+        assert_ne!(
+            key.signing_subkey(SigningPurpose::Attestation).public_key(),
+            other.signing_subkey(SigningPurpose::Attestation).public_key(),
+        );
+    }
+
+    #[test]
+    fn test_signing_subkey_signs() {
+        let key = device_key(1234);
+        let attestation = key.signing_subkey(SigningPurpose::Attestation);
+
+        let message = b"measured boot: ok";
+        let signature = attestation.sign(message).unwrap();
+        assert!(signature.verify(SignatureScheme::Schnorr, message, &attestation.public_key()));
+        assert!(!signature.verify(SignatureScheme::Schnorr, message, &key.master_public.public_key));
+    }
+
+    #[test]
+    fn test_encryption_subkey_agreement() {
+        let device = device_key(1234).encryption_subkey(EncryptionPurpose::KeychainWrapping);
+        let peer = device_key(5678).encryption_subkey(EncryptionPurpose::KeychainWrapping);
+
+        let a = device.diffie_hellman(&peer.public_key()).unwrap();
+        let b = peer.diffie_hellman(&device.public_key()).unwrap();
+        assert_ne!(*a, *b);  // Bound to (own, peer) ordering
+
+        let kek = device_key(1234).encryption_subkey(EncryptionPurpose::DiskEncryptionKek);
+        assert_ne!(*kek.diffie_hellman(&peer.public_key()).unwrap(), *a);
+    }
+}
@@ -0,0 +1,725 @@
+//! Tamper-resistant usage ledger for offline session tokens
+//!
+//! A token's spending state cannot live on the token: restoring an old copy
+//! of the token cache would restore the allowance. Instead every offline use
+//! is appended to a hash-chained ledger whose length is pinned by a hardware
+//! monotonic counter:
+//!
+//! ```text
+//! entry_i.hash = H(tag || entry_{i-1}.hash || seq || device_id || token_id || timestamp || operation || amount)
+//! counter      = seq of the last entry
+//! ```
+//!
+//! Rolling storage back leaves the chain shorter than the counter, which is
+//! detected on load. Daily allowances are computed from the ledger, per
+//! device across all of its tokens, in windows anchored at the paying
+//! token's `PaymentLimits::daily_reset_at`, so holding several tokens does
+//! not multiply the allowance; the clock
+//! is never allowed to run backwards past the newest entry. Uses are also
+//! tallied per token ID, which attenuated copies share, for `UseCountBelow`
+//! policies. When online, the device hands its entries to the issuer, which
+//! checks them with `reconcile` and can spot any overspend.
+
+use crate::policy::RequestContext;
+use crate::session_token::{PaymentLimits, SessionToken, TokenRequest, TokenSignature};
+use crate::{FrostError, FrostResult};
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+
+/// Length of one allowance window (seconds)
+pub const DAY_SECONDS: u64 = 86_400;
+
+/// Clock skew tolerated before a backwards clock is treated as rollback
+pub const CLOCK_SKEW_TOLERANCE: u64 = 300;
+
+/// Persistent backing for the ledger (secure storage + monotonic counter)
+pub trait LedgerStore {
+    /// Load the serialized ledger, `None` if nothing was stored yet
+    fn load(&self) -> FrostResult<Option<Vec<u8>>>;
+
+    /// Replace the serialized ledger
+    fn store(&mut self, data: &[u8]) -> FrostResult<()>;
+
+    /// Current value of the monotonic counter
+    fn counter(&self) -> FrostResult<u64>;
+
+    /// Increment the monotonic counter, returning the new value
+    fn increment_counter(&mut self) -> FrostResult<u64>;
+}
+
+/// One recorded token use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Position in the ledger (1-based, equals the counter after this entry)
+    pub sequence: u64,
+    /// Device the token is bound to
+    pub device_id: [u8; 32],
+    /// Token the operation was performed under
+    pub token_id: [u8; 16],
+    /// Time of use (Unix epoch seconds, never earlier than the previous entry)
+    pub timestamp: u64,
+    /// `TokenRequest::to_bytes` of the operation
+    pub operation: Vec<u8>,
+    /// Amount spent (cents), zero for non-payment operations
+    pub amount: u64,
+    /// Hash of the previous entry
+    pub prev_hash: [u8; 32],
+    /// Hash of this entry
+    pub hash: [u8; 32],
+}
+
+impl LedgerEntry {
+    fn compute_hash(
+        prev_hash: &[u8; 32],
+        sequence: u64,
+        device_id: &[u8; 32],
+        token_id: &[u8; 16],
+        timestamp: u64,
+        operation: &[u8],
+        amount: u64,
+    ) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"FROST-USAGE-LEDGER-v3");
+        hasher.update(prev_hash);
+        hasher.update(sequence.to_le_bytes());
+        hasher.update(device_id);
+        hasher.update(token_id);
+        hasher.update(timestamp.to_le_bytes());
+        hasher.update((operation.len() as u32).to_le_bytes());
+        hasher.update(operation);
+        hasher.update(amount.to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Recompute the hash and check it against `prev_hash`
+    pub fn verify(&self, prev_hash: &[u8; 32]) -> bool {
+        &self.prev_hash == prev_hash
+            && self.hash == Self::compute_hash(
+                prev_hash,
+                self.sequence,
+                &self.device_id,
+                &self.token_id,
+                self.timestamp,
+                &self.operation,
+                self.amount,
+            )
+    }
+}
+
+/// Uses recorded under one token ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TokenUses {
+    token_id: [u8; 16],
+    /// Expiry of the root token; the tally is dropped after it
+    expires_at: u64,
+    count: u64,
+}
+
+/// Serialized ledger state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LedgerState {
+    /// Sequence of the last pruned (reconciled) entry
+    anchor_sequence: u64,
+    /// Hash of the last pruned entry
+    anchor_hash: [u8; 32],
+    /// Newest timestamp ever recorded
+    high_water_time: u64,
+    /// Unreconciled entries
+    entries: Vec<LedgerEntry>,
+    /// Uses per unexpired token, surviving `acknowledge`
+    #[serde(default)]
+    uses: Vec<TokenUses>,
+}
+
+impl LedgerState {
+    fn head(&self) -> (u64, [u8; 32]) {
+        self.entries.last()
+            .map(|e| (e.sequence, e.hash))
+            .unwrap_or((self.anchor_sequence, self.anchor_hash))
+    }
+
+    fn verify_chain(&self) -> bool {
+        let mut sequence = self.anchor_sequence;
+        let mut hash = self.anchor_hash;
+        for entry in &self.entries {
+            if entry.sequence != sequence + 1 || !entry.verify(&hash) {
+                return false;
+            }
+            sequence = entry.sequence;
+            hash = entry.hash;
+        }
+        true
+    }
+}
+
+/// Device-side usage ledger
+pub struct UsageLedger<S: LedgerStore> {
+    store: S,
+    state: LedgerState,
+}
+
+impl<S: LedgerStore> UsageLedger<S> {
+    /// Open the ledger, checking the chain and the monotonic counter
+    pub fn open(mut store: S) -> FrostResult<Self> {
+        let state = match store.load()? {
+            Some(data) => serde_json::from_slice(&data)
+                .map_err(|e| FrostError::SerializationError(e.to_string()))?,
+            None => LedgerState::default(),
+        };
+
+        if !state.verify_chain() {
+            return Err(FrostError::RollbackDetected("Usage ledger hash chain is broken".to_string()));
+        }
+
+        // Entries are written before the counter is bumped, so the stored
+        // head may be one ahead after a crash; anything else is a rollback.
+        let (head, _) = state.head();
+        let counter = store.counter()?;
+        if head == counter + 1 {
+            store.increment_counter()?;
+        } else if head != counter {
+            return Err(FrostError::RollbackDetected(format!(
+                "Usage ledger at {} but monotonic counter at {}",
+                head, counter,
+            )));
+        }
+
+        Ok(UsageLedger { store, state })
+    }
+
+    /// Use `token` for `operation`, enforcing limits from the ledger
+    ///
+    /// The only way to make a payment with a token: the daily spend comes
+    /// from the ledger, not from the token, and counts every token of the
+    /// same device. Other operations may also be recorded here rather than
+    /// through `SessionToken::use_for_operation`, and must be for tokens
+    /// with a `UseCountBelow` policy: the use count comes from the ledger,
+    /// shared by every copy attenuated from the same token. The ledger's
+    /// clock may move `context.now` forward, never back.
+    pub fn record_use(
+        &mut self,
+        token: &mut SessionToken,
+        operation: TokenRequest,
+        context: &RequestContext,
+    ) -> FrostResult<TokenSignature> {
+        let now = self.effective_time(context.now)?;
+        let context = RequestContext {
+            now,
+            use_count: Some(self.uses(&token.token_id)),
+            ..context.clone()
+        };
+
+        if let TokenRequest::Payment { amount } = operation {
+            let limits = token.capabilities.payment_limits.as_mut()
+                .ok_or(FrostError::CryptoError("Token does not allow operation".to_string()))?;
+            let spent = spent_in_window(&self.state.entries, &token.device_id, limits, now);
+            if spent.saturating_add(amount) > limits.max_per_day {
+                return Err(FrostError::CryptoError("Daily payment limit reached".to_string()));
+            }
+            // The token's own counter is only a cached view of the ledger
+            limits.remaining_today = limits.max_per_day - spent;
+        }
+
+        if !token.allows_operation(&operation, &context) {
+            return Err(FrostError::CryptoError("Token does not allow operation".to_string()));
+        }
+
+        // Log before signing: a crash in between costs allowance, never grants it
+        self.append(token, &operation, now)?;
+        token.perform_operation(operation, &context)
+    }
+
+    /// Amount `token`'s device has spent in the token's current window
+    pub fn spent_today(&self, token: &SessionToken, now: u64) -> u64 {
+        match &token.capabilities.payment_limits {
+            Some(limits) => {
+                let now = now.max(self.state.high_water_time);
+                spent_in_window(&self.state.entries, &token.device_id, limits, now)
+            }
+            None => 0,
+        }
+    }
+
+    /// Uses recorded under `token_id`, by the token and its attenuated copies
+    pub fn uses(&self, token_id: &[u8; 16]) -> u64 {
+        self.state.uses.iter()
+            .find(|u| &u.token_id == token_id)
+            .map_or(0, |u| u.count)
+    }
+
+    /// Close the ledger, returning its store
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Unreconciled entries, for upload to the issuer
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.state.entries
+    }
+
+    /// Sequence and hash the unreconciled entries chain from
+    pub fn anchor(&self) -> (u64, [u8; 32]) {
+        (self.state.anchor_sequence, self.state.anchor_hash)
+    }
+
+    /// Drop entries up to `sequence` once the issuer has reconciled them
+    ///
+    /// Entries still inside a spending window are kept.
+    pub fn acknowledge(&mut self, sequence: u64, now: u64) -> FrostResult<()> {
+        let cutoff = now.max(self.state.high_water_time).saturating_sub(DAY_SECONDS);
+        let keep_from = self.state.entries.iter()
+            .position(|e| e.sequence > sequence || e.timestamp >= cutoff)
+            .unwrap_or(self.state.entries.len());
+
+        if let Some(last) = keep_from.checked_sub(1).map(|i| &self.state.entries[i]) {
+            self.state.anchor_sequence = last.sequence;
+            self.state.anchor_hash = last.hash;
+        }
+        self.state.entries.drain(..keep_from);
+        self.persist()
+    }
+
+    /// Current time, refusing to go back before the newest entry
+    fn effective_time(&self, now: u64) -> FrostResult<u64> {
+        if now + CLOCK_SKEW_TOLERANCE < self.state.high_water_time {
+            return Err(FrostError::RollbackDetected(format!(
+                "Clock at {} but ledger already at {}",
+                now, self.state.high_water_time,
+            )));
+        }
+        Ok(now.max(self.state.high_water_time))
+    }
+
+    fn append(&mut self, token: &SessionToken, operation: &TokenRequest, now: u64) -> FrostResult<()> {
+        let (device_id, token_id) = (token.device_id, token.token_id);
+        let (head, prev_hash) = self.state.head();
+        let sequence = head + 1;
+        let operation_bytes = operation.to_bytes();
+        let amount = match operation {
+            TokenRequest::Payment { amount } => *amount,
+            _ => 0,
+        };
+
+        self.state.entries.push(LedgerEntry {
+            sequence,
+            device_id,
+            token_id,
+            timestamp: now,
+            hash: LedgerEntry::compute_hash(&prev_hash, sequence, &device_id, &token_id, now, &operation_bytes, amount),
+            operation: operation_bytes,
+            amount,
+            prev_hash,
+        });
+        self.state.high_water_time = now;
+
+        self.state.uses.retain(|u| u.expires_at > now);
+        match self.state.uses.iter_mut().find(|u| u.token_id == token_id) {
+            Some(uses) => uses.count += 1,
+            None => self.state.uses.push(TokenUses { token_id, expires_at: token.expires_at, count: 1 }),
+        }
+
+        self.persist()?;
+        let counter = self.store.increment_counter()?;
+        if counter != sequence {
+            return Err(FrostError::RollbackDetected(format!(
+                "Monotonic counter at {} after writing entry {}",
+                counter, sequence,
+            )));
+        }
+        Ok(())
+    }
+
+    fn persist(&mut self) -> FrostResult<()> {
+        let data = serde_json::to_vec(&self.state)
+            .map_err(|e| FrostError::SerializationError(e.to_string()))?;
+        self.store.store(&data)
+    }
+}
+
+/// Index of the allowance window containing `time`
+fn window(limits: &PaymentLimits, time: u64) -> u64 {
+    time.saturating_sub(limits.daily_reset_at) / DAY_SECONDS
+}
+
+/// Amount `device_id` spent, under any token, in the window of `limits` containing `now`
+fn spent_in_window(entries: &[LedgerEntry], device_id: &[u8; 32], limits: &PaymentLimits, now: u64) -> u64 {
+    let current = window(limits, now);
+    entries.iter()
+        .filter(|e| &e.device_id == device_id && window(limits, e.timestamp) == current)
+        .map(|e| e.amount)
+        .sum()
+}
+
+/// Daily limit exceeded by a device, found during reconciliation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overspend {
+    /// Device that overspent
+    pub device_id: [u8; 32],
+    /// Token whose limit was exceeded
+    pub token_id: [u8; 16],
+    /// Allowance window index (days since the token's `daily_reset_at`)
+    pub window: u64,
+    /// Amount the device spent in the window, across all its tokens
+    pub spent: u64,
+    /// Token's signed daily limit
+    pub limit: u64,
+}
+
+/// Issuer's view of an uploaded ledger segment
+#[derive(Debug, Clone, Default)]
+pub struct ReconciliationReport {
+    /// Sequence of the last entry checked
+    pub head_sequence: u64,
+    /// Hash of the last entry checked
+    pub head_hash: [u8; 32],
+    /// Devices that spent more than a token's daily limit
+    pub overspends: Vec<Overspend>,
+    /// Entries naming tokens the issuer does not know
+    pub unknown_tokens: Vec<[u8; 16]>,
+}
+
+/// Check an uploaded ledger segment against the issued tokens
+///
+/// `anchor` is the (sequence, hash) the issuer last reconciled to. Fails if
+/// the chain is broken, skips entries or does not continue from `anchor`.
+pub fn reconcile(
+    anchor: (u64, [u8; 32]),
+    entries: &[LedgerEntry],
+    issued: &HashMap<[u8; 16], Option<PaymentLimits>>,
+) -> FrostResult<ReconciliationReport> {
+    let (mut sequence, mut hash) = anchor;
+    let mut last_time = 0;
+    let mut report = ReconciliationReport::default();
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.sequence != sequence + 1 || !entry.verify(&hash) {
+            return Err(FrostError::RollbackDetected(format!(
+                "Ledger entry {} does not continue from {}",
+                entry.sequence, sequence,
+            )));
+        }
+        if entry.timestamp < last_time {
+            return Err(FrostError::RollbackDetected(format!("Ledger entry {} goes back in time", entry.sequence)));
+        }
+        sequence = entry.sequence;
+        hash = entry.hash;
+        last_time = entry.timestamp;
+
+        match issued.get(&entry.token_id) {
+            None => {
+                if !report.unknown_tokens.contains(&entry.token_id) {
+                    report.unknown_tokens.push(entry.token_id);
+                }
+            }
+            Some(limits) => {
+                if entry.amount == 0 {
+                    continue;
+                }
+                // The device's spend in this token's window, as the device
+                // itself must have computed it
+                let (limit, per_transaction, window) = match limits {
+                    Some(l) => (l.max_per_day, l.max_per_transaction, window(l, entry.timestamp)),
+                    None => (0, 0, 0),
+                };
+                let total = match limits {
+                    Some(l) => spent_in_window(&entries[..=index], &entry.device_id, l, entry.timestamp),
+                    None => entry.amount,
+                };
+                if total > limit || entry.amount > per_transaction {
+                    let key = (entry.device_id, entry.token_id, window);
+                    match report.overspends.iter_mut().find(|o| (o.device_id, o.token_id, o.window) == key) {
+                        Some(o) => o.spent = total,
+                        None => report.overspends.push(Overspend {
+                            device_id: entry.device_id,
+                            token_id: entry.token_id,
+                            window,
+                            spent: total,
+                            limit,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    report.head_sequence = sequence;
+    report.head_hash = hash;
+    Ok(report)
+}
+
+/// In-memory `LedgerStore` for simulation and tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLedgerStore {
+    data: Option<Vec<u8>>,
+    counter: u64,
+}
+
+impl LedgerStore for MemoryLedgerStore {
+    fn load(&self) -> FrostResult<Option<Vec<u8>>> {
+        Ok(self.data.clone())
+    }
+
+    fn store(&mut self, data: &[u8]) -> FrostResult<()> {
+        self.data = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn counter(&self) -> FrostResult<u64> {
+        Ok(self.counter)
+    }
+
+    fn increment_counter(&mut self) -> FrostResult<u64> {
+        self.counter += 1;
+        Ok(self.counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_token::Capabilities;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn at(now: u64) -> RequestContext {
+        RequestContext { now, ..RequestContext::default() }
+    }
+
+    /// Token with $100/transaction, $500/day, windows starting at `reset_at`,
+    /// valid from then for the next three days
+    fn token(reset_at: u64) -> SessionToken {
+        let mut caps = Capabilities::default();
+        caps.payment_limits.as_mut().unwrap().daily_reset_at = reset_at;
+        let mut token = SessionToken::new([1u8; 32], caps, Duration::from_secs(3 * DAY_SECONDS));
+        token.issued_at = token.issued_at.min(reset_at);
+        token
+    }
+
+    fn pay(amount: u64) -> TokenRequest {
+        TokenRequest::Payment { amount }
+    }
+
+    #[test]
+    fn test_daily_limit_enforced_from_ledger() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+
+        for _ in 0..5 {
+            ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        }
+        assert_eq!(ledger.spent_today(&token, t0), 50_000);
+
+        // Editing the token's cached allowance does not help
+        token.capabilities.payment_limits.as_mut().unwrap().remaining_today = 50_000;
+        assert!(ledger.record_use(&mut token, pay(10_000), &at(t0 + 60)).is_err());
+
+        // Non-payment operations are logged but free
+        ledger.record_use(&mut token, TokenRequest::DeviceUnlock, &at(t0 + 60)).unwrap();
+        assert_eq!(ledger.entries().len(), 6);
+
+        // The next window restores the allowance
+        ledger.record_use(&mut token, pay(10_000), &at(t0 + DAY_SECONDS)).unwrap();
+        assert_eq!(ledger.spent_today(&token, t0 + DAY_SECONDS), 10_000);
+    }
+
+    #[test]
+    fn test_daily_limit_shared_by_device_tokens() {
+        let t0 = now();
+        let mut first = token(t0);
+        let mut second = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+
+        for _ in 0..4 {
+            ledger.record_use(&mut first, pay(10_000), &at(t0)).unwrap();
+        }
+        ledger.record_use(&mut second, pay(10_000), &at(t0)).unwrap();
+        assert_eq!(ledger.spent_today(&first, t0), 50_000);
+
+        // A second token of the same device does not bring a fresh allowance
+        assert!(ledger.record_use(&mut second, pay(10_000), &at(t0)).is_err());
+        assert!(ledger.record_use(&mut first, pay(10_000), &at(t0)).is_err());
+
+        // The issuer counts the device's tokens together as well
+        let mut limits = first.capabilities.payment_limits.clone().unwrap();
+        limits.max_per_day = 40_000;
+        let mut issued = HashMap::new();
+        issued.insert(first.token_id, Some(limits.clone()));
+        issued.insert(second.token_id, Some(limits));
+        let report = reconcile(ledger.anchor(), ledger.entries(), &issued).unwrap();
+        assert_eq!(report.overspends.len(), 1);
+        assert_eq!(report.overspends[0].token_id, second.token_id);
+        assert_eq!(report.overspends[0].spent, 50_000);
+    }
+
+    #[test]
+    fn test_storage_rollback_detected() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+
+        ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        let snapshot = ledger.store.data.clone();
+        for _ in 0..4 {
+            ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        }
+
+        // Restore the old ledger; the counter has moved on
+        let mut store = ledger.store.clone();
+        store.data = snapshot;
+        assert!(matches!(UsageLedger::open(store), Err(FrostError::RollbackDetected(_))));
+
+        // Reopening the genuine state works
+        assert!(UsageLedger::open(ledger.store.clone()).is_ok());
+    }
+
+    #[test]
+    fn test_crash_before_counter_increment_recovers() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+
+        let mut store = ledger.store.clone();
+        store.counter -= 1;
+        let reopened = UsageLedger::open(store).unwrap();
+        assert_eq!(reopened.store.counter, 1);
+    }
+
+    #[test]
+    fn test_edited_entry_detected() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+
+        let mut state = ledger.state.clone();
+        state.entries[0].amount = 0;
+        state.entries[0].operation = pay(0).to_bytes();
+        let mut store = ledger.store.clone();
+        store.data = Some(serde_json::to_vec(&state).unwrap());
+        assert!(matches!(UsageLedger::open(store), Err(FrostError::RollbackDetected(_))));
+
+        // The amount is hashed on its own, not only through the operation
+        let mut state = ledger.state.clone();
+        state.entries[1].amount = 0;
+        let mut store = ledger.store.clone();
+        store.data = Some(serde_json::to_vec(&state).unwrap());
+        assert!(matches!(UsageLedger::open(store), Err(FrostError::RollbackDetected(_))));
+    }
+
+    #[test]
+    fn test_clock_rollback() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+
+        // Jump a day ahead to spend tomorrow's allowance, then set the clock back
+        for _ in 0..5 {
+            ledger.record_use(&mut token, pay(10_000), &at(t0 + DAY_SECONDS)).unwrap();
+        }
+        assert!(matches!(
+            ledger.record_use(&mut token, pay(10_000), &at(t0)),
+            Err(FrostError::RollbackDetected(_)),
+        ));
+
+        // Small skew is tolerated but still counts against the newest window
+        assert!(ledger.record_use(&mut token, pay(10_000), &at(t0 + DAY_SECONDS - 60)).is_err());
+        assert_eq!(ledger.spent_today(&token, t0), 50_000);
+    }
+
+    #[test]
+    fn test_reconcile_detects_overspend() {
+        let t0 = now();
+        let mut token = token(t0);
+        let limits = token.capabilities.payment_limits.clone();
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+        for _ in 0..3 {
+            ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        }
+
+        let mut issued = HashMap::new();
+        issued.insert(token.token_id, limits.clone());
+        let report = reconcile(ledger.anchor(), ledger.entries(), &issued).unwrap();
+        assert!(report.overspends.is_empty());
+        assert_eq!(report.head_sequence, 3);
+
+        // A device that spent beyond the signed limit is caught
+        let mut limits = limits.unwrap();
+        limits.max_per_day = 20_000;
+        issued.insert(token.token_id, Some(limits));
+        let report = reconcile(ledger.anchor(), ledger.entries(), &issued).unwrap();
+        assert_eq!(report.overspends, vec![Overspend {
+            device_id: token.device_id,
+            token_id: token.token_id,
+            window: 0,
+            spent: 30_000,
+            limit: 20_000,
+        }]);
+
+        // Dropped entries break the chain
+        let gapped: Vec<_> = ledger.entries().iter().skip(1).cloned().collect();
+        assert!(reconcile(ledger.anchor(), &gapped, &issued).is_err());
+        assert!(reconcile((0, [0u8; 32]), &ledger.entries()[1..], &issued).is_err());
+
+        assert!(reconcile(ledger.anchor(), ledger.entries(), &HashMap::new()).unwrap()
+            .unknown_tokens.contains(&token.token_id));
+    }
+
+    #[test]
+    fn test_acknowledge_prunes_and_keeps_chain() {
+        let t0 = now();
+        let mut token = token(t0);
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0)).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0 + DAY_SECONDS)).unwrap();
+        ledger.record_use(&mut token, pay(10_000), &at(t0 + DAY_SECONDS + 60)).unwrap();
+
+        ledger.acknowledge(2, t0 + DAY_SECONDS + 60).unwrap();
+        assert_eq!(ledger.entries().len(), 2);
+        assert_eq!(ledger.anchor().0, 1);
+        assert_eq!(ledger.spent_today(&token, t0 + DAY_SECONDS + 60), 20_000);
+
+        let reopened = UsageLedger::open(ledger.store.clone()).unwrap();
+        assert_eq!(reopened.entries().len(), 2);
+        assert_eq!(reopened.uses(&token.token_id), 3);
+    }
+
+    #[test]
+    fn test_use_count_shared_by_attenuated_copies() {
+        use crate::policy::Rule;
+        use crate::session_token::verify_token_operation;
+        use crate::testing::issue;
+        use curve25519_dalek::scalar::Scalar;
+
+        let t0 = now();
+        let caps = Capabilities { policy: Some(Rule::UseCountBelow(2)), ..Capabilities::default() };
+        let mut parent = SessionToken::new([1u8; 32], caps, Duration::from_secs(3600));
+        let group_pk = issue(&mut parent, &Scalar::random(&mut rand::thread_rng()));
+        let mut first = parent.attenuate(parent.capabilities.clone(), None).unwrap();
+        let mut second = parent.attenuate(parent.capabilities.clone(), None).unwrap();
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+
+        // Each copy has used the token once, but together they reach the limit
+        let sig = ledger.record_use(&mut first, TokenRequest::DeviceUnlock, &at(t0)).unwrap();
+        ledger.record_use(&mut second, TokenRequest::DeviceUnlock, &at(t0)).unwrap();
+        assert_eq!(ledger.uses(&parent.token_id), 2);
+        assert!(ledger.record_use(&mut first, TokenRequest::DeviceUnlock, &at(t0)).is_err());
+        assert!(ledger.record_use(&mut second, TokenRequest::DeviceUnlock, &at(t0)).is_err());
+        assert!(ledger.record_use(&mut parent, TokenRequest::DeviceUnlock, &at(t0)).is_err());
+
+        // Outside the ledger there is no tally to check against
+        assert!(first.use_for_operation(TokenRequest::DeviceUnlock, &at(t0)).is_err());
+
+        // Verifiers go by their own tally, not the count the holder signed
+        let seen = |count| RequestContext { use_count: Some(count), ..at(t0) };
+        assert!(verify_token_operation(&group_pk, &first, &TokenRequest::DeviceUnlock, &sig, &seen(0)).is_ok());
+        assert!(verify_token_operation(&group_pk, &first, &TokenRequest::DeviceUnlock, &sig, &seen(2)).is_err());
+        assert!(verify_token_operation(&group_pk, &first, &TokenRequest::DeviceUnlock, &sig, &at(t0)).is_err());
+    }
+}
@@ -19,14 +19,52 @@ pub mod transcript;
 pub mod session_token;
 pub mod hybrid;
 pub mod derived_key;
+pub mod merkle;
+pub mod key_hierarchy;
+pub mod nonce;
+pub mod ledger;
+pub mod revocation;
+pub mod policy;
+pub mod token_store;
+pub mod refresh;
+pub mod health;
+pub mod cose;
+pub mod audit;
+pub mod transport;
+#[cfg(feature = "https")]
+pub mod https;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub use types::*;
-pub use dkg::{DkgParticipant, DkgRound1, DkgRound2, DkgOutput};
-pub use signing::{SigningRound1, SigningRound2, aggregate_signatures};
+pub use dkg::{DkgParticipant, DkgRound1Broadcast, DkgRound2P2PMessage, DkgOutput};
+pub use signing::{SigningRound1, SigningRound2, SigningCommitment, PartialSignature, aggregate_signatures};
 pub use rotation::ShareRotation;
-pub use session_token::{SessionToken, SessionTokenCache, TokenRequest, Capabilities};
-pub use hybrid::{HybridFROSTDevice, SigningMode, RemoteShareEndpoint};
-pub use derived_key::{DerivedDeviceKey, DerivationProof, ManufacturingProvisioner};
+pub use session_token::{
+    SessionToken, SessionTokenCache, TokenRequest, TokenSignature, Capabilities, Caveat, verify_token_operation,
+};
+pub use hybrid::{HybridFROSTDevice, SigningMode, RemoteShareEndpoint, DeviceSignature};
+pub use transport::{
+    RemoteShareTransport, RemoteHealth, ShareAttestation, SessionId, DisconnectedTransport, LoopbackTransport,
+};
+#[cfg(feature = "https")]
+pub use https::{HttpsTransport, HttpsConfig, TlsIdentity};
+pub use derived_key::{
+    DerivedDeviceKey, DerivationProof, DerivationStatement, ParticipantSignature,
+    DerivationPolicy, DerivationVerdict, DerivationCheckFailure,
+    DerivationRequest, EncryptedPartialEvaluation, DerivationParticipant, DerivationSession,
+    PossessionProof,
+};
+pub use policy::{Rule, RequestContext, Decision, AttributeValue};
+pub use audit::{AuditLog, AuditEntry, AuditExport, AuditOutcome, AUDIT_LOG_CAPACITY};
+pub use cose::{verify_cwt, token_from_sig_structure, COSE_ALG_FROST_RISTRETTO255_SHA512};
+pub use refresh::{RefreshPolicy, RefreshScheduler, CacheHealth, TokenRefreshTask, spawn_token_refresh};
+pub use health::{HealthPolicy, HealthTracker, CircuitState, RemoteHealthStats, HealthProbeTask, spawn_health_probes};
+pub use token_store::{TokenCacheStore, MemoryTokenCacheStore, TOKEN_CACHE_SLOTS};
+pub use revocation::{RevocationList, RevokedTokens, BloomFilter};
+pub use ledger::{UsageLedger, LedgerStore, LedgerEntry, MemoryLedgerStore, ReconciliationReport, Overspend, reconcile};
+pub use nonce::{EntropySource, OsEntropy, DefaultEntropy, DeterministicEntropy, set_default_entropy};
+pub use key_hierarchy::{SigningPurpose, EncryptionPurpose, SigningSubkey, EncryptionSubkey};
 
 use thiserror::Error;
 
@@ -64,6 +102,18 @@ pub enum FrostError {
     /// Cryptographic error
     #[error("Cryptographic error: {0}")]
     CryptoError(String),
+
+    /// Persistent storage error
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    /// Stored state or clock was rolled back
+    #[error("Rollback detected: {0}")]
+    RollbackDetected(String),
+
+    /// Remote share unreachable or answered with an error
+    #[error("Transport error: {0}")]
+    TransportError(String),
 }
 
 /// Result type for FROST operations
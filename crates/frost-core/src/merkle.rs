@@ -0,0 +1,461 @@
+//! Merkle tree hashing and proof verification (RFC 6962 / RFC 9162)
+//!
+//! Just enough of the transparency log for a device or verifier to check
+//! that an entry is included under a signed tree head, and that a later
+//! tree head extends an earlier one.
+//!
+//! The FROST group signs tree heads, but the same quorum issues keys, so a
+//! signature alone cannot rule out different views shown to different
+//! devices. Independent witnesses countersign a tree head only after
+//! checking it extends the last one they saw; a `WitnessPolicy` says how
+//! many of which witnesses a verifier requires.
+
+use crate::{FrostError, FrostResult};
+use crate::types::*;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::CompressedRistretto,
+    scalar::Scalar,
+};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::HashSet;
+
+/// Leaf hash: SHA-256(0x00 || data)
+pub fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Interior node hash: SHA-256(0x01 || left || right)
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Verify an inclusion proof (RFC 9162, section 2.1.3.2)
+pub fn verify_inclusion(
+    leaf_hash: &[u8; 32],
+    leaf_index: u64,
+    tree_size: u64,
+    proof: &[[u8; 32]],
+    root_hash: &[u8; 32],
+) -> bool {
+    if leaf_index >= tree_size {
+        return false;
+    }
+
+    let mut fn_ = leaf_index;
+    let mut sn = tree_size - 1;
+    let mut r = *leaf_hash;
+
+    for p in proof {
+        if sn == 0 {
+            return false;
+        }
+
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    sn == 0 && &r == root_hash
+}
+
+/// Verify a consistency proof between two tree sizes (RFC 9162, section 2.1.4.2)
+pub fn verify_consistency(
+    old_size: u64,
+    new_size: u64,
+    proof: &[[u8; 32]],
+    old_root: &[u8; 32],
+    new_root: &[u8; 32],
+) -> bool {
+    if old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && old_root == new_root;
+    }
+    if old_size == 0 {
+        return proof.is_empty();
+    }
+
+    // A power-of-two old tree is a subtree of the new one, so its root
+    // starts the path
+    let mut path = Vec::with_capacity(proof.len() + 1);
+    if old_size.is_power_of_two() {
+        path.push(*old_root);
+    }
+    path.extend_from_slice(proof);
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+
+    let mut fn_ = old_size - 1;
+    let mut sn = new_size - 1;
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    let mut fr = *first;
+    let mut sr = *first;
+    for c in rest {
+        if sn == 0 {
+            return false;
+        }
+
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    sn == 0 && &fr == old_root && &sr == new_root
+}
+
+/// Domain tag opening every tree head's signing data
+pub const TREE_HEAD_TAG: &[u8] = b"FROST-TREE-HEAD-v1";
+
+/// Tree head signed by the FROST group key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTreeHead {
+    /// Number of entries in the log
+    pub tree_size: u64,
+    /// Timestamp (Unix epoch seconds)
+    pub timestamp: u64,
+    /// Merkle tree root hash
+    pub root_hash: [u8; 32],
+    /// Threshold signature over `to_signing_data`
+    pub signature: SchnorrSignature,
+    /// Witness countersignatures
+    #[serde(default)]
+    pub cosignatures: Vec<Cosignature>,
+}
+
+impl SignedTreeHead {
+    /// Data covered by the tree head signature
+    pub fn to_signing_data(tree_size: u64, timestamp: u64, root_hash: &[u8; 32]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(18 + 8 + 8 + 32);
+        data.extend_from_slice(TREE_HEAD_TAG);
+        data.extend_from_slice(&tree_size.to_le_bytes());
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data.extend_from_slice(root_hash);
+        data
+    }
+
+    /// Verify the tree head signature against the log's group public key
+    pub fn verify(&self, log_public_key: &CompressedRistretto) -> bool {
+        let data = Self::to_signing_data(self.tree_size, self.timestamp, &self.root_hash);
+        self.signature.verify(SignatureScheme::Threshold, &data, log_public_key)
+    }
+
+    /// Verify that `leaf_hash` is at `leaf_index` in this tree
+    pub fn verify_inclusion(&self, leaf_hash: &[u8; 32], leaf_index: u64, proof: &[[u8; 32]]) -> bool {
+        verify_inclusion(leaf_hash, leaf_index, self.tree_size, proof, &self.root_hash)
+    }
+
+    /// Verify that this tree extends the `older` one
+    pub fn verify_consistency(&self, older: &SignedTreeHead, proof: &[[u8; 32]]) -> bool {
+        verify_consistency(older.tree_size, self.tree_size, proof, &older.root_hash, &self.root_hash)
+    }
+}
+
+/// Witness countersignature on a tree head
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cosignature {
+    /// Witness public key
+    pub witness_key: CompressedRistretto,
+    /// When the witness cosigned (Unix epoch seconds)
+    pub timestamp: u64,
+    /// Witness signature over `to_signing_data`
+    pub signature: SchnorrSignature,
+}
+
+impl Cosignature {
+    /// Data covered by a cosignature
+    ///
+    /// Binds the log key, tree size and root hash, not the log's timestamp:
+    /// the witness vouches for the tree, at its own time.
+    pub fn to_signing_data(
+        log_public_key: &CompressedRistretto,
+        timestamp: u64,
+        tree_size: u64,
+        root_hash: &[u8; 32],
+    ) -> Vec<u8> {
+        let mut data = Vec::with_capacity(20 + 32 + 8 + 8 + 32);
+        data.extend_from_slice(b"FROST-COSIGNATURE-v1");
+        data.extend_from_slice(log_public_key.as_bytes());
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data.extend_from_slice(&tree_size.to_le_bytes());
+        data.extend_from_slice(root_hash);
+        data
+    }
+
+    /// Verify this cosignature on `tree_head` of the log with `log_public_key`
+    pub fn verify(&self, log_public_key: &CompressedRistretto, tree_head: &SignedTreeHead) -> bool {
+        let data = Self::to_signing_data(log_public_key, self.timestamp, tree_head.tree_size, &tree_head.root_hash);
+        self.signature.verify(SignatureScheme::Schnorr, &data, &self.witness_key)
+    }
+}
+
+/// Witness signing key
+pub struct WitnessKey {
+    secret: SecretScalar,
+}
+
+impl WitnessKey {
+    /// Fresh random key
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        WitnessKey { secret: SecretScalar::new(Scalar::random(rng)) }
+    }
+
+    /// Key from its 32-byte canonical encoding
+    pub fn from_bytes(bytes: &[u8; 32]) -> FrostResult<Self> {
+        Option::<Scalar>::from(Scalar::from_canonical_bytes(*bytes))
+            .filter(|s| s != &Scalar::ZERO)
+            .map(|s| WitnessKey { secret: SecretScalar::new(s) })
+            .ok_or_else(|| FrostError::CryptoError("witness key is not a canonical non-zero scalar".to_string()))
+    }
+
+    /// Public key verifiers list in their `WitnessPolicy`
+    pub fn public_key(&self) -> CompressedRistretto {
+        (self.secret.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress()
+    }
+
+    /// Countersign `tree_head` of the log with `log_public_key`
+    ///
+    /// The caller checks consistency with the last cosigned tree first.
+    pub fn cosign<R: RngCore + CryptoRng>(
+        &self,
+        log_public_key: &CompressedRistretto,
+        tree_head: &SignedTreeHead,
+        timestamp: u64,
+        rng: &mut R,
+    ) -> Cosignature {
+        let data = Cosignature::to_signing_data(log_public_key, timestamp, tree_head.tree_size, &tree_head.root_hash);
+        let nonce = Scalar::random(rng);
+        Cosignature {
+            witness_key: self.public_key(),
+            timestamp,
+            signature: SchnorrSignature::sign_with_nonce(self.secret.as_scalar(), &nonce, &data),
+        }
+    }
+}
+
+/// Witnesses a verifier trusts, and how many must cosign a tree head
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WitnessPolicy {
+    /// Trusted witness keys
+    pub witnesses: Vec<CompressedRistretto>,
+    /// Cosignatures required from distinct trusted witnesses
+    pub threshold: usize,
+}
+
+impl WitnessPolicy {
+    /// Number of distinct trusted witnesses with a valid cosignature on `tree_head`
+    pub fn count_cosignatures(&self, log_public_key: &CompressedRistretto, tree_head: &SignedTreeHead) -> usize {
+        let mut seen = HashSet::new();
+        tree_head.cosignatures.iter()
+            .filter(|c| self.witnesses.contains(&c.witness_key))
+            .filter(|c| c.verify(log_public_key, tree_head))
+            .filter(|c| seen.insert(c.witness_key.to_bytes()))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// MTH(D[n]) from RFC 6962
+    fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        match leaves.len() {
+            1 => leaves[0],
+            n => {
+                let k = split(n);
+                node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+            }
+        }
+    }
+
+    /// PATH(m, D[n]) from RFC 6962
+    fn path(m: usize, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        if leaves.len() == 1 {
+            return Vec::new();
+        }
+        let k = split(leaves.len());
+        if m < k {
+            let mut p = path(m, &leaves[..k]);
+            p.push(root(&leaves[k..]));
+            p
+        } else {
+            let mut p = path(m - k, &leaves[k..]);
+            p.push(root(&leaves[..k]));
+            p
+        }
+    }
+
+    /// PROOF(m, D[n]) from RFC 6962
+    fn consistency(m: usize, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        subproof(m, leaves, true)
+    }
+
+    fn subproof(m: usize, leaves: &[[u8; 32]], complete: bool) -> Vec<[u8; 32]> {
+        let n = leaves.len();
+        if m == n {
+            return if complete { Vec::new() } else { vec![root(leaves)] };
+        }
+        let k = split(n);
+        if m <= k {
+            let mut p = subproof(m, &leaves[..k], complete);
+            p.push(root(&leaves[k..]));
+            p
+        } else {
+            let mut p = subproof(m - k, &leaves[k..], false);
+            p.push(root(&leaves[..k]));
+            p
+        }
+    }
+
+    /// Largest power of two smaller than n
+    fn split(n: usize) -> usize {
+        let mut k = 1;
+        while k * 2 < n {
+            k *= 2;
+        }
+        k
+    }
+
+    #[test]
+    fn test_inclusion_all_sizes() {
+        let leaves: Vec<_> = (0u8..13).map(|i| leaf_hash(&[i])).collect();
+
+        for size in 1..=leaves.len() {
+            let tree = &leaves[..size];
+            let root_hash = root(tree);
+            for index in 0..size {
+                let proof = path(index, tree);
+                assert!(verify_inclusion(&tree[index], index as u64, size as u64, &proof, &root_hash));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inclusion_rejects_wrong_position() {
+        let leaves: Vec<_> = (0u8..7).map(|i| leaf_hash(&[i])).collect();
+        let root_hash = root(&leaves);
+        let proof = path(3, &leaves);
+
+        assert!(!verify_inclusion(&leaves[3], 2, 7, &proof, &root_hash));
+        assert!(!verify_inclusion(&leaves[4], 3, 7, &proof, &root_hash));
+        assert!(!verify_inclusion(&leaves[3], 3, 4, &proof, &root_hash));
+        assert!(!verify_inclusion(&leaves[3], 7, 7, &proof, &root_hash));
+    }
+
+    #[test]
+    fn test_consistency_all_sizes() {
+        let leaves: Vec<_> = (0u8..13).map(|i| leaf_hash(&[i])).collect();
+
+        for new_size in 1..=leaves.len() {
+            let new_root = root(&leaves[..new_size]);
+            for old_size in 1..=new_size {
+                let old_root = root(&leaves[..old_size]);
+                let proof = consistency(old_size, &leaves[..new_size]);
+                assert!(verify_consistency(old_size as u64, new_size as u64, &proof, &old_root, &new_root));
+
+                // A forked old tree of the same size does not verify
+                let mut forked = leaves[..old_size].to_vec();
+                forked[old_size - 1] = leaf_hash(b"fork");
+                assert!(!verify_consistency(old_size as u64, new_size as u64, &proof, &root(&forked), &new_root));
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_rejects_wrong_sizes() {
+        let leaves: Vec<_> = (0u8..7).map(|i| leaf_hash(&[i])).collect();
+        let proof = consistency(3, &leaves);
+        let old_root = root(&leaves[..3]);
+        let new_root = root(&leaves);
+
+        assert!(!verify_consistency(3, 4, &proof, &old_root, &new_root));
+        assert!(!verify_consistency(2, 7, &proof, &old_root, &new_root));
+        assert!(!verify_consistency(7, 3, &proof, &new_root, &old_root));
+        assert!(!verify_consistency(7, 7, &proof, &new_root, &new_root));
+    }
+
+    #[test]
+    fn test_witness_policy_counts_distinct_trusted_cosigners() {
+        let mut rng = rand::rngs::OsRng;
+        let log_key = WitnessKey::generate(&mut rng);
+        let log_public_key = log_key.public_key();
+        let witnesses: Vec<_> = (0..3).map(|_| WitnessKey::generate(&mut rng)).collect();
+        let outsider = WitnessKey::generate(&mut rng);
+
+        let root_hash = leaf_hash(b"entry");
+        let data = SignedTreeHead::to_signing_data(1, 1704196800, &root_hash);
+        let mut tree_head = SignedTreeHead {
+            tree_size: 1,
+            timestamp: 1704196800,
+            root_hash,
+            signature: SchnorrSignature::sign_with_scheme(SignatureScheme::Threshold, log_key.secret.as_scalar(), &Scalar::random(&mut rng), &data),
+            cosignatures: Vec::new(),
+        };
+        let policy = WitnessPolicy {
+            witnesses: witnesses.iter().map(|w| w.public_key()).collect(),
+            threshold: 2,
+        };
+
+        let first = witnesses[0].cosign(&log_public_key, &tree_head, 1704196860, &mut rng);
+        tree_head.cosignatures = vec![
+            first.clone(),
+            first,
+            outsider.cosign(&log_public_key, &tree_head, 1704196860, &mut rng),
+        ];
+        assert_eq!(policy.count_cosignatures(&log_public_key, &tree_head), 1);
+
+        tree_head.cosignatures.push(witnesses[2].cosign(&log_public_key, &tree_head, 1704196870, &mut rng));
+        assert_eq!(policy.count_cosignatures(&log_public_key, &tree_head), 2);
+
+        // Cosignatures are for one log's tree
+        let other_log = WitnessKey::generate(&mut rng).public_key();
+        assert_eq!(policy.count_cosignatures(&other_log, &tree_head), 0);
+        tree_head.root_hash[0] ^= 1;
+        assert_eq!(policy.count_cosignatures(&log_public_key, &tree_head), 0);
+    }
+
+    #[test]
+    fn test_leaf_and_node_domain_separation() {
+        let a = leaf_hash(b"a");
+        let b = leaf_hash(b"b");
+        let mut concat = Vec::new();
+        concat.extend_from_slice(&a);
+        concat.extend_from_slice(&b);
+        assert_ne!(node_hash(&a, &b), leaf_hash(&concat));
+    }
+}
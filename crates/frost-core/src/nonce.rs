@@ -0,0 +1,137 @@
+//! Hedged nonce generation for single-signer paths
+//!
+//! Derived-key and degraded-mode signatures are made by one signer, so a
+//! weak or failing TRNG there leaks the key. Nonces are therefore hedged:
+//!
+//! ```text
+//! k = H("FROST-HEDGED-NONCE-v1" || len(tag) || tag || randomness || secret || public_key || message)
+//! ```
+//!
+//! With good randomness `k` is uniformly random; with broken randomness it
+//! is still deterministic in (secret, message) and never repeats across
+//! messages.
+//!
+//! Signers that are not handed a source use `DefaultEntropy`: the source
+//! installed with `set_default_entropy` (the secure element TRNG, once the
+//! hardware layer is up), or the OS RNG before that.
+
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use rand_core::RngCore;
+use sha2::{Sha512, Digest};
+use std::sync::{Arc, RwLock};
+
+/// Source of fresh randomness for hedged nonces
+pub trait EntropySource {
+    /// Fill `dest` with random bytes
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()>;
+}
+
+/// Operating system RNG
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()> {
+        rand::rngs::OsRng.try_fill_bytes(dest)
+            .map_err(|e| FrostError::CryptoError(format!("OS RNG failed: {}", e)))
+    }
+}
+
+/// Source installed by `set_default_entropy`
+static DEFAULT_ENTROPY: RwLock<Option<Arc<dyn EntropySource + Send + Sync>>> = RwLock::new(None);
+
+/// Make `source` the randomness behind `DefaultEntropy` for this process
+pub fn set_default_entropy(source: Arc<dyn EntropySource + Send + Sync>) {
+    let mut default = DEFAULT_ENTROPY.write().unwrap_or_else(|e| e.into_inner());
+    *default = Some(source);
+}
+
+/// Process-wide default: the installed source, or the OS RNG
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultEntropy;
+
+impl EntropySource for DefaultEntropy {
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()> {
+        let installed = DEFAULT_ENTROPY.read().unwrap_or_else(|e| e.into_inner()).clone();
+        match installed {
+            Some(source) => source.fill(dest),
+            None => OsEntropy.fill(dest),
+        }
+    }
+}
+
+/// Fixed randomness (deterministic test mode)
+///
+/// Every call returns the same bytes, so signatures become a pure function
+/// of key and message. Only for known-answer tests.
+#[derive(Debug, Clone, Copy)]
+pub struct DeterministicEntropy(pub [u8; 32]);
+
+impl EntropySource for DeterministicEntropy {
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()> {
+        for (i, byte) in dest.iter_mut().enumerate() {
+            *byte = self.0[i % 32];
+        }
+        Ok(())
+    }
+}
+
+/// Hedged nonce for a single-signer Schnorr signature
+pub fn hedged_nonce(
+    tag: &[u8],
+    secret: &Scalar,
+    public_key: &CompressedRistretto,
+    message: &[u8],
+    entropy: &dyn EntropySource,
+) -> FrostResult<SecretScalar> {
+    let mut randomness = [0u8; 32];
+    entropy.fill(&mut randomness)?;
+
+    let mut hasher = Sha512::new();
+    hasher.update(b"FROST-HEDGED-NONCE-v1");
+    hasher.update((tag.len() as u32).to_le_bytes());
+    hasher.update(tag);
+    hasher.update(randomness);
+    hasher.update(secret.as_bytes());
+    hasher.update(public_key.as_bytes());
+    hasher.update(message);
+    Ok(SecretScalar::new(Scalar::from_hash(hasher)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+    fn nonce_hex(entropy: &dyn EntropySource, message: &[u8]) -> String {
+        let secret = Scalar::from(42u64);
+        let public_key = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+        let nonce = hedged_nonce(b"test", &secret, &public_key, message, entropy).unwrap();
+        hex::encode(nonce.as_scalar().to_bytes())
+    }
+
+    #[test]
+    fn test_hedged_nonce_known_answer() {
+        let entropy = DeterministicEntropy([0u8; 32]);
+        assert_eq!(nonce_hex(&entropy, b"message"), "47bbd6af973498ff2c96b051bda6b0e40a994159134aabea0309a97ab2fe9d0d");
+        let entropy = DeterministicEntropy([0xa5u8; 32]);
+        assert_eq!(nonce_hex(&entropy, b"message"), "f5c9389aa55bda0f3fd57b1753d5406223f72d4450e07652ff7967d1c8483302");
+    }
+
+    #[test]
+    fn test_hedged_nonce_binds_inputs() {
+        let entropy = DeterministicEntropy([0u8; 32]);
+        assert_eq!(nonce_hex(&entropy, b"message"), nonce_hex(&entropy, b"message"));
+        assert_ne!(nonce_hex(&entropy, b"message"), nonce_hex(&entropy, b"other"));
+        assert_ne!(nonce_hex(&OsEntropy, b"message"), nonce_hex(&OsEntropy, b"message"));
+
+        let secret = Scalar::from(42u64);
+        let public_key = (secret * RISTRETTO_BASEPOINT_POINT).compress();
+        let a = hedged_nonce(b"a", &secret, &public_key, b"m", &entropy).unwrap();
+        let b = hedged_nonce(b"b", &secret, &public_key, b"m", &entropy).unwrap();
+        assert_ne!(a.as_scalar(), b.as_scalar());
+    }
+}
@@ -0,0 +1,507 @@
+//! Declarative authorization policies for session tokens
+//!
+//! A policy is a typed rule tree carried in `Capabilities`, so it is
+//! covered by the FROST signature over the token (and by caveat signatures
+//! when a holder narrows it). Evaluation is a pure function of the request
+//! and a `RequestContext`, and returns a trace explaining the decision.
+//!
+//! Rules that need context the caller did not supply (no region, no
+//! network, no such attribute) evaluate to unknown rather than false.
+//! Unknown propagates through `Not` unchanged and through `All`/`Any` as in
+//! Kleene logic, and a policy that ends up unknown denies, so negating a
+//! rule never turns missing context into an allow.
+
+use crate::session_token::{CanonicalReader, TokenRequest};
+use crate::{FrostError, FrostResult};
+use serde::{Serialize, Deserialize};
+
+/// Maximum nesting depth of a rule tree
+pub const MAX_POLICY_DEPTH: usize = 16;
+
+/// Seconds in a UTC day, the range of `Rule::TimeOfDay` bounds
+const SECONDS_PER_DAY: u32 = 86_400;
+
+/// Attribute value of an operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttributeValue {
+    /// Unsigned integer (amounts, levels, counts)
+    Integer(u64),
+    /// UTF-8 text
+    Text(String),
+}
+
+/// Policy rule tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rule {
+    /// Always true
+    Always,
+    /// Always false
+    Never,
+    /// Every sub-rule holds
+    All(Vec<Rule>),
+    /// At least one sub-rule holds
+    Any(Vec<Rule>),
+    /// The sub-rule does not hold
+    Not(Box<Rule>),
+    /// Operation has this name (see `TokenRequest::name`)
+    Operation(String),
+    /// Operation amount is at most this many cents
+    AmountAtMost(u64),
+    /// Integer attribute is at most `max`
+    AttributeAtMost {
+        /// Attribute name
+        key: String,
+        /// Inclusive upper bound
+        max: u64,
+    },
+    /// Attribute equals `value`
+    AttributeEquals {
+        /// Attribute name
+        key: String,
+        /// Required value
+        value: AttributeValue,
+    },
+    /// UTC time of day in `[start, end)` seconds since midnight; wraps if `start > end`
+    TimeOfDay {
+        /// Window start
+        start: u32,
+        /// Window end (exclusive)
+        end: u32,
+    },
+    /// Device reports this region (e.g. ISO 3166 country code)
+    Region(String),
+    /// Device is on this network (e.g. SSID or enterprise network ID)
+    Network(String),
+    /// Token, with every copy attenuated from it, has been used fewer than
+    /// this many times
+    ///
+    /// Copies count separately and the holder signs its own count, so only
+    /// a tally the holder cannot reset enforces this: the device's
+    /// `UsageLedger`, or a verifier's own records. Without one the rule is
+    /// unknown.
+    UseCountBelow(u64),
+}
+
+/// Circumstances of a request, supplied by the device
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    /// Current time (Unix epoch seconds)
+    pub now: u64,
+    /// Times the token and its copies have been used so far, if tallied
+    pub use_count: Option<u64>,
+    /// Region the device is in, if known
+    pub region: Option<String>,
+    /// Network the device is on, if known
+    pub network: Option<String>,
+}
+
+/// Three-valued result of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Truth {
+    True,
+    False,
+    /// The context or attribute the rule looks at is missing
+    Unknown,
+}
+
+impl Truth {
+    fn from_bool(value: bool) -> Self {
+        if value { Truth::True } else { Truth::False }
+    }
+
+    fn not(self) -> Self {
+        match self {
+            Truth::True => Truth::False,
+            Truth::False => Truth::True,
+            Truth::Unknown => Truth::Unknown,
+        }
+    }
+
+    fn all(results: &[Truth]) -> Self {
+        if results.contains(&Truth::False) {
+            Truth::False
+        } else if results.contains(&Truth::Unknown) {
+            Truth::Unknown
+        } else {
+            Truth::True
+        }
+    }
+
+    fn any(results: &[Truth]) -> Self {
+        if results.contains(&Truth::True) {
+            Truth::True
+        } else if results.contains(&Truth::Unknown) {
+            Truth::Unknown
+        } else {
+            Truth::False
+        }
+    }
+}
+
+impl std::fmt::Display for Truth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Truth::True => "true",
+            Truth::False => "false",
+            Truth::Unknown => "unknown",
+        })
+    }
+}
+
+/// Outcome of evaluating a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether the request is allowed
+    pub allowed: bool,
+    /// One line per rule evaluated, indented by depth
+    pub trace: Vec<String>,
+}
+
+impl Rule {
+    /// Evaluate against a request
+    ///
+    /// Allowed only if the policy is true; false and unknown both deny.
+    pub fn evaluate(&self, request: &TokenRequest, context: &RequestContext) -> Decision {
+        let mut trace = Vec::new();
+        let allowed = self.eval(request, context, 0, &mut trace) == Truth::True;
+        Decision { allowed, trace }
+    }
+
+    fn eval(&self, request: &TokenRequest, context: &RequestContext, depth: usize, trace: &mut Vec<String>) -> Truth {
+        if depth >= MAX_POLICY_DEPTH {
+            trace.push(format!("{}policy nested too deeply: {}", "  ".repeat(depth), Truth::Unknown));
+            return Truth::Unknown;
+        }
+
+        // Composite rules evaluate every child so the trace is complete
+        let mut children = Vec::new();
+        let (result, description) = match self {
+            Rule::Always => (Truth::True, "always".to_string()),
+            Rule::Never => (Truth::False, "never".to_string()),
+            Rule::All(rules) => {
+                let results: Vec<Truth> = rules.iter()
+                    .map(|r| r.eval(request, context, depth + 1, &mut children))
+                    .collect();
+                (Truth::all(&results), format!("all of {}", rules.len()))
+            }
+            Rule::Any(rules) => {
+                let results: Vec<Truth> = rules.iter()
+                    .map(|r| r.eval(request, context, depth + 1, &mut children))
+                    .collect();
+                (Truth::any(&results), format!("any of {}", rules.len()))
+            }
+            Rule::Not(rule) => (rule.eval(request, context, depth + 1, &mut children).not(), "not".to_string()),
+            Rule::Operation(name) => (
+                Truth::from_bool(request.name() == name),
+                format!("operation {:?} is {:?}", request.name(), name),
+            ),
+            Rule::AmountAtMost(max) => match request.attribute("amount") {
+                Some(AttributeValue::Integer(amount)) => {
+                    (Truth::from_bool(amount <= *max), format!("amount {} <= {}", amount, max))
+                }
+                _ => (Truth::Unknown, format!("amount <= {} (no amount)", max)),
+            },
+            Rule::AttributeAtMost { key, max } => match request.attribute(key) {
+                Some(AttributeValue::Integer(value)) => {
+                    (Truth::from_bool(value <= *max), format!("{} {} <= {}", key, value, max))
+                }
+                _ => (Truth::Unknown, format!("{} <= {} (no integer attribute)", key, max)),
+            },
+            Rule::AttributeEquals { key, value } => match request.attribute(key) {
+                Some(actual) => (Truth::from_bool(&actual == value), format!("{} {:?} is {:?}", key, actual, value)),
+                None => (Truth::Unknown, format!("{} is {:?} (no attribute)", key, value)),
+            },
+            Rule::TimeOfDay { start, end } => {
+                let seconds = (context.now % SECONDS_PER_DAY as u64) as u32;
+                let inside = if start <= end {
+                    *start <= seconds && seconds < *end
+                } else {
+                    seconds >= *start || seconds < *end
+                };
+                (Truth::from_bool(inside), format!("time of day {} in [{}, {})", seconds, start, end))
+            }
+            Rule::Region(region) => match &context.region {
+                Some(actual) => (Truth::from_bool(actual == region), format!("region {:?} is {:?}", actual, region)),
+                None => (Truth::Unknown, format!("region is {:?} (no region)", region)),
+            },
+            Rule::Network(network) => match &context.network {
+                Some(actual) => (Truth::from_bool(actual == network), format!("network {:?} is {:?}", actual, network)),
+                None => (Truth::Unknown, format!("network is {:?} (no network)", network)),
+            },
+            Rule::UseCountBelow(max) => match context.use_count {
+                Some(count) => (Truth::from_bool(count < *max), format!("use count {} < {}", count, max)),
+                None => (Truth::Unknown, format!("use count < {} (no use count)", max)),
+            },
+        };
+
+        trace.push(format!("{}{}: {}", "  ".repeat(depth), description, result));
+        trace.append(&mut children);
+        result
+    }
+
+    /// Canonical encoding, bound into the signed capabilities
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// Decode a canonical encoding, rejecting trailing bytes
+    pub fn from_canonical_bytes(bytes: &[u8]) -> FrostResult<Self> {
+        let mut reader = CanonicalReader { bytes };
+        let rule = Rule::decode(&mut reader, 0)?;
+        if !reader.bytes.is_empty() {
+            return Err(FrostError::SerializationError("Trailing bytes after policy".to_string()));
+        }
+        Ok(rule)
+    }
+
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Rule::Always => out.push(0),
+            Rule::Never => out.push(1),
+            Rule::All(rules) | Rule::Any(rules) => {
+                out.push(if matches!(self, Rule::All(_)) { 2 } else { 3 });
+                out.extend_from_slice(&(rules.len() as u32).to_le_bytes());
+                for rule in rules {
+                    rule.encode(out);
+                }
+            }
+            Rule::Not(rule) => {
+                out.push(4);
+                rule.encode(out);
+            }
+            Rule::Operation(name) => {
+                out.push(5);
+                encode_str(name, out);
+            }
+            Rule::AmountAtMost(max) => {
+                out.push(6);
+                out.extend_from_slice(&max.to_le_bytes());
+            }
+            Rule::AttributeAtMost { key, max } => {
+                out.push(7);
+                encode_str(key, out);
+                out.extend_from_slice(&max.to_le_bytes());
+            }
+            Rule::AttributeEquals { key, value } => {
+                out.push(8);
+                encode_str(key, out);
+                value.encode(out);
+            }
+            Rule::TimeOfDay { start, end } => {
+                out.push(9);
+                out.extend_from_slice(&start.to_le_bytes());
+                out.extend_from_slice(&end.to_le_bytes());
+            }
+            Rule::Region(region) => {
+                out.push(10);
+                encode_str(region, out);
+            }
+            Rule::Network(network) => {
+                out.push(11);
+                encode_str(network, out);
+            }
+            Rule::UseCountBelow(max) => {
+                out.push(12);
+                out.extend_from_slice(&max.to_le_bytes());
+            }
+        }
+    }
+
+    pub(crate) fn decode(reader: &mut CanonicalReader<'_>, depth: usize) -> FrostResult<Self> {
+        if depth >= MAX_POLICY_DEPTH {
+            return Err(FrostError::SerializationError("Policy nested too deeply".to_string()));
+        }
+
+        Ok(match reader.byte()? {
+            0 => Rule::Always,
+            1 => Rule::Never,
+            tag @ (2 | 3) => {
+                let count = reader.u32()? as usize;
+                let rules = (0..count)
+                    .map(|_| Rule::decode(reader, depth + 1))
+                    .collect::<FrostResult<Vec<_>>>()?;
+                if tag == 2 { Rule::All(rules) } else { Rule::Any(rules) }
+            }
+            4 => Rule::Not(Box::new(Rule::decode(reader, depth + 1)?)),
+            5 => Rule::Operation(decode_str(reader)?),
+            6 => Rule::AmountAtMost(reader.u64()?),
+            7 => Rule::AttributeAtMost { key: decode_str(reader)?, max: reader.u64()? },
+            8 => Rule::AttributeEquals { key: decode_str(reader)?, value: AttributeValue::decode(reader)? },
+            9 => {
+                let (start, end) = (reader.u32()?, reader.u32()?);
+                if start >= SECONDS_PER_DAY || end > SECONDS_PER_DAY {
+                    return Err(FrostError::SerializationError("Time of day out of range".to_string()));
+                }
+                Rule::TimeOfDay { start, end }
+            }
+            10 => Rule::Region(decode_str(reader)?),
+            11 => Rule::Network(decode_str(reader)?),
+            12 => Rule::UseCountBelow(reader.u64()?),
+            tag => return Err(FrostError::SerializationError(format!("Unknown policy rule {}", tag))),
+        })
+    }
+}
+
+impl AttributeValue {
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            AttributeValue::Integer(value) => {
+                out.push(0);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            AttributeValue::Text(text) => {
+                out.push(1);
+                encode_str(text, out);
+            }
+        }
+    }
+
+    fn decode(reader: &mut CanonicalReader<'_>) -> FrostResult<Self> {
+        match reader.byte()? {
+            0 => Ok(AttributeValue::Integer(reader.u64()?)),
+            1 => Ok(AttributeValue::Text(decode_str(reader)?)),
+            tag => Err(FrostError::SerializationError(format!("Unknown attribute type {}", tag))),
+        }
+    }
+}
+
+/// `len (u32 LE) || UTF-8`
+pub(crate) fn encode_str(s: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn decode_str(reader: &mut CanonicalReader<'_>) -> FrostResult<String> {
+    let len = reader.u32()? as usize;
+    core::str::from_utf8(reader.take(len)?)
+        .map(str::to_string)
+        .map_err(|_| FrostError::SerializationError("Policy string is not UTF-8".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn context(now: u64) -> RequestContext {
+        RequestContext {
+            now,
+            use_count: Some(3),
+            region: Some("CH".to_string()),
+            network: Some("corp-wifi".to_string()),
+        }
+    }
+
+    fn office_payments() -> Rule {
+        Rule::Any(vec![
+            Rule::Operation("device-unlock".to_string()),
+            Rule::All(vec![
+                Rule::Operation("payment".to_string()),
+                Rule::AmountAtMost(2_000),
+                Rule::TimeOfDay { start: 8 * 3600, end: 18 * 3600 },
+                Rule::Network("corp-wifi".to_string()),
+                Rule::UseCountBelow(10),
+            ]),
+        ])
+    }
+
+    #[test]
+    fn test_policy_evaluation() {
+        let policy = office_payments();
+        let noon = 19_000 * 86_400 + 12 * 3600;
+        let midnight = 19_000 * 86_400;
+
+        assert!(policy.evaluate(&TokenRequest::DeviceUnlock, &context(midnight)).allowed);
+        assert!(policy.evaluate(&TokenRequest::Payment { amount: 1_500 }, &context(noon)).allowed);
+        assert!(!policy.evaluate(&TokenRequest::Payment { amount: 2_500 }, &context(noon)).allowed);
+        assert!(!policy.evaluate(&TokenRequest::Payment { amount: 1_500 }, &context(midnight)).allowed);
+        assert!(!policy.evaluate(&TokenRequest::CodeSigning, &context(noon)).allowed);
+
+        // Missing context fails closed
+        let mut offsite = context(noon);
+        offsite.network = None;
+        assert!(!policy.evaluate(&TokenRequest::Payment { amount: 1_500 }, &offsite).allowed);
+
+        // Wrapping window covers the night
+        let night = Rule::TimeOfDay { start: 22 * 3600, end: 6 * 3600 };
+        assert!(night.evaluate(&TokenRequest::DeviceUnlock, &context(midnight)).allowed);
+        assert!(!night.evaluate(&TokenRequest::DeviceUnlock, &context(noon)).allowed);
+    }
+
+    #[test]
+    fn test_decision_trace_explains_denial() {
+        let noon = 19_000 * 86_400 + 12 * 3600;
+        let decision = office_payments().evaluate(&TokenRequest::Payment { amount: 2_500 }, &context(noon));
+
+        assert!(!decision.allowed);
+        assert_eq!(decision.trace[0], "any of 2: false");
+        assert!(decision.trace.contains(&"    amount 2500 <= 2000: false".to_string()));
+        assert!(decision.trace.contains(&"    network \"corp-wifi\" is \"corp-wifi\": true".to_string()));
+    }
+
+    #[test]
+    fn test_custom_operation_attributes() {
+        let mut attributes = BTreeMap::new();
+        attributes.insert("door".to_string(), AttributeValue::Text("lab-2".to_string()));
+        attributes.insert("amount".to_string(), AttributeValue::Integer(300));
+        let open_door = TokenRequest::Custom { name: "open-door".to_string(), attributes };
+
+        let policy = Rule::All(vec![
+            Rule::Operation("open-door".to_string()),
+            Rule::AttributeEquals { key: "door".to_string(), value: AttributeValue::Text("lab-2".to_string()) },
+            Rule::AttributeAtMost { key: "amount".to_string(), max: 500 },
+            Rule::Not(Box::new(Rule::Region("KP".to_string()))),
+        ]);
+        assert!(policy.evaluate(&open_door, &context(0)).allowed);
+        assert!(!Rule::AttributeEquals { key: "door".to_string(), value: AttributeValue::Text("lab-1".to_string()) }
+            .evaluate(&open_door, &context(0)).allowed);
+    }
+
+    #[test]
+    fn test_missing_context_denies_under_negation() {
+        let mut unknown = context(0);
+        unknown.region = None;
+        unknown.network = None;
+
+        // Not(Region) must not allow just because the region is unknown
+        let outside_kp = Rule::Not(Box::new(Rule::Region("KP".to_string())));
+        assert!(outside_kp.evaluate(&TokenRequest::DeviceUnlock, &context(0)).allowed);
+        let decision = outside_kp.evaluate(&TokenRequest::DeviceUnlock, &unknown);
+        assert!(!decision.allowed);
+        assert_eq!(decision.trace[0], "not: unknown");
+        assert!(!Rule::Not(Box::new(Rule::Network("guest".to_string())))
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+        assert!(!Rule::Not(Box::new(Rule::AmountAtMost(100)))
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+
+        // A known outcome elsewhere still decides All and Any
+        let unlock = Rule::Operation("device-unlock".to_string());
+        assert!(Rule::Any(vec![outside_kp.clone(), unlock.clone()])
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+        assert!(!Rule::Any(vec![outside_kp.clone(), Rule::Never])
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+        assert!(!Rule::All(vec![outside_kp.clone(), unlock])
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+        assert!(Rule::Not(Box::new(Rule::All(vec![outside_kp, Rule::Never])))
+            .evaluate(&TokenRequest::DeviceUnlock, &unknown).allowed);
+    }
+
+    #[test]
+    fn test_policy_canonical_round_trip() {
+        let policy = office_payments();
+        let bytes = policy.to_canonical_bytes();
+        assert_eq!(Rule::from_canonical_bytes(&bytes).unwrap(), policy);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Rule::from_canonical_bytes(&trailing).is_err());
+        assert!(Rule::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Rule::from_canonical_bytes(&[9, 0x80, 0x51, 0x01, 0, 0, 0, 0, 0]).is_err());
+
+        // Nesting beyond the limit is refused rather than recursed into
+        let deep = vec![4u8; MAX_POLICY_DEPTH + 1].into_iter().chain([0u8]).collect::<Vec<_>>();
+        assert!(Rule::from_canonical_bytes(&deep).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Proactive session token renewal
+//!
+//! `RefreshScheduler` decides when the token cache must be topped up: a
+//! renewal is due once fewer than `target_tokens` tokens would outlive the
+//! `renew_before` window. Each schedule is brought forward by a random
+//! jitter so a fleet does not renew in lockstep, failed attempts back off
+//! exponentially, and no two attempts are closer than `min_interval`. `spawn_token_refresh` runs the scheduler as a
+//! background task against a shared `HybridFROSTDevice`.
+//!
+//! New tokens are only ever signed through the threshold path; degraded
+//! and token modes cannot issue tokens.
+
+use crate::hybrid::HybridFROSTDevice;
+use crate::session_token::SessionTokenCache;
+use crate::transport::RemoteShareTransport;
+use crate::FrostError;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// Renewal parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshPolicy {
+    /// Valid tokens to keep on hand
+    pub target_tokens: usize,
+    /// Lifetime of each newly issued token
+    pub token_lifetime: Duration,
+    /// Renew a token this long before it expires
+    pub renew_before: Duration,
+    /// Maximum random delay a renewal is brought forward by
+    pub jitter: Duration,
+    /// First retry delay after a failed renewal
+    pub initial_backoff: Duration,
+    /// Ceiling on the retry delay
+    pub max_backoff: Duration,
+    /// Shortest time between two attempts
+    pub min_interval: Duration,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        RefreshPolicy {
+            target_tokens: 5,
+            token_lifetime: Duration::from_secs(4 * 3600),
+            renew_before: Duration::from_secs(3600),
+            jitter: Duration::from_secs(10 * 60),
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(30 * 60),
+            min_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Token cache health, for monitoring
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheHealth {
+    /// Tokens valid now
+    pub valid_tokens: usize,
+    /// Tokens that must be issued to meet the target
+    pub tokens_needed: usize,
+    /// Soonest expiry among valid tokens
+    pub earliest_expiry: Option<u64>,
+    /// When the next renewal attempt is scheduled
+    pub next_attempt_at: u64,
+    /// Failed attempts since the last success
+    pub consecutive_failures: u32,
+    /// Successful renewals so far
+    pub renewals: u64,
+    /// Failed renewals so far
+    pub failures: u64,
+    /// Time of the last successful renewal
+    pub last_success_at: Option<u64>,
+    /// Error of the last failed renewal
+    pub last_error: Option<String>,
+}
+
+/// Decides when to renew tokens
+#[derive(Debug, Clone)]
+pub struct RefreshScheduler {
+    policy: RefreshPolicy,
+    jitter: u64,
+    retry_at: Option<u64>,
+    last_attempt_at: Option<u64>,
+    consecutive_failures: u32,
+    renewals: u64,
+    failures: u64,
+    last_success_at: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl RefreshScheduler {
+    /// Scheduler for `policy`
+    pub fn new(policy: RefreshPolicy) -> Self {
+        let jitter = draw_jitter(&policy);
+        RefreshScheduler {
+            policy,
+            jitter,
+            retry_at: None,
+            last_attempt_at: None,
+            consecutive_failures: 0,
+            renewals: 0,
+            failures: 0,
+            last_success_at: None,
+            last_error: None,
+        }
+    }
+
+    /// Renewal parameters
+    pub fn policy(&self) -> &RefreshPolicy {
+        &self.policy
+    }
+
+    /// Tokens to issue now so `target_tokens` outlive the renewal window
+    ///
+    /// The window is widened by the current jitter, as the schedule is, so
+    /// an attempt made when due always finds tokens to issue.
+    pub fn tokens_needed(&self, cache: &SessionTokenCache, now: u64) -> usize {
+        let horizon = now.saturating_add(self.policy.renew_before.as_secs() + self.jitter);
+        let lasting = valid_expiries(cache, now).iter().filter(|e| **e > horizon).count();
+        self.policy.target_tokens.saturating_sub(lasting)
+    }
+
+    /// When the next renewal attempt is due
+    pub fn next_attempt_at(&self, cache: &SessionTokenCache, now: u64) -> u64 {
+        let due = match self.retry_at {
+            Some(retry_at) => retry_at,
+            None => {
+                // Due when the target-th longest-lived token enters the renewal window
+                let mut expiries = valid_expiries(cache, now);
+                expiries.sort_unstable_by(|a, b| b.cmp(a));
+                match self.policy.target_tokens.checked_sub(1).and_then(|i| expiries.get(i)) {
+                    Some(expiry) => expiry
+                        .saturating_sub(self.policy.renew_before.as_secs() + self.jitter)
+                        .max(now),
+                    None if self.policy.target_tokens == 0 => u64::MAX,
+                    None => now,
+                }
+            }
+        };
+        match self.last_attempt_at {
+            Some(last) => due.max(last.saturating_add(self.policy.min_interval.as_secs())),
+            None => due,
+        }
+    }
+
+    /// Record a successful attempt that issued `issued` tokens
+    ///
+    /// The jitter is redrawn only when tokens were issued: the schedule
+    /// then moves to the new tokens, while an empty attempt keeps it.
+    pub fn record_success(&mut self, now: u64, issued: usize) {
+        self.retry_at = None;
+        self.last_attempt_at = Some(now);
+        self.consecutive_failures = 0;
+        if issued > 0 {
+            self.renewals += 1;
+            self.last_success_at = Some(now);
+            self.jitter = draw_jitter(&self.policy);
+        }
+    }
+
+    /// Record a failed renewal, returning when to retry
+    ///
+    /// The delay doubles with every consecutive failure up to
+    /// `max_backoff`, and is randomized over its upper half.
+    pub fn record_failure(&mut self, now: u64, error: &FrostError) -> u64 {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.failures += 1;
+        self.last_attempt_at = Some(now);
+        self.last_error = Some(error.to_string());
+
+        let backoff = self.policy.initial_backoff.as_secs().max(1)
+            .saturating_mul(1u64 << (self.consecutive_failures - 1).min(32))
+            .min(self.policy.max_backoff.as_secs().max(1));
+        let delay = backoff / 2 + rand::thread_rng().gen_range(0..=backoff - backoff / 2);
+
+        let retry_at = now.saturating_add(delay);
+        self.retry_at = Some(retry_at);
+        retry_at
+    }
+
+    /// Current health of `cache` under this scheduler
+    pub fn health(&self, cache: &SessionTokenCache, now: u64) -> CacheHealth {
+        let expiries = valid_expiries(cache, now);
+        CacheHealth {
+            valid_tokens: expiries.len(),
+            tokens_needed: self.tokens_needed(cache, now),
+            earliest_expiry: expiries.iter().min().copied(),
+            next_attempt_at: self.next_attempt_at(cache, now),
+            consecutive_failures: self.consecutive_failures,
+            renewals: self.renewals,
+            failures: self.failures,
+            last_success_at: self.last_success_at,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+fn draw_jitter(policy: &RefreshPolicy) -> u64 {
+    rand::thread_rng().gen_range(0..=policy.jitter.as_secs())
+}
+
+/// Effective expiries of the tokens valid at `now`
+fn valid_expiries(cache: &SessionTokenCache, now: u64) -> Vec<u64> {
+    cache.tokens.iter()
+        .filter(|t| t.issued_at <= now && now < t.effective_expires_at())
+        .map(|t| t.effective_expires_at())
+        .collect()
+}
+
+/// Handle to a background renewal task
+pub struct TokenRefreshTask {
+    health: watch::Receiver<CacheHealth>,
+    handle: JoinHandle<()>,
+}
+
+impl TokenRefreshTask {
+    /// Most recently published cache health
+    pub fn health(&self) -> CacheHealth {
+        self.health.borrow().clone()
+    }
+
+    /// Subscribe to health updates
+    pub fn subscribe(&self) -> watch::Receiver<CacheHealth> {
+        self.health.clone()
+    }
+
+    /// Stop the task
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+/// Renew `device`'s tokens in the background until stopped
+///
+/// The device lock is held only while checking the cache and while a
+/// renewal is in flight. Must be called within a Tokio runtime.
+pub fn spawn_token_refresh<T: RemoteShareTransport + 'static>(
+    device: Arc<Mutex<HybridFROSTDevice<T>>>,
+    policy: RefreshPolicy,
+) -> TokenRefreshTask {
+    let (health_tx, health_rx) = watch::channel(CacheHealth::default());
+    let mut scheduler = RefreshScheduler::new(policy);
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let delay = {
+                let device = device.lock().await;
+                let now = unix_now();
+                health_tx.send_replace(scheduler.health(device.token_cache(), now));
+                scheduler.next_attempt_at(device.token_cache(), now).saturating_sub(now)
+            };
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+
+            let mut device = device.lock().await;
+            let now = unix_now();
+            match device.refresh_with(&mut scheduler, now).await {
+                Ok(issued) if issued > 0 => log::info!("Renewed {} session tokens", issued),
+                Ok(_) => {}
+                Err(e) => log::warn!("Session token renewal failed: {}", e),
+            }
+            health_tx.send_replace(scheduler.health(device.token_cache(), now));
+        }
+    });
+
+    TokenRefreshTask { health: health_rx, handle }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_token::{Capabilities, SessionToken};
+
+    fn cache_with_lifetimes(lifetimes: &[u64]) -> (SessionTokenCache, u64) {
+        let mut cache = SessionTokenCache::new(20);
+        for lifetime in lifetimes {
+            cache.add_token(SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(*lifetime))).unwrap();
+        }
+        (cache, unix_now())
+    }
+
+    #[test]
+    fn test_renewal_scheduled_before_expiry_with_jitter() {
+        let policy = RefreshPolicy { target_tokens: 2, ..RefreshPolicy::default() };
+        let scheduler = RefreshScheduler::new(policy.clone());
+
+        let (empty, now) = cache_with_lifetimes(&[]);
+        assert_eq!(scheduler.tokens_needed(&empty, now), 2);
+        assert_eq!(scheduler.next_attempt_at(&empty, now), now);
+
+        // Due when the second-longest token enters the renewal window
+        let (cache, now) = cache_with_lifetimes(&[8 * 3600, 4 * 3600]);
+        assert_eq!(scheduler.tokens_needed(&cache, now), 0);
+        let due = now + 4 * 3600 - policy.renew_before.as_secs();
+        let at = scheduler.next_attempt_at(&cache, now);
+        assert!(at <= due && at >= due - policy.jitter.as_secs(), "{} not in jitter window before {}", at, due);
+
+        // A token already inside the window counts as needing renewal
+        let (cache, now) = cache_with_lifetimes(&[8 * 3600, 1800]);
+        assert_eq!(scheduler.tokens_needed(&cache, now), 1);
+        assert_eq!(scheduler.next_attempt_at(&cache, now), now);
+    }
+
+    #[test]
+    fn test_failures_back_off_exponentially() {
+        let policy = RefreshPolicy {
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(300),
+            ..RefreshPolicy::default()
+        };
+        let mut scheduler = RefreshScheduler::new(policy);
+        let (cache, now) = cache_with_lifetimes(&[]);
+        let error = FrostError::CryptoError("offline".to_string());
+
+        let mut previous_max = 0;
+        for (attempt, ceiling) in [30u64, 60, 120, 240, 300, 300].into_iter().enumerate() {
+            let retry_at = scheduler.record_failure(now, &error);
+            let delay = retry_at - now;
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {}", attempt, delay);
+            assert!(ceiling >= previous_max);
+            previous_max = ceiling;
+            assert_eq!(scheduler.next_attempt_at(&cache, now), retry_at);
+        }
+
+        let health = scheduler.health(&cache, now);
+        assert_eq!(health.consecutive_failures, 6);
+        assert_eq!(health.failures, 6);
+        assert_eq!(health.last_error.as_deref(), Some("Cryptographic error: offline"));
+
+        scheduler.record_success(now + 10, 5);
+        let health = scheduler.health(&cache, now + 10);
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.renewals, 1);
+        assert_eq!(health.next_attempt_at, now + 10 + scheduler.policy().min_interval.as_secs());
+    }
+
+    #[test]
+    fn test_due_attempts_find_tokens_to_issue() {
+        let policy = RefreshPolicy { target_tokens: 1, ..RefreshPolicy::default() };
+        let renew_before = policy.renew_before.as_secs();
+
+        // Whatever the jitter, a token expiring in the jittered window is
+        // needed once the attempt is due
+        for offset in [0, 1, 300, policy.jitter.as_secs()] {
+            let scheduler = RefreshScheduler::new(policy.clone());
+            let (cache, now) = cache_with_lifetimes(&[renew_before + offset]);
+            let due = scheduler.next_attempt_at(&cache, now);
+            assert_eq!(scheduler.tokens_needed(&cache, due), 1, "offset {}", offset);
+        }
+
+        // An attempt that issues nothing keeps the jitter, and even a cache
+        // short of tokens waits out the minimum interval
+        let mut scheduler = RefreshScheduler::new(policy.clone());
+        let (empty, now) = cache_with_lifetimes(&[]);
+        let jitter = scheduler.jitter;
+        scheduler.record_success(now, 0);
+        assert_eq!(scheduler.jitter, jitter);
+        assert_eq!(scheduler.health(&empty, now).renewals, 0);
+        assert_eq!(scheduler.next_attempt_at(&empty, now), now + policy.min_interval.as_secs());
+    }
+}
@@ -0,0 +1,251 @@
+//! Session token revocation lists
+//!
+//! The quorum FROST-signs a list of revoked token IDs together with a
+//! sequence number. Devices accept only lists with a higher sequence than
+//! the one they hold, so an old list cannot be replayed to un-revoke a
+//! token. Large lists can be shipped as a Bloom filter; a false positive
+//! only ever refuses a token, never accepts one.
+
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+
+/// Domain tag opening every revocation list's signing data
+pub const REVOCATION_LIST_TAG: &[u8] = b"FROST-REVOCATION-LIST-v1";
+
+/// Set of revoked token IDs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RevokedTokens {
+    /// Exact list of token IDs (sorted, unique)
+    Ids(Vec<[u8; 16]>),
+    /// Bloom filter over token IDs
+    Bloom(BloomFilter),
+}
+
+impl RevokedTokens {
+    /// Exact set from any list of IDs
+    pub fn from_ids(mut ids: Vec<[u8; 16]>) -> Self {
+        ids.sort();
+        ids.dedup();
+        RevokedTokens::Ids(ids)
+    }
+
+    /// Whether `token_id` is (possibly, for a Bloom filter) revoked
+    pub fn contains(&self, token_id: &[u8; 16]) -> bool {
+        match self {
+            RevokedTokens::Ids(ids) => ids.binary_search(token_id).is_ok(),
+            RevokedTokens::Bloom(filter) => filter.contains(token_id),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            RevokedTokens::Ids(ids) => {
+                out.push(0);
+                out.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+                for id in ids {
+                    out.extend_from_slice(id);
+                }
+            }
+            RevokedTokens::Bloom(filter) => {
+                out.push(1);
+                out.push(filter.hashes);
+                out.extend_from_slice(&(filter.bits.len() as u32).to_le_bytes());
+                out.extend_from_slice(&filter.bits);
+            }
+        }
+    }
+}
+
+/// Bloom filter over 16-byte token IDs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BloomFilter {
+    /// Filter bits
+    pub bits: Vec<u8>,
+    /// Number of hash functions
+    pub hashes: u8,
+}
+
+impl BloomFilter {
+    /// Empty filter of `bytes` bytes using `hashes` hash functions
+    pub fn new(bytes: usize, hashes: u8) -> Self {
+        BloomFilter {
+            bits: vec![0u8; bytes.max(1)],
+            hashes: hashes.max(1),
+        }
+    }
+
+    /// Add a token ID
+    pub fn insert(&mut self, token_id: &[u8; 16]) {
+        let indices: Vec<usize> = self.bit_indices(token_id).collect();
+        for bit in indices {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Whether the filter may contain `token_id`
+    pub fn contains(&self, token_id: &[u8; 16]) -> bool {
+        self.bit_indices(token_id)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn bit_indices<'a>(&'a self, token_id: &'a [u8; 16]) -> impl Iterator<Item = usize> + 'a {
+        let total_bits = self.bits.len() as u64 * 8;
+        (0..self.hashes).map(move |i| {
+            let mut hasher = Sha256::new();
+            hasher.update(b"FROST-REVOCATION-BLOOM-v1");
+            hasher.update([i]);
+            hasher.update(token_id);
+            let digest = hasher.finalize();
+            let word = u64::from_le_bytes(digest[..8].try_into().expect("8-byte slice"));
+            (word % total_bits) as usize
+        })
+    }
+}
+
+/// FROST-signed list of revoked session tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    /// Monotonic list number; higher replaces lower
+    pub sequence: u64,
+    /// Issue time (Unix epoch seconds)
+    pub issued_at: u64,
+    /// Revoked token IDs
+    pub revoked: RevokedTokens,
+    /// Threshold signature over `to_signing_data`
+    pub signature: SchnorrSignature,
+}
+
+impl RevocationList {
+    /// Data covered by the quorum signature
+    pub fn to_signing_data(sequence: u64, issued_at: u64, revoked: &RevokedTokens) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(REVOCATION_LIST_TAG);
+        data.extend_from_slice(&sequence.to_le_bytes());
+        data.extend_from_slice(&issued_at.to_le_bytes());
+        revoked.encode(&mut data);
+        data
+    }
+
+    /// Verify the quorum signature
+    pub fn verify(&self, group_public_key: &GroupPublicKey) -> bool {
+        let data = Self::to_signing_data(self.sequence, self.issued_at, &self.revoked);
+        self.signature.verify(SignatureScheme::Threshold, &data, &group_public_key.public_key)
+    }
+
+    /// Whether `token_id` is revoked by this list
+    pub fn is_revoked(&self, token_id: &[u8; 16]) -> bool {
+        self.revoked.contains(token_id)
+    }
+
+    /// Check signature, shape and ordering against the list currently held
+    pub fn check_supersedes(
+        &self,
+        current: Option<&RevocationList>,
+        group_public_key: &GroupPublicKey,
+    ) -> FrostResult<()> {
+        if !self.verify(group_public_key) {
+            return Err(FrostError::CryptoError("Revocation list is not signed by the FROST group".to_string()));
+        }
+        match &self.revoked {
+            // An empty filter has no bit to index; lookups would divide by zero
+            RevokedTokens::Bloom(filter) if filter.bits.is_empty() => {
+                return Err(FrostError::CryptoError("Revocation Bloom filter is empty".to_string()));
+            }
+            // Lookups binary-search the IDs, which misses entries of an unsorted list
+            RevokedTokens::Ids(ids) if ids.windows(2).any(|pair| pair[0] >= pair[1]) => {
+                return Err(FrostError::CryptoError("Revoked token IDs are not sorted and unique".to_string()));
+            }
+            _ => {}
+        }
+        if let Some(current) = current {
+            if self.sequence <= current.sequence {
+                return Err(FrostError::RollbackDetected(format!(
+                    "Revocation list {} does not supersede {}",
+                    self.sequence, current.sequence,
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{group, signed_list};
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn test_signed_list_verifies_and_binds_contents() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let list = signed_list(&secret, 7, 1000, RevokedTokens::from_ids(vec![[2u8; 16], [1u8; 16]]));
+
+        assert!(list.verify(&group(&secret)));
+        assert!(list.is_revoked(&[1u8; 16]));
+        assert!(!list.is_revoked(&[3u8; 16]));
+
+        let mut unrevoked = list.clone();
+        unrevoked.revoked = RevokedTokens::from_ids(vec![[2u8; 16]]);
+        assert!(!unrevoked.verify(&group(&secret)));
+
+        let mut renumbered = list.clone();
+        renumbered.sequence = 8;
+        assert!(!renumbered.verify(&group(&secret)));
+
+        let other = Scalar::random(&mut rand::thread_rng());
+        assert!(!list.verify(&group(&other)));
+    }
+
+    #[test]
+    fn test_older_list_rejected() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let current = signed_list(&secret, 7, 1000, RevokedTokens::from_ids(vec![[1u8; 16]]));
+        let older = signed_list(&secret, 6, 900, RevokedTokens::from_ids(vec![]));
+        let newer = signed_list(&secret, 8, 1100, RevokedTokens::from_ids(vec![]));
+
+        assert!(matches!(
+            older.check_supersedes(Some(&current), &group(&secret)),
+            Err(FrostError::RollbackDetected(_)),
+        ));
+        assert!(current.check_supersedes(Some(&current), &group(&secret)).is_err());
+        assert!(newer.check_supersedes(Some(&current), &group(&secret)).is_ok());
+    }
+
+    #[test]
+    fn test_empty_bloom_filter_rejected() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let empty = BloomFilter { bits: Vec::new(), hashes: 3 };
+        let list = signed_list(&secret, 1, 1000, RevokedTokens::Bloom(empty));
+
+        assert!(list.verify(&group(&secret)));
+        assert!(list.check_supersedes(None, &group(&secret)).is_err());
+    }
+
+    #[test]
+    fn test_unsorted_ids_rejected() {
+        let secret = Scalar::random(&mut rand::thread_rng());
+        for ids in [vec![[2u8; 16], [1u8; 16]], vec![[1u8; 16], [1u8; 16]]] {
+            let list = signed_list(&secret, 1, 1000, RevokedTokens::Ids(ids));
+            assert!(list.verify(&group(&secret)));
+            assert!(list.check_supersedes(None, &group(&secret)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(256, 5);
+        let revoked: Vec<[u8; 16]> = (0u8..50).map(|i| [i; 16]).collect();
+        for id in &revoked {
+            filter.insert(id);
+        }
+
+        // No false negatives
+        assert!(revoked.iter().all(|id| filter.contains(id)));
+
+        // Few false positives at ~40 bits per entry
+        let false_positives = (100u8..=255).filter(|i| filter.contains(&[*i; 16])).count();
+        assert!(false_positives < 5);
+    }
+}
@@ -7,7 +7,7 @@ use crate::types::*;
 use crate::{FrostError, FrostResult};
 use crate::dkg::pedersen_h_generator;
 use curve25519_dalek::{
-    ristretto::RistrettoPoint,
+    ristretto::CompressedRistretto,
     scalar::Scalar,
     constants::RISTRETTO_BASEPOINT_POINT,
 };
@@ -23,6 +23,9 @@ pub struct ShareRotation {
     my_id: ParticipantId,
     /// Current secret share
     current_share: SecretScalar,
+    /// Group public key, unchanged by rotation
+    #[zeroize(skip)]
+    group_public_key: CompressedRistretto,
     /// Threshold
     #[zeroize(skip)]
     threshold: u32,
@@ -56,6 +59,7 @@ impl ShareRotation {
         Ok(ShareRotation {
             my_id,
             current_share: current_share.value.clone(),
+            group_public_key: current_share.group_public_key,
             threshold,
             num_participants,
             delta_poly: Polynomial { coefficients },
@@ -164,6 +168,7 @@ impl ShareRotation {
             participant_id: self.my_id,
             value: SecretScalar::new(new_share),
             blinding: SecretScalar::new(Scalar::ZERO),
+            group_public_key: self.group_public_key,
         })
     }
 }
@@ -297,16 +302,16 @@ mod tests {
         let mut partial_sigs = Vec::new();
         let mut group_commitment = None;
 
-        for round1 in round1_states.iter().take(2) {
-            let round2 = round1.clone().into_round2(message1, &commitments).unwrap();
+        for round1 in round1_states.into_iter().take(2) {
+            let round2 = round1.into_round2(message1, &commitments).unwrap();
             if group_commitment.is_none() {
                 group_commitment = Some(round2.group_commitment());
             }
             partial_sigs.push(round2.partial_signature());
         }
 
-        let sig1 = aggregate_signatures(message1, &group_commitment.unwrap(), &partial_sigs).unwrap();
-        assert!(sig1.verify(message1, &original_pk));
+        let sig1 = aggregate_signatures(&group_commitment.unwrap(), &partial_sigs).unwrap();
+        assert!(sig1.verify(SignatureScheme::Threshold, message1, &original_pk));
 
         // Rotate shares
         let rotation_coordinator = RotationCoordinator::new(2, 3).unwrap();
@@ -323,10 +328,10 @@ mod tests {
         let mut round1_states = Vec::new();
         let mut commitments = Vec::new();
 
-        for i in 0..2 {
+        for share in &new_shares[..2] {
             let round1 = SigningRound1::new(
-                new_shares[i].participant_id,
-                &new_shares[i],
+                share.participant_id,
+                share,
                 &mut rng,
             );
             commitments.push(round1.commitment());
@@ -336,17 +341,17 @@ mod tests {
         let mut partial_sigs = Vec::new();
         let mut group_commitment = None;
 
-        for round1 in round1_states.iter() {
-            let round2 = round1.clone().into_round2(message2, &commitments).unwrap();
+        for round1 in round1_states.into_iter() {
+            let round2 = round1.into_round2(message2, &commitments).unwrap();
             if group_commitment.is_none() {
                 group_commitment = Some(round2.group_commitment());
             }
             partial_sigs.push(round2.partial_signature());
         }
 
-        let sig2 = aggregate_signatures(message2, &group_commitment.unwrap(), &partial_sigs).unwrap();
+        let sig2 = aggregate_signatures(&group_commitment.unwrap(), &partial_sigs).unwrap();
 
         // Signature should verify with SAME public key
-        assert!(sig2.verify(message2, &original_pk));
+        assert!(sig2.verify(SignatureScheme::Threshold, message2, &original_pk));
     }
 }
@@ -5,9 +5,18 @@
 //! then cached locally for offline use.
 
 use crate::types::*;
+use crate::audit::{AuditExport, AuditLog, AuditOutcome};
+use crate::nonce::{hedged_nonce, DefaultEntropy};
+use crate::policy::{encode_str, AttributeValue, Decision, RequestContext, Rule};
+use crate::revocation::RevocationList;
+use crate::transport::{share_request_digest, SessionId};
 use crate::{FrostError, FrostResult};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
 use serde::{Serialize, Deserialize};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use sha2::{Sha256, Digest};
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 
 /// Session token capabilities
@@ -31,12 +40,19 @@ pub struct SessionToken {
     /// Usage tracking
     pub usage: UsageTracker,
 
+    /// Public half of the ephemeral key, bound into the signed token body
+    pub ephemeral_public: CompressedRistretto,
+
     /// FROST signature (2-of-3 threshold)
     pub frost_signature: SchnorrSignature,
 
-    /// Ephemeral signing key (for token operations)
+    /// Holder-appended restrictions, oldest first
+    #[serde(default)]
+    pub caveats: Vec<Caveat>,
+
+    /// Signing key of the current holder (for token operations)
     #[serde(skip)]
-    ephemeral_key: Option<SecretScalar>,
+    pub(crate) ephemeral_key: Option<SecretScalar>,
 }
 
 /// Capabilities granted by this token
@@ -57,8 +73,16 @@ pub struct Capabilities {
     /// FileVault decryption
     pub filevault_decrypt: bool,
 
-    /// Custom capability flags
+    /// Signing arbitrary messages (`TokenRequest::SignMessage`)
+    #[serde(default)]
+    pub sign_message: bool,
+
+    /// Custom operations allowed (by `TokenRequest::Custom` name)
     pub custom: Vec<String>,
+
+    /// Additional conditions every operation must satisfy
+    #[serde(default)]
+    pub policy: Option<Rule>,
 }
 
 /// Keychain access levels
@@ -93,27 +117,38 @@ pub struct PaymentLimits {
 /// Usage tracking for token
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageTracker {
-    /// Number of times used
+    /// Number of times this copy was used
+    ///
+    /// Policies do not rely on it: attenuated copies start from the count
+    /// of their parent, so they tally uses in the `UsageLedger`.
     pub use_count: u64,
 
     /// Last used timestamp
     pub last_used_at: Option<u64>,
 
-    /// Operations performed
-    pub operations: Vec<TokenOperation>,
+    /// Signed log of every attempted use by the current holder
+    #[serde(default)]
+    pub audit: AuditLog,
 }
 
-/// Record of token operation
+/// Restriction appended to a token by its current holder
+///
+/// Each caveat hands the token to a new holder key and is signed by the
+/// previous one, chaining back to the FROST-signed ephemeral key. Caveats
+/// are intersected with everything before them, so they can only narrow.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TokenOperation {
-    /// Operation type
-    pub operation: String,
+pub struct Caveat {
+    /// Ceiling on the token's capabilities
+    pub capabilities: Capabilities,
 
-    /// Timestamp
-    pub timestamp: u64,
+    /// Earlier expiry (Unix epoch seconds)
+    pub expires_at: Option<u64>,
+
+    /// Key of the holder the restricted token is handed to
+    pub holder_public: CompressedRistretto,
 
-    /// Success/failure
-    pub success: bool,
+    /// Previous holder's signature over the chain and this caveat
+    pub signature: SchnorrSignature,
 }
 
 impl SessionToken {
@@ -128,9 +163,14 @@ impl SessionToken {
             .unwrap()
             .as_secs();
 
+        let mut rng = rand::thread_rng();
         let mut token_id = [0u8; 16];
         use rand::RngCore;
-        rand::thread_rng().fill_bytes(&mut token_id);
+        rng.fill_bytes(&mut token_id);
+
+        // Ephemeral key the FROST group delegates to for this token's lifetime
+        let ephemeral_key = SecretScalar::new(Scalar::random(&mut rng));
+        let ephemeral_public = (ephemeral_key.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
 
         SessionToken {
             token_id,
@@ -141,13 +181,16 @@ impl SessionToken {
             usage: UsageTracker {
                 use_count: 0,
                 last_used_at: None,
-                operations: Vec::new(),
+                audit: AuditLog::default(),
             },
             frost_signature: SchnorrSignature {
                 z: [0u8; 32],  // Will be filled by FROST signing
                 commitment: CompressedRistretto::default(),
+                scheme: SignatureScheme::Threshold,
             },
-            ephemeral_key: None,
+            ephemeral_public,
+            caveats: Vec::new(),
+            ephemeral_key: Some(ephemeral_key),
         }
     }
 
@@ -158,7 +201,7 @@ impl SessionToken {
             .unwrap()
             .as_secs();
 
-        now >= self.issued_at && now < self.expires_at
+        now >= self.issued_at && now < self.effective_expires_at()
     }
 
     /// Time until expiration
@@ -172,22 +215,325 @@ impl SessionToken {
             .unwrap()
             .as_secs();
 
-        Some(Duration::from_secs(self.expires_at - now))
+        Some(Duration::from_secs(self.effective_expires_at() - now))
     }
 
-    /// Check if token allows an operation
-    pub fn allows_operation(&self, operation: &TokenRequest) -> bool {
-        if !self.is_valid() {
-            return false;
+    /// Expiry after applying every caveat
+    pub fn effective_expires_at(&self) -> u64 {
+        self.caveats.iter()
+            .filter_map(|c| c.expires_at)
+            .fold(self.expires_at, u64::min)
+    }
+
+    /// Granted capabilities intersected with every caveat
+    pub fn effective_capabilities(&self) -> Capabilities {
+        self.caveats.iter()
+            .fold(self.capabilities.clone(), |capabilities, c| capabilities.restrict(&c.capabilities))
+    }
+
+    /// Public key operations under this token must be signed with
+    pub fn holder_public(&self) -> CompressedRistretto {
+        self.caveats.last().map_or(self.ephemeral_public, |c| c.holder_public)
+    }
+
+    /// Hand out a narrower copy of this token without a signing round
+    ///
+    /// The copy carries one more caveat, signed by the current holder key,
+    /// and a fresh holder key of its own. This token stays usable.
+    pub fn attenuate(&self, capabilities: Capabilities, expires_at: Option<u64>) -> FrostResult<SessionToken> {
+        let holder_key = self.ephemeral_key.as_ref()
+            .ok_or(FrostError::CryptoError("Token has no ephemeral key".to_string()))?;
+
+        let next_key = SecretScalar::new(Scalar::random(&mut rand::thread_rng()));
+        let holder_public = (next_key.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress();
+        let link = caveat_link(&self.chain_head(), &capabilities, expires_at, &holder_public);
+        let nonce = hedged_nonce(b"token-caveat", holder_key.as_scalar(), &self.holder_public(), &link, &DefaultEntropy)?;
+
+        let mut attenuated = self.clone();
+        attenuated.caveats.push(Caveat {
+            capabilities,
+            expires_at,
+            holder_public,
+            signature: SchnorrSignature::sign_with_nonce(holder_key.as_scalar(), nonce.as_scalar(), &link),
+        });
+        attenuated.ephemeral_key = Some(next_key);
+        // The new holder signs its own audit trail
+        attenuated.usage.audit = AuditLog::default();
+        Ok(attenuated)
+    }
+
+    /// Verify every caveat was signed by the holder before it
+    pub fn verify_caveats(&self) -> bool {
+        let mut previous = self.to_signing_data();
+        let mut signer = self.ephemeral_public;
+        for caveat in &self.caveats {
+            let link = caveat_link(&previous, &caveat.capabilities, caveat.expires_at, &caveat.holder_public);
+            if !caveat.signature.verify(SignatureScheme::Schnorr, &link, &signer) {
+                return false;
+            }
+            previous = link.to_vec();
+            signer = caveat.holder_public;
+        }
+        true
+    }
+
+    /// Hash the next caveat chains from
+    fn chain_head(&self) -> Vec<u8> {
+        self.caveats.iter().fold(self.to_signing_data(), |previous, c| {
+            caveat_link(&previous, &c.capabilities, c.expires_at, &c.holder_public).to_vec()
+        })
+    }
+
+    /// Check if token allows an operation in `context`
+    pub fn allows_operation(&self, operation: &TokenRequest, context: &RequestContext) -> bool {
+        self.authorize(operation, context).allowed
+    }
+
+    /// Decide whether `operation` is allowed in `context`, with a trace
+    ///
+    /// Checks validity, the effective capabilities, today's remaining
+    /// spend, and the effective policy.
+    pub fn authorize(&self, operation: &TokenRequest, context: &RequestContext) -> Decision {
+        let mut trace = Vec::new();
+
+        let valid = context.now >= self.issued_at && context.now < self.effective_expires_at();
+        trace.push(format!("token valid at {}: {}", context.now, valid));
+
+        let capabilities = self.effective_capabilities();
+        let permitted = capabilities.permits(operation);
+        trace.push(format!("capabilities grant {}: {}", operation.name(), permitted));
+
+        // Daily spend is usage state, checked on top of the signed limits
+        let affordable = match operation {
+            TokenRequest::Payment { amount } => {
+                let available = capabilities.payment_limits.as_ref().map_or(0, |l| l.available_today());
+                trace.push(format!("amount {} within {} left today: {}", amount, available, *amount <= available));
+                *amount <= available
+            }
+            _ => true,
+        };
+
+        let compliant = match &capabilities.policy {
+            None => true,
+            Some(policy) => {
+                let decision = policy.evaluate(operation, context);
+                trace.extend(decision.trace);
+                decision.allowed
+            }
+        };
+
+        Decision {
+            allowed: valid && permitted && affordable && compliant,
+            trace,
+        }
+    }
+
+    /// Use token for an operation
+    ///
+    /// Allowed and denied attempts are both appended to the audit log;
+    /// once the log is full the token refuses all use until it is uploaded.
+    /// Payments are refused: the remaining allowance on the token comes
+    /// back with any restored copy, so they go through
+    /// `UsageLedger::record_use`. The operation is timestamped with
+    /// `context.now`.
+    pub fn use_for_operation(&mut self, operation: TokenRequest, context: &RequestContext) -> FrostResult<TokenSignature> {
+        if matches!(operation, TokenRequest::Payment { .. }) {
+            return Err(FrostError::CryptoError("Payments must be recorded in the usage ledger".to_string()));
+        }
+        self.perform_operation(operation, context)
+    }
+
+    /// `use_for_operation` without the payment check, once the ledger has
+    /// enforced the daily limit
+    pub(crate) fn perform_operation(&mut self, operation: TokenRequest, context: &RequestContext) -> FrostResult<TokenSignature> {
+        let ephemeral_key = self.ephemeral_key.clone()
+            .ok_or(FrostError::CryptoError("Token has no ephemeral key".to_string()))?;
+        let holder_public = self.holder_public();
+        let now = context.now;
+
+        if !self.allows_operation(&operation, context) {
+            let operation_hash = operation_hash(&self.token_id, self.usage.use_count, now, &operation);
+            self.usage.audit.append(&self.token_id, &ephemeral_key, &holder_public, now, operation_hash, AuditOutcome::Denied)?;
+            return Err(FrostError::CryptoError("Token does not allow operation".to_string()));
+        }
+
+        // Log before signing, so a full log refuses the operation
+        let use_count = self.usage.use_count + 1;
+        let operation_hash = operation_hash(&self.token_id, use_count, now, &operation);
+        self.usage.audit.append(&self.token_id, &ephemeral_key, &holder_public, now, operation_hash, AuditOutcome::Allowed)?;
+
+        // Update usage tracking
+        self.usage.use_count = use_count;
+        self.usage.last_used_at = Some(now);
+
+        // Update payment limits if applicable
+        if let TokenRequest::Payment { amount } = operation {
+            if let Some(limits) = &mut self.capabilities.payment_limits {
+                limits.remaining_today = limits.available_today().saturating_sub(amount);
+            }
+        }
+
+        // Sign the operation with the delegated ephemeral key
+        let nonce = hedged_nonce(
+            b"session-token",
+            ephemeral_key.as_scalar(),
+            &holder_public,
+            &operation_hash,
+            &DefaultEntropy,
+        )?;
+
+        Ok(TokenSignature {
+            token_id: self.token_id,
+            use_count,
+            timestamp: now,
+            operation_hash,
+            signature: SchnorrSignature::sign_with_nonce(ephemeral_key.as_scalar(), nonce.as_scalar(), &operation_hash),
+        })
+    }
+
+    /// Sign a remote share request with the holder key
+    ///
+    /// Share servers check this against the bearer token's holder key, so
+    /// a leaked bearer token alone cannot open signing sessions.
+    pub fn sign_share_request(&self, session_id: &SessionId, message_hash: &[u8; 32]) -> FrostResult<SchnorrSignature> {
+        let holder_key = self.ephemeral_key.as_ref()
+            .ok_or(FrostError::CryptoError("Token has no ephemeral key".to_string()))?;
+        let digest = share_request_digest(session_id, message_hash);
+        let nonce = hedged_nonce(b"share-request", holder_key.as_scalar(), &self.holder_public(), &digest, &DefaultEntropy)?;
+        Ok(SchnorrSignature::sign_with_nonce(holder_key.as_scalar(), nonce.as_scalar(), &digest))
+    }
+
+    /// Audit entries awaiting upload, signed by the current holder
+    pub fn export_audit(&self) -> AuditExport {
+        self.usage.audit.export(self.token_id, self.holder_public())
+    }
+
+    /// Verify FROST signature on token
+    pub fn verify_frost_signature(&self, public_key: &GroupPublicKey) -> bool {
+        // Serialize token data
+        let token_data = self.to_signing_data();
+
+        // Verify FROST signature
+        public_key.verify_signature(&token_data, &self.frost_signature)
+    }
+
+    /// Get token data for signing
+    ///
+    /// The COSE_Sign1 `Sig_structure` of the token's CWT form (see
+    /// `cose`), so the FROST signature is also the CWT signature. Binds the
+    /// ephemeral public key and the granted capabilities, so the group
+    /// delegates exactly these rights to exactly this key.
+    pub fn to_signing_data(&self) -> Vec<u8> {
+        crate::cose::token_sig_structure(self)
+    }
+}
+
+/// Hash of one token operation: what the ephemeral key signs
+fn operation_hash(token_id: &[u8; 16], use_count: u64, timestamp: u64, operation: &TokenRequest) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"FROST-TOKEN-OPERATION-v1");
+    hasher.update(token_id);
+    hasher.update(use_count.to_le_bytes());
+    hasher.update(timestamp.to_le_bytes());
+    hasher.update(operation.to_bytes());
+    hasher.finalize().into()
+}
+
+/// Hash of one caveat chained onto `previous`: what the prior holder signs
+fn caveat_link(
+    previous: &[u8],
+    capabilities: &Capabilities,
+    expires_at: Option<u64>,
+    holder_public: &CompressedRistretto,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"FROST-TOKEN-CAVEAT-v1");
+    hasher.update(previous);
+    hasher.update(capabilities.to_canonical_bytes());
+    match expires_at {
+        None => hasher.update([0u8]),
+        Some(expires_at) => {
+            hasher.update([1u8]);
+            hasher.update(expires_at.to_le_bytes());
+        }
+    }
+    hasher.update(holder_public.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Verify an offline token operation end to end
+///
+/// Checks every link of the delegation chain: the FROST group signed the
+/// token (and with it the ephemeral key and capabilities), each caveat was
+/// signed by the holder before it, and the last holder signed this
+/// operation while the restricted token was valid and its policy allowed
+/// it. The policy is evaluated at the signed timestamp, with the region,
+/// network and use count the verifier knows from `context`. The use count
+/// in the signature is the holder's own claim and is not trusted: a
+/// `UseCountBelow` policy needs the verifier to tally uses of the token.
+pub fn verify_token_operation(
+    group_public_key: &GroupPublicKey,
+    token: &SessionToken,
+    operation: &TokenRequest,
+    token_signature: &TokenSignature,
+    context: &RequestContext,
+) -> FrostResult<()> {
+    if !token.frost_signature.verify(SignatureScheme::Threshold, &token.to_signing_data(), &group_public_key.public_key) {
+        return Err(FrostError::CryptoError("Token is not signed by the FROST group".to_string()));
+    }
+
+    if !token.verify_caveats() {
+        return Err(FrostError::CryptoError("Caveat chain is broken".to_string()));
+    }
+
+    if token_signature.token_id != token.token_id {
+        return Err(FrostError::CryptoError("Operation was signed under a different token".to_string()));
+    }
+
+    if token_signature.timestamp < token.issued_at || token_signature.timestamp >= token.effective_expires_at() {
+        return Err(FrostError::CryptoError("Operation is outside the token validity window".to_string()));
+    }
+
+    let capabilities = token.effective_capabilities();
+    if !capabilities.permits(operation) {
+        return Err(FrostError::CryptoError("Token does not grant this operation".to_string()));
+    }
+
+    if let Some(policy) = &capabilities.policy {
+        let context = RequestContext {
+            now: token_signature.timestamp,
+            ..context.clone()
+        };
+        if !policy.evaluate(operation, &context).allowed {
+            return Err(FrostError::CryptoError("Token policy does not allow this operation".to_string()));
         }
+    }
+
+    let expected = operation_hash(&token.token_id, token_signature.use_count, token_signature.timestamp, operation);
+    if token_signature.operation_hash != expected {
+        return Err(FrostError::CryptoError("Operation hash mismatch".to_string()));
+    }
 
+    if !token_signature.signature.verify(SignatureScheme::Schnorr, &expected, &token.holder_public()) {
+        return Err(FrostError::CryptoError("Operation is not signed by the token key".to_string()));
+    }
+
+    Ok(())
+}
+
+impl Capabilities {
+    /// Whether the granted capabilities cover `operation`
+    ///
+    /// Only looks at signed limits, not at usage state such as the
+    /// remaining daily spend.
+    pub fn permits(&self, operation: &TokenRequest) -> bool {
         match operation {
             TokenRequest::DeviceUnlock => {
-                self.capabilities.device_unlock
+                self.device_unlock
             }
 
             TokenRequest::KeychainAccess { level } => {
-                match (self.capabilities.keychain_access, level) {
+                match (self.keychain_access, level) {
                     (KeychainAccessLevel::None, _) => false,
                     (KeychainAccessLevel::LowSecurity, KeychainAccessLevel::LowSecurity) => true,
                     (KeychainAccessLevel::MediumSecurity, KeychainAccessLevel::LowSecurity) => true,
@@ -198,107 +544,391 @@ impl SessionToken {
             }
 
             TokenRequest::Payment { amount } => {
-                if let Some(limits) = &self.capabilities.payment_limits {
-                    *amount <= limits.max_per_transaction
-                        && *amount <= limits.remaining_today
-                } else {
-                    false
-                }
+                self.payment_limits.as_ref()
+                    .is_some_and(|limits| *amount <= limits.max_per_transaction)
             }
 
             TokenRequest::CodeSigning => {
-                self.capabilities.code_signing
+                self.code_signing
             }
 
             TokenRequest::FileVaultDecrypt => {
-                self.capabilities.filevault_decrypt
+                self.filevault_decrypt
+            }
+
+            TokenRequest::SignMessage { .. } => {
+                self.sign_message
+            }
+
+            TokenRequest::Custom { name, .. } => {
+                !BUILTIN_OPERATIONS.contains(&name.as_str()) && self.custom.contains(name)
             }
         }
     }
 
-    /// Use token for an operation
-    pub fn use_for_operation(&mut self, operation: TokenRequest) -> FrostResult<TokenSignature> {
-        if !self.allows_operation(&operation) {
-            return Err(FrostError::CryptoError("Token does not allow operation".to_string()));
+    /// Intersection with `ceiling`
+    ///
+    /// Usage state (today's spend) carries over from `self`, so a lower
+    /// daily ceiling leaves correspondingly less remaining.
+    pub fn restrict(&self, ceiling: &Capabilities) -> Capabilities {
+        let keychain_access = if ceiling.keychain_access.to_byte() < self.keychain_access.to_byte() {
+            ceiling.keychain_access
+        } else {
+            self.keychain_access
+        };
+
+        let payment_limits = match (&self.payment_limits, &ceiling.payment_limits) {
+            (Some(limits), Some(cap)) => {
+                let spent = limits.max_per_day - limits.available_today();
+                let max_per_day = limits.max_per_day.min(cap.max_per_day);
+                Some(PaymentLimits {
+                    max_per_transaction: limits.max_per_transaction.min(cap.max_per_transaction),
+                    max_per_day,
+                    remaining_today: max_per_day.saturating_sub(spent),
+                    daily_reset_at: limits.daily_reset_at,
+                })
+            }
+            _ => None,
+        };
+
+        let policy = match (&self.policy, &ceiling.policy) {
+            (Some(a), Some(b)) => Some(Rule::All(vec![a.clone(), b.clone()])),
+            (a, b) => a.clone().or_else(|| b.clone()),
+        };
+
+        Capabilities {
+            device_unlock: self.device_unlock && ceiling.device_unlock,
+            keychain_access,
+            payment_limits,
+            code_signing: self.code_signing && ceiling.code_signing,
+            filevault_decrypt: self.filevault_decrypt && ceiling.filevault_decrypt,
+            sign_message: self.sign_message && ceiling.sign_message,
+            custom: self.custom.iter().filter(|f| ceiling.custom.contains(f)).cloned().collect(),
+            policy,
         }
+    }
 
-        // Update usage tracking
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// Canonical encoding, bound into the FROST-signed token body
+    ///
+    /// ```text
+    /// version (1) = 1 without a policy, 2 with one
+    /// flags (1)   = device_unlock | code_signing << 1 | filevault_decrypt << 2 | sign_message << 3
+    /// keychain (1)
+    /// payment (1) = 0, or 1 followed by PaymentLimits (24)
+    /// custom      = count (u32 LE), then per flag: len (u32 LE) || UTF-8
+    /// policy      = Rule::to_canonical_bytes (version 2 only)
+    /// ```
+    ///
+    /// Custom flags are a set: they are encoded sorted and deduplicated, so
+    /// every capability set has exactly one encoding.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + PaymentLimits::CANONICAL_LEN + 4);
+        out.push(match self.policy {
+            None => CAPABILITIES_ENCODING_VERSION,
+            Some(_) => CAPABILITIES_POLICY_ENCODING_VERSION,
+        });
+        out.push(
+            self.device_unlock as u8
+                | (self.code_signing as u8) << 1
+                | (self.filevault_decrypt as u8) << 2
+                | (self.sign_message as u8) << 3,
+        );
+        out.push(self.keychain_access.to_byte());
+        match &self.payment_limits {
+            None => out.push(0),
+            Some(limits) => {
+                out.push(1);
+                out.extend_from_slice(&limits.to_canonical_bytes());
+            }
+        }
 
-        self.usage.use_count += 1;
-        self.usage.last_used_at = Some(now);
+        let mut custom: Vec<&String> = self.custom.iter().collect();
+        custom.sort();
+        custom.dedup();
+        out.extend_from_slice(&(custom.len() as u32).to_le_bytes());
+        for flag in custom {
+            out.extend_from_slice(&(flag.len() as u32).to_le_bytes());
+            out.extend_from_slice(flag.as_bytes());
+        }
+        if let Some(policy) = &self.policy {
+            policy.encode(&mut out);
+        }
+        out
+    }
 
-        // Update payment limits if applicable
-        if let TokenRequest::Payment { amount } = operation {
-            if let Some(limits) = &mut self.capabilities.payment_limits {
-                limits.remaining_today = limits.remaining_today.saturating_sub(amount);
+    /// Decode a canonical encoding, rejecting anything non-canonical
+    pub fn from_canonical_bytes(bytes: &[u8]) -> FrostResult<Self> {
+        let mut reader = CanonicalReader { bytes };
+        let version = reader.byte()?;
+        if version != CAPABILITIES_ENCODING_VERSION && version != CAPABILITIES_POLICY_ENCODING_VERSION {
+            return Err(FrostError::SerializationError("Unknown capabilities encoding version".to_string()));
+        }
+
+        let flags = reader.byte()?;
+        if flags & !0b1111 != 0 {
+            return Err(FrostError::SerializationError("Unknown capability flags".to_string()));
+        }
+        let keychain_access = KeychainAccessLevel::from_byte(reader.byte()?)?;
+        let payment_limits = match reader.byte()? {
+            0 => None,
+            1 => Some(PaymentLimits::from_canonical_bytes(reader.take(PaymentLimits::CANONICAL_LEN)?)?),
+            _ => return Err(FrostError::SerializationError("Invalid payment limits marker".to_string())),
+        };
+
+        let count = reader.u32()? as usize;
+        let mut custom: Vec<String> = Vec::new();
+        for _ in 0..count {
+            let len = reader.u32()? as usize;
+            let flag = core::str::from_utf8(reader.take(len)?)
+                .map_err(|_| FrostError::SerializationError("Custom flag is not UTF-8".to_string()))?;
+            if custom.last().is_some_and(|last| last.as_str() >= flag) {
+                return Err(FrostError::SerializationError("Custom flags not sorted and unique".to_string()));
             }
+            custom.push(flag.to_string());
+        }
+        let policy = match version {
+            CAPABILITIES_POLICY_ENCODING_VERSION => Some(Rule::decode(&mut reader, 0)?),
+            _ => None,
+        };
+        if !reader.bytes.is_empty() {
+            return Err(FrostError::SerializationError("Trailing bytes after capabilities".to_string()));
         }
 
-        // Record operation
-        self.usage.operations.push(TokenOperation {
-            operation: format!("{:?}", operation),
-            timestamp: now,
-            success: true,
-        });
+        Ok(Capabilities {
+            device_unlock: flags & 1 != 0,
+            keychain_access,
+            payment_limits,
+            code_signing: flags & 2 != 0,
+            filevault_decrypt: flags & 4 != 0,
+            sign_message: flags & 8 != 0,
+            custom,
+            policy,
+        })
+    }
+}
 
-        // Sign with ephemeral key (in real impl)
-        Ok(TokenSignature {
-            token_id: self.token_id,
-            operation_hash: [0u8; 32],  // Hash of operation
-            signature: [0u8; 64],        // Signature with ephemeral key
+/// Version byte of `Capabilities::to_canonical_bytes`
+const CAPABILITIES_ENCODING_VERSION: u8 = 1;
+
+/// Version byte of `Capabilities::to_canonical_bytes` when a policy is attached
+const CAPABILITIES_POLICY_ENCODING_VERSION: u8 = 2;
+
+/// Names of the built-in operations, which custom operations cannot take
+const BUILTIN_OPERATIONS: [&str; 6] = [
+    "device-unlock", "keychain-access", "payment", "code-signing", "filevault-decrypt", "sign-message",
+];
+
+impl KeychainAccessLevel {
+    /// Canonical byte (stable regardless of declaration order)
+    pub fn to_byte(self) -> u8 {
+        match self {
+            KeychainAccessLevel::None => 0,
+            KeychainAccessLevel::LowSecurity => 1,
+            KeychainAccessLevel::MediumSecurity => 2,
+            KeychainAccessLevel::HighSecurity => 3,
+        }
+    }
+
+    /// Decode a canonical byte
+    pub fn from_byte(byte: u8) -> FrostResult<Self> {
+        match byte {
+            0 => Ok(KeychainAccessLevel::None),
+            1 => Ok(KeychainAccessLevel::LowSecurity),
+            2 => Ok(KeychainAccessLevel::MediumSecurity),
+            3 => Ok(KeychainAccessLevel::HighSecurity),
+            _ => Err(FrostError::SerializationError(format!("Unknown keychain access level {}", byte))),
+        }
+    }
+}
+
+impl PaymentLimits {
+    /// Length of the canonical encoding
+    pub const CANONICAL_LEN: usize = 24;
+
+    /// Canonical encoding of the signed limits
+    ///
+    /// `max_per_transaction || max_per_day || daily_reset_at`, u64 LE each.
+    /// `remaining_today` is usage state, not a grant, and is not signed;
+    /// it is never trusted above `max_per_day`.
+    pub fn to_canonical_bytes(&self) -> [u8; Self::CANONICAL_LEN] {
+        let mut out = [0u8; Self::CANONICAL_LEN];
+        out[..8].copy_from_slice(&self.max_per_transaction.to_le_bytes());
+        out[8..16].copy_from_slice(&self.max_per_day.to_le_bytes());
+        out[16..].copy_from_slice(&self.daily_reset_at.to_le_bytes());
+        out
+    }
+
+    /// Decode the canonical encoding; the day starts with the full allowance
+    pub fn from_canonical_bytes(bytes: &[u8]) -> FrostResult<Self> {
+        if bytes.len() != Self::CANONICAL_LEN {
+            return Err(FrostError::SerializationError("Invalid payment limits length".to_string()));
+        }
+        let word = |i: usize| u64::from_le_bytes(bytes[i * 8..(i + 1) * 8].try_into().expect("8-byte slice"));
+        Ok(PaymentLimits {
+            max_per_transaction: word(0),
+            max_per_day: word(1),
+            remaining_today: word(1),
+            daily_reset_at: word(2),
         })
     }
 
-    /// Verify FROST signature on token
-    pub fn verify_frost_signature(&self, public_key: &GroupPublicKey) -> bool {
-        // Serialize token data
-        let token_data = self.to_signing_data();
+    /// Spend left today, capped by the signed daily limit
+    pub fn available_today(&self) -> u64 {
+        self.remaining_today.min(self.max_per_day)
+    }
+}
 
-        // Verify FROST signature
-        public_key.verify_signature(&token_data, &self.frost_signature)
+/// Cursor over a canonical encoding
+pub(crate) struct CanonicalReader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> CanonicalReader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> FrostResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(FrostError::SerializationError("Truncated capabilities encoding".to_string()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
     }
 
-    /// Get token data for signing
-    fn to_signing_data(&self) -> Vec<u8> {
-        use sha2::{Sha256, Digest};
+    pub(crate) fn byte(&mut self) -> FrostResult<u8> {
+        Ok(self.take(1)?[0])
+    }
 
-        let mut hasher = Sha256::new();
-        hasher.update(b"FROST-SESSION-TOKEN-v1");
-        hasher.update(&self.token_id);
-        hasher.update(&self.issued_at.to_le_bytes());
-        hasher.update(&self.expires_at.to_le_bytes());
-        hasher.update(&self.device_id);
+    pub(crate) fn u32(&mut self) -> FrostResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4-byte slice")))
+    }
 
-        hasher.finalize().to_vec()
+    pub(crate) fn u64(&mut self) -> FrostResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8-byte slice")))
     }
 }
 
 /// Request to use a token
 #[derive(Debug, Clone)]
 pub enum TokenRequest {
+    /// Unlock the device
     DeviceUnlock,
-    KeychainAccess { level: KeychainAccessLevel },
-    Payment { amount: u64 },
+    /// Read keychain items up to a security level
+    KeychainAccess {
+        /// Requested level
+        level: KeychainAccessLevel,
+    },
+    /// Authorize a payment
+    Payment {
+        /// Amount (cents)
+        amount: u64,
+    },
+    /// Sign code
     CodeSigning,
+    /// Decrypt the FileVault volume
     FileVaultDecrypt,
+    /// Sign a message as the device, granted with `sign_message`
+    SignMessage {
+        /// SHA-256 of the message
+        message_hash: [u8; 32],
+    },
+    /// Application-defined operation
+    Custom {
+        /// Operation name, granted through `Capabilities::custom`
+        name: String,
+        /// Attributes policies can inspect
+        attributes: BTreeMap<String, AttributeValue>,
+    },
+}
+
+impl TokenRequest {
+    /// Request to sign `message`
+    pub fn sign_message(message: &[u8]) -> Self {
+        TokenRequest::SignMessage { message_hash: Sha256::digest(message).into() }
+    }
+
+    /// Unambiguous encoding, hashed into the operation signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            TokenRequest::DeviceUnlock => vec![0],
+            TokenRequest::KeychainAccess { level } => vec![1, *level as u8],
+            TokenRequest::Payment { amount } => {
+                let mut out = vec![2];
+                out.extend_from_slice(&amount.to_le_bytes());
+                out
+            }
+            TokenRequest::CodeSigning => vec![3],
+            TokenRequest::FileVaultDecrypt => vec![4],
+            TokenRequest::SignMessage { message_hash } => {
+                let mut out = vec![6];
+                out.extend_from_slice(message_hash);
+                out
+            }
+            TokenRequest::Custom { name, attributes } => {
+                let mut out = vec![5];
+                encode_str(name, &mut out);
+                out.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+                for (key, value) in attributes {
+                    encode_str(key, &mut out);
+                    value.encode(&mut out);
+                }
+                out
+            }
+        }
+    }
+
+    /// Operation name, as matched by `Rule::Operation`
+    pub fn name(&self) -> &str {
+        match self {
+            TokenRequest::DeviceUnlock => "device-unlock",
+            TokenRequest::KeychainAccess { .. } => "keychain-access",
+            TokenRequest::Payment { .. } => "payment",
+            TokenRequest::CodeSigning => "code-signing",
+            TokenRequest::FileVaultDecrypt => "filevault-decrypt",
+            TokenRequest::SignMessage { .. } => "sign-message",
+            TokenRequest::Custom { name, .. } => name,
+        }
+    }
+
+    /// Attribute of the operation, as inspected by policy rules
+    ///
+    /// Payments expose `amount`, keychain access exposes `level`.
+    pub fn attribute(&self, key: &str) -> Option<AttributeValue> {
+        match (self, key) {
+            (TokenRequest::Payment { amount }, "amount") => Some(AttributeValue::Integer(*amount)),
+            (TokenRequest::KeychainAccess { level }, "level") => Some(AttributeValue::Integer(level.to_byte() as u64)),
+            (TokenRequest::Custom { attributes, .. }, key) => attributes.get(key).cloned(),
+            _ => None,
+        }
+    }
 }
 
 /// Token signature result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenSignature {
+    /// Token the operation was performed under
     pub token_id: [u8; 16],
+    /// Token use counter at signing time
+    pub use_count: u64,
+    /// Signing time (Unix epoch seconds)
+    pub timestamp: u64,
+    /// Hash of the operation
     pub operation_hash: [u8; 32],
-    pub signature: [u8; 64],
+    /// Ephemeral-key signature over `operation_hash`
+    pub signature: SchnorrSignature,
 }
 
 /// Session token cache
 pub struct SessionTokenCache {
-    tokens: Vec<SessionToken>,
+    pub(crate) tokens: Vec<SessionToken>,
     max_tokens: usize,
+
+    /// Newest revocation list accepted
+    pub(crate) revocations: Option<RevocationList>,
+
+    /// Maximum age of revocation knowledge before tokens are refused
+    staleness_bound: Option<Duration>,
+
+    /// Generation of the last image saved or loaded (see `token_store`)
+    pub(crate) generation: u64,
 }
 
 impl SessionTokenCache {
@@ -307,11 +937,78 @@ impl SessionTokenCache {
         SessionTokenCache {
             tokens: Vec::new(),
             max_tokens,
+            revocations: None,
+            staleness_bound: None,
+            generation: 0,
+        }
+    }
+
+    /// Refuse tokens once revocation knowledge is older than `bound`
+    ///
+    /// A token counts as checked at the later of its own issue time and the
+    /// issue time of the revocation list held. Only the quorum's signature
+    /// vouches for freshness: fetching a list does not, since anyone can
+    /// replay one.
+    pub fn set_staleness_bound(&mut self, bound: Option<Duration>) {
+        self.staleness_bound = bound;
+    }
+
+    /// Accept a newer quorum-signed revocation list and purge revoked tokens
+    ///
+    /// Returns the number of tokens removed.
+    pub fn apply_revocation_list(
+        &mut self,
+        list: RevocationList,
+        group_public_key: &GroupPublicKey,
+    ) -> FrostResult<usize> {
+        list.check_supersedes(self.revocations.as_ref(), group_public_key)?;
+
+        let before = self.tokens.len();
+        self.tokens.retain(|t| !list.is_revoked(&t.token_id));
+        self.revocations = Some(list);
+        Ok(before - self.tokens.len())
+    }
+
+    /// Accept a re-issue of the held list: same sequence and contents,
+    /// signed again by the quorum at a later time
+    ///
+    /// Nothing was revoked since, so revocation knowledge is current as of
+    /// the re-issue. A copy of the held list proves nothing and is refused.
+    pub fn confirm_revocation_list(
+        &mut self,
+        list: RevocationList,
+        group_public_key: &GroupPublicKey,
+    ) -> FrostResult<()> {
+        if !list.verify(group_public_key) {
+            return Err(FrostError::CryptoError("Revocation list is not signed by the FROST group".to_string()));
         }
+        let held = self.revocations.as_ref()
+            .filter(|held| held.sequence == list.sequence && held.revoked == list.revoked)
+            .ok_or_else(|| FrostError::RollbackDetected(format!(
+                "Revocation list {} is not the list held",
+                list.sequence,
+            )))?;
+        if list.issued_at <= held.issued_at {
+            return Err(FrostError::RollbackDetected(format!(
+                "Revocation list {} is not newer than the list held",
+                list.sequence,
+            )));
+        }
+        self.revocations = Some(list);
+        Ok(())
+    }
+
+    /// Sequence number of the newest revocation list accepted
+    pub fn revocation_sequence(&self) -> Option<u64> {
+        self.revocations.as_ref().map(|l| l.sequence)
     }
 
     /// Add a token to cache
     pub fn add_token(&mut self, token: SessionToken) -> FrostResult<()> {
+        if self.revocations.as_ref().is_some_and(|l| l.is_revoked(&token.token_id)) {
+            return Err(FrostError::CryptoError("Token has been revoked".to_string()));
+        }
+
         // Remove expired tokens
         self.cleanup_expired();
 
@@ -327,15 +1024,50 @@ impl SessionTokenCache {
         Ok(())
     }
 
-    /// Get a valid token for an operation
-    pub fn get_valid_token(&mut self, operation: &TokenRequest) -> Option<&mut SessionToken> {
+    /// Get a valid token for an operation in `context`
+    pub fn get_valid_token(&mut self, operation: &TokenRequest, context: &RequestContext) -> Option<&mut SessionToken> {
         self.cleanup_expired();
 
+        let revocations = self.revocations.as_ref();
+        let staleness_bound = self.staleness_bound;
         self.tokens.iter_mut()
-            .filter(|t| t.is_valid() && t.allows_operation(operation))
+            .filter(|t| Self::usable(t, operation, context, revocations, staleness_bound))
             .max_by_key(|t| t.expires_at)  // Get longest-lived token
     }
 
+    /// Whether a valid token for an operation in `context` is cached
+    pub fn has_valid_token(&self, operation: &TokenRequest, context: &RequestContext) -> bool {
+        self.tokens.iter()
+            .any(|t| {
+                Self::usable(t, operation, context, self.revocations.as_ref(), self.staleness_bound)
+            })
+    }
+
+    /// Valid, allowed, not revoked, and checked for revocation recently enough
+    fn usable(
+        token: &SessionToken,
+        operation: &TokenRequest,
+        context: &RequestContext,
+        revocations: Option<&RevocationList>,
+        staleness_bound: Option<Duration>,
+    ) -> bool {
+        if !token.allows_operation(operation, context) {
+            return false;
+        }
+        if revocations.is_some_and(|l| l.is_revoked(&token.token_id)) {
+            return false;
+        }
+
+        match staleness_bound {
+            None => true,
+            Some(bound) => {
+                let checked_at = revocations
+                    .map_or(token.issued_at, |l| l.issued_at.max(token.issued_at));
+                context.now <= checked_at.saturating_add(bound.as_secs())
+            }
+        }
+    }
+
     /// Remove expired tokens
     fn cleanup_expired(&mut self) {
         self.tokens.retain(|t| t.is_valid());
@@ -385,7 +1117,9 @@ impl Default for Capabilities {
             }),
             code_signing: false,
             filevault_decrypt: true,
+            sign_message: false,
             custom: Vec::new(),
+            policy: None,
         }
     }
 }
@@ -393,8 +1127,19 @@ impl Default for Capabilities {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ledger::{MemoryLedgerStore, UsageLedger};
+    use crate::testing::{issue, now_context};
     use std::time::Duration;
 
+    fn ledger() -> UsageLedger<MemoryLedgerStore> {
+        UsageLedger::open(MemoryLedgerStore::default()).unwrap()
+    }
+
+    /// Pay through `ledger`, the only route for payments
+    fn pay(ledger: &mut UsageLedger<MemoryLedgerStore>, token: &mut SessionToken, amount: u64) -> FrostResult<TokenSignature> {
+        ledger.record_use(token, TokenRequest::Payment { amount }, &now_context())
+    }
+
     #[test]
     fn test_session_token_creation() {
         let device_id = [1u8; 32];
@@ -412,10 +1157,10 @@ mod tests {
         let caps = Capabilities::default();
         let token = SessionToken::new(device_id, caps, Duration::from_secs(3600));
 
-        assert!(token.allows_operation(&TokenRequest::DeviceUnlock));
-        assert!(token.allows_operation(&TokenRequest::Payment { amount: 5000 }));
-        assert!(!token.allows_operation(&TokenRequest::Payment { amount: 100_000 }));
-        assert!(!token.allows_operation(&TokenRequest::CodeSigning));
+        assert!(token.allows_operation(&TokenRequest::DeviceUnlock, &now_context()));
+        assert!(token.allows_operation(&TokenRequest::Payment { amount: 5000 }, &now_context()));
+        assert!(!token.allows_operation(&TokenRequest::Payment { amount: 100_000 }, &now_context()));
+        assert!(!token.allows_operation(&TokenRequest::CodeSigning, &now_context()));
     }
 
     #[test]
@@ -432,7 +1177,7 @@ mod tests {
 
         assert_eq!(cache.token_count(), 5);
 
-        let token = cache.get_valid_token(&TokenRequest::DeviceUnlock);
+        let token = cache.get_valid_token(&TokenRequest::DeviceUnlock, &now_context());
         assert!(token.is_some());
     }
 
@@ -442,13 +1187,491 @@ mod tests {
         let caps = Capabilities::default();
         let mut token = SessionToken::new(device_id, caps, Duration::from_secs(3600));
 
+        // Payments bypassing the ledger are refused
+        assert!(token.use_for_operation(TokenRequest::Payment { amount: 5000 }, &now_context()).is_err());
+
         // First payment should succeed
-        assert!(token.allows_operation(&TokenRequest::Payment { amount: 5000 }));
-        let _ = token.use_for_operation(TokenRequest::Payment { amount: 5000 });
+        assert!(token.allows_operation(&TokenRequest::Payment { amount: 5000 }, &now_context()));
+        pay(&mut ledger(), &mut token, 5000).unwrap();
 
         // Check remaining balance decreased
         if let Some(limits) = &token.capabilities.payment_limits {
             assert_eq!(limits.remaining_today, 45_000);
         }
     }
+
+    #[test]
+    fn test_delegation_chain_verifies() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+        assert!(token.verify_frost_signature(&group_pk));
+
+        let unlock = TokenRequest::DeviceUnlock;
+        let unlock_sig = token.use_for_operation(unlock.clone(), &now_context()).unwrap();
+        let payment = TokenRequest::Payment { amount: 5000 };
+        let payment_sig = pay(&mut ledger(), &mut token, 5000).unwrap();
+
+        // Spending changes usage state but not the signed body
+        assert!(verify_token_operation(&group_pk, &token, &unlock, &unlock_sig, &now_context()).is_ok());
+        assert!(verify_token_operation(&group_pk, &token, &payment, &payment_sig, &now_context()).is_ok());
+        assert_ne!(unlock_sig.operation_hash, payment_sig.operation_hash);
+    }
+
+    #[test]
+    fn test_delegation_chain_rejects_tampering() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+        let payment = TokenRequest::Payment { amount: 5000 };
+        let sig = pay(&mut ledger(), &mut token, 5000).unwrap();
+
+        // Operation signed, but a different operation presented
+        let other = TokenRequest::Payment { amount: 9000 };
+        assert!(verify_token_operation(&group_pk, &token, &other, &sig, &now_context()).is_err());
+
+        // Capabilities edited after issuance
+        let mut escalated = token.clone();
+        escalated.capabilities.code_signing = true;
+        assert!(!escalated.verify_frost_signature(&group_pk));
+        assert!(verify_token_operation(&group_pk, &escalated, &payment, &sig, &now_context()).is_err());
+
+        // Ephemeral key swapped for one the attacker holds
+        let mut attacker = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let forged = pay(&mut ledger(), &mut attacker, 5000).unwrap();
+        let mut swapped = token.clone();
+        swapped.ephemeral_public = attacker.ephemeral_public;
+        let forged = TokenSignature { token_id: token.token_id, ..forged };
+        assert!(verify_token_operation(&group_pk, &swapped, &payment, &forged, &now_context()).is_err());
+        assert!(verify_token_operation(&group_pk, &token, &payment, &forged, &now_context()).is_err());
+
+        // Token never signed by the group
+        assert!(verify_token_operation(&group_pk, &attacker, &payment, &forged, &now_context()).is_err());
+
+        // Signed by a different group
+        let other_group = issue(&mut token.clone(), &Scalar::random(&mut rand::thread_rng()));
+        assert!(verify_token_operation(&other_group, &token, &payment, &sig, &now_context()).is_err());
+    }
+
+    #[test]
+    fn test_sign_message_binds_message() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let caps = Capabilities { sign_message: true, ..Capabilities::default() };
+        let mut token = SessionToken::new([1u8; 32], caps, Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+
+        let request = TokenRequest::sign_message(b"challenge");
+        let sig = token.use_for_operation(request.clone(), &now_context()).unwrap();
+        assert!(verify_token_operation(&group_pk, &token, &request, &sig, &now_context()).is_ok());
+        assert!(verify_token_operation(&group_pk, &token, &TokenRequest::sign_message(b"other"), &sig, &now_context()).is_err());
+        assert!(verify_token_operation(&group_pk, &token, &TokenRequest::DeviceUnlock, &sig, &now_context()).is_err());
+
+        // Granted on its own, not with device unlock
+        assert!(!Capabilities::default().permits(&request));
+        let signer = Capabilities { device_unlock: false, sign_message: true, ..Capabilities::default() };
+        assert!(signer.permits(&request));
+        assert!(!signer.permits(&TokenRequest::DeviceUnlock));
+    }
+
+    #[test]
+    fn test_deserialized_token_cannot_sign() {
+        let token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let json = serde_json::to_string(&token).unwrap();
+        let mut restored: SessionToken = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.ephemeral_public, token.ephemeral_public);
+        assert!(restored.use_for_operation(TokenRequest::DeviceUnlock, &now_context()).is_err());
+        assert_eq!(restored.usage.use_count, 0);
+    }
+
+    /// Named edit to a token's signed body
+    type Mutation = (&'static str, fn(&mut SessionToken));
+
+    #[test]
+    fn test_any_signed_field_change_breaks_verification() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let caps = Capabilities { custom: vec!["ssh".to_string(), "git".to_string()], ..Capabilities::default() };
+        let mut token = SessionToken::new([1u8; 32], caps, Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+        assert!(token.verify_frost_signature(&group_pk));
+
+        let mutations: Vec<Mutation> = vec![
+            ("token_id", |t| t.token_id[0] ^= 1),
+            ("issued_at", |t| t.issued_at -= 1),
+            ("expires_at", |t| t.expires_at += 3600),
+            ("device_id", |t| t.device_id[31] ^= 1),
+            ("ephemeral_public", |t| t.ephemeral_public = CompressedRistretto::default()),
+            ("device_unlock", |t| t.capabilities.device_unlock = false),
+            ("keychain_access", |t| t.capabilities.keychain_access = KeychainAccessLevel::HighSecurity),
+            ("payment_limits", |t| t.capabilities.payment_limits = None),
+            ("max_per_transaction", |t| t.capabilities.payment_limits.as_mut().unwrap().max_per_transaction += 1),
+            ("max_per_day", |t| t.capabilities.payment_limits.as_mut().unwrap().max_per_day += 1),
+            ("daily_reset_at", |t| t.capabilities.payment_limits.as_mut().unwrap().daily_reset_at += 1),
+            ("code_signing", |t| t.capabilities.code_signing = true),
+            ("filevault_decrypt", |t| t.capabilities.filevault_decrypt = false),
+            ("custom added", |t| t.capabilities.custom.push("admin".to_string())),
+            ("custom removed", |t| { t.capabilities.custom.pop(); }),
+            ("custom renamed", |t| t.capabilities.custom[0] = "ssh2".to_string()),
+        ];
+
+        for (field, mutate) in mutations {
+            let mut tampered = token.clone();
+            mutate(&mut tampered);
+            assert!(!tampered.verify_frost_signature(&group_pk), "{} is not bound", field);
+        }
+
+        // Usage state and custom-flag order are not part of the grant
+        let mut reordered = token.clone();
+        reordered.capabilities.custom.reverse();
+        assert!(reordered.verify_frost_signature(&group_pk));
+        let mut spent = token.clone();
+        pay(&mut ledger(), &mut spent, 5000).unwrap();
+        assert!(spent.verify_frost_signature(&group_pk));
+    }
+
+    #[test]
+    fn test_remaining_today_capped_by_signed_limit() {
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let limits = token.capabilities.payment_limits.as_mut().unwrap();
+        limits.remaining_today = u64::MAX;
+
+        // 6 x $100 exceeds the signed $500 daily limit despite the edited balance
+        let mut ledger = ledger();
+        for _ in 0..5 {
+            pay(&mut ledger, &mut token, 10_000).unwrap();
+        }
+        assert!(!token.allows_operation(&TokenRequest::Payment { amount: 10_000 }, &now_context()));
+        assert!(pay(&mut ledger, &mut token, 10_000).is_err());
+    }
+
+    #[test]
+    fn test_capabilities_canonical_encoding() {
+        let caps = Capabilities {
+            custom: vec!["ssh".to_string(), "git".to_string(), "ssh".to_string()],
+            ..Capabilities::default()
+        };
+        let bytes = caps.to_canonical_bytes();
+
+        assert_eq!(
+            hex::encode(&bytes),
+            concat!(
+                "01", "05", "01",
+                "01", "1027000000000000", "50c3000000000000", "0000000000000000",
+                "02000000", "03000000", "676974", "03000000", "737368",
+            ),
+        );
+
+        let decoded = Capabilities::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded.custom, vec!["git".to_string(), "ssh".to_string()]);
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+
+        // Non-canonical forms are rejected
+        let mut unknown_flag = bytes.clone();
+        unknown_flag[1] |= 0x80;
+        assert!(Capabilities::from_canonical_bytes(&unknown_flag).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Capabilities::from_canonical_bytes(&trailing).is_err());
+        let mut unsorted = bytes.clone();
+        let len = unsorted.len();
+        unsorted[len - 3..].copy_from_slice(b"abc");
+        assert!(Capabilities::from_canonical_bytes(&unsorted).is_err());
+        assert!(Capabilities::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_policy_bound_and_enforced() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let caps = Capabilities {
+            custom: vec!["open-door".to_string(), "payment".to_string()],
+            policy: Some(Rule::Any(vec![
+                Rule::Not(Box::new(Rule::Operation("open-door".to_string()))),
+                Rule::Network("corp-wifi".to_string()),
+            ])),
+            ..Capabilities::default()
+        };
+        let mut token = SessionToken::new([1u8; 32], caps, Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+
+        let encoded = token.capabilities.to_canonical_bytes();
+        assert_eq!(encoded[0], 2);
+        assert_eq!(Capabilities::from_canonical_bytes(&encoded).unwrap().policy, token.capabilities.policy);
+
+        let open_door = TokenRequest::Custom { name: "open-door".to_string(), attributes: BTreeMap::new() };
+        let mut context = RequestContext { now: token.issued_at, ..RequestContext::default() };
+        let decision = token.authorize(&open_door, &context);
+        assert!(!decision.allowed);
+        assert!(decision.trace.contains(&"  network is \"corp-wifi\" (no network): unknown".to_string()));
+
+        context.network = Some("corp-wifi".to_string());
+        assert!(token.authorize(&open_door, &context).allowed);
+        assert!(token.authorize(&TokenRequest::DeviceUnlock, &context).allowed);
+
+        // Unlisted custom operations, and custom names shadowing built-ins, are refused
+        let unlisted = TokenRequest::Custom { name: "open-vault".to_string(), attributes: BTreeMap::new() };
+        assert!(!token.authorize(&unlisted, &context).allowed);
+        let spoofed = TokenRequest::Custom { name: "payment".to_string(), attributes: BTreeMap::new() };
+        assert!(!token.authorize(&spoofed, &context).allowed);
+
+        // The policy is signed: dropping it breaks the FROST signature
+        let mut stripped = token.clone();
+        stripped.capabilities.policy = None;
+        assert!(!stripped.verify_frost_signature(&group_pk));
+
+        // Caveat policies add to the signed one
+        let narrower = Capabilities {
+            custom: vec!["open-door".to_string()],
+            policy: Some(Rule::UseCountBelow(1)),
+            ..Capabilities::default()
+        };
+        let restricted = token.attenuate(narrower, None).unwrap();
+        context.use_count = Some(1);
+        assert!(token.authorize(&open_door, &context).allowed);
+        assert!(!restricted.authorize(&open_door, &context).allowed);
+    }
+
+    #[test]
+    fn test_policy_checked_on_use_and_verification() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let caps = Capabilities {
+            custom: vec!["open-door".to_string()],
+            policy: Some(Rule::All(vec![
+                Rule::Network("corp-wifi".to_string()),
+                Rule::UseCountBelow(1),
+            ])),
+            ..Capabilities::default()
+        };
+        let mut token = SessionToken::new([1u8; 32], caps, Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+
+        let open_door = TokenRequest::Custom { name: "open-door".to_string(), attributes: BTreeMap::new() };
+        let offsite = now_context();
+        let onsite = RequestContext { network: Some("corp-wifi".to_string()), ..now_context() };
+
+        // The ledger supplies the use count, whatever the caller says
+        let mut ledger = ledger();
+        assert!(ledger.record_use(&mut token, open_door.clone(), &offsite).is_err());
+        let sig = ledger.record_use(&mut token, open_door.clone(), &onsite).unwrap();
+        let unused = RequestContext { use_count: Some(0), ..onsite.clone() };
+        assert!(ledger.record_use(&mut token, open_door.clone(), &unused).is_err());
+        assert!(token.use_for_operation(open_door.clone(), &onsite).is_err());
+
+        // Verifiers evaluate the signed policy too, with their own use count
+        assert!(verify_token_operation(&group_pk, &token, &open_door, &sig, &unused).is_ok());
+        let offsite = RequestContext { use_count: Some(0), ..offsite };
+        assert!(verify_token_operation(&group_pk, &token, &open_door, &sig, &offsite).is_err());
+    }
+
+    fn unlock_and_small_payments() -> Capabilities {
+        Capabilities {
+            device_unlock: true,
+            keychain_access: KeychainAccessLevel::None,
+            payment_limits: Some(PaymentLimits {
+                max_per_transaction: 2_000,
+                max_per_day: 2_000,
+                remaining_today: 2_000,
+                daily_reset_at: 0,
+            }),
+            code_signing: true,  // Not granted upstream; must not widen
+            filevault_decrypt: false,
+            sign_message: false,
+            custom: vec![],
+            policy: None,
+        }
+    }
+
+    #[test]
+    fn test_caveats_only_narrow() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+
+        let mut restricted = token.attenuate(unlock_and_small_payments(), None).unwrap();
+        assert!(restricted.verify_caveats());
+        assert_ne!(restricted.holder_public(), token.holder_public());
+
+        assert!(restricted.allows_operation(&TokenRequest::DeviceUnlock, &now_context()));
+        assert!(restricted.allows_operation(&TokenRequest::Payment { amount: 1_500 }, &now_context()));
+        assert!(!restricted.allows_operation(&TokenRequest::Payment { amount: 2_500 }, &now_context()));
+        assert!(!restricted.allows_operation(&TokenRequest::KeychainAccess { level: KeychainAccessLevel::LowSecurity }, &now_context()));
+        assert!(!restricted.allows_operation(&TokenRequest::CodeSigning, &now_context()));
+        assert!(token.allows_operation(&TokenRequest::KeychainAccess { level: KeychainAccessLevel::LowSecurity }, &now_context()));
+
+        // The restricted holder signs with its own key; the daily ceiling applies
+        let payment = TokenRequest::Payment { amount: 1_500 };
+        let sig = pay(&mut ledger(), &mut restricted, 1_500).unwrap();
+        assert!(verify_token_operation(&group_pk, &restricted, &payment, &sig, &now_context()).is_ok());
+        assert!(verify_token_operation(&group_pk, &token, &payment, &sig, &now_context()).is_err());
+        assert!(!restricted.allows_operation(&TokenRequest::Payment { amount: 1_000 }, &now_context()));
+
+        // A further caveat cannot restore what an earlier one removed
+        let rewidened = restricted.attenuate(Capabilities::default(), None).unwrap();
+        assert!(!rewidened.allows_operation(&TokenRequest::KeychainAccess { level: KeychainAccessLevel::LowSecurity }, &now_context()));
+        assert!(!rewidened.allows_operation(&TokenRequest::Payment { amount: 1_000 }, &now_context()));
+
+        // Expiry can be shortened but never extended
+        let short = token.attenuate(Capabilities::default(), Some(token.issued_at + 60)).unwrap();
+        assert_eq!(short.effective_expires_at(), token.issued_at + 60);
+        let long = token.attenuate(Capabilities::default(), Some(token.expires_at + 3600)).unwrap();
+        assert_eq!(long.effective_expires_at(), token.expires_at);
+    }
+
+    #[test]
+    fn test_caveat_chain_rejects_tampering() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let group_pk = issue(&mut token, &group_secret);
+        let mut restricted = token.attenuate(unlock_and_small_payments(), None).unwrap();
+        let unlock = TokenRequest::DeviceUnlock;
+        let sig = restricted.use_for_operation(unlock.clone(), &now_context()).unwrap();
+        assert!(verify_token_operation(&group_pk, &restricted, &unlock, &sig, &now_context()).is_ok());
+
+        // Widening a caveat after the fact breaks its signature
+        let mut widened = restricted.clone();
+        widened.caveats[0].capabilities = Capabilities::default();
+        assert!(!widened.verify_caveats());
+        assert!(verify_token_operation(&group_pk, &widened, &unlock, &sig, &now_context()).is_err());
+
+        // Stripping the caveat leaves an operation signed by the wrong key
+        let mut stripped = restricted.clone();
+        stripped.caveats.clear();
+        assert!(verify_token_operation(&group_pk, &stripped, &unlock, &sig, &now_context()).is_err());
+
+        // A caveat chain cannot be moved onto another token
+        let mut other = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        issue(&mut other, &group_secret);
+        other.caveats = restricted.caveats.clone();
+        assert!(!other.verify_caveats());
+    }
+
+    #[test]
+    fn test_revocation_list_purges_cache() {
+        use crate::testing::{group, signed_list};
+        use crate::revocation::{BloomFilter, RevokedTokens};
+
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let group_pk = group(&group_secret);
+        let mut cache = SessionTokenCache::new(10);
+        let tokens: Vec<_> = (0..3)
+            .map(|_| SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600)))
+            .collect();
+        for token in &tokens {
+            cache.add_token(token.clone()).unwrap();
+        }
+
+        let now = tokens[0].issued_at;
+        let list = signed_list(&group_secret, 1, now, RevokedTokens::from_ids(vec![tokens[0].token_id]));
+        assert_eq!(cache.apply_revocation_list(list.clone(), &group_pk).unwrap(), 1);
+        assert_eq!(cache.token_count(), 2);
+        assert!(cache.add_token(tokens[0].clone()).is_err());
+
+        // Replaying a list, or one signed by someone else, is refused
+        assert!(cache.apply_revocation_list(list, &group_pk).is_err());
+        let forged = signed_list(&Scalar::random(&mut rand::thread_rng()), 5, now, RevokedTokens::from_ids(vec![]));
+        assert!(cache.apply_revocation_list(forged, &group_pk).is_err());
+
+        // A Bloom filter list works the same way
+        let mut filter = BloomFilter::new(64, 4);
+        filter.insert(&tokens[1].token_id);
+        let list = signed_list(&group_secret, 2, now, RevokedTokens::Bloom(filter));
+        assert_eq!(cache.apply_revocation_list(list, &group_pk).unwrap(), 1);
+        assert_eq!(cache.revocation_sequence(), Some(2));
+
+        let remaining = cache.get_valid_token(&TokenRequest::DeviceUnlock, &now_context()).unwrap();
+        assert_eq!(remaining.token_id, tokens[2].token_id);
+    }
+
+    #[test]
+    fn test_stale_tokens_refused_until_revocation_refresh() {
+        use crate::testing::{group, signed_list};
+        use crate::revocation::RevokedTokens;
+
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let mut cache = SessionTokenCache::new(10);
+        cache.set_staleness_bound(Some(Duration::from_secs(3600)));
+
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(4 * 3600));
+        let now = token.issued_at;
+        token.issued_at -= 2 * 3600;
+        cache.add_token(token).unwrap();
+        assert!(!cache.has_valid_token(&TokenRequest::DeviceUnlock, &now_context()));
+
+        // A fresh revocation list that does not name the token vouches for it
+        let list = signed_list(&group_secret, 1, now, RevokedTokens::from_ids(vec![]));
+        cache.apply_revocation_list(list, &group(&group_secret)).unwrap();
+        assert!(cache.has_valid_token(&TokenRequest::DeviceUnlock, &now_context()));
+        assert!(cache.get_valid_token(&TokenRequest::DeviceUnlock, &now_context()).is_some());
+    }
+
+    #[test]
+    fn test_reissued_revocation_list_counts_as_checked() {
+        use crate::testing::{group, signed_list};
+        use crate::revocation::RevokedTokens;
+
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let group_pk = group(&group_secret);
+        let mut cache = SessionTokenCache::new(10);
+        cache.set_staleness_bound(Some(Duration::from_secs(3600)));
+
+        // Hours pass after the list was issued
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(4 * 3600));
+        let now = token.issued_at;
+        token.issued_at -= 2 * 3600;
+        let list = signed_list(&group_secret, 1, token.issued_at, RevokedTokens::from_ids(vec![]));
+        cache.add_token(token).unwrap();
+        cache.apply_revocation_list(list.clone(), &group_pk).unwrap();
+        assert!(!cache.has_valid_token(&TokenRequest::DeviceUnlock, &now_context()));
+
+        // Replaying the held list, or a list that differs, vouches for nothing
+        assert!(cache.confirm_revocation_list(list.clone(), &group_pk).is_err());
+        let older = signed_list(&group_secret, 0, now, RevokedTokens::from_ids(vec![]));
+        assert!(cache.confirm_revocation_list(older, &group_pk).is_err());
+        let changed = signed_list(&group_secret, 1, now, RevokedTokens::from_ids(vec![[9u8; 16]]));
+        assert!(cache.confirm_revocation_list(changed, &group_pk).is_err());
+        let forged = signed_list(&Scalar::random(&mut rand::thread_rng()), 1, now, RevokedTokens::from_ids(vec![]));
+        assert!(cache.confirm_revocation_list(forged, &group_pk).is_err());
+        assert!(!cache.has_valid_token(&TokenRequest::DeviceUnlock, &now_context()));
+
+        // The quorum re-issuing the list confirms nothing was revoked since
+        let reissued = signed_list(&group_secret, 1, now, RevokedTokens::from_ids(vec![]));
+        cache.confirm_revocation_list(reissued, &group_pk).unwrap();
+        assert!(cache.has_valid_token(&TokenRequest::DeviceUnlock, &now_context()));
+        assert_eq!(cache.revocation_sequence(), Some(1));
+    }
+
+    #[test]
+    fn test_uses_recorded_in_signed_audit_log() {
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+
+        let unlock = token.use_for_operation(TokenRequest::DeviceUnlock, &now_context()).unwrap();
+        assert!(token.use_for_operation(TokenRequest::CodeSigning, &now_context()).is_err());
+        pay(&mut ledger(), &mut token, 100).unwrap();
+
+        let export = token.export_audit();
+        assert_eq!(export.verify(None).unwrap(), token.usage.audit.head());
+        let outcomes: Vec<_> = export.entries.iter().map(|e| e.outcome).collect();
+        assert_eq!(outcomes, [AuditOutcome::Allowed, AuditOutcome::Denied, AuditOutcome::Allowed]);
+        assert_eq!(export.entries[0].operation_digest, unlock.operation_hash);
+        assert_eq!(token.usage.use_count, 2);
+
+        // A delegate starts its own log under its own key
+        let delegate = token.attenuate(Capabilities::default(), None).unwrap();
+        assert!(delegate.usage.audit.entries().is_empty());
+        assert_eq!(delegate.export_audit().holder_public, delegate.holder_public());
+    }
+
+    #[test]
+    fn test_full_audit_log_refuses_use() {
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        token.usage.audit = AuditLog::new(2);
+        token.use_for_operation(TokenRequest::DeviceUnlock, &now_context()).unwrap();
+        token.use_for_operation(TokenRequest::DeviceUnlock, &now_context()).unwrap();
+
+        assert!(matches!(token.use_for_operation(TokenRequest::DeviceUnlock, &now_context()), Err(FrostError::StorageError(_))));
+        assert_eq!(token.usage.use_count, 2);
+
+        let (uploaded, _) = token.export_audit().verify(None).unwrap();
+        token.usage.audit.acknowledge(uploaded);
+        assert!(token.use_for_operation(TokenRequest::DeviceUnlock, &now_context()).is_ok());
+    }
 }
@@ -10,6 +10,7 @@ use curve25519_dalek::{
     ristretto::{RistrettoPoint, CompressedRistretto},
     scalar::Scalar,
     constants::RISTRETTO_BASEPOINT_POINT,
+    traits::Identity,
 };
 use rand_core::{RngCore, CryptoRng};
 use serde::{Serialize, Deserialize};
@@ -35,6 +36,9 @@ pub struct SigningRound1 {
     /// Binding nonce commitment E_i = e_i * G
     #[zeroize(skip)]
     binding_commitment: RistrettoPoint,
+    /// Group public key bound into the challenge
+    #[zeroize(skip)]
+    group_public_key: CompressedRistretto,
 }
 
 impl SigningRound1 {
@@ -58,6 +62,7 @@ impl SigningRound1 {
             binding_nonce,
             hiding_commitment,
             binding_commitment,
+            group_public_key: secret_share.group_public_key,
         }
     }
 
@@ -84,8 +89,7 @@ impl SigningRound1 {
         let group_commitment = compute_group_commitment(commitments, &binding_factors)?;
 
         // Compute challenge c = H(R || PK || m)
-        // Note: In real usage, PK would be passed in
-        let challenge = compute_challenge(&group_commitment, message);
+        let challenge = compute_challenge(&group_commitment, &self.group_public_key, message);
 
         // Compute my binding factor
         let my_binding = binding_factors
@@ -216,18 +220,17 @@ fn compute_group_commitment(
     Ok(group_commitment)
 }
 
-/// Compute challenge c = H(R || PK || m)
-fn compute_challenge(group_commitment: &RistrettoPoint, message: &[u8]) -> Scalar {
-    let mut hasher = Sha512::new();
-    hasher.update(b"FROST-RISTRETTO255-SHA512-v1-challenge");
-    hasher.update(group_commitment.compress().as_bytes());
-    hasher.update(message);
-
-    Scalar::from_hash(hasher)
+/// Compute challenge c = H(tag || R || PK || m), see `SignatureScheme::Threshold`
+fn compute_challenge(
+    group_commitment: &RistrettoPoint,
+    group_public_key: &CompressedRistretto,
+    message: &[u8],
+) -> Scalar {
+    threshold_challenge(&group_commitment.compress(), group_public_key, message)
 }
 
 /// Compute Lagrange coefficient λ_i for participant i over the set S
-fn compute_lagrange_coefficient(
+pub(crate) fn compute_lagrange_coefficient(
     participant_id: ParticipantId,
     participants: &[ParticipantId],
 ) -> Scalar {
@@ -248,8 +251,10 @@ fn compute_lagrange_coefficient(
 }
 
 /// Aggregate partial signatures into final signature
+///
+/// Partials are summed as given; check them with `verify_partial_signature`
+/// first to find a misbehaving signer.
 pub fn aggregate_signatures(
-    message: &[u8],
     group_commitment: &CompressedRistretto,
     partial_signatures: &[PartialSignature],
 ) -> FrostResult<SchnorrSignature> {
@@ -275,9 +280,15 @@ pub fn aggregate_signatures(
     Ok(SchnorrSignature {
         z: z.to_bytes(),
         commitment: *group_commitment,
+        scheme: SignatureScheme::Threshold,
     })
 }
 
+/// Compute the group commitment R for `message` over the signing set's commitments
+pub fn group_commitment(message: &[u8], commitments: &[SigningCommitment]) -> FrostResult<RistrettoPoint> {
+    compute_group_commitment(commitments, &compute_binding_factors(message, commitments))
+}
+
 /// Verify a partial signature (requires verification share)
 pub fn verify_partial_signature(
     message: &[u8],
@@ -285,6 +296,7 @@ pub fn verify_partial_signature(
     partial_sig: &PartialSignature,
     verification_share: &PublicKeyShare,
     group_commitment: &RistrettoPoint,
+    group_public_key: &CompressedRistretto,
 ) -> FrostResult<bool> {
     let g = RISTRETTO_BASEPOINT_POINT;
 
@@ -310,7 +322,7 @@ pub fn verify_partial_signature(
         .ok_or(FrostError::InvalidParticipantIndex(partial_sig.participant_id.as_u32()))?;
 
     // Compute challenge and Lagrange coefficient
-    let challenge = compute_challenge(group_commitment, message);
+    let challenge = compute_challenge(group_commitment, group_public_key, message);
     let participants: Vec<_> = commitments.iter().map(|c| c.participant_id).collect();
     let lambda = compute_lagrange_coefficient(partial_sig.participant_id, &participants);
 
@@ -356,8 +368,8 @@ mod tests {
         let mut partial_sigs = Vec::new();
         let mut group_commitment = None;
 
-        for round1 in round1_states.iter().take(2) {
-            let round2 = round1.clone().into_round2(message, &signing_commitments).unwrap();
+        for round1 in round1_states.into_iter().take(2) {
+            let round2 = round1.into_round2(message, &signing_commitments).unwrap();
             if group_commitment.is_none() {
                 group_commitment = Some(round2.group_commitment());
             }
@@ -366,7 +378,6 @@ mod tests {
 
         // Aggregate signatures
         let signature = aggregate_signatures(
-            message,
             &group_commitment.unwrap(),
             &partial_sigs,
         ).unwrap();
@@ -379,7 +390,6 @@ mod tests {
     fn test_lagrange_coefficient() {
         let p1 = ParticipantId::new(1).unwrap();
         let p2 = ParticipantId::new(2).unwrap();
-        let p3 = ParticipantId::new(3).unwrap();
 
         let participants = vec![p1, p2];
 
@@ -0,0 +1,56 @@
+//! Fixtures shared by the unit tests
+//!
+//! Tokens and revocation lists are signed by a 1-of-1 FROST group: the
+//! group secret is a plain scalar and signatures are made directly with it.
+
+use crate::policy::RequestContext;
+use crate::revocation::{RevocationList, RevokedTokens};
+use crate::session_token::SessionToken;
+use crate::types::*;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::scalar::Scalar;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Public key of the 1-of-1 group holding `group_secret`
+pub(crate) fn group(group_secret: &Scalar) -> GroupPublicKey {
+    GroupPublicKey {
+        public_key: (group_secret * RISTRETTO_BASEPOINT_POINT).compress(),
+        participant_shares: Vec::new(),
+        threshold: 1,
+        num_participants: 1,
+    }
+}
+
+/// Threshold-scheme signature by the 1-of-1 group
+pub(crate) fn group_sign(group_secret: &Scalar, data: &[u8]) -> SchnorrSignature {
+    let nonce = Scalar::random(&mut rand::thread_rng());
+    SchnorrSignature::sign_with_scheme(SignatureScheme::Threshold, group_secret, &nonce, data)
+}
+
+/// Sign the token body as the group, returning the group's public key
+pub(crate) fn issue(token: &mut SessionToken, group_secret: &Scalar) -> GroupPublicKey {
+    token.frost_signature = group_sign(group_secret, &token.to_signing_data());
+    group(group_secret)
+}
+
+/// Revocation list signed by the group
+pub(crate) fn signed_list(
+    group_secret: &Scalar,
+    sequence: u64,
+    issued_at: u64,
+    revoked: RevokedTokens,
+) -> RevocationList {
+    let data = RevocationList::to_signing_data(sequence, issued_at, &revoked);
+    RevocationList {
+        sequence,
+        issued_at,
+        revoked,
+        signature: group_sign(group_secret, &data),
+    }
+}
+
+/// Context of a request made now, with no region or network
+pub(crate) fn now_context() -> RequestContext {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    RequestContext { now, ..RequestContext::default() }
+}
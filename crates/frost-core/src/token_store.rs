@@ -0,0 +1,388 @@
+//! Encrypted, crash-safe persistence for `SessionTokenCache`
+//!
+//! The whole cache (tokens, their holder keys, usage trackers and the newest
+//! revocation list) is serialized as one unit and sealed with
+//! XChaCha20-Poly1305 under a device-bound key, normally
+//! `DerivedDeviceKey::encryption_subkey(EncryptionPurpose::TokenCache)`.
+//! Saves alternate between two slots with an increasing generation, so a
+//! write torn by power loss leaves the previous image intact:
+//!
+//! ```text
+//! slot = magic(4) || format(1) || generation(8) || nonce(24) || AEAD(payload)
+//! ```
+//!
+//! Restoring an older image brings back older usage trackers, including
+//! `remaining_today`. That grants nothing: tokens only pay through
+//! `UsageLedger::record_use`, which takes the daily spend from the
+//! counter-pinned ledger.
+
+use crate::revocation::RevocationList;
+use crate::session_token::{SessionToken, SessionTokenCache};
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, KeyInit};
+use chacha20poly1305::aead::{Aead, Payload};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Serialize, Deserialize};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Number of alternating slots an image can be written to
+pub const TOKEN_CACHE_SLOTS: usize = 2;
+
+/// Persistent backing for the token cache
+///
+/// A store only needs to write each slot as a whole; atomicity across
+/// slots comes from alternating between them.
+pub trait TokenCacheStore {
+    /// Read a slot, `None` if it was never written
+    fn load(&self, slot: usize) -> FrostResult<Option<Vec<u8>>>;
+
+    /// Replace a slot
+    fn store(&mut self, slot: usize, data: &[u8]) -> FrostResult<()>;
+}
+
+/// In-memory `TokenCacheStore` (tests and simulation)
+#[derive(Debug, Default, Clone)]
+pub struct MemoryTokenCacheStore {
+    slots: [Option<Vec<u8>>; TOKEN_CACHE_SLOTS],
+}
+
+impl TokenCacheStore for MemoryTokenCacheStore {
+    fn load(&self, slot: usize) -> FrostResult<Option<Vec<u8>>> {
+        Ok(self.slots[slot].clone())
+    }
+
+    fn store(&mut self, slot: usize, data: &[u8]) -> FrostResult<()> {
+        self.slots[slot] = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+/// Magic bytes at the start of a sealed token cache image
+const TOKEN_CACHE_MAGIC: &[u8; 4] = b"FRTC";
+
+/// Current image format version
+const TOKEN_CACHE_FORMAT_VERSION: u8 = 1;
+
+const TOKEN_CACHE_NONCE_LEN: usize = 24;
+
+/// magic(4) || format(1) || generation(8) || nonce(24)
+const TOKEN_CACHE_HEADER_LEN: usize = 4 + 1 + 8 + TOKEN_CACHE_NONCE_LEN;
+
+/// Plaintext of a sealed image
+#[derive(Serialize, Deserialize)]
+struct PersistedCache {
+    tokens: Vec<PersistedToken>,
+    revocations: Option<RevocationList>,
+}
+
+/// Token together with the holder key serde skips on `SessionToken`
+#[derive(Serialize, Deserialize)]
+struct PersistedToken {
+    token: SessionToken,
+    holder_key: Option<[u8; 32]>,
+}
+
+impl Drop for PersistedToken {
+    fn drop(&mut self) {
+        self.holder_key.zeroize();
+    }
+}
+
+/// What a slot holds
+enum Slot {
+    Empty,
+    Corrupt(FrostError),
+    Intact {
+        generation: u64,
+        payload: Zeroizing<Vec<u8>>,
+    },
+}
+
+impl SessionTokenCache {
+    /// Seal the cache into `store` under the device-bound `key`
+    ///
+    /// Overwrites an empty or corrupt slot if there is one, otherwise the
+    /// older image, so the newest intact image survives a torn write.
+    pub fn save<S: TokenCacheStore>(&mut self, store: &mut S, key: &[u8; 32]) -> FrostResult<()> {
+        let slots = read_slots(store, key)?;
+        let newest = slots.iter()
+            .filter_map(|s| match s {
+                Slot::Intact { generation, .. } => Some(*generation),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let target = slots.iter()
+            .position(|s| !matches!(s, Slot::Intact { .. }))
+            .unwrap_or_else(|| {
+                slots.iter()
+                    .enumerate()
+                    .min_by_key(|(_, s)| match s {
+                        Slot::Intact { generation, .. } => *generation,
+                        _ => 0,
+                    })
+                    .map(|(i, _)| i)
+                    .unwrap_or(0)
+            });
+        let generation = newest.max(self.generation)
+            .checked_add(1)
+            .ok_or(FrostError::StorageError("Token cache generation exhausted".to_string()))?;
+
+        let persisted = PersistedCache {
+            tokens: self.tokens.iter()
+                .map(|token| PersistedToken {
+                    token: token.clone(),
+                    holder_key: token.ephemeral_key.as_ref().map(|k| k.as_scalar().to_bytes()),
+                })
+                .collect(),
+            revocations: self.revocations.clone(),
+        };
+        let payload = Zeroizing::new(
+            serde_json::to_vec(&persisted).map_err(|e| FrostError::SerializationError(e.to_string()))?,
+        );
+
+        store.store(target, &seal(key, generation, &payload)?)?;
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Load the newest intact image from `store`
+    ///
+    /// Every token is re-checked against `group_public_key`: tokens that are
+    /// expired, revoked, not signed by the group, carry a broken caveat
+    /// chain, or whose holder key does not match are dropped. An empty store
+    /// yields an empty cache; a store with no image that opens under `key`
+    /// is an error.
+    pub fn load<S: TokenCacheStore>(
+        store: &S,
+        key: &[u8; 32],
+        group_public_key: &GroupPublicKey,
+        max_tokens: usize,
+    ) -> FrostResult<Self> {
+        let mut cache = SessionTokenCache::new(max_tokens);
+
+        let mut newest: Option<(u64, Zeroizing<Vec<u8>>)> = None;
+        let mut last_error = None;
+        for slot in read_slots(store, key)? {
+            match slot {
+                Slot::Empty => {}
+                Slot::Corrupt(e) => {
+                    log::warn!("Skipping unreadable token cache image: {}", e);
+                    last_error = Some(e);
+                }
+                Slot::Intact { generation, payload } => {
+                    if newest.as_ref().is_none_or(|(g, _)| generation > *g) {
+                        newest = Some((generation, payload));
+                    }
+                }
+            }
+        }
+        let (generation, payload) = match (newest, last_error) {
+            (Some(newest), _) => newest,
+            (None, Some(e)) => return Err(e),
+            (None, None) => return Ok(cache),
+        };
+
+        let persisted: PersistedCache = serde_json::from_slice(&payload)
+            .map_err(|e| FrostError::SerializationError(e.to_string()))?;
+        cache.generation = generation;
+        cache.revocations = persisted.revocations
+            .filter(|list| list.check_supersedes(None, group_public_key).is_ok());
+
+        for mut entry in persisted.tokens {
+            let holder_key = entry.holder_key
+                .and_then(|bytes| Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes)))
+                .map(SecretScalar::new);
+            let mut token = entry.token.clone();
+            entry.holder_key.zeroize();
+
+            let signed = token.frost_signature.verify(
+                SignatureScheme::Threshold,
+                &token.to_signing_data(),
+                &group_public_key.public_key,
+            );
+            let key_matches = holder_key.as_ref()
+                .is_some_and(|k| (k.as_scalar() * RISTRETTO_BASEPOINT_POINT).compress() == token.holder_public());
+            let revoked = cache.revocations.as_ref().is_some_and(|l| l.is_revoked(&token.token_id));
+
+            if !signed || !token.verify_caveats() || !key_matches || revoked || !token.is_valid() {
+                log::warn!("Dropping persisted session token {:02x?}", &token.token_id[..4]);
+                continue;
+            }
+
+            token.ephemeral_key = holder_key;
+            cache.add_token(token)?;
+        }
+
+        Ok(cache)
+    }
+}
+
+fn read_slots<S: TokenCacheStore>(store: &S, key: &[u8; 32]) -> FrostResult<Vec<Slot>> {
+    (0..TOKEN_CACHE_SLOTS)
+        .map(|slot| {
+            Ok(match store.load(slot)? {
+                None => Slot::Empty,
+                Some(blob) => match open(key, &blob) {
+                    Ok((generation, payload)) => Slot::Intact { generation, payload },
+                    Err(e) => Slot::Corrupt(e),
+                },
+            })
+        })
+        .collect()
+}
+
+fn seal(key: &[u8; 32], generation: u64, payload: &[u8]) -> FrostResult<Vec<u8>> {
+    use rand::RngCore;
+
+    let mut header = [0u8; TOKEN_CACHE_HEADER_LEN];
+    header[..4].copy_from_slice(TOKEN_CACHE_MAGIC);
+    header[4] = TOKEN_CACHE_FORMAT_VERSION;
+    header[5..13].copy_from_slice(&generation.to_le_bytes());
+    rand::thread_rng().fill_bytes(&mut header[13..]);
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&header[13..]), Payload { msg: payload, aad: &header })
+        .map_err(|_| FrostError::CryptoError("Token cache encryption failed".to_string()))?;
+
+    let mut blob = Vec::with_capacity(TOKEN_CACHE_HEADER_LEN + ciphertext.len());
+    blob.extend_from_slice(&header);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+fn open(key: &[u8; 32], blob: &[u8]) -> FrostResult<(u64, Zeroizing<Vec<u8>>)> {
+    if blob.len() < TOKEN_CACHE_HEADER_LEN || &blob[..4] != TOKEN_CACHE_MAGIC {
+        return Err(FrostError::SerializationError("Not a token cache image".to_string()));
+    }
+    if blob[4] != TOKEN_CACHE_FORMAT_VERSION {
+        return Err(FrostError::SerializationError(
+            format!("Unsupported token cache format version {}", blob[4]),
+        ));
+    }
+
+    let header = &blob[..TOKEN_CACHE_HEADER_LEN];
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let payload = cipher
+        .decrypt(
+            XNonce::from_slice(&header[13..]),
+            Payload { msg: &blob[TOKEN_CACHE_HEADER_LEN..], aad: header },
+        )
+        .map_err(|_| FrostError::CryptoError("Token cache authentication failed".to_string()))?;
+
+    let generation = u64::from_le_bytes(header[5..13].try_into().expect("8-byte slice"));
+    Ok((generation, Zeroizing::new(payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_token::{verify_token_operation, Capabilities, TokenRequest};
+    use crate::ledger::{MemoryLedgerStore, UsageLedger};
+    use crate::testing::{group, issue, now_context};
+    use std::time::Duration;
+
+    fn issued_token(group_secret: &Scalar, lifetime: Duration) -> SessionToken {
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), lifetime);
+        issue(&mut token, group_secret);
+        token
+    }
+
+    #[test]
+    fn test_cache_survives_restart() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let key = [7u8; 32];
+        let mut store = MemoryTokenCacheStore::default();
+
+        let mut cache = SessionTokenCache::new(10);
+        cache.add_token(issued_token(&group_secret, Duration::from_secs(3600))).unwrap();
+        let restricted = issued_token(&group_secret, Duration::from_secs(7200))
+            .attenuate(Capabilities::default(), None)
+            .unwrap();
+        cache.add_token(restricted).unwrap();
+        let mut ledger = UsageLedger::open(MemoryLedgerStore::default()).unwrap();
+        let token = cache.get_valid_token(&TokenRequest::DeviceUnlock, &now_context()).unwrap();
+        ledger.record_use(token, TokenRequest::Payment { amount: 1_000 }, &now_context()).unwrap();
+        cache.save(&mut store, &key).unwrap();
+
+        let mut restored = SessionTokenCache::load(&store, &key, &group(&group_secret), 10).unwrap();
+        assert_eq!(restored.token_count(), 2);
+
+        // Usage survived, and the restored holder key still signs
+        let token = restored.get_valid_token(&TokenRequest::DeviceUnlock, &now_context()).unwrap();
+        assert_eq!(token.usage.use_count, 1);
+        assert_eq!(token.capabilities.payment_limits.as_ref().unwrap().remaining_today, 49_000);
+        let unlock = TokenRequest::DeviceUnlock;
+        let sig = token.use_for_operation(unlock.clone(), &now_context()).unwrap();
+        assert!(verify_token_operation(&group(&group_secret), token, &unlock, &sig, &now_context()).is_ok());
+
+        // Nothing is readable without the device-bound key
+        let blob = store.load(0).unwrap().or(store.load(1).unwrap()).unwrap();
+        assert!(!blob.windows(8).any(|w| w == b"token_id"));
+        assert!(SessionTokenCache::load(&store, &[8u8; 32], &group(&group_secret), 10).is_err());
+    }
+
+    #[test]
+    fn test_load_rechecks_tokens() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let key = [7u8; 32];
+        let mut store = MemoryTokenCacheStore::default();
+
+        let good = issued_token(&group_secret, Duration::from_secs(3600));
+        let mut widened = issued_token(&group_secret, Duration::from_secs(3600));
+        widened.capabilities.code_signing = true;
+        let forged = issued_token(&Scalar::random(&mut rand::thread_rng()), Duration::from_secs(3600));
+        let expired = issued_token(&group_secret, Duration::from_secs(0));
+
+        let mut cache = SessionTokenCache::new(10);
+        cache.tokens = vec![good.clone(), widened, forged, expired];
+        cache.save(&mut store, &key).unwrap();
+
+        let restored = SessionTokenCache::load(&store, &key, &group(&group_secret), 10).unwrap();
+        assert_eq!(restored.token_count(), 1);
+        assert_eq!(restored.tokens[0].token_id, good.token_id);
+
+        // Against another group nothing survives
+        let other = group(&Scalar::random(&mut rand::thread_rng()));
+        assert_eq!(SessionTokenCache::load(&store, &key, &other, 10).unwrap().token_count(), 0);
+    }
+
+    #[test]
+    fn test_torn_write_keeps_previous_image() {
+        let group_secret = Scalar::random(&mut rand::thread_rng());
+        let key = [7u8; 32];
+        let mut store = MemoryTokenCacheStore::default();
+        assert_eq!(SessionTokenCache::load(&store, &key, &group(&group_secret), 10).unwrap().token_count(), 0);
+
+        let mut cache = SessionTokenCache::new(10);
+        cache.add_token(issued_token(&group_secret, Duration::from_secs(3600))).unwrap();
+        cache.save(&mut store, &key).unwrap();
+        cache.add_token(issued_token(&group_secret, Duration::from_secs(3600))).unwrap();
+        cache.save(&mut store, &key).unwrap();
+        assert_eq!(cache.generation, 2);
+
+        // Power loss halfway through writing generation 3
+        cache.add_token(issued_token(&group_secret, Duration::from_secs(3600))).unwrap();
+        let mut torn = store.clone();
+        cache.save(&mut torn, &key).unwrap();
+        let newest = (0..TOKEN_CACHE_SLOTS)
+            .find(|&slot| torn.load(slot).unwrap() != store.load(slot).unwrap())
+            .unwrap();
+        let image = torn.load(newest).unwrap().unwrap();
+        torn.store(newest, &image[..image.len() / 2]).unwrap();
+
+        let restored = SessionTokenCache::load(&torn, &key, &group(&group_secret), 10).unwrap();
+        assert_eq!(restored.generation, 2);
+        assert_eq!(restored.token_count(), 2);
+
+        // The next save replaces the torn slot, not the surviving image
+        let mut restored = restored;
+        restored.save(&mut torn, &key).unwrap();
+        assert_eq!(restored.generation, 3);
+        assert!(torn.load(newest).unwrap().unwrap().len() > image.len() / 2);
+        assert_eq!(SessionTokenCache::load(&torn, &key, &group(&group_secret), 10).unwrap().generation, 3);
+    }
+}
@@ -48,10 +48,10 @@ impl DkgTranscript {
     fn compute_hash(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update(b"FROST-DKG-TRANSCRIPT-v1");
-        hasher.update(&self.version.to_le_bytes());
-        hasher.update(&self.timestamp.to_le_bytes());
-        hasher.update(&self.threshold.to_le_bytes());
-        hasher.update(&self.num_participants.to_le_bytes());
+        hasher.update(self.version.to_le_bytes());
+        hasher.update(self.timestamp.to_le_bytes());
+        hasher.update(self.threshold.to_le_bytes());
+        hasher.update(self.num_participants.to_le_bytes());
 
         // Hash each broadcast
         for broadcast in &self.round1_broadcasts {
@@ -106,9 +106,9 @@ impl RotationTranscript {
     fn compute_hash(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update(b"FROST-ROTATION-TRANSCRIPT-v1");
-        hasher.update(&self.version.to_le_bytes());
-        hasher.update(&self.timestamp.to_le_bytes());
-        hasher.update(&self.previous_hash);
+        hasher.update(self.version.to_le_bytes());
+        hasher.update(self.timestamp.to_le_bytes());
+        hasher.update(self.previous_hash);
 
         for commitment in &self.commitments {
             if let Ok(json) = serde_json::to_vec(commitment) {
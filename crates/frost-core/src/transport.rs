@@ -0,0 +1,432 @@
+//! Transport between a hybrid device and its remote share holders
+//!
+//! `RemoteShareTransport` carries the two FROST signing rounds, health
+//! checks and attestation fetches to a remote share. A signing session is
+//! named by a random session ID: the remote keeps its round-1 nonces under
+//! that ID and spends them on the matching round-2 request.
+//!
+//! `LoopbackTransport` serves remote shares in-process from real
+//! `SigningRound1` state, for tests and simulation.
+
+use crate::cose::SIG_STRUCTURE_PREFIX;
+use crate::merkle::TREE_HEAD_TAG;
+use crate::revocation::{RevocationList, REVOCATION_LIST_TAG};
+use crate::signing::{PartialSignature, SigningCommitment, SigningRound1};
+use crate::hybrid::RemoteShareEndpoint;
+use crate::types::*;
+use crate::{FrostError, FrostResult};
+use async_trait::async_trait;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Random identifier of one signing session
+pub type SessionId = [u8; 32];
+
+/// Health of a remote share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteHealth {
+    /// Whether the remote will take signing requests
+    pub available: bool,
+    /// Round-trip time of the check (ms)
+    pub response_time_ms: u64,
+}
+
+/// Proof that a remote holds its share, bound to a fresh challenge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareAttestation {
+    /// Participant the remote claims to be
+    pub participant_id: ParticipantId,
+    /// Challenge chosen by the device
+    pub challenge: [u8; 32],
+    /// Hash of the firmware serving the share
+    pub firmware_hash: [u8; 32],
+    /// Schnorr signature by the share over `to_signing_data`
+    pub signature: SchnorrSignature,
+}
+
+impl ShareAttestation {
+    /// Data signed by the share
+    pub fn to_signing_data(participant_id: ParticipantId, challenge: &[u8; 32], firmware_hash: &[u8; 32]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"FROST-SHARE-ATTESTATION-v1");
+        hasher.update(participant_id.as_u32().to_le_bytes());
+        hasher.update(challenge);
+        hasher.update(firmware_hash);
+        hasher.finalize().to_vec()
+    }
+
+    /// Check the signature against the participant's verification share
+    pub fn verify(&self, group_public_key: &GroupPublicKey, challenge: &[u8; 32]) -> bool {
+        let Some(share) = group_public_key.participant_shares.iter()
+            .find(|s| s.participant_id == self.participant_id) else {
+            return false;
+        };
+        &self.challenge == challenge
+            && self.signature.verify(
+                SignatureScheme::Schnorr,
+                &Self::to_signing_data(self.participant_id, &self.challenge, &self.firmware_hash),
+                &share.public_key,
+            )
+    }
+}
+
+/// Carries signing rounds and control requests to remote shares
+#[async_trait]
+pub trait RemoteShareTransport: Send + Sync {
+    /// Round 1: have `remote` commit to nonces for `session_id`
+    ///
+    /// Only the SHA-256 of the message is sent; round 2 must be for the
+    /// message it hashes. Repeating a request returns the same commitment.
+    async fn request_commitment(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message_hash: &[u8; 32],
+    ) -> FrostResult<SigningCommitment>;
+
+    /// Round 2: have `remote` sign `message` with the nonces of `session_id`
+    async fn request_partial(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message: &[u8],
+        commitments: &[SigningCommitment],
+    ) -> FrostResult<PartialSignature>;
+
+    /// Check that `remote` is reachable and serving
+    async fn health_check(&self, remote: &RemoteShareEndpoint) -> FrostResult<RemoteHealth>;
+
+    /// Fetch an attestation from `remote` bound to `challenge`
+    async fn fetch_attestation(
+        &self,
+        remote: &RemoteShareEndpoint,
+        challenge: &[u8; 32],
+    ) -> FrostResult<ShareAttestation>;
+
+    /// Fetch the newest revocation list, unless older than `since_sequence`
+    ///
+    /// The list held is served again, so a re-issue of it reaches devices.
+    async fn fetch_revocation_list(
+        &self,
+        remote: &RemoteShareEndpoint,
+        since_sequence: Option<u64>,
+    ) -> FrostResult<RevocationList> {
+        let _ = since_sequence;
+        Err(FrostError::CryptoError(format!("{} does not serve revocation lists", remote.operator)))
+    }
+}
+
+/// Transport for a device with no network path to its remote shares
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisconnectedTransport;
+
+#[async_trait]
+impl RemoteShareTransport for DisconnectedTransport {
+    async fn request_commitment(
+        &self,
+        remote: &RemoteShareEndpoint,
+        _session_id: &SessionId,
+        _message_hash: &[u8; 32],
+    ) -> FrostResult<SigningCommitment> {
+        Err(unreachable(remote))
+    }
+
+    async fn request_partial(
+        &self,
+        remote: &RemoteShareEndpoint,
+        _session_id: &SessionId,
+        _message: &[u8],
+        _commitments: &[SigningCommitment],
+    ) -> FrostResult<PartialSignature> {
+        Err(unreachable(remote))
+    }
+
+    async fn health_check(&self, remote: &RemoteShareEndpoint) -> FrostResult<RemoteHealth> {
+        Err(unreachable(remote))
+    }
+
+    async fn fetch_attestation(
+        &self,
+        remote: &RemoteShareEndpoint,
+        _challenge: &[u8; 32],
+    ) -> FrostResult<ShareAttestation> {
+        Err(unreachable(remote))
+    }
+}
+
+fn unreachable(remote: &RemoteShareEndpoint) -> FrostError {
+    FrostError::CryptoError(format!("No transport to {} ({})", remote.operator, remote.location))
+}
+
+/// One remote share served in-process
+struct LoopbackShare {
+    share: SecretShare,
+    online: bool,
+    /// Round-1 state per session, with the hash of the message it was opened for
+    sessions: HashMap<SessionId, ([u8; 32], SigningRound1)>,
+}
+
+/// In-process remote shares backed by real signing state
+#[derive(Default)]
+pub struct LoopbackTransport {
+    shares: Mutex<HashMap<ParticipantId, LoopbackShare>>,
+    revocations: Mutex<Option<RevocationList>>,
+}
+
+impl LoopbackTransport {
+    /// Serve each of `shares` as a remote, all online
+    pub fn new(shares: Vec<SecretShare>) -> Self {
+        let transport = LoopbackTransport::default();
+        for share in shares {
+            transport.add_share(share);
+        }
+        transport
+    }
+
+    /// Serve one more share
+    pub fn add_share(&self, share: SecretShare) {
+        self.lock().insert(share.participant_id, LoopbackShare {
+            share,
+            online: true,
+            sessions: HashMap::new(),
+        });
+    }
+
+    /// Take a share offline or bring it back
+    pub fn set_online(&self, participant_id: ParticipantId, online: bool) {
+        if let Some(share) = self.lock().get_mut(&participant_id) {
+            share.online = online;
+        }
+    }
+
+    /// Serve `list` to revocation list requests
+    pub fn set_revocation_list(&self, list: RevocationList) {
+        *self.revocations.lock().unwrap_or_else(|e| e.into_inner()) = Some(list);
+    }
+
+    /// Signing sessions opened but not yet finished
+    pub fn open_sessions(&self) -> usize {
+        self.lock().values().map(|s| s.sessions.len()).sum()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<ParticipantId, LoopbackShare>> {
+        self.shares.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `f` on the share behind `remote`, if it is online
+    fn with_share<R>(
+        &self,
+        remote: &RemoteShareEndpoint,
+        f: impl FnOnce(&mut LoopbackShare) -> FrostResult<R>,
+    ) -> FrostResult<R> {
+        let mut shares = self.lock();
+        match shares.get_mut(&remote.participant_id) {
+            Some(share) if share.online => f(share),
+            _ => Err(unreachable(remote)),
+        }
+    }
+}
+
+/// SHA-256 of a message, as sent in round 1
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    Sha256::digest(message).into()
+}
+
+/// What the holder key of the device's session token signs in each
+/// round, so only the token holder can open or finish a session
+pub fn share_request_digest(session_id: &SessionId, message_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"FROST-SHARE-REQUEST-v1");
+    hasher.update(session_id);
+    hasher.update(message_hash);
+    hasher.finalize().into()
+}
+
+/// Data only the quorum signs, never a device through a generic share
+/// request; session token bodies have their own, checked, round 2
+/// (`https::ISSUE_TOKEN_PATH`)
+const RESERVED_PREFIXES: [(&[u8], &str); 3] = [
+    (SIG_STRUCTURE_PREFIX, "session token"),
+    (REVOCATION_LIST_TAG, "revocation list"),
+    (TREE_HEAD_TAG, "tree head"),
+];
+
+/// Kind of quorum-only data `message` is, if it is any
+///
+/// A device that could get such a message co-signed could mint its own
+/// session tokens, un-revoke them, or fork the transparency log.
+pub fn reserved_message_kind(message: &[u8]) -> Option<&'static str> {
+    RESERVED_PREFIXES.iter()
+        .find(|(prefix, _)| message.starts_with(prefix))
+        .map(|(_, kind)| *kind)
+}
+
+#[async_trait]
+impl RemoteShareTransport for LoopbackTransport {
+    async fn request_commitment(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message_hash: &[u8; 32],
+    ) -> FrostResult<SigningCommitment> {
+        self.with_share(remote, |share| {
+            match share.sessions.get(session_id) {
+                Some((opened_for, round1)) if opened_for == message_hash => return Ok(round1.commitment()),
+                Some(_) => return Err(FrostError::CryptoError("Signing session already open".to_string())),
+                None => {}
+            }
+            let round1 = SigningRound1::new(share.share.participant_id, &share.share, &mut rand::rngs::OsRng);
+            let commitment = round1.commitment();
+            share.sessions.insert(*session_id, (*message_hash, round1));
+            Ok(commitment)
+        })
+    }
+
+    async fn request_partial(
+        &self,
+        remote: &RemoteShareEndpoint,
+        session_id: &SessionId,
+        message: &[u8],
+        commitments: &[SigningCommitment],
+    ) -> FrostResult<PartialSignature> {
+        self.with_share(remote, |share| {
+            // Nonces are spent whatever the outcome
+            let (opened_for, round1) = share.sessions.remove(session_id)
+                .ok_or(FrostError::CryptoError("Unknown signing session".to_string()))?;
+            if opened_for != message_hash(message) {
+                return Err(FrostError::CryptoError("Message differs from round 1".to_string()));
+            }
+            if !commitments.contains(&round1.commitment()) {
+                return Err(FrostError::CommitmentVerificationFailed(share.share.participant_id.as_u32()));
+            }
+            Ok(round1.into_round2(message, commitments)?.partial_signature())
+        })
+    }
+
+    async fn health_check(&self, remote: &RemoteShareEndpoint) -> FrostResult<RemoteHealth> {
+        let started = Instant::now();
+        let available = self.lock().get(&remote.participant_id).is_some_and(|s| s.online);
+        Ok(RemoteHealth {
+            available,
+            response_time_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn fetch_attestation(
+        &self,
+        remote: &RemoteShareEndpoint,
+        challenge: &[u8; 32],
+    ) -> FrostResult<ShareAttestation> {
+        // Nothing to measure in-process
+        let firmware_hash = [0u8; 32];
+        self.with_share(remote, |share| {
+            let participant_id = share.share.participant_id;
+            let data = ShareAttestation::to_signing_data(participant_id, challenge, &firmware_hash);
+            let nonce = Scalar::random(&mut rand::rngs::OsRng);
+            let secret = share.share.value.as_scalar();
+            Ok(ShareAttestation {
+                participant_id,
+                challenge: *challenge,
+                firmware_hash,
+                signature: SchnorrSignature::sign_with_nonce(secret, &nonce, &data),
+            })
+        })
+    }
+
+    async fn fetch_revocation_list(
+        &self,
+        remote: &RemoteShareEndpoint,
+        since_sequence: Option<u64>,
+    ) -> FrostResult<RevocationList> {
+        self.with_share(remote, |_| Ok(()))?;
+        match &*self.revocations.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(list) if since_sequence.is_none_or(|s| list.sequence >= s) => Ok(list.clone()),
+            Some(_) => Err(FrostError::CryptoError("Revocation list held is newer".to_string())),
+            None => Err(FrostError::CryptoError("No revocation list published".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dkg::DkgCoordinator;
+    use crate::signing::aggregate_signatures;
+    use rand::rngs::OsRng;
+
+    fn endpoint(id: u32) -> RemoteShareEndpoint {
+        RemoteShareEndpoint {
+            participant_id: ParticipantId::new(id).unwrap(),
+            location: "loopback".to_string(),
+            operator: format!("share {}", id),
+            endpoint_url: String::new(),
+            cert_fingerprint: [0u8; 32],
+            available: true,
+            avg_response_time: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loopback_rounds_produce_threshold_signature() {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let group = outputs[0].group_public_key.clone();
+        let transport = LoopbackTransport::new(vec![outputs[1].secret_share.clone()]);
+        let remote = endpoint(2);
+        let message = b"local and loopback";
+        let session = [7u8; 32];
+        let hash = message_hash(message);
+
+        let local = SigningRound1::new(outputs[0].participant_id, &outputs[0].secret_share, &mut OsRng);
+        let commitments = vec![
+            local.commitment(),
+            transport.request_commitment(&remote, &session, &hash).await.unwrap(),
+        ];
+        // Retries are idempotent, but a session is bound to one message
+        assert_eq!(transport.request_commitment(&remote, &session, &hash).await.unwrap(), commitments[1]);
+        assert!(transport.request_commitment(&remote, &session, &[0u8; 32]).await.is_err());
+
+        // Round 2 must be for the message round 1 was opened for, and spends the nonces
+        assert!(transport.request_partial(&remote, &session, b"other", &commitments).await.is_err());
+        assert!(transport.request_partial(&remote, &session, message, &commitments).await.is_err());
+        assert_eq!(transport.open_sessions(), 0);
+
+        let session = [8u8; 32];
+        let commitments = vec![
+            local.commitment(),
+            transport.request_commitment(&remote, &session, &hash).await.unwrap(),
+        ];
+        let remote_partial = transport.request_partial(&remote, &session, message, &commitments).await.unwrap();
+        let local = local.into_round2(message, &commitments).unwrap();
+        let signature = aggregate_signatures(
+            &local.group_commitment(),
+            &[local.partial_signature(), remote_partial],
+        ).unwrap();
+        assert!(group.verify_signature(message, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_offline_share_and_attestation() {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let group = outputs[0].group_public_key.clone();
+        let transport = LoopbackTransport::new(vec![outputs[1].secret_share.clone()]);
+        let remote = endpoint(2);
+
+        let challenge = [3u8; 32];
+        let attestation = transport.fetch_attestation(&remote, &challenge).await.unwrap();
+        assert!(attestation.verify(&group, &challenge));
+        assert!(!attestation.verify(&group, &[4u8; 32]));
+        let mut impostor = attestation.clone();
+        impostor.participant_id = ParticipantId::new(3).unwrap();
+        assert!(!impostor.verify(&group, &challenge));
+
+        assert!(transport.health_check(&remote).await.unwrap().available);
+        transport.set_online(remote.participant_id, false);
+        assert!(!transport.health_check(&remote).await.unwrap().available);
+        assert!(transport.request_commitment(&remote, &[1u8; 32], &[0u8; 32]).await.is_err());
+        assert!(!transport.health_check(&endpoint(3)).await.unwrap().available);
+    }
+}
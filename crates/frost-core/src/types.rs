@@ -6,10 +6,10 @@ use curve25519_dalek::{
     traits::Identity,
 };
 use serde::{Serialize, Deserialize};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// Participant identifier (1-indexed)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Zeroize)]
 pub struct ParticipantId(pub u32);
 
 impl ParticipantId {
@@ -49,6 +49,12 @@ impl SecretScalar {
     }
 }
 
+impl core::fmt::Debug for SecretScalar {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SecretScalar(<redacted>)")
+    }
+}
+
 /// Polynomial of degree t-1 for secret sharing
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct Polynomial {
@@ -162,6 +168,41 @@ pub struct SecretShare {
     pub value: SecretScalar,
     /// Blinding factor for Pedersen commitment
     pub blinding: SecretScalar,
+    /// Group public key the share signs for
+    #[zeroize(skip)]
+    pub group_public_key: CompressedRistretto,
+}
+
+impl SecretShare {
+    /// Encoded length: participant ID, value, blinding and group public key
+    pub const ENCODED_LEN: usize = 4 + 32 + 32 + 32;
+
+    /// Encode for sealed storage
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(Self::ENCODED_LEN));
+        bytes.extend_from_slice(&self.participant_id.as_u32().to_le_bytes());
+        bytes.extend_from_slice(self.value.as_scalar().as_bytes());
+        bytes.extend_from_slice(self.blinding.as_scalar().as_bytes());
+        bytes.extend_from_slice(self.group_public_key.as_bytes());
+        bytes
+    }
+
+    /// Decode `to_bytes` output, rejecting non-canonical scalars
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::ENCODED_LEN {
+            return None;
+        }
+        let scalar = |range: core::ops::Range<usize>| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes[range].try_into().ok()?)).map(SecretScalar::new)
+        };
+
+        Some(SecretShare {
+            participant_id: ParticipantId::new(u32::from_le_bytes(bytes[0..4].try_into().ok()?))?,
+            value: scalar(4..36)?,
+            blinding: scalar(36..68)?,
+            group_public_key: CompressedRistretto::from_slice(&bytes[68..100]).ok()?,
+        })
+    }
 }
 
 /// Public key share
@@ -187,16 +228,82 @@ pub struct GroupPublicKey {
 }
 
 impl GroupPublicKey {
-    /// Verify a Schnorr signature
+    /// Verify a FROST threshold signature by the group
     pub fn verify_signature(
         &self,
         message: &[u8],
         signature: &SchnorrSignature,
     ) -> bool {
-        signature.verify(message, &self.public_key)
+        signature.verify(SignatureScheme::Threshold, message, &self.public_key)
+    }
+}
+
+/// Signing path a Schnorr signature was produced by
+///
+/// Each path hashes a different challenge. The scheme travels with the
+/// signature, but verifiers name the scheme they expect rather than trust it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    /// Plain single-key Schnorr: c = H(R || PK || m)
+    #[default]
+    Schnorr,
+    /// FROST threshold signature: c = H(tag || R || PK || m)
+    Threshold,
+    /// Derived device key: c = H(tag || R || PK || m)
+    Derived,
+    /// Degraded local-only signature by one share: c = H(tag || R || PK || m)
+    Degraded,
+}
+
+impl SignatureScheme {
+    /// Challenge scalar for this scheme
+    pub fn challenge(
+        &self,
+        commitment: &CompressedRistretto,
+        public_key: &CompressedRistretto,
+        message: &[u8],
+    ) -> Scalar {
+        use sha2::{Sha512, Digest};
+
+        let mut hasher = Sha512::new();
+        match self {
+            SignatureScheme::Schnorr => {
+                hasher.update(commitment.as_bytes());
+                hasher.update(public_key.as_bytes());
+            }
+            SignatureScheme::Threshold => return threshold_challenge(commitment, public_key, message),
+            SignatureScheme::Derived => {
+                hasher.update(b"FROST-DERIVED-SIGNATURE-v1");
+                hasher.update(commitment.as_bytes());
+                hasher.update(public_key.as_bytes());
+            }
+            SignatureScheme::Degraded => {
+                hasher.update(b"FROST-DEGRADED-v2");
+                hasher.update(commitment.as_bytes());
+                hasher.update(public_key.as_bytes());
+            }
+        }
+        hasher.update(message);
+        Scalar::from_hash(hasher)
     }
 }
 
+/// FROST threshold challenge c = H(tag || R || PK || m)
+pub(crate) fn threshold_challenge(
+    group_commitment: &CompressedRistretto,
+    group_public_key: &CompressedRistretto,
+    message: &[u8],
+) -> Scalar {
+    use sha2::{Sha512, Digest};
+
+    let mut hasher = Sha512::new();
+    hasher.update(b"FROST-RISTRETTO255-SHA512-v1-challenge");
+    hasher.update(group_commitment.as_bytes());
+    hasher.update(group_public_key.as_bytes());
+    hasher.update(message);
+    Scalar::from_hash(hasher)
+}
+
 /// Schnorr signature
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchnorrSignature {
@@ -204,12 +311,20 @@ pub struct SchnorrSignature {
     pub z: [u8; 32],
     /// Commitment R
     pub commitment: CompressedRistretto,
+    /// Signing path, selects the challenge; absent in pre-scheme encodings
+    #[serde(default)]
+    pub scheme: SignatureScheme,
 }
 
 impl SchnorrSignature {
-    /// Verify signature against public key
-    pub fn verify(&self, message: &[u8], public_key: &CompressedRistretto) -> bool {
-        use sha2::{Sha512, Digest};
+    /// Verify a signature produced by `scheme` against public key
+    ///
+    /// The scheme carried in the signature is attacker-controlled, so it
+    /// only has to agree with the one the caller expects.
+    pub fn verify(&self, scheme: SignatureScheme, message: &[u8], public_key: &CompressedRistretto) -> bool {
+        if self.scheme != scheme {
+            return false;
+        }
 
         let pk = match public_key.decompress() {
             Some(pk) => pk,
@@ -221,17 +336,12 @@ impl SchnorrSignature {
             None => return false,
         };
 
-        let z = match Scalar::from_canonical_bytes(self.z) {
+        let z = match Option::<Scalar>::from(Scalar::from_canonical_bytes(self.z)) {
             Some(z) => z,
             None => return false,
         };
 
-        // Compute challenge c = H(R || PK || m)
-        let mut hasher = Sha512::new();
-        hasher.update(r.compress().as_bytes());
-        hasher.update(public_key.as_bytes());
-        hasher.update(message);
-        let challenge = Scalar::from_hash(hasher);
+        let challenge = scheme.challenge(&self.commitment, public_key, message);
 
         // Verify: z * G == R + c * PK
         let lhs = z * curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
@@ -239,6 +349,29 @@ impl SchnorrSignature {
 
         lhs == rhs
     }
+
+    /// Produce a plain single-signer signature
+    ///
+    /// The caller is responsible for supplying a fresh, secret nonce.
+    pub(crate) fn sign_with_nonce(secret: &Scalar, nonce: &Scalar, message: &[u8]) -> Self {
+        Self::sign_with_scheme(SignatureScheme::Schnorr, secret, nonce, message)
+    }
+
+    /// Produce a single-signer signature under `scheme`
+    pub(crate) fn sign_with_scheme(scheme: SignatureScheme, secret: &Scalar, nonce: &Scalar, message: &[u8]) -> Self {
+        let g = curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+        let public_key = (secret * g).compress();
+        let commitment = (nonce * g).compress();
+
+        let challenge = scheme.challenge(&commitment, &public_key, message);
+        let z = nonce + challenge * secret;
+
+        SchnorrSignature {
+            z: z.to_bytes(),
+            commitment,
+            scheme,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -302,4 +435,40 @@ mod tests {
 
         assert!(commitment.verify_share(id, &share, &blinding, &g, &h));
     }
+
+    #[test]
+    fn test_signature_without_scheme_is_schnorr() {
+        let secret = Scalar::random(&mut OsRng);
+        let public_key = (secret * curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT).compress();
+        let signature = SchnorrSignature::sign_with_nonce(&secret, &Scalar::random(&mut OsRng), b"legacy");
+
+        let mut json = serde_json::to_value(&signature).unwrap();
+        json.as_object_mut().unwrap().remove("scheme");
+        let legacy: SchnorrSignature = serde_json::from_value(json).unwrap();
+
+        assert_eq!(legacy.scheme, SignatureScheme::Schnorr);
+        assert!(legacy.verify(SignatureScheme::Schnorr, b"legacy", &public_key));
+        assert!(!legacy.verify(SignatureScheme::Threshold, b"legacy", &public_key));
+    }
+
+    #[test]
+    fn test_secret_share_encoding() {
+        let share = SecretShare {
+            participant_id: ParticipantId::new(3).unwrap(),
+            value: SecretScalar::new(Scalar::random(&mut OsRng)),
+            blinding: SecretScalar::new(Scalar::ZERO),
+            group_public_key: RistrettoPoint::random(&mut OsRng).compress(),
+        };
+        let bytes = share.to_bytes();
+        let decoded = SecretShare::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.participant_id, share.participant_id);
+        assert_eq!(decoded.value.as_scalar(), share.value.as_scalar());
+        assert_eq!(decoded.group_public_key, share.group_public_key);
+        assert!(SecretShare::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+
+        let mut zero_id = bytes.to_vec();
+        zero_id[..4].copy_from_slice(&[0; 4]);
+        assert!(SecretShare::from_bytes(&zero_id).is_none());
+    }
 }
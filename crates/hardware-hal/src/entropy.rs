@@ -0,0 +1,163 @@
+//! Secure element TRNG as the randomness source for hedged nonces
+//!
+//! `frost_core::EntropySource` is synchronous because signing is. On std
+//! builds the secure element API is async, so TRNG output is prefetched into
+//! a pool before signing and topped up with `refill`; no_std backends are
+//! read directly.
+//!
+//! Pooled bytes are XORed with the OS RNG, so the output is as strong as
+//! the better of the two. A drained pool falls back to the OS RNG alone
+//! instead of failing the signature; the nonce is hedged either way.
+
+use crate::traits::SecureElement;
+use crate::HardwareResult;
+use frost_core::{EntropySource, FrostError, FrostResult};
+
+#[cfg(feature = "std")]
+use frost_core::OsEntropy;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Bytes of randomness consumed by one hedged nonce
+pub const ENTROPY_PER_NONCE: usize = 32;
+
+/// TRNG output prefetched from a secure element
+#[cfg(feature = "std")]
+pub struct SecureElementEntropy {
+    se: Arc<dyn SecureElement>,
+    capacity: usize,
+    pool: Mutex<Zeroizing<Vec<u8>>>,
+}
+
+#[cfg(feature = "std")]
+impl SecureElementEntropy {
+    /// Draw enough randomness from `se` for `nonces` signatures
+    pub async fn prefetch(se: Arc<dyn SecureElement>, nonces: usize) -> HardwareResult<Self> {
+        let mut pool = Zeroizing::new(vec![0u8; nonces * ENTROPY_PER_NONCE]);
+        se.random_bytes(&mut pool).await?;
+        Ok(SecureElementEntropy {
+            se,
+            capacity: nonces * ENTROPY_PER_NONCE,
+            pool: Mutex::new(pool),
+        })
+    }
+
+    /// Prefetch from `se` and install it as frost-core's default entropy
+    ///
+    /// Derived-key and degraded-mode signers then draw from the secure
+    /// element unless given another source.
+    pub async fn install(se: Arc<dyn SecureElement>, nonces: usize) -> HardwareResult<Arc<Self>> {
+        let entropy = Arc::new(Self::prefetch(se, nonces).await?);
+        frost_core::set_default_entropy(entropy.clone());
+        Ok(entropy)
+    }
+
+    /// Top the pool back up to its prefetched size
+    pub async fn refill(&self) -> HardwareResult<()> {
+        let missing = self.capacity.saturating_sub(self.remaining_nonces() * ENTROPY_PER_NONCE);
+        if missing == 0 {
+            return Ok(());
+        }
+
+        let mut fresh = Zeroizing::new(vec![0u8; missing]);
+        self.se.random_bytes(&mut fresh).await?;
+        if let Ok(mut pool) = self.pool.lock() {
+            let room = self.capacity.saturating_sub(pool.len());
+            pool.extend_from_slice(&fresh[..room.min(fresh.len())]);
+        }
+        Ok(())
+    }
+
+    /// Number of nonces the pool can still supply
+    pub fn remaining_nonces(&self) -> usize {
+        self.pool.lock().map(|pool| pool.len() / ENTROPY_PER_NONCE).unwrap_or(0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl EntropySource for SecureElementEntropy {
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()> {
+        OsEntropy.fill(dest)?;
+
+        let mut pool = self.pool.lock()
+            .map_err(|_| FrostError::CryptoError("Entropy pool poisoned".to_string()))?;
+        if pool.len() < dest.len() {
+            log::warn!("Secure element entropy pool drained, hedging with the OS RNG only");
+            return Ok(());
+        }
+
+        let start = pool.len() - dest.len();
+        for (byte, pooled) in dest.iter_mut().zip(&pool[start..]) {
+            *byte ^= pooled;
+        }
+        pool[start..].zeroize();
+        pool.truncate(start);
+        Ok(())
+    }
+}
+
+/// Secure element read directly on every nonce
+#[cfg(not(feature = "std"))]
+pub struct SecureElementEntropy<'a, S: SecureElement> {
+    se: &'a S,
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, S: SecureElement> SecureElementEntropy<'a, S> {
+    /// Wrap a secure element
+    pub fn new(se: &'a S) -> Self {
+        SecureElementEntropy { se }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, S: SecureElement> EntropySource for SecureElementEntropy<'a, S> {
+    fn fill(&self, dest: &mut [u8]) -> FrostResult<()> {
+        self.se.random_bytes(dest)
+            .map_err(|e| FrostError::CryptoError(format!("Secure element TRNG failed: {}", e)))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::software::SoftwareSecureElement;
+
+    async fn element() -> Arc<dyn SecureElement> {
+        let mut se = SoftwareSecureElement::new();
+        se.initialize().await.unwrap();
+        Arc::new(se)
+    }
+
+    #[tokio::test]
+    async fn test_pool_drains_then_falls_back_and_refills() {
+        let entropy = SecureElementEntropy::prefetch(element().await, 2).await.unwrap();
+        assert_eq!(entropy.remaining_nonces(), 2);
+
+        let mut a = [0u8; ENTROPY_PER_NONCE];
+        let mut b = [0u8; ENTROPY_PER_NONCE];
+        entropy.fill(&mut a).unwrap();
+        entropy.fill(&mut b).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(entropy.remaining_nonces(), 0);
+
+        // A drained pool still yields randomness, from the OS RNG
+        let mut c = [0u8; ENTROPY_PER_NONCE];
+        entropy.fill(&mut c).unwrap();
+        assert_ne!(c, [0u8; ENTROPY_PER_NONCE]);
+        assert_ne!(c, b);
+
+        entropy.refill().await.unwrap();
+        assert_eq!(entropy.remaining_nonces(), 2);
+        entropy.refill().await.unwrap();
+        assert_eq!(entropy.remaining_nonces(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_requires_initialized_element() {
+        let se: Arc<dyn SecureElement> = Arc::new(SoftwareSecureElement::new());
+        assert!(SecureElementEntropy::prefetch(se, 1).await.is_err());
+    }
+}
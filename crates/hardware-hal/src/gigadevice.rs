@@ -8,8 +8,7 @@
 
 use crate::{HardwareError, HardwareResult, SecureElementInfo, SecureElementFeatures};
 use crate::traits::{SecureElement, Attestation, SelfTestReport};
-use frost_core::{SecretShare, ParticipantId, SigningCommitment, PartialSignature};
-use zeroize::Zeroizing;
+use frost_core::{SecretShare, SigningCommitment, PartialSignature};
 
 #[cfg(feature = "std")]
 use async_trait::async_trait;
@@ -55,7 +54,8 @@ impl GigaDeviceGD32 {
     }
 
     /// Hardware-specific: Use ECC accelerator
-    fn hw_ecc_point_multiply(&self, scalar: &[u8], point: &[u8]) -> HardwareResult<Vec<u8>> {
+    #[allow(dead_code)]
+    fn hw_ecc_point_multiply(&self, _scalar: &[u8], _point: &[u8]) -> HardwareResult<Vec<u8>> {
         // In real hardware:
         // - Program PKA (Public Key Accelerator) registers
         // - Start operation via PKA_CR
@@ -119,9 +119,7 @@ impl SecureElement for GigaDeviceGD32 {
             return Err(HardwareError::NotInitialized);
         }
 
-        // Serialize share
-        let serialized = bincode::serialize(share)
-            .map_err(|e| HardwareError::StorageError(e.to_string()))?;
+        let serialized = share.to_bytes().to_vec();
 
         // In real hardware: store in TrustZone secure SRAM
         // Encrypt with hardware key from PUF or OTP
@@ -140,10 +138,8 @@ impl SecureElement for GigaDeviceGD32 {
             .get(share_id)
             .ok_or_else(|| HardwareError::StorageError("Share not found".to_string()))?;
 
-        let share = bincode::deserialize(data)
-            .map_err(|e| HardwareError::StorageError(e.to_string()))?;
-
-        Ok(share)
+        SecretShare::from_bytes(data)
+            .ok_or_else(|| HardwareError::StorageError("Corrupt share".to_string()))
     }
 
     async fn delete_share(&mut self, share_id: &str) -> HardwareResult<()> {
@@ -163,8 +159,8 @@ impl SecureElement for GigaDeviceGD32 {
 
     async fn signing_round1(
         &self,
-        share_id: &str,
-        session_id: &[u8],
+        _share_id: &str,
+        _session_id: &[u8],
     ) -> HardwareResult<SigningCommitment> {
         // Real implementation would:
         // 1. Load share from secure storage
@@ -177,10 +173,10 @@ impl SecureElement for GigaDeviceGD32 {
 
     async fn signing_round2(
         &self,
-        share_id: &str,
-        session_id: &[u8],
-        message: &[u8],
-        commitments: &[SigningCommitment],
+        _share_id: &str,
+        _session_id: &[u8],
+        _message: &[u8],
+        _commitments: &[SigningCommitment],
     ) -> HardwareResult<PartialSignature> {
         // Real implementation would:
         // 1. Load share and Round 1 nonces
@@ -192,6 +188,12 @@ impl SecureElement for GigaDeviceGD32 {
         Err(HardwareError::CryptoError("Not implemented in simulation".to_string()))
     }
 
+    async fn abort_session(&self, _share_id: &str, _session_id: &[u8]) -> HardwareResult<()> {
+        // Real implementation would zeroize the session's Round 1 nonces;
+        // the simulation never issues any
+        Ok(())
+    }
+
     async fn get_attestation(&self) -> HardwareResult<Attestation> {
         // Real implementation:
         // 1. Read device unique ID from OTP
@@ -0,0 +1,124 @@
+//! Usage ledger persistence on secure storage and a monotonic counter
+
+use crate::traits::{MonotonicCounter, SecureStorage};
+use crate::HardwareError;
+use frost_core::{FrostError, FrostResult, LedgerStore};
+
+/// Storage key the ledger is written under
+pub const LEDGER_STORAGE_KEY: &str = "frost/usage-ledger";
+
+/// `LedgerStore` backed by a secure element's storage and counter
+pub struct SecureLedgerStore<S: SecureStorage, C: MonotonicCounter> {
+    storage: S,
+    counter: C,
+}
+
+impl<S: SecureStorage, C: MonotonicCounter> SecureLedgerStore<S, C> {
+    /// Combine secure storage with a monotonic counter
+    pub fn new(storage: S, counter: C) -> Self {
+        SecureLedgerStore { storage, counter }
+    }
+
+    /// Release the underlying storage and counter
+    pub fn into_parts(self) -> (S, C) {
+        (self.storage, self.counter)
+    }
+}
+
+pub(crate) fn storage_error(e: HardwareError) -> FrostError {
+    FrostError::StorageError(e.to_string())
+}
+
+impl<S: SecureStorage, C: MonotonicCounter> LedgerStore for SecureLedgerStore<S, C> {
+    fn load(&self) -> FrostResult<Option<Vec<u8>>> {
+        let present = self.storage.list_keys()
+            .map_err(storage_error)?
+            .iter()
+            .any(|k| k == LEDGER_STORAGE_KEY);
+        if !present {
+            return Ok(None);
+        }
+        self.storage.read(LEDGER_STORAGE_KEY).map(Some).map_err(storage_error)
+    }
+
+    fn store(&mut self, data: &[u8]) -> FrostResult<()> {
+        self.storage.write(LEDGER_STORAGE_KEY, data).map_err(storage_error)
+    }
+
+    fn counter(&self) -> FrostResult<u64> {
+        self.counter.read().map_err(storage_error)
+    }
+
+    fn increment_counter(&mut self) -> FrostResult<u64> {
+        self.counter.increment().map_err(storage_error)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::HardwareResult;
+    use frost_core::{Capabilities, RequestContext, SessionToken, TokenRequest, UsageLedger};
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Default, Clone)]
+    pub(crate) struct MapStorage(pub(crate) HashMap<String, Vec<u8>>);
+
+    impl SecureStorage for MapStorage {
+        fn write(&mut self, key: &str, data: &[u8]) -> HardwareResult<()> {
+            self.0.insert(key.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn read(&self, key: &str) -> HardwareResult<Vec<u8>> {
+            self.0.get(key).cloned().ok_or(HardwareError::StorageError("not found".to_string()))
+        }
+
+        fn delete(&mut self, key: &str) -> HardwareResult<()> {
+            self.0.remove(key);
+            Ok(())
+        }
+
+        fn list_keys(&self) -> HardwareResult<Vec<String>> {
+            Ok(self.0.keys().cloned().collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct Counter(u64);
+
+    impl MonotonicCounter for Counter {
+        fn read(&self) -> HardwareResult<u64> {
+            Ok(self.0)
+        }
+
+        fn increment(&mut self) -> HardwareResult<u64> {
+            self.0 += 1;
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn test_ledger_survives_restart_and_detects_rollback() {
+        let mut token = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let context = RequestContext { now, ..RequestContext::default() };
+
+        let store = SecureLedgerStore::new(MapStorage::default(), Counter::default());
+        let mut ledger = UsageLedger::open(store).unwrap();
+        ledger.record_use(&mut token, TokenRequest::Payment { amount: 10_000 }, &context).unwrap();
+        let (storage, counter) = ledger.into_store().into_parts();
+        let snapshot = storage.clone();
+
+        // Restart with the same storage and counter
+        let mut ledger = UsageLedger::open(SecureLedgerStore::new(storage, counter)).unwrap();
+        assert_eq!(ledger.spent_today(&token, now), 10_000);
+        ledger.record_use(&mut token, TokenRequest::Payment { amount: 10_000 }, &context).unwrap();
+        let (_, counter) = ledger.into_store().into_parts();
+
+        // Restoring an older storage image does not fool the counter
+        let rolled_back = SecureLedgerStore::new(snapshot, counter);
+        assert!(matches!(UsageLedger::open(rolled_back), Err(FrostError::RollbackDetected(_))));
+    }
+}
@@ -5,6 +5,7 @@
 //! - Nations Technologies secure elements
 //! - Feitian HSM modules
 //! - Allwinner/T-Head RISC-V
+//! - Software emulation, for development and tests
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
@@ -14,8 +15,18 @@ pub mod gigadevice;
 pub mod nations;
 pub mod feitian;
 pub mod memory;
+pub mod entropy;
+pub mod ledger;
+pub mod token_store;
+#[cfg(feature = "std")]
+pub mod software;
 
 pub use traits::*;
+pub use entropy::SecureElementEntropy;
+pub use ledger::SecureLedgerStore;
+pub use token_store::SecureTokenCacheStore;
+#[cfg(feature = "std")]
+pub use software::SoftwareSecureElement;
 
 use thiserror::Error;
 
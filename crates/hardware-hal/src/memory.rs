@@ -19,16 +19,6 @@ impl SecureMemory {
         }
     }
 
-    /// Get mutable reference to buffer
-    pub fn as_mut(&mut self) -> &mut [u8] {
-        &mut self.buffer
-    }
-
-    /// Get immutable reference to buffer
-    pub fn as_ref(&self) -> &[u8] {
-        &self.buffer
-    }
-
     /// Copy data into secure memory
     pub fn copy_from(&mut self, data: &[u8]) -> HardwareResult<()> {
         if data.len() > self.buffer.len() {
@@ -42,6 +32,18 @@ impl SecureMemory {
     }
 }
 
+impl AsMut<[u8]> for SecureMemory {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+}
+
+impl AsRef<[u8]> for SecureMemory {
+    fn as_ref(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
 /// Memory protection levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MemoryProtection {
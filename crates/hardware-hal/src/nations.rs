@@ -29,7 +29,8 @@ impl NationsTechSE {
     }
 
     /// Hardware-specific: DPA-resistant scalar multiplication
-    fn dpa_resistant_ecc_multiply(&self, scalar: &[u8]) -> HardwareResult<Vec<u8>> {
+    #[allow(dead_code)]
+    fn dpa_resistant_ecc_multiply(&self, _scalar: &[u8]) -> HardwareResult<Vec<u8>> {
         // Real hardware uses randomized projective coordinates
         // and constant-time operations to resist power analysis
         Ok(vec![0u8; 64])
@@ -91,8 +92,7 @@ impl SecureElement for NationsTechSE {
             return Err(HardwareError::NotInitialized);
         }
 
-        let serialized = bincode::serialize(share)
-            .map_err(|e| HardwareError::StorageError(e.to_string()))?;
+        let serialized = share.to_bytes().to_vec();
 
         // Real HW: Encrypt with PUF-derived key, store in secure EEPROM
         self.secure_storage.insert(share_id.to_string(), serialized);
@@ -108,8 +108,8 @@ impl SecureElement for NationsTechSE {
             .get(share_id)
             .ok_or_else(|| HardwareError::StorageError("Share not found".to_string()))?;
 
-        bincode::deserialize(data)
-            .map_err(|e| HardwareError::StorageError(e.to_string()))
+        SecretShare::from_bytes(data)
+            .ok_or_else(|| HardwareError::StorageError("Corrupt share".to_string()))
     }
 
     async fn delete_share(&mut self, share_id: &str) -> HardwareResult<()> {
@@ -126,22 +126,27 @@ impl SecureElement for NationsTechSE {
 
     async fn signing_round1(
         &self,
-        share_id: &str,
-        session_id: &[u8],
+        _share_id: &str,
+        _session_id: &[u8],
     ) -> HardwareResult<SigningCommitment> {
         Err(HardwareError::CryptoError("Not implemented in simulation".to_string()))
     }
 
     async fn signing_round2(
         &self,
-        share_id: &str,
-        session_id: &[u8],
-        message: &[u8],
-        commitments: &[SigningCommitment],
+        _share_id: &str,
+        _session_id: &[u8],
+        _message: &[u8],
+        _commitments: &[SigningCommitment],
     ) -> HardwareResult<PartialSignature> {
         Err(HardwareError::CryptoError("Not implemented in simulation".to_string()))
     }
 
+    async fn abort_session(&self, _share_id: &str, _session_id: &[u8]) -> HardwareResult<()> {
+        // The simulation issues no nonces to discard
+        Ok(())
+    }
+
     async fn get_attestation(&self) -> HardwareResult<Attestation> {
         Ok(Attestation {
             identity_key: vec![0u8; 32],
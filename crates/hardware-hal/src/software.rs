@@ -0,0 +1,199 @@
+//! Software secure element
+//!
+//! Runs FROST signing on the host CPU with shares held in process memory.
+//! It offers none of the protections of the hardware backends and exists
+//! for development, CI and staging share servers.
+
+use crate::{HardwareError, HardwareResult, SecureElementInfo, SecureElementFeatures};
+use crate::traits::{SecureElement, Attestation, SelfTestReport};
+use frost_core::{SecretShare, SigningCommitment, PartialSignature, SigningRound1};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+/// Secure element emulated in software
+#[derive(Default)]
+pub struct SoftwareSecureElement {
+    initialized: bool,
+    shares: HashMap<String, SecretShare>,
+    /// Round-1 nonces by (share, session); removed when round 2 runs
+    sessions: Mutex<HashMap<(String, Vec<u8>), SigningRound1>>,
+}
+
+impl SoftwareSecureElement {
+    /// Create an empty software secure element
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn share(&self, share_id: &str) -> HardwareResult<&SecretShare> {
+        if !self.initialized {
+            return Err(HardwareError::NotInitialized);
+        }
+        self.shares.get(share_id)
+            .ok_or_else(|| HardwareError::StorageError("Share not found".to_string()))
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<(String, Vec<u8>), SigningRound1>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl SecureElement for SoftwareSecureElement {
+    async fn initialize(&mut self) -> HardwareResult<()> {
+        log::warn!("Software secure element in use: shares are not hardware protected");
+        self.initialized = true;
+        Ok(())
+    }
+
+    async fn get_info(&self) -> HardwareResult<SecureElementInfo> {
+        Ok(SecureElementInfo {
+            manufacturer: "FROST RoT".to_string(),
+            model: "Software".to_string(),
+            firmware_version: env!("CARGO_PKG_VERSION").to_string(),
+            serial_number: [0u8; 16],
+            features: SecureElementFeatures {
+                has_trng: false,
+                has_aes: false,
+                has_ecc: false,
+                has_secure_boot: false,
+                has_trustzone: false,
+                has_puf: false,
+                has_tamper_detection: false,
+            },
+        })
+    }
+
+    async fn random_bytes(&self, buffer: &mut [u8]) -> HardwareResult<()> {
+        use rand::RngCore;
+        if !self.initialized {
+            return Err(HardwareError::NotInitialized);
+        }
+        rand::rngs::OsRng.fill_bytes(buffer);
+        Ok(())
+    }
+
+    async fn store_share(&mut self, share_id: &str, share: &SecretShare) -> HardwareResult<()> {
+        if !self.initialized {
+            return Err(HardwareError::NotInitialized);
+        }
+        self.shares.insert(share_id.to_string(), share.clone());
+        Ok(())
+    }
+
+    async fn load_share(&self, share_id: &str) -> HardwareResult<SecretShare> {
+        self.share(share_id).cloned()
+    }
+
+    async fn delete_share(&mut self, share_id: &str) -> HardwareResult<()> {
+        // SecretShare zeroizes on drop
+        self.shares.remove(share_id);
+        self.sessions().retain(|(id, _), _| id != share_id);
+        Ok(())
+    }
+
+    async fn signing_round1(
+        &self,
+        share_id: &str,
+        session_id: &[u8],
+    ) -> HardwareResult<SigningCommitment> {
+        let share = self.share(share_id)?;
+        let mut sessions = self.sessions();
+        let key = (share_id.to_string(), session_id.to_vec());
+        if sessions.contains_key(&key) {
+            return Err(HardwareError::InvalidParameter("Signing session already open".to_string()));
+        }
+
+        let round1 = SigningRound1::new(share.participant_id, share, &mut rand::rngs::OsRng);
+        let commitment = round1.commitment();
+        sessions.insert(key, round1);
+        Ok(commitment)
+    }
+
+    async fn signing_round2(
+        &self,
+        share_id: &str,
+        session_id: &[u8],
+        message: &[u8],
+        commitments: &[SigningCommitment],
+    ) -> HardwareResult<PartialSignature> {
+        self.share(share_id)?;
+        // Nonces are spent whatever the outcome
+        let round1 = self.sessions()
+            .remove(&(share_id.to_string(), session_id.to_vec()))
+            .ok_or_else(|| HardwareError::InvalidParameter("Unknown signing session".to_string()))?;
+        if !commitments.contains(&round1.commitment()) {
+            return Err(HardwareError::InvalidParameter("Own commitment missing from round 2".to_string()));
+        }
+
+        round1.into_round2(message, commitments)
+            .map(|round2| round2.partial_signature())
+            .map_err(|e| HardwareError::CryptoError(e.to_string()))
+    }
+
+    async fn abort_session(&self, share_id: &str, session_id: &[u8]) -> HardwareResult<()> {
+        self.share(share_id)?;
+        self.sessions().remove(&(share_id.to_string(), session_id.to_vec()));
+        Ok(())
+    }
+
+    async fn get_attestation(&self) -> HardwareResult<Attestation> {
+        // Nothing to attest without hardware
+        Err(HardwareError::AttestationFailed)
+    }
+
+    async fn self_test(&self) -> HardwareResult<SelfTestReport> {
+        Ok(SelfTestReport {
+            passed: self.initialized,
+            trng_ok: true,
+            crypto_ok: true,
+            memory_ok: true,
+            tamper_ok: true,
+            errors: if self.initialized { vec![] } else { vec!["Not initialized".to_string()] },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frost_core::aggregate_signatures;
+    use frost_core::dkg::DkgCoordinator;
+    use rand::rngs::OsRng;
+
+    #[tokio::test]
+    async fn test_software_signing_spends_nonces_once() {
+        let outputs = DkgCoordinator::new(2, 2).unwrap().run_dkg(&mut OsRng).unwrap();
+        let mut se = SoftwareSecureElement::new();
+        assert!(se.signing_round1("share", b"s1").await.is_err());
+        se.initialize().await.unwrap();
+        se.store_share("share", &outputs[1].secret_share).await.unwrap();
+
+        let message = b"software secure element";
+        let local = SigningRound1::new(outputs[0].secret_share.participant_id, &outputs[0].secret_share, &mut OsRng);
+        let remote = se.signing_round1("share", b"s1").await.unwrap();
+        assert!(se.signing_round1("share", b"s1").await.is_err());
+
+        let commitments = vec![local.commitment(), remote];
+        let partial = se.signing_round2("share", b"s1", message, &commitments).await.unwrap();
+        let round2 = local.into_round2(message, &commitments).unwrap();
+        let signature = aggregate_signatures(
+            &round2.group_commitment(),
+            &[round2.partial_signature(), partial],
+        ).unwrap();
+        assert!(outputs[0].group_public_key.verify_signature(message, &signature));
+
+        // The session's nonces are gone
+        assert!(se.signing_round2("share", b"s1", message, &commitments).await.is_err());
+
+        // Aborted sessions free their nonces and the session ID
+        let aborted = se.signing_round1("share", b"s2").await.unwrap();
+        se.abort_session("share", b"s2").await.unwrap();
+        se.abort_session("share", b"s2").await.unwrap();
+        let commitments = vec![commitments[0].clone(), aborted];
+        assert!(se.signing_round2("share", b"s2", message, &commitments).await.is_err());
+        assert!(se.signing_round1("share", b"s2").await.is_ok());
+    }
+}
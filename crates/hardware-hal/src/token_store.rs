@@ -0,0 +1,88 @@
+//! Session token cache persistence on secure storage
+
+use crate::ledger::storage_error;
+use crate::traits::SecureStorage;
+use frost_core::{FrostResult, TokenCacheStore, TOKEN_CACHE_SLOTS};
+
+/// Storage key prefix the token cache slots are written under
+pub const TOKEN_CACHE_STORAGE_PREFIX: &str = "frost/token-cache";
+
+/// `TokenCacheStore` backed by a secure element's storage
+pub struct SecureTokenCacheStore<S: SecureStorage> {
+    storage: S,
+}
+
+impl<S: SecureStorage> SecureTokenCacheStore<S> {
+    /// Wrap secure storage
+    pub fn new(storage: S) -> Self {
+        SecureTokenCacheStore { storage }
+    }
+
+    /// Release the underlying storage
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn slot_key(slot: usize) -> String {
+        format!("{}/{}", TOKEN_CACHE_STORAGE_PREFIX, slot)
+    }
+}
+
+impl<S: SecureStorage> TokenCacheStore for SecureTokenCacheStore<S> {
+    fn load(&self, slot: usize) -> FrostResult<Option<Vec<u8>>> {
+        debug_assert!(slot < TOKEN_CACHE_SLOTS);
+        let key = Self::slot_key(slot);
+        let present = self.storage.list_keys()
+            .map_err(storage_error)?
+            .contains(&key);
+        if !present {
+            return Ok(None);
+        }
+        self.storage.read(&key).map(Some).map_err(storage_error)
+    }
+
+    fn store(&mut self, slot: usize, data: &[u8]) -> FrostResult<()> {
+        self.storage.write(&Self::slot_key(slot), data).map_err(storage_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::tests::MapStorage;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+    use frost_core::{GroupPublicKey, SessionTokenCache};
+
+    #[test]
+    fn test_slots_map_to_storage_keys() {
+        let mut store = SecureTokenCacheStore::new(MapStorage::default());
+        assert_eq!(store.load(0).unwrap(), None);
+
+        store.store(1, b"image").unwrap();
+        assert_eq!(store.load(0).unwrap(), None);
+        assert_eq!(store.load(1).unwrap(), Some(b"image".to_vec()));
+        assert!(store.into_inner().0.contains_key("frost/token-cache/1"));
+    }
+
+    #[test]
+    fn test_token_cache_alternates_slots() {
+        let group = GroupPublicKey {
+            public_key: (Scalar::from(42u64) * RISTRETTO_BASEPOINT_POINT).compress(),
+            participant_shares: Vec::new(),
+            threshold: 1,
+            num_participants: 1,
+        };
+        let key = [3u8; 32];
+
+        let mut cache = SessionTokenCache::new(10);
+        let mut store = SecureTokenCacheStore::new(MapStorage::default());
+        cache.save(&mut store, &key).unwrap();
+        cache.save(&mut store, &key).unwrap();
+        assert!(store.load(0).unwrap().is_some());
+        assert!(store.load(1).unwrap().is_some());
+
+        assert!(SessionTokenCache::load(&store, &key, &group, 10).is_ok());
+        assert!(SessionTokenCache::load(&store, &[4u8; 32], &group, 10).is_err());
+    }
+}
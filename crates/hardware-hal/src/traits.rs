@@ -1,7 +1,7 @@
 //! Traits for hardware abstraction
 
-use crate::{HardwareError, HardwareResult};
-use frost_core::{SecretShare, ParticipantId, SigningCommitment, PartialSignature};
+use crate::HardwareResult;
+use frost_core::{SecretShare, SigningCommitment, PartialSignature};
 use serde::{Serialize, Deserialize};
 
 #[cfg(feature = "std")]
@@ -79,6 +79,11 @@ pub trait SecureElement: Send + Sync {
         commitments: &[SigningCommitment],
     ) -> HardwareResult<PartialSignature>;
 
+    /// Discard the Round 1 nonces of a session that will not reach Round 2
+    ///
+    /// Unknown sessions are not an error.
+    async fn abort_session(&self, share_id: &str, session_id: &[u8]) -> HardwareResult<()>;
+
     /// Get hardware attestation
     async fn get_attestation(&self) -> HardwareResult<Attestation>;
 
@@ -158,3 +163,12 @@ pub trait SecureStorage {
     /// List all keys
     fn list_keys(&self) -> HardwareResult<Vec<String>>;
 }
+
+/// Hardware monotonic counter (OTP fuses, RPMB or secure element counter)
+pub trait MonotonicCounter {
+    /// Current value
+    fn read(&self) -> HardwareResult<u64>;
+
+    /// Increment and return the new value; the value can never decrease
+    fn increment(&mut self) -> HardwareResult<u64>;
+}
@@ -0,0 +1,20 @@
+//! Installing the secure element as frost-core's default entropy
+//!
+//! `set_default_entropy` changes process-wide state, so this runs in its
+//! own test binary where no other test signs with the installed source.
+
+use frost_core::{DefaultEntropy, EntropySource};
+use frost_hardware_hal::entropy::ENTROPY_PER_NONCE;
+use frost_hardware_hal::{SecureElement, SecureElementEntropy, SoftwareSecureElement};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_installed_as_default_entropy() {
+    let mut se = SoftwareSecureElement::new();
+    se.initialize().await.unwrap();
+    let entropy = SecureElementEntropy::install(Arc::new(se), 4).await.unwrap();
+
+    let mut nonce = [0u8; ENTROPY_PER_NONCE];
+    DefaultEntropy.fill(&mut nonce).unwrap();
+    assert_eq!(entropy.remaining_nonces(), 3);
+}
@@ -0,0 +1,146 @@
+//! Alerts
+//!
+//! One JSON object per alert, with the log index and an `alert` tag:
+//!
+//! ```json
+//! {"log_index":42,"alert":"conflicting_device_key","device_id":"0b0b…","version":1,"public_key":"…","active_key":"…","active_index":7}
+//! ```
+//!
+//! Hashes, keys and device IDs are hex.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Something in the log that should not be there
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    /// Index of the offending entry
+    pub log_index: u64,
+    /// What is wrong with it
+    #[serde(flatten)]
+    pub kind: AlertKind,
+}
+
+/// Kind of alert, with its details
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "alert", rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Entry has a known type but does not parse
+    MalformedEntry {
+        /// Parse error
+        reason: String,
+    },
+    /// Transcript hash does not match its contents
+    InvalidTranscriptHash {
+        /// Hash recorded in the transcript
+        transcript_hash: String,
+    },
+    /// Transcript with this hash was logged before
+    DuplicateTranscript {
+        /// Hash of the transcript
+        transcript_hash: String,
+        /// Index of the first copy
+        first_index: u64,
+    },
+    /// Rotation chains to a transcript that was never logged
+    BrokenRotationChain {
+        /// Hash of the rotation transcript
+        transcript_hash: String,
+        /// Hash it chains to
+        previous_hash: String,
+    },
+    /// Rotation chains to a transcript another rotation already follows
+    ForkedRotationChain {
+        /// Hash of the rotation transcript
+        transcript_hash: String,
+        /// Hash it chains to
+        previous_hash: String,
+        /// Index of the rotation that followed it first
+        successor_index: u64,
+    },
+    /// Device key derived by a ceremony whose transcript was never logged
+    UnknownCeremony {
+        /// Device ID
+        device_id: String,
+        /// Transcript hash in the derivation statement
+        dkg_transcript_hash: String,
+    },
+    /// Second, different key for a device and version
+    ConflictingDeviceKey {
+        /// Device ID
+        device_id: String,
+        /// Key version
+        version: u32,
+        /// Key in this entry
+        public_key: String,
+        /// Key logged first
+        active_key: String,
+        /// Index of the first key
+        active_index: u64,
+    },
+    /// Key version that goes back or skips ahead
+    UnexpectedRekey {
+        /// Device ID
+        device_id: String,
+        /// Key version in this entry
+        version: u32,
+        /// Highest version logged before
+        previous_version: u32,
+    },
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entry {}: ", self.log_index)?;
+        match &self.kind {
+            AlertKind::MalformedEntry { reason } => write!(f, "{}", reason),
+            AlertKind::InvalidTranscriptHash { transcript_hash } => {
+                write!(f, "transcript {} does not match its hash", transcript_hash)
+            }
+            AlertKind::DuplicateTranscript { transcript_hash, first_index } => {
+                write!(f, "transcript {} already logged at {}", transcript_hash, first_index)
+            }
+            AlertKind::BrokenRotationChain { transcript_hash, previous_hash } => {
+                write!(f, "rotation {} follows unknown transcript {}", transcript_hash, previous_hash)
+            }
+            AlertKind::ForkedRotationChain { transcript_hash, previous_hash, successor_index } => write!(
+                f,
+                "rotation {} forks the chain at {}, already followed at {}",
+                transcript_hash, previous_hash, successor_index
+            ),
+            AlertKind::UnknownCeremony { device_id, dkg_transcript_hash } => {
+                write!(f, "device {} derived by unknown ceremony {}", device_id, dkg_transcript_hash)
+            }
+            AlertKind::ConflictingDeviceKey { device_id, version, active_index, .. } => write!(
+                f,
+                "device {} has a second key for version {} (first at {})",
+                device_id, version, active_index
+            ),
+            AlertKind::UnexpectedRekey { device_id, version, previous_version } => write!(
+                f,
+                "device {} rekeyed to version {} after version {}",
+                device_id, version, previous_version
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_json_line() {
+        let alert = Alert {
+            log_index: 42,
+            kind: AlertKind::UnexpectedRekey { device_id: "0b".to_string(), version: 3, previous_version: 1 },
+        };
+        let line = serde_json::to_string(&alert).unwrap();
+        assert_eq!(
+            line,
+            r#"{"log_index":42,"alert":"unexpected_rekey","device_id":"0b","version":3,"previous_version":1}"#
+        );
+        assert_eq!(serde_json::from_str::<Alert>(&line).unwrap(), alert);
+        assert_eq!(alert.to_string(), "entry 42: device 0b rekeyed to version 3 after version 1");
+    }
+}
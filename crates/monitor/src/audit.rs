@@ -0,0 +1,291 @@
+//! Entry-by-entry audit of the log
+//!
+//! The auditor remembers every transcript it has seen, which rotation
+//! follows each one, and the keys logged per device and version. Each new
+//! entry is checked against that and the state updated, alerts or not, so
+//! one bad entry is reported once rather than at every later entry.
+
+use crate::alert::{Alert, AlertKind};
+use crate::{MonitorError, MonitorResult};
+use frost_core::derived_key::DerivationStatement;
+use frost_core::transcript::{DkgTranscript, RotationTranscript};
+use frost_transparency_log::{LogEntry, MerkleLog, TileStore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Where a transcript was logged and which rotation follows it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TranscriptRecord {
+    log_index: u64,
+    successor: Option<u64>,
+}
+
+/// First key logged for one device and version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LoggedKey {
+    public_key: String,
+    log_index: u64,
+}
+
+/// Audit state, resumable from its JSON form
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Auditor {
+    next_index: u64,
+    transcripts: BTreeMap<String, TranscriptRecord>,
+    devices: BTreeMap<String, BTreeMap<u32, LoggedKey>>,
+}
+
+impl Auditor {
+    /// Auditor starting at the first entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resume from the state at `path`, or start afresh if there is none
+    pub fn load(path: &Path) -> MonitorResult<Self> {
+        match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| MonitorError::State(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(MonitorError::State(format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    /// Atomically replace the state at `path`
+    pub fn save(&self, path: &Path) -> MonitorResult<()> {
+        let state_error = |e: std::io::Error| MonitorError::State(format!("{}: {}", path.display(), e));
+        let data = serde_json::to_vec(self).map_err(|e| MonitorError::State(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp).map_err(state_error)?;
+        file.write_all(&data).map_err(state_error)?;
+        file.sync_all().map_err(state_error)?;
+        std::fs::rename(&tmp, path).map_err(state_error)
+    }
+
+    /// Index of the next entry to audit
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Audit the entries appended to `log` since the last call
+    pub fn catch_up<S: TileStore>(&mut self, log: &MerkleLog<S>) -> MonitorResult<Vec<Alert>> {
+        let mut alerts = Vec::new();
+        while self.next_index < log.size() {
+            let data = log.entry(self.next_index)?;
+            alerts.extend(self.audit(self.next_index, &data));
+        }
+        Ok(alerts)
+    }
+
+    /// Audit the entry at `log_index`
+    pub fn audit(&mut self, log_index: u64, data: &[u8]) -> Vec<Alert> {
+        self.next_index = log_index + 1;
+        let kinds = match LogEntry::parse(data) {
+            Ok(LogEntry::Derivation(statement)) => self.derivation(log_index, &statement),
+            Ok(LogEntry::DkgTranscript(transcript)) => self.dkg(log_index, &transcript),
+            Ok(LogEntry::RotationTranscript(transcript)) => self.rotation(log_index, &transcript),
+            Ok(LogEntry::Other(_)) => Vec::new(),
+            Err(e) => vec![AlertKind::MalformedEntry { reason: e.to_string() }],
+        };
+
+        kinds.into_iter()
+            .map(|kind| {
+                let alert = Alert { log_index, kind };
+                log::warn!("{}", alert);
+                alert
+            })
+            .collect()
+    }
+
+    /// Record a transcript, unless its hash is wrong or already taken
+    fn transcript(&mut self, log_index: u64, transcript_hash: &[u8; 32], valid: bool) -> Option<AlertKind> {
+        let transcript_hash = hex::encode(transcript_hash);
+        if !valid {
+            return Some(AlertKind::InvalidTranscriptHash { transcript_hash });
+        }
+        if let Some(first) = self.transcripts.get(&transcript_hash) {
+            return Some(AlertKind::DuplicateTranscript { transcript_hash, first_index: first.log_index });
+        }
+        self.transcripts.insert(transcript_hash, TranscriptRecord { log_index, successor: None });
+        None
+    }
+
+    fn dkg(&mut self, log_index: u64, transcript: &DkgTranscript) -> Vec<AlertKind> {
+        self.transcript(log_index, &transcript.transcript_hash, transcript.verify())
+            .into_iter()
+            .collect()
+    }
+
+    fn rotation(&mut self, log_index: u64, transcript: &RotationTranscript) -> Vec<AlertKind> {
+        if let Some(alert) = self.transcript(log_index, &transcript.transcript_hash, transcript.verify()) {
+            return vec![alert];
+        }
+
+        let transcript_hash = hex::encode(transcript.transcript_hash);
+        let previous_hash = hex::encode(transcript.previous_hash);
+        match self.transcripts.get_mut(&previous_hash) {
+            None => vec![AlertKind::BrokenRotationChain { transcript_hash, previous_hash }],
+            Some(TranscriptRecord { successor: Some(successor_index), .. }) => {
+                vec![AlertKind::ForkedRotationChain { transcript_hash, previous_hash, successor_index: *successor_index }]
+            }
+            Some(previous) => {
+                previous.successor = Some(log_index);
+                Vec::new()
+            }
+        }
+    }
+
+    fn derivation(&mut self, log_index: u64, statement: &DerivationStatement) -> Vec<AlertKind> {
+        let mut alerts = Vec::new();
+        let device_id = hex::encode(statement.device_id);
+        let public_key = hex::encode(statement.public_key.as_bytes());
+
+        let dkg_transcript_hash = hex::encode(statement.dkg_transcript_hash);
+        if !self.transcripts.contains_key(&dkg_transcript_hash) {
+            alerts.push(AlertKind::UnknownCeremony { device_id: device_id.clone(), dkg_transcript_hash });
+        }
+
+        let keys = self.devices.entry(device_id.clone()).or_default();
+        if let Some(active) = keys.get(&statement.version) {
+            // Logging the same key again is harmless
+            if active.public_key != public_key {
+                alerts.push(AlertKind::ConflictingDeviceKey {
+                    device_id,
+                    version: statement.version,
+                    public_key,
+                    active_key: active.public_key.clone(),
+                    active_index: active.log_index,
+                });
+            }
+            return alerts;
+        }
+
+        if let Some(&previous_version) = keys.keys().next_back() {
+            if statement.version < previous_version || statement.version > previous_version + 1 {
+                alerts.push(AlertKind::UnexpectedRekey {
+                    device_id,
+                    version: statement.version,
+                    previous_version,
+                });
+            }
+        }
+        keys.insert(statement.version, LoggedKey { public_key, log_index });
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::ristretto::CompressedRistretto;
+    use frost_transparency_log::MemoryTileStore;
+
+    fn statement(device: u8, version: u32, key: u8, dkg: &DkgTranscript) -> LogEntry {
+        LogEntry::Derivation(DerivationStatement {
+            device_id: [device; 32],
+            public_key: CompressedRistretto([key; 32]),
+            version,
+            firmware_hash: [0u8; 32],
+            dkg_transcript_hash: dkg.transcript_hash,
+        })
+    }
+
+    fn kinds(alerts: Vec<Alert>) -> Vec<&'static str> {
+        alerts.iter()
+            .map(|alert| match alert.kind {
+                AlertKind::MalformedEntry { .. } => "malformed",
+                AlertKind::InvalidTranscriptHash { .. } => "invalid_hash",
+                AlertKind::DuplicateTranscript { .. } => "duplicate",
+                AlertKind::BrokenRotationChain { .. } => "broken_chain",
+                AlertKind::ForkedRotationChain { .. } => "forked_chain",
+                AlertKind::UnknownCeremony { .. } => "unknown_ceremony",
+                AlertKind::ConflictingDeviceKey { .. } => "conflicting_key",
+                AlertKind::UnexpectedRekey { .. } => "unexpected_rekey",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_history_raises_nothing() {
+        let dkg = DkgTranscript::new(1_700_000_000, 2, 3, vec![serde_json::json!({ "participant": 1 })]);
+        let first = RotationTranscript::new(1_700_000_100, dkg.transcript_hash, vec![]);
+        let second = RotationTranscript::new(1_700_000_200, first.transcript_hash, vec![]);
+
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        for entry in [
+            LogEntry::Other(b"note".to_vec()),
+            LogEntry::DkgTranscript(dkg.clone()),
+            statement(1, 1, 10, &dkg),
+            LogEntry::RotationTranscript(first),
+            statement(1, 2, 11, &dkg),
+            statement(1, 2, 11, &dkg),
+            LogEntry::RotationTranscript(second),
+            statement(2, 5, 20, &dkg),
+        ] {
+            log.append_entry(&entry).unwrap();
+        }
+
+        let mut auditor = Auditor::new();
+        assert!(auditor.catch_up(&log).unwrap().is_empty());
+        assert_eq!(auditor.next_index(), 8);
+        assert!(auditor.catch_up(&log).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_every_inconsistency_is_flagged_once() {
+        let dkg = DkgTranscript::new(1_700_000_000, 2, 3, vec![]);
+        let rotation = RotationTranscript::new(1_700_000_100, dkg.transcript_hash, vec![]);
+        let fork = RotationTranscript::new(1_700_000_150, dkg.transcript_hash, vec![]);
+        let orphan = RotationTranscript::new(1_700_000_200, [9u8; 32], vec![]);
+        let mut tampered = RotationTranscript::new(1_700_000_300, rotation.transcript_hash, vec![]);
+        tampered.timestamp += 1;
+        let unlogged = DkgTranscript::new(1_600_000_000, 2, 3, vec![]);
+
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let entries = [
+            (LogEntry::DkgTranscript(dkg.clone()), vec![]),
+            (LogEntry::RotationTranscript(rotation.clone()), vec![]),
+            (LogEntry::RotationTranscript(fork), vec!["forked_chain"]),
+            (LogEntry::RotationTranscript(orphan), vec!["broken_chain"]),
+            (LogEntry::RotationTranscript(tampered), vec!["invalid_hash"]),
+            (LogEntry::RotationTranscript(rotation), vec!["duplicate"]),
+            (statement(1, 1, 10, &dkg), vec![]),
+            (statement(1, 1, 11, &dkg), vec!["conflicting_key"]),
+            (statement(1, 3, 12, &dkg), vec!["unexpected_rekey"]),
+            (statement(1, 2, 13, &dkg), vec!["unexpected_rekey"]),
+            (statement(2, 1, 20, &unlogged), vec!["unknown_ceremony"]),
+            (LogEntry::Other(b"FROST-DERIVATION-STATEMENT-v1 truncated".to_vec()), vec!["malformed"]),
+        ];
+        for (entry, _) in &entries {
+            log.append_entry(entry).unwrap();
+        }
+
+        let mut auditor = Auditor::new();
+        for (index, (_, expected)) in entries.iter().enumerate() {
+            let data = log.entry(index as u64).unwrap();
+            assert_eq!(kinds(auditor.audit(index as u64, &data)), *expected, "entry {}", index);
+        }
+    }
+
+    #[test]
+    fn test_state_resumes_from_file() {
+        let dkg = DkgTranscript::new(1_700_000_000, 2, 3, vec![]);
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        log.append_entry(&LogEntry::DkgTranscript(dkg.clone())).unwrap();
+        log.append_entry(&statement(1, 1, 10, &dkg)).unwrap();
+
+        let path = std::env::temp_dir().join(format!("frost-monitor-state-{}.json", std::process::id()));
+        let mut auditor = Auditor::load(&path).unwrap();
+        assert!(auditor.catch_up(&log).unwrap().is_empty());
+        auditor.save(&path).unwrap();
+
+        // Only the new entry is audited, against what was seen before
+        log.append_entry(&statement(1, 1, 11, &dkg)).unwrap();
+        let mut resumed = Auditor::load(&path).unwrap();
+        assert_eq!(resumed.next_index(), 2);
+        assert_eq!(kinds(resumed.catch_up(&log).unwrap()), vec!["conflicting_key"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+//! Transparency log monitor for FROST RoT
+//!
+//! Tails the log and audits every entry a device or operator relies on:
+//!
+//! ```text
+//! MerkleLog ──entries──► Auditor ──► Alert (JSON lines)
+//!                           │
+//!                           ├── DKG / rotation transcripts: recompute hashes
+//!                           ├── rotations: previous_hash chains unbroken, unforked
+//!                           └── derivations: one key per device and version,
+//!                               versions only step forward by one
+//! ```
+//!
+//! The auditor's state is plain JSON, so a monitor can stop and resume
+//! where it left off.
+
+#![warn(missing_docs)]
+
+pub mod alert;
+pub mod audit;
+
+pub use alert::{Alert, AlertKind};
+pub use audit::Auditor;
+
+use frost_transparency_log::LogError;
+use thiserror::Error;
+
+/// Monitor errors
+#[derive(Error, Debug)]
+pub enum MonitorError {
+    /// Log could not be read
+    #[error(transparent)]
+    Log(#[from] LogError),
+
+    /// Monitor state file missing or invalid
+    #[error("State error: {0}")]
+    State(String),
+}
+
+/// Result type for monitor operations
+pub type MonitorResult<T> = Result<T, MonitorError>;
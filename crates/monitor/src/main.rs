@@ -0,0 +1,92 @@
+//! frost-monitor: audit the transparency log, printing alerts as JSON lines
+//!
+//! ```text
+//! frost-monitor --log DIR [--state FILE] [--follow SECONDS]
+//! ```
+//!
+//! Without `--state` every run audits from the first entry. Without
+//! `--follow` the monitor exits after catching up, with status 2 if it
+//! raised any alert.
+
+use frost_monitor::{Auditor, MonitorError, MonitorResult};
+use frost_transparency_log::{FileTileStore, MerkleLog};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const USAGE: &str = "usage: frost-monitor --log DIR [--state FILE] [--follow SECONDS]";
+
+struct Options {
+    log: PathBuf,
+    state: Option<PathBuf>,
+    follow: Option<Duration>,
+}
+
+fn options() -> MonitorResult<Options> {
+    let usage = || MonitorError::State(USAGE.to_string());
+    let (mut log, mut state, mut follow) = (None, None, None);
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(usage)?;
+        match flag.as_str() {
+            "--log" => log = Some(PathBuf::from(value)),
+            "--state" => state = Some(PathBuf::from(value)),
+            "--follow" => follow = Some(Duration::from_secs(value.parse().map_err(|_| usage())?)),
+            _ => return Err(usage()),
+        }
+    }
+    Ok(Options { log: log.ok_or_else(usage)?, state, follow })
+}
+
+/// Audit new entries once; returns how many alerts were raised
+fn poll(options: &Options, auditor: &mut Auditor) -> MonitorResult<usize> {
+    // The log's size is read on open, so reopen to see new entries
+    let log = MerkleLog::open(FileTileStore::new(options.log.clone()))?;
+    let alerts = auditor.catch_up(&log)?;
+
+    let mut stdout = std::io::stdout().lock();
+    for alert in &alerts {
+        let line = serde_json::to_string(alert).expect("alert serializes");
+        writeln!(stdout, "{}", line).map_err(|e| MonitorError::State(e.to_string()))?;
+    }
+    stdout.flush().map_err(|e| MonitorError::State(e.to_string()))?;
+
+    if let Some(path) = &options.state {
+        auditor.save(path)?;
+    }
+    Ok(alerts.len())
+}
+
+fn run() -> MonitorResult<usize> {
+    let options = options()?;
+    let mut auditor = match &options.state {
+        Some(path) => Auditor::load(path)?,
+        None => Auditor::new(),
+    };
+    log::info!("Auditing {} from entry {}", options.log.display(), auditor.next_index());
+
+    let raised = poll(&options, &mut auditor)?;
+    let Some(interval) = options.follow else {
+        return Ok(raised);
+    };
+    loop {
+        std::thread::sleep(interval);
+        poll(&options, &mut auditor)?;
+    }
+}
+
+fn main() {
+    env_logger::init();
+    match run() {
+        Ok(0) => {}
+        Ok(raised) => {
+            log::info!("{} alerts", raised);
+            std::process::exit(2);
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            eprintln!("frost-monitor: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Device authorization, per-device policy and rate limiting
+//!
+//! Devices present `Authorization: Bearer <token>` on every request, where
+//! the token is a session token CWT signed by the FROST group
+//! (`SessionToken::to_cwt`), base64url-encoded without padding. A request
+//! is authorized when the CWT verifies under the group key, is within its
+//! validity window, is not revoked, and the token's device is allowed by
+//! this server's policy. The bearer token alone opens nothing: each
+//! signing request must also be signed by the token's holder key, and the
+//! token's capabilities and signed policy must allow the message (see
+//! `service`).
+//!
+//! Rate limits apply to round-1 requests, one per signing session, as a
+//! token bucket per device.
+
+use crate::config::DevicePolicy;
+use crate::{ServerError, ServerResult};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use frost_core::{verify_cwt, GroupPublicKey, RevocationList, SessionToken};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Tolerated clock difference between device and server (seconds)
+pub const CLOCK_SKEW_SECS: u64 = 60;
+
+/// Devices tracked before idle rate-limit buckets are dropped
+const MAX_TRACKED_DEVICES: usize = 10_000;
+
+/// Bearer token for a CWT
+pub fn encode_bearer(cwt: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(cwt)
+}
+
+/// Device a request was authorized for
+#[derive(Debug, Clone)]
+pub struct AuthorizedDevice {
+    /// Device the token is bound to
+    pub device_id: [u8; 32],
+    /// Token presented
+    pub token_id: [u8; 16],
+    /// The verified token, whose holder key must sign each signing request
+    pub token: SessionToken,
+    /// Policy the device is held to
+    pub policy: DevicePolicy,
+}
+
+/// Checks device tokens against the group key, revocations and policy
+pub struct DeviceAuthorizer {
+    group_public_key: GroupPublicKey,
+    revocations: Option<RevocationList>,
+    policies: HashMap<[u8; 32], DevicePolicy>,
+    default_policy: Option<DevicePolicy>,
+}
+
+impl DeviceAuthorizer {
+    /// Authorizer for tokens of `group_public_key`
+    ///
+    /// Devices without an entry in `policies` get `default_policy`, or are
+    /// refused if there is none.
+    pub fn new(
+        group_public_key: GroupPublicKey,
+        policies: HashMap<[u8; 32], DevicePolicy>,
+        default_policy: Option<DevicePolicy>,
+    ) -> Self {
+        DeviceAuthorizer {
+            group_public_key,
+            revocations: None,
+            policies,
+            default_policy,
+        }
+    }
+
+    /// Replace the revocation list, if `list` supersedes the current one
+    pub fn set_revocation_list(&mut self, list: RevocationList) -> ServerResult<()> {
+        list.check_supersedes(self.revocations.as_ref(), &self.group_public_key)
+            .map_err(|e| ServerError::Config(e.to_string()))?;
+        self.revocations = Some(list);
+        Ok(())
+    }
+
+    /// Revocation list in force
+    pub fn revocation_list(&self) -> Option<&RevocationList> {
+        self.revocations.as_ref()
+    }
+
+    /// Authorize a request from its `Authorization` header
+    pub fn authorize(&self, authorization: Option<&str>, now: u64) -> ServerResult<AuthorizedDevice> {
+        let bearer = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| ServerError::Unauthorized("Missing bearer token".to_string()))?;
+        let cwt = URL_SAFE_NO_PAD.decode(bearer.trim())
+            .map_err(|_| ServerError::Unauthorized("Bearer token is not base64url".to_string()))?;
+        let token = verify_cwt(&cwt, &self.group_public_key)
+            .map_err(|e| ServerError::Unauthorized(e.to_string()))?;
+
+        if token.issued_at > now + CLOCK_SKEW_SECS {
+            return Err(ServerError::Unauthorized("Token issued in the future".to_string()));
+        }
+        if now >= token.expires_at + CLOCK_SKEW_SECS {
+            return Err(ServerError::Unauthorized("Token expired".to_string()));
+        }
+        if self.revocations.as_ref().is_some_and(|list| list.is_revoked(&token.token_id)) {
+            return Err(ServerError::Unauthorized("Token revoked".to_string()));
+        }
+
+        let policy = self.policies.get(&token.device_id)
+            .or(self.default_policy.as_ref())
+            .filter(|policy| policy.enabled)
+            .ok_or_else(|| ServerError::Forbidden(format!("Device {} not allowed", hex::encode(token.device_id))))?;
+
+        Ok(AuthorizedDevice {
+            device_id: token.device_id,
+            token_id: token.token_id,
+            token,
+            policy: policy.clone(),
+        })
+    }
+}
+
+/// Token bucket of one device
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-device token buckets
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: HashMap<[u8; 32], Bucket>,
+}
+
+impl RateLimiter {
+    /// Empty limiter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one request from the device's bucket
+    ///
+    /// The bucket holds `burst + 1` requests and refills at
+    /// `requests_per_minute`.
+    pub fn check(&mut self, device: &AuthorizedDevice, now: Instant) -> ServerResult<()> {
+        let capacity = device.policy.burst as f64 + 1.0;
+        let rate_per_sec = device.policy.requests_per_minute as f64 / 60.0;
+
+        if self.buckets.len() >= MAX_TRACKED_DEVICES && !self.buckets.contains_key(&device.device_id) {
+            self.buckets.retain(|_, b| now.saturating_duration_since(b.updated).as_secs_f64() * rate_per_sec < capacity);
+        }
+
+        let bucket = self.buckets.entry(device.device_id)
+            .or_insert(Bucket { tokens: capacity, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(ServerError::RateLimited);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use frost_core::dkg::{DkgCoordinator, DkgOutput};
+    use frost_core::session_token::KeychainAccessLevel;
+    use frost_core::{aggregate_signatures, Capabilities, RevokedTokens, SchnorrSignature, SigningRound1};
+    use rand::rngs::OsRng;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// A 2-of-3 FROST group
+    pub(crate) fn group() -> Vec<DkgOutput> {
+        DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap()
+    }
+
+    /// Threshold-sign `data` with the group's first two shares
+    pub(crate) fn sign_as_group(outputs: &[DkgOutput], data: &[u8]) -> SchnorrSignature {
+        let round1: Vec<_> = outputs[..2].iter()
+            .map(|o| SigningRound1::new(o.secret_share.participant_id, &o.secret_share, &mut OsRng))
+            .collect();
+        let commitments: Vec<_> = round1.iter().map(|r| r.commitment()).collect();
+        let round2: Vec<_> = round1.into_iter().map(|r| r.into_round2(data, &commitments).unwrap()).collect();
+        let partials: Vec<_> = round2.iter().map(|r| r.partial_signature()).collect();
+        aggregate_signatures(&round2[0].group_commitment(), &partials).unwrap()
+    }
+
+    pub(crate) fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    /// Bearer token for `device_id`, issued by the group, and the token
+    /// holding its key
+    pub(crate) fn bearer(outputs: &[DkgOutput], device_id: [u8; 32]) -> (String, SessionToken) {
+        bearer_with(outputs, device_id, true)
+    }
+
+    /// `bearer`, with or without the message signing capability
+    pub(crate) fn bearer_with(outputs: &[DkgOutput], device_id: [u8; 32], sign_message: bool) -> (String, SessionToken) {
+        bearer_for(outputs, device_id, signing_capabilities(sign_message))
+    }
+
+    /// Unlock capabilities, with or without message signing
+    pub(crate) fn signing_capabilities(sign_message: bool) -> Capabilities {
+        Capabilities {
+            device_unlock: true,
+            keychain_access: KeychainAccessLevel::None,
+            payment_limits: None,
+            code_signing: false,
+            filevault_decrypt: false,
+            sign_message,
+            custom: vec![],
+            policy: None,
+        }
+    }
+
+    /// Bearer token for `device_id` granting `capabilities`
+    pub(crate) fn bearer_for(outputs: &[DkgOutput], device_id: [u8; 32], capabilities: Capabilities) -> (String, SessionToken) {
+        let mut token = SessionToken::new(device_id, capabilities, Duration::from_secs(3600));
+        token.frost_signature = sign_as_group(outputs, &token.to_signing_data());
+        (format!("Bearer {}", encode_bearer(&token.to_cwt().unwrap())), token)
+    }
+
+    #[test]
+    fn test_authorize_tokens() {
+        let outputs = group();
+        let gpk = outputs[0].group_public_key.clone();
+        let listed = HashMap::from([([2u8; 32], DevicePolicy { enabled: false, ..DevicePolicy::default() })]);
+        let mut authorizer = DeviceAuthorizer::new(gpk.clone(), listed, Some(DevicePolicy::default()));
+
+        let (token, issued) = bearer(&outputs, [1u8; 32]);
+        let token_id = issued.token_id;
+        let device = authorizer.authorize(Some(&token), now()).unwrap();
+        assert_eq!((device.device_id, device.token_id), ([1u8; 32], token_id));
+        assert_eq!(device.token.holder_public(), issued.holder_public());
+        assert!(device.token.effective_capabilities().sign_message);
+
+        assert!(matches!(authorizer.authorize(None, now()), Err(ServerError::Unauthorized(_))));
+        assert!(matches!(authorizer.authorize(Some("Bearer !!"), now()), Err(ServerError::Unauthorized(_))));
+        assert!(authorizer.authorize(Some(&token), now() + 2 * 3600).is_err());
+
+        // A token from another group
+        let (foreign, _) = bearer(&group(), [1u8; 32]);
+        assert!(matches!(authorizer.authorize(Some(&foreign), now()), Err(ServerError::Unauthorized(_))));
+
+        // Disabled devices are refused even with a valid token
+        let (disabled, _) = bearer(&outputs, [2u8; 32]);
+        assert!(matches!(authorizer.authorize(Some(&disabled), now()), Err(ServerError::Forbidden(_))));
+
+        // Revoked tokens are refused; the list must be signed by the group
+        let revoked = RevokedTokens::from_ids(vec![token_id]);
+        let data = RevocationList::to_signing_data(1, now(), &revoked);
+        let list = RevocationList { sequence: 1, issued_at: now(), signature: sign_as_group(&outputs, &data), revoked };
+        let mut forged = list.clone();
+        forged.sequence = 2;
+        assert!(authorizer.set_revocation_list(forged).is_err());
+        authorizer.set_revocation_list(list.clone()).unwrap();
+        assert!(authorizer.set_revocation_list(list).is_err());
+        assert!(matches!(authorizer.authorize(Some(&token), now()), Err(ServerError::Unauthorized(_))));
+
+        // Without a default policy, unlisted devices are refused
+        let strict = DeviceAuthorizer::new(gpk, HashMap::new(), None);
+        let (other, _) = bearer(&outputs, [3u8; 32]);
+        assert!(matches!(strict.authorize(Some(&other), now()), Err(ServerError::Forbidden(_))));
+    }
+
+    #[test]
+    fn test_rate_limit_refills() {
+        let device = AuthorizedDevice {
+            device_id: [1u8; 32],
+            token_id: [0u8; 16],
+            token: SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600)),
+            policy: DevicePolicy { requests_per_minute: 60, burst: 2, ..DevicePolicy::default() },
+        };
+        let mut limiter = RateLimiter::new();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.check(&device, start).unwrap();
+        }
+        assert!(matches!(limiter.check(&device, start), Err(ServerError::RateLimited)));
+
+        // Other devices have their own bucket
+        let other = AuthorizedDevice { device_id: [2u8; 32], ..device.clone() };
+        assert!(limiter.check(&other, start).is_ok());
+
+        // One request per second refills
+        assert!(limiter.check(&device, start + Duration::from_millis(1100)).is_ok());
+        assert!(limiter.check(&device, start + Duration::from_millis(1200)).is_err());
+    }
+}
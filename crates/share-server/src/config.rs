@@ -0,0 +1,386 @@
+//! Configuration in the `/etc/frost/shares.conf` schema
+//!
+//! Operators run from the same file devices are provisioned with, plus a
+//! `server` section naming which share this server holds:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "public_key": "<hex group public key>",
+//!   "threshold": 2,
+//!   "shares": [
+//!     { "id": 2, "location": "Zürich, CH", "operator": "Swisscom Trust Services",
+//!       "endpoint": "https://share2.frost.swisscom.ch:8443",
+//!       "pubkey": "<hex verification share>", "certificate": "-----BEGIN CERTIFICATE-----\n..." }
+//!   ],
+//!   "failover_policy": { "timeout_ms": 5000, "retry_attempts": 3, "offline_mode": "cached_session_token" },
+//!   "server": {
+//!     "participant_id": 2,
+//!     "backend": "feitian",
+//!     "share_id": "frost-share-2",
+//!     "tls_certificate": "/etc/frost/server.crt",
+//!     "tls_private_key": "/etc/frost/server.key",
+//!     "state_dir": "/var/lib/frost"
+//!   }
+//! }
+//! ```
+//!
+//! Devices ignore the `server` section.
+
+use crate::{ServerError, ServerResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::{Capabilities, GroupPublicKey, ParticipantId, PublicKeyShare};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Default configuration file
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/frost/shares.conf";
+
+/// Schema version this server reads
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Contents of `shares.conf`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharesConfig {
+    /// Schema version
+    pub version: u32,
+    /// Group public key (hex)
+    pub public_key: String,
+    /// Signing threshold
+    pub threshold: u32,
+    /// Every share of the group
+    pub shares: Vec<ShareEntry>,
+    /// Device failover settings
+    #[serde(default)]
+    pub failover_policy: Option<FailoverPolicy>,
+    /// Settings of the share server reading this file
+    #[serde(default)]
+    pub server: Option<ServerSection>,
+}
+
+/// One share of the group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareEntry {
+    /// Participant ID
+    pub id: u32,
+    /// Location description
+    pub location: String,
+    /// Operator name
+    pub operator: String,
+    /// HTTPS endpoint URL
+    pub endpoint: String,
+    /// Verification share (hex)
+    pub pubkey: String,
+    /// TLS certificate (PEM) devices pin
+    pub certificate: String,
+}
+
+/// Device failover settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverPolicy {
+    /// Per-request timeout
+    pub timeout_ms: u64,
+    /// Retries after a transient failure
+    pub retry_attempts: u32,
+    /// What devices do when no quorum is reachable
+    pub offline_mode: String,
+}
+
+/// Secure element backend holding the share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Feitian HSM
+    Feitian,
+    /// Nations Technologies secure element
+    Nations,
+    /// GigaDevice GD32 with TrustZone
+    GigaDevice,
+    /// Software emulation; development and staging only
+    Software,
+}
+
+/// Share server settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerSection {
+    /// Participant whose share this server holds
+    pub participant_id: u32,
+    /// Listen address; defaults to all interfaces on the endpoint's port
+    #[serde(default)]
+    pub listen: Option<SocketAddr>,
+    /// Secure element backend
+    pub backend: Backend,
+    /// Share identifier within the secure element
+    pub share_id: String,
+    /// Share file for the software backend (`participant_id`, `value`, `blinding`)
+    #[serde(default)]
+    pub software_share_file: Option<PathBuf>,
+    /// TLS certificate chain (PEM); must match this share's `certificate`
+    pub tls_certificate: PathBuf,
+    /// TLS private key (PEM)
+    pub tls_private_key: PathBuf,
+    /// Directory for the session journal and transparency log
+    pub state_dir: PathBuf,
+    /// FROST-signed revocation list (JSON), checked against tokens and served to devices
+    #[serde(default)]
+    pub revocation_list: Option<PathBuf>,
+    /// Seconds a signing session stays open after round 1
+    #[serde(default = "default_session_ttl")]
+    pub session_ttl_secs: u64,
+    /// Policy for devices not listed in `devices`; absent refuses them
+    #[serde(default)]
+    pub default_device_policy: Option<DevicePolicy>,
+    /// Per-device policy, by hex device ID
+    #[serde(default)]
+    pub devices: HashMap<String, DevicePolicy>,
+}
+
+fn default_session_ttl() -> u64 {
+    120
+}
+
+/// What one device may do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicePolicy {
+    /// Whether the device may sign at all
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Sustained signing requests per minute
+    pub requests_per_minute: u32,
+    /// Requests allowed in a burst above the sustained rate
+    #[serde(default)]
+    pub burst: u32,
+    /// Signing sessions the device may have open at once
+    #[serde(default = "default_max_open_sessions")]
+    pub max_open_sessions: usize,
+    /// Session tokens the device may have co-signed; absent refuses issuance
+    #[serde(default)]
+    pub token_issuance: Option<TokenIssuance>,
+}
+
+/// Bounds on the session tokens a device may have co-signed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenIssuance {
+    /// Most a token may grant; a policy here must be part of every token's
+    pub capabilities: Capabilities,
+    /// Longest token lifetime (seconds)
+    #[serde(default = "default_max_token_lifetime")]
+    pub max_lifetime_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_open_sessions() -> usize {
+    4
+}
+
+fn default_max_token_lifetime() -> u64 {
+    24 * 3600
+}
+
+impl Default for DevicePolicy {
+    fn default() -> Self {
+        DevicePolicy {
+            enabled: true,
+            requests_per_minute: 60,
+            burst: 10,
+            max_open_sessions: default_max_open_sessions(),
+            token_issuance: None,
+        }
+    }
+}
+
+impl SharesConfig {
+    /// Read and validate a configuration file
+    pub fn load(path: &Path) -> ServerResult<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// Parse and validate configuration text
+    pub fn parse(text: &str) -> ServerResult<Self> {
+        let config: SharesConfig = serde_json::from_str(text)
+            .map_err(|e| ServerError::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> ServerResult<()> {
+        if self.version != CONFIG_VERSION {
+            return Err(ServerError::Config(format!("Unsupported version {}", self.version)));
+        }
+        let n = self.shares.len() as u32;
+        if self.threshold == 0 || self.threshold > n {
+            return Err(ServerError::Config(format!("Invalid threshold: t={}, n={}", self.threshold, n)));
+        }
+        let mut ids: Vec<u32> = self.shares.iter().map(|s| s.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() != self.shares.len() || ids.contains(&0) {
+            return Err(ServerError::Config("Share IDs must be distinct and nonzero".to_string()));
+        }
+        self.group_public_key()?;
+
+        if let Some(server) = &self.server {
+            self.own_share()?;
+            if server.backend == Backend::Software && server.software_share_file.is_none() {
+                return Err(ServerError::Config("Software backend needs software_share_file".to_string()));
+            }
+            for device in server.devices.keys() {
+                parse_device_id(device)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Group key with every share's verification key
+    pub fn group_public_key(&self) -> ServerResult<GroupPublicKey> {
+        let participant_shares = self.shares.iter()
+            .map(|s| Ok(PublicKeyShare {
+                participant_id: ParticipantId(s.id),
+                public_key: parse_point(&s.pubkey)?,
+            }))
+            .collect::<ServerResult<Vec<_>>>()?;
+
+        Ok(GroupPublicKey {
+            public_key: parse_point(&self.public_key)?,
+            participant_shares,
+            threshold: self.threshold,
+            num_participants: self.shares.len() as u32,
+        })
+    }
+
+    /// Server section; required to run a server
+    pub fn server(&self) -> ServerResult<&ServerSection> {
+        self.server.as_ref().ok_or_else(|| ServerError::Config("Missing server section".to_string()))
+    }
+
+    /// Entry of the share this server holds
+    pub fn own_share(&self) -> ServerResult<&ShareEntry> {
+        let id = self.server()?.participant_id;
+        self.shares.iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| ServerError::Config(format!("No share {} in shares", id)))
+    }
+
+    /// Address to listen on
+    pub fn listen_addr(&self) -> ServerResult<SocketAddr> {
+        if let Some(addr) = self.server()?.listen {
+            return Ok(addr);
+        }
+        let endpoint = &self.own_share()?.endpoint;
+        let port = endpoint.rsplit_once(':')
+            .and_then(|(_, port)| port.split('/').next())
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(443);
+        Ok(SocketAddr::from(([0, 0, 0, 0], port)))
+    }
+
+    /// Device policies keyed by device ID
+    pub fn device_policies(&self) -> ServerResult<HashMap<[u8; 32], DevicePolicy>> {
+        self.server()?.devices.iter()
+            .map(|(id, policy)| Ok((parse_device_id(id)?, policy.clone())))
+            .collect()
+    }
+}
+
+/// Decode hex with an optional `0x` prefix
+pub fn parse_hex(text: &str) -> ServerResult<Vec<u8>> {
+    hex::decode(text.strip_prefix("0x").unwrap_or(text))
+        .map_err(|e| ServerError::Config(format!("Invalid hex {:?}: {}", text, e)))
+}
+
+fn parse_bytes32(text: &str) -> ServerResult<[u8; 32]> {
+    parse_hex(text)?.try_into()
+        .map_err(|_| ServerError::Config(format!("Expected 32 bytes: {:?}", text)))
+}
+
+fn parse_point(text: &str) -> ServerResult<CompressedRistretto> {
+    let point = CompressedRistretto(parse_bytes32(text)?);
+    point.decompress()
+        .ok_or_else(|| ServerError::Config(format!("Not a ristretto255 point: {:?}", text)))?;
+    Ok(point)
+}
+
+fn parse_device_id(text: &str) -> ServerResult<[u8; 32]> {
+    parse_bytes32(text)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+
+    pub(crate) fn point_hex(secret: u64) -> String {
+        hex::encode((Scalar::from(secret) * RISTRETTO_BASEPOINT_POINT).compress().as_bytes())
+    }
+
+    fn example() -> serde_json::Value {
+        serde_json::json!({
+            "version": 1,
+            "public_key": point_hex(7),
+            "threshold": 2,
+            "shares": ([1, 2, 3].map(|id| serde_json::json!({
+                "id": id,
+                "location": "test",
+                "operator": format!("operator {}", id),
+                "endpoint": format!("https://share{}.example.com:8443", id),
+                "pubkey": format!("0x{}", point_hex(id)),
+                "certificate": "",
+            }))),
+            "failover_policy": { "timeout_ms": 5000, "retry_attempts": 3, "offline_mode": "cached_session_token" },
+            "server": {
+                "participant_id": 2,
+                "backend": "feitian",
+                "share_id": "frost-share-2",
+                "tls_certificate": "/etc/frost/server.crt",
+                "tls_private_key": "/etc/frost/server.key",
+                "state_dir": "/var/lib/frost",
+                "devices": { (hex::encode([9u8; 32])): { "requests_per_minute": 6, "enabled": false } },
+            },
+        })
+    }
+
+    #[test]
+    fn test_parse_documented_schema() {
+        let config = SharesConfig::parse(&example().to_string()).unwrap();
+        let gpk = config.group_public_key().unwrap();
+        assert_eq!(gpk.participant_shares.len(), 3);
+        assert_eq!(config.own_share().unwrap().operator, "operator 2");
+        assert_eq!(config.listen_addr().unwrap().port(), 8443);
+        assert_eq!(config.server().unwrap().session_ttl_secs, 120);
+
+        let policies = config.device_policies().unwrap();
+        let policy = &policies[&[9u8; 32]];
+        assert!(!policy.enabled);
+        assert_eq!((policy.requests_per_minute, policy.burst, policy.max_open_sessions), (6, 0, 4));
+        assert!(policy.token_issuance.is_none());
+
+        // Devices read the same file without a server section
+        let mut device_view = example();
+        device_view.as_object_mut().unwrap().remove("server");
+        assert!(SharesConfig::parse(&device_view.to_string()).unwrap().server().is_err());
+    }
+
+    #[test]
+    fn test_invalid_configs_rejected() {
+        let broken = |f: fn(&mut serde_json::Value)| {
+            let mut config = example();
+            f(&mut config);
+            SharesConfig::parse(&config.to_string())
+        };
+        assert!(broken(|c| c["version"] = 2.into()).is_err());
+        assert!(broken(|c| c["threshold"] = 4.into()).is_err());
+        assert!(broken(|c| c["shares"][1]["id"] = 1.into()).is_err());
+        assert!(broken(|c| c["public_key"] = "02a1b2".into()).is_err());
+        assert!(broken(|c| c["server"]["participant_id"] = 5.into()).is_err());
+        assert!(broken(|c| c["server"]["backend"] = "software".into()).is_err());
+        assert!(broken(|c| c["server"]["devices"] = serde_json::json!({ "abcd": { "requests_per_minute": 1 } })).is_err());
+    }
+}
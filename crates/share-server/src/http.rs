@@ -0,0 +1,363 @@
+//! HTTP/2 over mutual TLS in front of `ShareService`
+//!
+//! Request and response bodies are CBOR (`frost_core::https`). Errors are
+//! answered with `ErrorResponse` and the status from `ServerError::status`.
+//!
+//! Device client certificates are self-signed (`TlsIdentity`), so any
+//! certificate is accepted as long as the client proves possession of its
+//! key; authorization rests on the bearer token and its holder signatures.
+
+use crate::service::ShareService;
+use crate::{ServerError, ServerResult};
+use bytes::Bytes;
+use frost_core::https::{
+    cert_fingerprint, from_cbor, to_cbor, ErrorResponse, ATTESTATION_PATH, CBOR_CONTENT_TYPE,
+    HEALTH_PATH, ISSUE_TOKEN_PATH, REVOCATIONS_PATH, ROUND1_PATH, ROUND2_PATH,
+};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{DigitallySignedStruct, DistinguishedName, ServerConfig, SignatureScheme};
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+/// Largest request body accepted
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Requires a client certificate and proof of its key, trusting any
+#[derive(Debug)]
+struct DeviceCertVerifier {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ClientCertVerifier for DeviceCertVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// TLS 1.3 server configuration requiring device client certificates
+pub fn tls_config(cert_chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> ServerResult<ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| ServerError::Config(e.to_string()))?
+        .with_client_cert_verifier(Arc::new(DeviceCertVerifier { provider }))
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| ServerError::Config(format!("TLS certificate: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(config)
+}
+
+/// Load the certificate chain and key, checking the chain against the pin
+///
+/// Devices pin the certificate listed for this share in `shares.conf`, so
+/// serving any other would lock every device out.
+pub fn load_tls_config(cert_path: &Path, key_path: &Path, pinned_pem: &str) -> ServerResult<ServerConfig> {
+    let config_error = |path: &Path, e: rustls::pki_types::pem::Error| {
+        ServerError::Config(format!("{}: {}", path.display(), e))
+    };
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| config_error(cert_path, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| config_error(cert_path, e))?;
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| config_error(key_path, e))?;
+
+    let served = chain.first()
+        .ok_or_else(|| ServerError::Config(format!("{}: no certificate", cert_path.display())))?;
+    if pinned_pem.trim().is_empty() {
+        log::warn!("No certificate listed for this share; devices cannot pin it");
+    } else {
+        let pinned = CertificateDer::from_pem_slice(pinned_pem.as_bytes())
+            .map_err(|e| ServerError::Config(format!("Share certificate: {}", e)))?;
+        if cert_fingerprint(&pinned) != cert_fingerprint(served) {
+            return Err(ServerError::Config(format!(
+                "{} is not the certificate devices pin for this share",
+                cert_path.display(),
+            )));
+        }
+    }
+    tls_config(chain, key)
+}
+
+fn cbor_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Full<Bytes>> {
+    let bytes = to_cbor(body).unwrap_or_default();
+    let mut response = Response::new(Full::new(Bytes::from(bytes)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(hyper::header::CONTENT_TYPE, hyper::header::HeaderValue::from_static(CBOR_CONTENT_TYPE));
+    response
+}
+
+fn answer<T: Serialize>(result: ServerResult<T>) -> Response<Full<Bytes>> {
+    match result {
+        Ok(body) => cbor_response(StatusCode::OK, &body),
+        Err(e) => {
+            let status = StatusCode::from_u16(e.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            if status.is_server_error() {
+                log::error!("{}", e);
+            }
+            cbor_response(status, &ErrorResponse { error: e.to_string() })
+        }
+    }
+}
+
+async fn decode<T: serde::de::DeserializeOwned>(body: Incoming) -> ServerResult<T> {
+    let bytes = Limited::new(body, MAX_BODY_BYTES).collect().await
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?
+        .to_bytes();
+    from_cbor(&bytes).map_err(|e| ServerError::BadRequest(e.to_string()))
+}
+
+/// Route one request to the service
+pub async fn handle(service: &ShareService, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let (parts, body) = request.into_parts();
+    let authorization = parts.headers.get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let authorization = authorization.as_deref();
+
+    match (&parts.method, parts.uri.path()) {
+        (&Method::POST, ROUND1_PATH) => match decode(body).await {
+            Ok(request) => answer(service.round1(authorization, request).await),
+            Err(e) => answer::<()>(Err(e)),
+        },
+        (&Method::POST, ROUND2_PATH) => match decode(body).await {
+            Ok(request) => answer(service.round2(authorization, request).await),
+            Err(e) => answer::<()>(Err(e)),
+        },
+        (&Method::POST, ISSUE_TOKEN_PATH) => match decode(body).await {
+            Ok(request) => answer(service.issue_token(authorization, request).await),
+            Err(e) => answer::<()>(Err(e)),
+        },
+        (&Method::GET, HEALTH_PATH) => answer(Ok(service.health().await)),
+        (&Method::POST, REVOCATIONS_PATH) => match decode(body).await {
+            Ok(request) => answer(service.revocations(&request)),
+            Err(e) => answer::<()>(Err(e)),
+        },
+        (_, ATTESTATION_PATH) => answer::<()>(Err(ServerError::NotFound(
+            "Share attestation is not offered by this backend".to_string(),
+        ))),
+        (_, path) => answer::<()>(Err(ServerError::NotFound(path.to_string()))),
+    }
+}
+
+/// Accept connections until `shutdown` completes
+pub async fn serve(
+    listener: TcpListener,
+    tls: ServerConfig,
+    service: Arc<ShareService>,
+    shutdown: impl Future<Output = ()>,
+) -> ServerResult<()> {
+    let acceptor = TlsAcceptor::from(Arc::new(tls));
+    tokio::pin!(shutdown);
+
+    loop {
+        let (tcp, peer) = tokio::select! {
+            accepted = listener.accept() => accepted.map_err(|e| ServerError::Storage(e.to_string()))?,
+            _ = &mut shutdown => return Ok(()),
+        };
+        let (acceptor, service) = (acceptor.clone(), service.clone());
+        tokio::spawn(async move {
+            let tls = match acceptor.accept(tcp).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    log::debug!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+            let handler = service_fn(move |request| {
+                let service = service.clone();
+                async move { Ok::<_, std::convert::Infallible>(handle(&service, request).await) }
+            });
+            if let Err(e) = hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(tls), handler)
+                .await
+            {
+                log::debug!("Connection from {} closed: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::tests::{bearer, group};
+    use crate::config::{DevicePolicy, TokenIssuance};
+    use crate::service::tests::service;
+    use frost_core::https::{HttpsConfig, HttpsTransport, TlsIdentity};
+    use frost_core::{
+        Capabilities, HybridFROSTDevice, RefreshPolicy, RefreshScheduler, RemoteShareEndpoint, ParticipantId, RequestContext,
+        SessionToken, TokenRequest,
+    };
+    use rustls::pki_types::PrivatePkcs8KeyDer;
+    use std::time::Duration;
+
+    /// Share servers for shares 2 and 3 of `outputs`, as device endpoints
+    async fn share_servers(outputs: &[frost_core::dkg::DkgOutput], policy: DevicePolicy) -> Vec<RemoteShareEndpoint> {
+        let mut endpoints = Vec::new();
+        for index in [1, 2] {
+            let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            let config = tls_config(
+                vec![certified.cert.der().clone()],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der())),
+            ).unwrap();
+
+            let (service, _) = service(outputs, index, policy.clone()).await;
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(serve(listener, config, Arc::new(service), std::future::pending()));
+
+            endpoints.push(RemoteShareEndpoint {
+                participant_id: ParticipantId(index as u32 + 1),
+                location: "localhost".to_string(),
+                operator: format!("share {}", index + 1),
+                endpoint_url: format!("https://localhost:{}", port),
+                cert_fingerprint: cert_fingerprint(certified.cert.der()),
+                available: true,
+                avg_response_time: 0,
+            });
+        }
+        endpoints
+    }
+
+    fn transport(token: Option<(String, SessionToken)>) -> HttpsTransport {
+        let identity = TlsIdentity::from_seed(&[7u8; 32], &[1u8; 32]).unwrap();
+        let config = HttpsConfig { timeout: Duration::from_secs(2), retry_attempts: 0, retry_backoff: Duration::ZERO };
+        let transport = HttpsTransport::new(identity, config);
+        match token {
+            Some((bearer, token)) => transport.with_bearer_token(bearer, token),
+            None => transport,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_device_signs_with_share_servers() {
+        let outputs = group();
+        let group_pk = outputs[0].group_public_key.clone();
+        let endpoints = share_servers(&outputs, DevicePolicy::default()).await;
+        let (bearer, token) = bearer(&outputs, [1u8; 32]);
+
+        let mut device = HybridFROSTDevice::with_transport(
+            [1u8; 32],
+            Some(outputs[0].secret_share.clone()),
+            group_pk.clone(),
+            endpoints.clone(),
+            transport(bearer.strip_prefix("Bearer ").map(|b| (b.to_string(), token))),
+        );
+        let message = b"signed with remote share servers";
+        let signature = device.sign(message).await.unwrap().into_schnorr().unwrap();
+        assert!(group_pk.verify_signature(message, &signature));
+
+        // Without a token the servers refuse to co-sign
+        let mut anonymous = HybridFROSTDevice::with_transport(
+            [1u8; 32],
+            Some(outputs[0].secret_share.clone()),
+            group_pk.clone(),
+            endpoints,
+            transport(None),
+        );
+        assert!(anonymous.sign(message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_device_refreshes_tokens_from_share_servers() {
+        let outputs = group();
+        let group_pk = outputs[0].group_public_key.clone();
+        let issuance = TokenIssuance {
+            capabilities: Capabilities { sign_message: true, ..Capabilities::default() },
+            max_lifetime_secs: 8 * 3600,
+        };
+        let policy = DevicePolicy { token_issuance: Some(issuance), ..DevicePolicy::default() };
+        let endpoints = share_servers(&outputs, policy).await;
+        let device = |device_id: [u8; 32], endpoints: &[RemoteShareEndpoint]| {
+            let (bearer, token) = bearer(&outputs, [1u8; 32]);
+            HybridFROSTDevice::with_transport(
+                device_id,
+                Some(outputs[0].secret_share.clone()),
+                group_pk.clone(),
+                endpoints.to_vec(),
+                transport(bearer.strip_prefix("Bearer ").map(|b| (b.to_string(), token))),
+            )
+        };
+        let refresh = RefreshPolicy { target_tokens: 2, ..RefreshPolicy::default() };
+        let now = SessionToken::new([0u8; 32], Default::default(), Duration::ZERO).issued_at;
+
+        let mut holder = device([1u8; 32], &endpoints);
+        let mut scheduler = RefreshScheduler::new(refresh.clone());
+        assert_eq!(holder.refresh_with(&mut scheduler, now).await.unwrap(), 2);
+        let context = RequestContext { now, ..RequestContext::default() };
+        assert!(holder.token_cache().has_valid_token(&TokenRequest::sign_message(b"offline"), &context));
+
+        // Tokens for another device, or outliving the bound, are not co-signed
+        let mut impostor = device([2u8; 32], &endpoints);
+        assert!(impostor.refresh_with(&mut RefreshScheduler::new(refresh.clone()), now).await.is_err());
+        let long_lived = RefreshPolicy { target_tokens: 3, token_lifetime: Duration::from_secs(9 * 3600), ..refresh.clone() };
+        assert!(holder.refresh_with(&mut RefreshScheduler::new(long_lived), now).await.is_err());
+        assert_eq!(holder.token_cache().token_count(), 2);
+
+        // Without an issuance policy the servers issue nothing
+        let strict = share_servers(&outputs, DevicePolicy::default()).await;
+        let mut refused = device([1u8; 32], &strict);
+        assert!(refused.refresh_with(&mut RefreshScheduler::new(refresh), now).await.is_err());
+        assert_eq!(refused.token_cache().token_count(), 0);
+    }
+
+    #[test]
+    fn test_served_certificate_must_match_pin() {
+        let dir = std::env::temp_dir().join(format!("frost-share-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert_path, key_path) = (dir.join("server.crt"), dir.join("server.key"));
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        assert!(load_tls_config(&cert_path, &key_path, &certified.cert.pem()).is_ok());
+        assert!(load_tls_config(&cert_path, &key_path, "").is_ok());
+        let other = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        assert!(matches!(load_tls_config(&cert_path, &key_path, &other.cert.pem()), Err(ServerError::Config(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
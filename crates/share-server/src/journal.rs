@@ -0,0 +1,370 @@
+//! Durable journal of signing sessions
+//!
+//! A share's nonces must be used for exactly one round-2 answer: two
+//! partial signatures over the same nonces but different commitment sets
+//! reveal the share. The journal records each session's progress and is
+//! written through before the secure element is asked to act:
+//!
+//! ```text
+//! round 1:  (absent) ──SE round 1──► Committed            replays return the same commitment
+//! round 2:  Committed ──persist──► Signing ──SE round 2──► Signed
+//!                                                         replays with the same commitments
+//!                                                         return the same partial signature
+//! ```
+//!
+//! A session found in `Signing` after a restart may have spent its nonces
+//! without the partial being recorded, so it is never resumed. Sessions
+//! expire `ttl_secs` after round 1; the service has the secure element
+//! discard the nonces of those pruned unsigned.
+
+use crate::{ServerError, ServerResult};
+use frost_core::signing::{PartialSignature, SigningCommitment};
+use frost_core::SessionId;
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Persistent storage for the serialized journal
+pub trait JournalStore: Send {
+    /// Load the serialized journal, `None` if nothing was stored yet
+    fn load(&self) -> ServerResult<Option<Vec<u8>>>;
+
+    /// Durably replace the serialized journal
+    fn store(&mut self, data: &[u8]) -> ServerResult<()>;
+}
+
+/// Journal in a file, replaced atomically
+pub struct FileJournalStore {
+    path: PathBuf,
+}
+
+impl FileJournalStore {
+    /// Journal at `path`
+    pub fn new(path: PathBuf) -> Self {
+        FileJournalStore { path }
+    }
+}
+
+fn storage_error(e: std::io::Error) -> ServerError {
+    ServerError::Storage(e.to_string())
+}
+
+impl JournalStore for FileJournalStore {
+    fn load(&self) -> ServerResult<Option<Vec<u8>>> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    fn store(&mut self, data: &[u8]) -> ServerResult<()> {
+        // Write aside, sync, rename over, then sync the directory entry
+        let tmp = self.path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp).map_err(storage_error)?;
+        file.write_all(data).map_err(storage_error)?;
+        file.sync_all().map_err(storage_error)?;
+        std::fs::rename(&tmp, &self.path).map_err(storage_error)?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::File::open(dir).and_then(|d| d.sync_all()).map_err(storage_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// In-memory `JournalStore` for tests; clones share the stored data
+#[derive(Debug, Clone, Default)]
+pub struct MemoryJournalStore {
+    data: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl JournalStore for MemoryJournalStore {
+    fn load(&self) -> ServerResult<Option<Vec<u8>>> {
+        Ok(self.data.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn store(&mut self, data: &[u8]) -> ServerResult<()> {
+        *self.data.lock().unwrap_or_else(|e| e.into_inner()) = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+/// Progress of one session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SessionState {
+    /// Commitment issued; the secure element holds the nonces
+    Committed,
+    /// Round 2 started for these commitments; the nonces may be spent
+    Signing { commitments_digest: [u8; 32] },
+    /// Partial signature issued for these commitments
+    Signed { commitments_digest: [u8; 32], partial: PartialSignature },
+}
+
+/// One signing session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionRecord {
+    session_id: SessionId,
+    device_id: [u8; 32],
+    message_hash: [u8; 32],
+    commitment: SigningCommitment,
+    opened_at: u64,
+    state: SessionState,
+}
+
+/// Signing sessions of one share, persisted on every change
+pub struct SessionJournal<J: JournalStore> {
+    store: J,
+    ttl_secs: u64,
+    sessions: HashMap<SessionId, SessionRecord>,
+}
+
+/// Digest binding a round-2 request to its commitment set
+fn commitments_digest(commitments: &[SigningCommitment]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"FROST-SHARE-COMMITMENTS-v1");
+    for c in commitments {
+        hasher.update(c.participant_id.as_u32().to_le_bytes());
+        hasher.update(c.hiding.as_bytes());
+        hasher.update(c.binding.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+impl<J: JournalStore> SessionJournal<J> {
+    /// Load the journal from `store`; sessions expire `ttl_secs` after round 1
+    pub fn open(store: J, ttl_secs: u64) -> ServerResult<Self> {
+        let records: Vec<SessionRecord> = match store.load()? {
+            Some(data) => serde_json::from_slice(&data)
+                .map_err(|e| ServerError::Storage(format!("Corrupt session journal: {}", e)))?,
+            None => Vec::new(),
+        };
+        Ok(SessionJournal {
+            store,
+            ttl_secs,
+            sessions: records.into_iter().map(|r| (r.session_id, r)).collect(),
+        })
+    }
+
+    fn persist(&mut self) -> ServerResult<()> {
+        let mut records: Vec<&SessionRecord> = self.sessions.values().collect();
+        records.sort_by_key(|r| (r.opened_at, r.session_id));
+        let data = serde_json::to_vec(&records)
+            .map_err(|e| ServerError::Storage(e.to_string()))?;
+        self.store.store(&data)
+    }
+
+    fn expired(&self, record: &SessionRecord, now: u64) -> bool {
+        now >= record.opened_at + self.ttl_secs
+    }
+
+    /// Drop sessions past their lifetime
+    ///
+    /// Returns the dropped sessions that were never signed, whose nonces
+    /// the secure element may still hold.
+    pub fn prune(&mut self, now: u64) -> ServerResult<Vec<SessionId>> {
+        let expired: Vec<SessionId> = self.sessions.values()
+            .filter(|r| self.expired(r, now))
+            .map(|r| r.session_id)
+            .collect();
+        if expired.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let unsigned = expired.iter()
+            .filter_map(|id| self.sessions.remove(id))
+            .filter(|r| !matches!(r.state, SessionState::Signed { .. }))
+            .map(|r| r.session_id)
+            .collect();
+        self.persist()?;
+        Ok(unsigned)
+    }
+
+    /// Sessions `device_id` has open (committed, not yet signed)
+    pub fn open_sessions(&self, device_id: &[u8; 32], now: u64) -> usize {
+        self.sessions.values()
+            .filter(|r| r.device_id == *device_id && matches!(r.state, SessionState::Committed))
+            .filter(|r| !self.expired(r, now))
+            .count()
+    }
+
+    fn session(&self, session_id: &SessionId, device_id: &[u8; 32], now: u64) -> ServerResult<Option<&SessionRecord>> {
+        match self.sessions.get(session_id) {
+            Some(r) if r.device_id != *device_id => Err(ServerError::Conflict("Session belongs to another device".to_string())),
+            Some(r) if self.expired(r, now) => Err(ServerError::Conflict("Signing session expired".to_string())),
+            other => Ok(other),
+        }
+    }
+
+    /// Check a round-1 request; `Some` is the commitment already issued for it
+    pub fn begin_round1(
+        &self,
+        session_id: &SessionId,
+        device_id: &[u8; 32],
+        message_hash: &[u8; 32],
+        now: u64,
+    ) -> ServerResult<Option<SigningCommitment>> {
+        match self.session(session_id, device_id, now)? {
+            None => Ok(None),
+            Some(r) if r.message_hash == *message_hash && matches!(r.state, SessionState::Committed) => {
+                Ok(Some(r.commitment.clone()))
+            }
+            Some(_) => Err(ServerError::Conflict("Signing session already open".to_string())),
+        }
+    }
+
+    /// Record the commitment the secure element issued for a new session
+    pub fn record_commitment(
+        &mut self,
+        session_id: SessionId,
+        device_id: [u8; 32],
+        message_hash: [u8; 32],
+        commitment: SigningCommitment,
+        now: u64,
+    ) -> ServerResult<()> {
+        self.sessions.insert(session_id, SessionRecord {
+            session_id,
+            device_id,
+            message_hash,
+            commitment,
+            opened_at: now,
+            state: SessionState::Committed,
+        });
+        self.persist()
+    }
+
+    /// Check a round-2 request and durably mark the nonces as in use
+    ///
+    /// `Some` is the partial signature already issued for the same request.
+    /// On `None` the caller runs round 2 and calls `record_partial`.
+    pub fn begin_round2(
+        &mut self,
+        session_id: &SessionId,
+        device_id: &[u8; 32],
+        message_hash: &[u8; 32],
+        commitments: &[SigningCommitment],
+        now: u64,
+    ) -> ServerResult<Option<PartialSignature>> {
+        let record = self.session(session_id, device_id, now)?
+            .ok_or_else(|| ServerError::Conflict("Unknown signing session".to_string()))?;
+        if record.message_hash != *message_hash {
+            return Err(ServerError::BadRequest("Message differs from round 1".to_string()));
+        }
+        if !commitments.contains(&record.commitment) {
+            return Err(ServerError::BadRequest("Own commitment missing from round 2".to_string()));
+        }
+
+        let digest = commitments_digest(commitments);
+        match &record.state {
+            SessionState::Committed => {}
+            SessionState::Signed { commitments_digest, partial } if *commitments_digest == digest => {
+                return Ok(Some(partial.clone()));
+            }
+            SessionState::Signed { .. } => {
+                return Err(ServerError::Conflict("Session already signed with other commitments".to_string()));
+            }
+            SessionState::Signing { .. } => {
+                return Err(ServerError::Conflict("Signing session interrupted; nonces discarded".to_string()));
+            }
+        }
+
+        if let Some(record) = self.sessions.get_mut(session_id) {
+            record.state = SessionState::Signing { commitments_digest: digest };
+        }
+        self.persist()?;
+        Ok(None)
+    }
+
+    /// Record the partial signature issued for a session
+    pub fn record_partial(&mut self, session_id: &SessionId, partial: PartialSignature) -> ServerResult<()> {
+        let record = self.sessions.get_mut(session_id)
+            .ok_or_else(|| ServerError::Conflict("Unknown signing session".to_string()))?;
+        let SessionState::Signing { commitments_digest } = record.state else {
+            return Err(ServerError::Conflict("Round 2 was not started".to_string()));
+        };
+        record.state = SessionState::Signed { commitments_digest, partial };
+        self.persist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+    use frost_core::ParticipantId;
+
+    fn commitment(id: u32) -> SigningCommitment {
+        let point = |k: u64| (Scalar::from(k) * RISTRETTO_BASEPOINT_POINT).compress();
+        SigningCommitment {
+            participant_id: ParticipantId(id),
+            hiding: point(id as u64),
+            binding: point(id as u64 + 100),
+        }
+    }
+
+    fn partial() -> PartialSignature {
+        PartialSignature { participant_id: ParticipantId(2), z: Scalar::from(5u64) }
+    }
+
+    const DEVICE: [u8; 32] = [1u8; 32];
+    const HASH: [u8; 32] = [2u8; 32];
+
+    #[test]
+    fn test_replays_and_conflicts() {
+        let mut journal = SessionJournal::open(MemoryJournalStore::default(), 60).unwrap();
+        let session = [9u8; 32];
+
+        assert!(journal.begin_round1(&session, &DEVICE, &HASH, 100).unwrap().is_none());
+        journal.record_commitment(session, DEVICE, HASH, commitment(2), 100).unwrap();
+        assert_eq!(journal.begin_round1(&session, &DEVICE, &HASH, 101).unwrap(), Some(commitment(2)));
+        assert!(matches!(journal.begin_round1(&session, &DEVICE, &[3u8; 32], 101), Err(ServerError::Conflict(_))));
+        assert!(matches!(journal.begin_round1(&session, &[4u8; 32], &HASH, 101), Err(ServerError::Conflict(_))));
+        assert_eq!(journal.open_sessions(&DEVICE, 101), 1);
+
+        let commitments = [commitment(1), commitment(2)];
+        assert!(matches!(journal.begin_round2(&session, &DEVICE, &HASH, &[commitment(1)], 102), Err(ServerError::BadRequest(_))));
+        assert!(matches!(journal.begin_round2(&session, &DEVICE, &[3u8; 32], &commitments, 102), Err(ServerError::BadRequest(_))));
+        assert!(journal.begin_round2(&session, &DEVICE, &HASH, &commitments, 102).unwrap().is_none());
+        journal.record_partial(&session, partial()).unwrap();
+        assert_eq!(journal.open_sessions(&DEVICE, 102), 0);
+
+        // The same request gets the same answer; another commitment set does not
+        let replay = journal.begin_round2(&session, &DEVICE, &HASH, &commitments, 103).unwrap().unwrap();
+        assert_eq!(replay.z, partial().z);
+        let other = [commitment(3), commitment(2)];
+        assert!(matches!(journal.begin_round2(&session, &DEVICE, &HASH, &other, 103), Err(ServerError::Conflict(_))));
+        assert!(journal.begin_round1(&session, &DEVICE, &HASH, 103).is_err());
+
+        // Sessions expire; only unsigned ones still hold nonces
+        let unsigned = [7u8; 32];
+        journal.record_commitment(unsigned, DEVICE, HASH, commitment(2), 101).unwrap();
+        assert!(journal.begin_round2(&session, &DEVICE, &HASH, &commitments, 160).is_err());
+        assert!(journal.prune(160).unwrap().is_empty());
+        assert!(journal.begin_round1(&session, &DEVICE, &HASH, 160).unwrap().is_none());
+        assert_eq!(journal.prune(161).unwrap(), vec![unsigned]);
+        assert!(journal.prune(161).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interrupted_round2_never_resumes() {
+        let store = MemoryJournalStore::default();
+        let session = [9u8; 32];
+        let commitments = [commitment(1), commitment(2)];
+        {
+            let mut journal = SessionJournal::open(store.clone(), 60).unwrap();
+            journal.record_commitment(session, DEVICE, HASH, commitment(2), 100).unwrap();
+            assert!(journal.begin_round2(&session, &DEVICE, &HASH, &commitments, 101).unwrap().is_none());
+            // Crash before the partial is recorded
+        }
+
+        let mut journal = SessionJournal::open(store, 60).unwrap();
+        assert!(matches!(
+            journal.begin_round2(&session, &DEVICE, &HASH, &commitments, 102),
+            Err(ServerError::Conflict(_)),
+        ));
+        assert!(journal.begin_round1(&session, &DEVICE, &HASH, 102).is_err());
+    }
+}
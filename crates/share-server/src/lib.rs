@@ -0,0 +1,100 @@
+//! Remote share server for FROST RoT
+//!
+//! Holds one participant's share in a secure element and co-signs for
+//! devices over the share API (`frost_core::https`):
+//!
+//! ```text
+//! device ──mTLS/HTTP2──► http ──► service ──► auth     (CWT bearer, policy, rate limit)
+//!                                    │    ──► journal  (exactly-once nonces)
+//!                                    │    ──► SecureElement (round 1 / round 2)
+//!                                    └──────► transparency log (every request)
+//! ```
+//!
+//! Configured from the `/etc/frost/shares.conf` schema; see `config`.
+
+#![warn(missing_docs)]
+
+pub mod config;
+pub mod auth;
+pub mod journal;
+pub mod transparency;
+pub mod service;
+pub mod http;
+
+pub use config::{SharesConfig, ServerSection, Backend, DevicePolicy, TokenIssuance};
+pub use auth::{DeviceAuthorizer, AuthorizedDevice, RateLimiter, encode_bearer};
+pub use journal::{SessionJournal, JournalStore, FileJournalStore, MemoryJournalStore};
+pub use transparency::{TransparencyLog, SigningLogEntry, LoggedOutcome, FileTransparencyLog, MemoryTransparencyLog};
+pub use service::ShareService;
+
+use thiserror::Error;
+
+/// Share server errors
+#[derive(Error, Debug)]
+pub enum ServerError {
+    /// Configuration file missing or invalid
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// No valid device authorization token
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// Device policy refuses the request
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// Device exceeded its request rate
+    #[error("Rate limit exceeded")]
+    RateLimited,
+
+    /// Malformed request
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    /// Request conflicts with the session's recorded state
+    #[error("Session conflict: {0}")]
+    Conflict(String),
+
+    /// Endpoint not served
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Persistent state could not be read or written
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// Secure element failure
+    #[error("Hardware error: {0}")]
+    Hardware(#[from] frost_hardware_hal::HardwareError),
+}
+
+impl ServerError {
+    /// HTTP status the error is answered with
+    pub fn status(&self) -> u16 {
+        match self {
+            ServerError::BadRequest(_) => 400,
+            ServerError::Unauthorized(_) => 401,
+            ServerError::Forbidden(_) => 403,
+            ServerError::NotFound(_) => 404,
+            ServerError::Conflict(_) => 409,
+            ServerError::RateLimited => 429,
+            ServerError::Config(_) | ServerError::Hardware(_) => 500,
+            ServerError::Storage(_) => 503,
+        }
+    }
+}
+
+/// Result type for share server operations
+pub type ServerResult<T> = Result<T, ServerError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status() {
+        assert_eq!(ServerError::RateLimited.status(), 429);
+        assert_eq!(ServerError::Conflict("reused".to_string()).to_string(), "Session conflict: reused");
+    }
+}
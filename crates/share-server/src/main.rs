@@ -0,0 +1,115 @@
+//! frost-share-server: serve one FROST share to devices
+//!
+//! ```text
+//! frost-share-server [--config /etc/frost/shares.conf]
+//! ```
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use frost_core::{ParticipantId, SecretScalar, SecretShare};
+use frost_hardware_hal::feitian::{FeitianHSM, FeitianInterface};
+use frost_hardware_hal::gigadevice::GigaDeviceGD32;
+use frost_hardware_hal::nations::NationsTechSE;
+use frost_hardware_hal::{SecureElement, SoftwareSecureElement};
+use frost_share_server::config::{parse_hex, Backend, ServerSection, DEFAULT_CONFIG_PATH};
+use frost_share_server::{http, ServerError, ServerResult, ShareService, SharesConfig};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Share file of the software backend
+#[derive(Deserialize)]
+struct SoftwareShareFile {
+    participant_id: u32,
+    value: String,
+    blinding: String,
+}
+
+fn parse_scalar(text: &str) -> ServerResult<SecretScalar> {
+    let bytes: [u8; 32] = parse_hex(text)?.try_into()
+        .map_err(|_| ServerError::Config("Share scalars are 32 bytes".to_string()))?;
+    Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes))
+        .map(SecretScalar::new)
+        .ok_or_else(|| ServerError::Config("Share scalar is not canonical".to_string()))
+}
+
+fn load_software_share(path: &Path, participant_id: u32, group_public_key: CompressedRistretto) -> ServerResult<SecretShare> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+    let file: SoftwareShareFile = serde_json::from_str(&text)
+        .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+    if file.participant_id != participant_id {
+        return Err(ServerError::Config(format!("{} holds share {}", path.display(), file.participant_id)));
+    }
+    Ok(SecretShare {
+        participant_id: ParticipantId(file.participant_id),
+        value: parse_scalar(&file.value)?,
+        blinding: parse_scalar(&file.blinding)?,
+        group_public_key,
+    })
+}
+
+/// Initialize the configured secure element
+///
+/// Hardware backends hold the share from provisioning; the software
+/// backend loads it from its share file.
+async fn secure_element(server: &ServerSection, group_public_key: CompressedRistretto) -> ServerResult<Box<dyn SecureElement>> {
+    let mut se: Box<dyn SecureElement> = match server.backend {
+        // Rack-mounted share servers attach the HSM over PCIe
+        Backend::Feitian => Box::new(FeitianHSM::new(FeitianInterface::PCIe)),
+        Backend::Nations => Box::new(NationsTechSE::new()),
+        Backend::GigaDevice => Box::new(GigaDeviceGD32::new()),
+        Backend::Software => Box::new(SoftwareSecureElement::new()),
+    };
+    se.initialize().await?;
+
+    if server.backend == Backend::Software {
+        let path = server.software_share_file.as_ref()
+            .ok_or_else(|| ServerError::Config("Software backend needs software_share_file".to_string()))?;
+        se.store_share(&server.share_id, &load_software_share(path, server.participant_id, group_public_key)?).await?;
+    }
+
+    let report = se.self_test().await?;
+    if !report.passed {
+        return Err(ServerError::Config(format!("Secure element self-test failed: {}", report.errors.join(", "))));
+    }
+    Ok(se)
+}
+
+fn config_path() -> ServerResult<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    match (args.next().as_deref(), args.next(), args.next()) {
+        (None, _, _) => Ok(PathBuf::from(DEFAULT_CONFIG_PATH)),
+        (Some("--config"), Some(path), None) => Ok(PathBuf::from(path)),
+        _ => Err(ServerError::Config("usage: frost-share-server [--config PATH]".to_string())),
+    }
+}
+
+async fn run() -> ServerResult<()> {
+    let config = SharesConfig::load(&config_path()?)?;
+    let server = config.server()?;
+    let own = config.own_share()?;
+
+    let tls = http::load_tls_config(&server.tls_certificate, &server.tls_private_key, &own.certificate)?;
+    let service = ShareService::from_config(&config, secure_element(server, config.group_public_key()?.public_key).await?)?;
+
+    let addr = config.listen_addr()?;
+    let listener = TcpListener::bind(addr).await
+        .map_err(|e| ServerError::Config(format!("{}: {}", addr, e)))?;
+    log::info!("Serving share {} ({}, {}) on {}", own.id, own.location, own.operator, addr);
+
+    http::serve(listener, tls, Arc::new(service), async {
+        let _ = tokio::signal::ctrl_c().await;
+        log::info!("Shutting down");
+    }).await
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    if let Err(e) = run().await {
+        log::error!("{}", e);
+        eprintln!("frost-share-server: {}", e);
+        std::process::exit(1);
+    }
+}
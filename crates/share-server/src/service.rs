@@ -0,0 +1,797 @@
+//! Request handling, independent of HTTP
+//!
+//! Each signing request is authorized, checked against the device's policy
+//! and the session journal, run on the secure element, and logged to the
+//! transparency log before it is answered. Requests without a valid token
+//! are refused without being logged, so anonymous clients cannot fill the
+//! log.
+//!
+//! Both rounds must be signed by the token's holder key over the session
+//! and message hash. Messages are generic, needing the token's
+//! `sign_message` capability and its signed policy to hold on this server's
+//! clock, unless they carry the domain tag of data only the quorum signs
+//! (session tokens, revocation lists, tree heads), which no token can get
+//! co-signed through round 2. Session token bodies have their own round 2,
+//! `issue_token`, which co-signs a token only for the requesting device
+//! and within the bounds of its `TokenIssuance` policy.
+
+use crate::auth::{AuthorizedDevice, DeviceAuthorizer, RateLimiter, CLOCK_SKEW_SECS};
+use crate::config::SharesConfig;
+use crate::journal::{FileJournalStore, JournalStore, SessionJournal};
+use crate::transparency::{FileTransparencyLog, LoggedOutcome, SigningLogEntry, TransparencyLog};
+use crate::{ServerError, ServerResult};
+use frost_core::https::{HealthResponse, Round1Request, Round1Response, Round2Request, Round2Response, RevocationRequest};
+use frost_core::transport::{message_hash, reserved_message_kind, share_request_digest};
+use curve25519_dalek::traits::IsIdentity;
+use frost_core::{
+    token_from_sig_structure, Capabilities, ParticipantId, RequestContext, RevocationList, Rule, SchnorrSignature,
+    SessionId, SignatureScheme, TokenRequest,
+};
+use frost_hardware_hal::SecureElement;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Journal file in the state directory
+pub const JOURNAL_FILE: &str = "sessions.json";
+
+/// Transparency log file in the state directory
+pub const TRANSPARENCY_LOG_FILE: &str = "signing.log";
+
+impl<J: JournalStore + ?Sized> JournalStore for Box<J> {
+    fn load(&self) -> ServerResult<Option<Vec<u8>>> {
+        (**self).load()
+    }
+
+    fn store(&mut self, data: &[u8]) -> ServerResult<()> {
+        (**self).store(data)
+    }
+}
+
+/// Mutable state, never held across an await
+struct ServiceState {
+    limiter: RateLimiter,
+    journal: SessionJournal<Box<dyn JournalStore>>,
+    log: Box<dyn TransparencyLog>,
+    /// Sessions whose round 1 is on the secure element, locked until recorded
+    opening: HashMap<SessionId, Arc<tokio::sync::Mutex<()>>>,
+}
+
+/// Reservation of a session's round 1, released when dropped
+struct Opening<'a> {
+    service: &'a ShareService,
+    session_id: SessionId,
+    _held: tokio::sync::OwnedMutexGuard<()>,
+}
+
+impl Drop for Opening<'_> {
+    fn drop(&mut self) {
+        // Waiters wake once the guard drops after this, and find the journal updated
+        self.service.state().opening.remove(&self.session_id);
+    }
+}
+
+/// One share, served to devices
+pub struct ShareService {
+    participant_id: ParticipantId,
+    share_id: String,
+    secure_element: Box<dyn SecureElement>,
+    authorizer: DeviceAuthorizer,
+    state: Mutex<ServiceState>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl ShareService {
+    /// Serve share `share_id` of `secure_element` as `participant_id`
+    pub fn new(
+        participant_id: ParticipantId,
+        share_id: String,
+        secure_element: Box<dyn SecureElement>,
+        authorizer: DeviceAuthorizer,
+        journal: SessionJournal<Box<dyn JournalStore>>,
+        log: Box<dyn TransparencyLog>,
+    ) -> Self {
+        ShareService {
+            participant_id,
+            share_id,
+            secure_element,
+            authorizer,
+            state: Mutex::new(ServiceState {
+                limiter: RateLimiter::new(),
+                journal,
+                log,
+                opening: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Service as configured, with its journal and log in `state_dir`
+    pub fn from_config(config: &SharesConfig, secure_element: Box<dyn SecureElement>) -> ServerResult<Self> {
+        let server = config.server()?;
+        std::fs::create_dir_all(&server.state_dir)
+            .map_err(|e| ServerError::Storage(format!("{}: {}", server.state_dir.display(), e)))?;
+
+        let mut authorizer = DeviceAuthorizer::new(
+            config.group_public_key()?,
+            config.device_policies()?,
+            server.default_device_policy.clone(),
+        );
+        if let Some(path) = &server.revocation_list {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+            let list: RevocationList = serde_json::from_str(&text)
+                .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+            authorizer.set_revocation_list(list)?;
+        }
+
+        let store: Box<dyn JournalStore> = Box::new(FileJournalStore::new(server.state_dir.join(JOURNAL_FILE)));
+        let journal = SessionJournal::open(store, server.session_ttl_secs)?;
+        let log = FileTransparencyLog::open(&server.state_dir.join(TRANSPARENCY_LOG_FILE))?;
+
+        Ok(Self::new(
+            ParticipantId(server.participant_id),
+            server.share_id.clone(),
+            secure_element,
+            authorizer,
+            journal,
+            Box::new(log),
+        ))
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ServiceState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn check_participant(&self, participant_id: ParticipantId) -> ServerResult<()> {
+        if participant_id != self.participant_id {
+            return Err(ServerError::BadRequest(format!(
+                "This server holds share {}, not {}",
+                self.participant_id.as_u32(), participant_id.as_u32(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the request was signed by the holder of the bearer token
+    fn check_holder(
+        device: &AuthorizedDevice,
+        session_id: &SessionId,
+        message_hash: &[u8; 32],
+        signature: Option<&SchnorrSignature>,
+    ) -> ServerResult<()> {
+        let digest = share_request_digest(session_id, message_hash);
+        match signature {
+            Some(signature) if signature.verify(SignatureScheme::Schnorr, &digest, &device.token.holder_public()) => Ok(()),
+            _ => Err(ServerError::Unauthorized("Request is not signed by the token holder".to_string())),
+        }
+    }
+
+    /// Check the token grants co-signing the message at `now`
+    ///
+    /// Round 1 carries only the hash; the message itself, checked in round
+    /// 2, must not be data reserved to the quorum. The token's policy sees
+    /// only the server's clock: rules on what the device alone can report
+    /// (region, network, use count) are unknown here and refuse.
+    fn check_message(device: &AuthorizedDevice, message_hash: &[u8; 32], message: Option<&[u8]>, now: u64) -> ServerResult<()> {
+        let operation = match message.and_then(reserved_message_kind) {
+            Some(kind) => return Err(ServerError::Forbidden(format!("A {} cannot be signed through a share request", kind))),
+            None => TokenRequest::SignMessage { message_hash: *message_hash },
+        };
+        // Tokens issued within the tolerated skew ahead of this clock were authorized
+        let context = RequestContext { now: now.max(device.token.issued_at), ..RequestContext::default() };
+        if !device.token.authorize(&operation, &context).allowed {
+            return Err(ServerError::Forbidden(format!("Token does not allow {}", operation.name())));
+        }
+        Ok(())
+    }
+
+    /// Check the session token body `message` may be co-signed for the device
+    fn check_issuance(device: &AuthorizedDevice, message: &[u8], now: u64) -> ServerResult<()> {
+        let issuance = device.policy.token_issuance.as_ref()
+            .ok_or_else(|| ServerError::Forbidden("Device may not have session tokens issued".to_string()))?;
+        let token = token_from_sig_structure(message)
+            .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+
+        if token.device_id != device.device_id {
+            return Err(ServerError::Forbidden("Session token is for another device".to_string()));
+        }
+        if token.ephemeral_public.decompress().is_none_or(|key| key.is_identity()) {
+            return Err(ServerError::BadRequest("Session token has no holder key".to_string()));
+        }
+        if token.issued_at.abs_diff(now) > CLOCK_SKEW_SECS {
+            return Err(ServerError::BadRequest("Session token issue time is off".to_string()));
+        }
+        if token.expires_at <= token.issued_at || token.expires_at - token.issued_at > issuance.max_lifetime_secs {
+            return Err(ServerError::Forbidden(format!(
+                "Session token lifetime must be at most {} seconds",
+                issuance.max_lifetime_secs,
+            )));
+        }
+        if !capabilities_within(&token.capabilities, &issuance.capabilities) {
+            return Err(ServerError::Forbidden("Session token grants more than the device may hold".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Append the outcome of an authorized request to the transparency log
+    ///
+    /// If the entry cannot be written the answer is withheld.
+    fn log<T>(
+        &self,
+        round: u8,
+        session_id: &SessionId,
+        message_hash: &[u8; 32],
+        device: Option<&AuthorizedDevice>,
+        result: ServerResult<T>,
+        now: u64,
+    ) -> ServerResult<T> {
+        let Some(device) = device else { return result };
+        let entry = SigningLogEntry {
+            timestamp: now,
+            participant_id: self.participant_id.as_u32(),
+            round,
+            session_id: *session_id,
+            device_id: Some(device.device_id),
+            token_id: Some(device.token_id),
+            message_hash: *message_hash,
+            outcome: match &result {
+                Ok(_) => LoggedOutcome::Answered,
+                Err(e) => LoggedOutcome::Refused(e.to_string()),
+            },
+        };
+        self.state().log.append(&entry)?;
+        result
+    }
+
+    /// `POST /v1/signing/round1`
+    pub async fn round1(&self, authorization: Option<&str>, request: Round1Request) -> ServerResult<Round1Response> {
+        let now = unix_now();
+        let mut device = None;
+        let result = self.open_session(authorization, &request, now, &mut device).await;
+        let commitment = self.log(1, &request.session_id, &request.message_hash, device.as_ref(), result, now)?;
+        Ok(Round1Response { commitment, timestamp: now })
+    }
+
+    async fn open_session(
+        &self,
+        authorization: Option<&str>,
+        request: &Round1Request,
+        now: u64,
+        authorized: &mut Option<AuthorizedDevice>,
+    ) -> ServerResult<frost_core::signing::SigningCommitment> {
+        let device = authorized.insert(self.authorizer.authorize(authorization, now)?);
+        self.check_participant(request.participant_id)?;
+        Self::check_holder(device, &request.session_id, &request.message_hash, request.holder_signature.as_ref())?;
+        // The hash may be of a token body, checked in round 2 by `issue_token`
+        if device.policy.token_issuance.is_none() {
+            Self::check_message(device, &request.message_hash, None, now)?;
+        }
+
+        let expired = self.state().journal.prune(now)?;
+        self.abort_sessions(&expired).await;
+
+        // Concurrent requests for one session wait for the first to record its commitment
+        let _opening = loop {
+            let pending = {
+                let mut state = self.state();
+                if let Some(commitment) = state.journal.begin_round1(&request.session_id, &device.device_id, &request.message_hash, now)? {
+                    return Ok(commitment);
+                }
+                match state.opening.get(&request.session_id) {
+                    Some(pending) => Arc::clone(pending),
+                    None => {
+                        if state.journal.open_sessions(&device.device_id, now) >= device.policy.max_open_sessions {
+                            return Err(ServerError::Forbidden("Too many open signing sessions".to_string()));
+                        }
+                        state.limiter.check(device, Instant::now())?;
+                        let lock = Arc::new(tokio::sync::Mutex::new(()));
+                        let held = Arc::clone(&lock).try_lock_owned().expect("new lock is free");
+                        state.opening.insert(request.session_id, lock);
+                        break Opening { service: self, session_id: request.session_id, _held: held };
+                    }
+                }
+            };
+            drop(pending.lock().await);
+        };
+
+        // Nonces the secure element holds under an unjournaled session are stale
+        self.secure_element.abort_session(&self.share_id, &request.session_id).await?;
+        let commitment = self.secure_element.signing_round1(&self.share_id, &request.session_id).await?;
+        let recorded = self.state().journal.record_commitment(
+            request.session_id,
+            device.device_id,
+            request.message_hash,
+            commitment.clone(),
+            now,
+        );
+        if let Err(e) = recorded {
+            self.abort_sessions(&[request.session_id]).await;
+            return Err(e);
+        }
+        Ok(commitment)
+    }
+
+    /// Have the secure element discard the nonces of sessions that will not be signed
+    async fn abort_sessions(&self, sessions: &[SessionId]) {
+        for session_id in sessions {
+            if let Err(e) = self.secure_element.abort_session(&self.share_id, session_id).await {
+                log::warn!("Could not discard nonces of session {}: {}", hex::encode(session_id), e);
+            }
+        }
+    }
+
+    /// `POST /v1/signing/round2`
+    pub async fn round2(&self, authorization: Option<&str>, request: Round2Request) -> ServerResult<Round2Response> {
+        let now = unix_now();
+        let hash = message_hash(&request.message);
+        let mut device = None;
+        let check = |device: &AuthorizedDevice| Self::check_message(device, &hash, Some(&request.message), now);
+        let result = self.sign(authorization, &request, &hash, now, &mut device, check).await;
+        let partial = self.log(2, &request.session_id, &hash, device.as_ref(), result, now)?;
+        Ok(Round2Response { partial })
+    }
+
+    /// `POST /v1/tokens/issue`: round 2 over a session token body
+    pub async fn issue_token(&self, authorization: Option<&str>, request: Round2Request) -> ServerResult<Round2Response> {
+        let now = unix_now();
+        let hash = message_hash(&request.message);
+        let mut device = None;
+        let check = |device: &AuthorizedDevice| Self::check_issuance(device, &request.message, now);
+        let result = self.sign(authorization, &request, &hash, now, &mut device, check).await;
+        let partial = self.log(2, &request.session_id, &hash, device.as_ref(), result, now)?;
+        Ok(Round2Response { partial })
+    }
+
+    async fn sign(
+        &self,
+        authorization: Option<&str>,
+        request: &Round2Request,
+        hash: &[u8; 32],
+        now: u64,
+        authorized: &mut Option<AuthorizedDevice>,
+        check_message: impl FnOnce(&AuthorizedDevice) -> ServerResult<()>,
+    ) -> ServerResult<frost_core::signing::PartialSignature> {
+        let device = authorized.insert(self.authorizer.authorize(authorization, now)?);
+        self.check_participant(request.participant_id)?;
+        Self::check_holder(device, &request.session_id, hash, request.holder_signature.as_ref())?;
+        check_message(device)?;
+
+        let replay = self.state().journal.begin_round2(
+            &request.session_id,
+            &device.device_id,
+            hash,
+            &request.commitments,
+            now,
+        )?;
+        if let Some(partial) = replay {
+            return Ok(partial);
+        }
+
+        let partial = self.secure_element.signing_round2(
+            &self.share_id,
+            &request.session_id,
+            &request.message,
+            &request.commitments,
+        ).await?;
+        self.state().journal.record_partial(&request.session_id, partial.clone())?;
+        Ok(partial)
+    }
+
+    /// `GET /v1/health`
+    pub async fn health(&self) -> HealthResponse {
+        let available = self.secure_element.self_test().await.is_ok_and(|report| report.passed);
+        HealthResponse { available }
+    }
+
+    /// `POST /v1/revocations`
+    pub fn revocations(&self, request: &RevocationRequest) -> ServerResult<RevocationList> {
+        match self.authorizer.revocation_list() {
+            Some(list) if request.since_sequence.is_none_or(|s| list.sequence >= s) => Ok(list.clone()),
+            Some(_) => Err(ServerError::NotFound("Revocation list held is newer".to_string())),
+            None => Err(ServerError::NotFound("No revocation list published".to_string())),
+        }
+    }
+}
+
+/// Whether `granted` asks for nothing beyond `ceiling`
+///
+/// A policy in the ceiling must be the token's policy or one of the rules
+/// it requires with `Rule::All`.
+fn capabilities_within(granted: &Capabilities, ceiling: &Capabilities) -> bool {
+    let payments = match (&granted.payment_limits, &ceiling.payment_limits) {
+        (None, _) => true,
+        (Some(limits), Some(cap)) => {
+            limits.max_per_transaction <= cap.max_per_transaction && limits.max_per_day <= cap.max_per_day
+        }
+        (Some(_), None) => false,
+    };
+    let policy = match (&granted.policy, &ceiling.policy) {
+        (_, None) => true,
+        (Some(rule), Some(required)) => rule == required || matches!(rule, Rule::All(rules) if rules.contains(required)),
+        (None, Some(_)) => false,
+    };
+
+    payments
+        && policy
+        && (ceiling.device_unlock || !granted.device_unlock)
+        && granted.keychain_access.to_byte() <= ceiling.keychain_access.to_byte()
+        && (ceiling.code_signing || !granted.code_signing)
+        && (ceiling.filevault_decrypt || !granted.filevault_decrypt)
+        && (ceiling.sign_message || !granted.sign_message)
+        && granted.custom.iter().all(|op| ceiling.custom.contains(op))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::auth::tests::{bearer, bearer_for, bearer_with, group, signing_capabilities};
+    use crate::config::{DevicePolicy, TokenIssuance};
+    use crate::journal::MemoryJournalStore;
+    use curve25519_dalek::ristretto::CompressedRistretto;
+    use crate::transparency::MemoryTransparencyLog;
+    use frost_core::dkg::DkgOutput;
+    use frost_core::merkle::SignedTreeHead;
+    use frost_core::{aggregate_signatures, Capabilities, RevokedTokens, Rule, SessionToken, SigningRound1};
+    use frost_core::signing::{PartialSignature, SigningCommitment};
+    use frost_core::SecretShare;
+    use frost_hardware_hal::{Attestation, HardwareResult, SecureElementInfo, SelfTestReport, SoftwareSecureElement};
+    use rand::rngs::OsRng;
+    use std::time::Duration;
+
+    /// Service for share `index` of `outputs`, on a software secure element
+    pub(crate) async fn service(
+        outputs: &[DkgOutput],
+        index: usize,
+        policy: DevicePolicy,
+    ) -> (ShareService, MemoryTransparencyLog) {
+        let se = software_share(outputs, index).await;
+        service_on(outputs, index, policy, Box::new(se), 60)
+    }
+
+    async fn software_share(outputs: &[DkgOutput], index: usize) -> SoftwareSecureElement {
+        let mut se = SoftwareSecureElement::new();
+        se.initialize().await.unwrap();
+        se.store_share("share", &outputs[index].secret_share).await.unwrap();
+        se
+    }
+
+    /// `service` on `secure_element`, with sessions expiring after `ttl_secs`
+    fn service_on(
+        outputs: &[DkgOutput],
+        index: usize,
+        policy: DevicePolicy,
+        secure_element: Box<dyn SecureElement>,
+        ttl_secs: u64,
+    ) -> (ShareService, MemoryTransparencyLog) {
+        let authorizer = DeviceAuthorizer::new(outputs[0].group_public_key.clone(), HashMap::new(), Some(policy));
+        let store: Box<dyn JournalStore> = Box::new(MemoryJournalStore::default());
+        let log = MemoryTransparencyLog::default();
+        let service = ShareService::new(
+            outputs[index].secret_share.participant_id,
+            "share".to_string(),
+            secure_element,
+            authorizer,
+            SessionJournal::open(store, ttl_secs).unwrap(),
+            Box::new(log.clone()),
+        );
+        (service, log)
+    }
+
+    /// Software secure element that yields to other tasks before each round 1
+    struct YieldingSecureElement(SoftwareSecureElement);
+
+    #[async_trait::async_trait]
+    impl SecureElement for YieldingSecureElement {
+        async fn initialize(&mut self) -> HardwareResult<()> {
+            self.0.initialize().await
+        }
+
+        async fn get_info(&self) -> HardwareResult<SecureElementInfo> {
+            self.0.get_info().await
+        }
+
+        async fn random_bytes(&self, buffer: &mut [u8]) -> HardwareResult<()> {
+            self.0.random_bytes(buffer).await
+        }
+
+        async fn store_share(&mut self, share_id: &str, share: &SecretShare) -> HardwareResult<()> {
+            self.0.store_share(share_id, share).await
+        }
+
+        async fn load_share(&self, share_id: &str) -> HardwareResult<SecretShare> {
+            self.0.load_share(share_id).await
+        }
+
+        async fn delete_share(&mut self, share_id: &str) -> HardwareResult<()> {
+            self.0.delete_share(share_id).await
+        }
+
+        async fn signing_round1(&self, share_id: &str, session_id: &[u8]) -> HardwareResult<SigningCommitment> {
+            tokio::task::yield_now().await;
+            self.0.signing_round1(share_id, session_id).await
+        }
+
+        async fn signing_round2(
+            &self,
+            share_id: &str,
+            session_id: &[u8],
+            message: &[u8],
+            commitments: &[SigningCommitment],
+        ) -> HardwareResult<PartialSignature> {
+            self.0.signing_round2(share_id, session_id, message, commitments).await
+        }
+
+        async fn abort_session(&self, share_id: &str, session_id: &[u8]) -> HardwareResult<()> {
+            self.0.abort_session(share_id, session_id).await
+        }
+
+        async fn get_attestation(&self) -> HardwareResult<Attestation> {
+            self.0.get_attestation().await
+        }
+
+        async fn self_test(&self) -> HardwareResult<SelfTestReport> {
+            self.0.self_test().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signing_session_is_exactly_once() {
+        let outputs = group();
+        let (service, log) = service(&outputs, 1, DevicePolicy::default()).await;
+        let (token, holder) = bearer(&outputs, [1u8; 32]);
+        let auth = Some(token.as_str());
+
+        let message = b"co-signed by share 2".to_vec();
+        let holder_signature = Some(holder.sign_share_request(&[5u8; 32], &message_hash(&message)).unwrap());
+        let round1 = Round1Request {
+            session_id: [5u8; 32],
+            participant_id: ParticipantId(2),
+            message_hash: message_hash(&message),
+            holder_signature: holder_signature.clone(),
+        };
+        let remote = service.round1(auth, round1.clone()).await.unwrap().commitment;
+        // A retried round 1 gets the same commitment
+        assert_eq!(service.round1(auth, round1.clone()).await.unwrap().commitment, remote);
+
+        let local = SigningRound1::new(ParticipantId(1), &outputs[0].secret_share, &mut OsRng);
+        let commitments = vec![local.commitment(), remote];
+        let round2 = Round2Request {
+            session_id: [5u8; 32],
+            participant_id: ParticipantId(2),
+            message: message.clone(),
+            commitments: commitments.clone(),
+            holder_signature,
+        };
+        let partial = service.round2(auth, round2.clone()).await.unwrap().partial;
+        // A retried round 2 gets the same partial without touching the nonces
+        assert_eq!(service.round2(auth, round2.clone()).await.unwrap().partial.z, partial.z);
+
+        let local = local.into_round2(&message, &commitments).unwrap();
+        let signature = aggregate_signatures(&local.group_commitment(), &[local.partial_signature(), partial]).unwrap();
+        assert!(outputs[0].group_public_key.verify_signature(&message, &signature));
+
+        // Another commitment set for the same nonces is refused
+        let other = SigningRound1::new(ParticipantId(1), &outputs[0].secret_share, &mut OsRng);
+        let reuse = Round2Request { commitments: vec![other.commitment(), commitments[1].clone()], ..round2 };
+        assert!(matches!(service.round2(auth, reuse).await, Err(ServerError::Conflict(_))));
+
+        // Every authorized request was logged, the refusal included
+        let entries = log.entries();
+        assert_eq!(entries.len(), 5);
+        assert!(entries.iter().all(|e| e.token_id == Some(holder.token_id) && e.message_hash == round1.message_hash));
+        assert_eq!(entries.iter().map(|e| e.round).collect::<Vec<_>>(), [1, 1, 2, 2, 2]);
+        assert!(matches!(entries[4].outcome, LoggedOutcome::Refused(_)));
+    }
+
+    #[tokio::test]
+    async fn test_policy_and_rate_limits() {
+        let outputs = group();
+        let policy = DevicePolicy { requests_per_minute: 1, burst: 0, max_open_sessions: 2, ..DevicePolicy::default() };
+        let (service, log) = service(&outputs, 1, policy).await;
+        let (token, holder) = bearer(&outputs, [1u8; 32]);
+        let (other, other_holder) = bearer(&outputs, [2u8; 32]);
+        let request = |session: u8, participant: u32| Round1Request {
+            session_id: [session; 32],
+            participant_id: ParticipantId(participant),
+            message_hash: [0u8; 32],
+            holder_signature: Some(holder.sign_share_request(&[session; 32], &[0u8; 32]).unwrap()),
+        };
+
+        // Unauthenticated requests are refused and not logged
+        assert!(matches!(service.round1(None, request(1, 2)).await, Err(ServerError::Unauthorized(_))));
+        assert!(log.entries().is_empty());
+
+        assert!(matches!(service.round1(Some(&token), request(1, 3)).await, Err(ServerError::BadRequest(_))));
+        assert!(service.round1(Some(&token), request(1, 2)).await.is_ok());
+        // Retries do not count against the rate; new sessions do
+        assert!(service.round1(Some(&token), request(1, 2)).await.is_ok());
+        assert!(matches!(service.round1(Some(&token), request(2, 2)).await, Err(ServerError::RateLimited)));
+
+        // Another device has its own budget
+        let other_request = Round1Request {
+            holder_signature: Some(other_holder.sign_share_request(&[3u8; 32], &[0u8; 32]).unwrap()),
+            ..request(3, 2)
+        };
+        assert!(service.round1(Some(&other), other_request).await.is_ok());
+        assert_eq!(log.entries().len(), 5);
+
+        // Open sessions are capped per device
+        let (capped, _) = self::service(&outputs, 1, DevicePolicy { max_open_sessions: 1, ..DevicePolicy::default() }).await;
+        assert!(capped.round1(Some(&token), request(1, 2)).await.is_ok());
+        assert!(matches!(capped.round1(Some(&token), request(2, 2)).await, Err(ServerError::Forbidden(_))));
+
+        assert!(service.health().await.available);
+        assert!(matches!(service.revocations(&RevocationRequest { since_sequence: None }), Err(ServerError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_holder_signature_and_capability() {
+        let outputs = group();
+        let (service, log) = service(&outputs, 1, DevicePolicy::default()).await;
+        let (token, holder) = bearer(&outputs, [1u8; 32]);
+        let auth = Some(token.as_str());
+        let hash = message_hash(b"generic message");
+        let round1 = |session: u8, signer: &SessionToken| Round1Request {
+            session_id: [session; 32],
+            participant_id: ParticipantId(2),
+            message_hash: hash,
+            holder_signature: Some(signer.sign_share_request(&[session; 32], &hash).unwrap()),
+        };
+
+        // The bearer token alone, or signed by another key, opens nothing
+        let unsigned = Round1Request { holder_signature: None, ..round1(1, &holder) };
+        assert!(matches!(service.round1(auth, unsigned).await, Err(ServerError::Unauthorized(_))));
+        let (_, stranger) = bearer(&outputs, [1u8; 32]);
+        assert!(matches!(service.round1(auth, round1(1, &stranger)).await, Err(ServerError::Unauthorized(_))));
+        let replayed = Round1Request { session_id: [2u8; 32], ..round1(1, &holder) };
+        assert!(matches!(service.round1(auth, replayed).await, Err(ServerError::Unauthorized(_))));
+        assert!(service.round1(auth, round1(1, &holder)).await.is_ok());
+
+        // Generic messages need the message signing capability
+        let (unlock_only, unlock_holder) = bearer_with(&outputs, [3u8; 32], false);
+        assert!(matches!(
+            service.round1(Some(&unlock_only), round1(3, &unlock_holder)).await,
+            Err(ServerError::Forbidden(_)),
+        ));
+
+        // Data only the quorum signs is refused in round 2, whatever the token grants
+        let mut minted = SessionToken::new([1u8; 32], Capabilities::default(), Duration::from_secs(3600));
+        minted.capabilities.code_signing = true;
+        let list = RevocationList::to_signing_data(9, 0, &RevokedTokens::from_ids(vec![]));
+        let head = SignedTreeHead::to_signing_data(1, 0, &[0u8; 32]);
+        for (session, message) in [(4u8, minted.to_signing_data()), (5, list), (6, head)] {
+            let session_id = [session; 32];
+            let holder_signature = Some(holder.sign_share_request(&session_id, &message_hash(&message)).unwrap());
+            let opened = Round1Request {
+                session_id,
+                participant_id: ParticipantId(2),
+                message_hash: message_hash(&message),
+                holder_signature: holder_signature.clone(),
+            };
+            let remote = service.round1(auth, opened).await.unwrap().commitment;
+            let local = SigningRound1::new(ParticipantId(1), &outputs[0].secret_share, &mut OsRng);
+            let request = Round2Request {
+                session_id,
+                participant_id: ParticipantId(2),
+                message,
+                commitments: vec![local.commitment(), remote],
+                holder_signature,
+            };
+            assert!(matches!(service.round2(auth, request).await, Err(ServerError::Forbidden(_))));
+        }
+        assert!(matches!(log.entries().last().unwrap().outcome, LoggedOutcome::Refused(_)));
+    }
+
+    #[tokio::test]
+    async fn test_token_policy_holds_on_server_clock() {
+        let outputs = group();
+        let (service, log) = service(&outputs, 1, DevicePolicy::default()).await;
+        let hash = message_hash(b"generic message");
+        let seconds_of_day = (unix_now() % 86_400) as u32;
+        let later = |hours: u32| (seconds_of_day + hours * 3600) % 86_400;
+
+        let policies = [
+            (Rule::Operation("sign-message".to_string()), true),
+            (Rule::TimeOfDay { start: later(2), end: later(3) }, false),
+            // The server cannot know where the device is, so the rule refuses
+            (Rule::Region("CH".to_string()), false),
+            (Rule::Not(Box::new(Rule::Network("guest".to_string()))), false),
+        ];
+        for (session, (policy, allowed)) in (1u8..).zip(policies) {
+            let capabilities = Capabilities { policy: Some(policy), ..signing_capabilities(true) };
+            let (token, holder) = bearer_for(&outputs, [session; 32], capabilities);
+            let request = Round1Request {
+                session_id: [session; 32],
+                participant_id: ParticipantId(2),
+                message_hash: hash,
+                holder_signature: Some(holder.sign_share_request(&[session; 32], &hash).unwrap()),
+            };
+            let result = service.round1(Some(&token), request).await;
+            assert_eq!(result.is_ok(), allowed);
+            if !allowed {
+                assert!(matches!(result, Err(ServerError::Forbidden(_))));
+            }
+        }
+        assert_eq!(log.entries().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_round1_sessions_are_released() {
+        let outputs = group();
+        let (token, holder) = bearer(&outputs, [1u8; 32]);
+        let auth = Some(token.as_str());
+        let request = |session: u8| Round1Request {
+            session_id: [session; 32],
+            participant_id: ParticipantId(2),
+            message_hash: [0u8; 32],
+            holder_signature: Some(holder.sign_share_request(&[session; 32], &[0u8; 32]).unwrap()),
+        };
+
+        // Concurrent round 1 requests for one session get the one commitment
+        let se = YieldingSecureElement(software_share(&outputs, 1).await);
+        let (service, _) = service_on(&outputs, 1, DevicePolicy::default(), Box::new(se), 60);
+        let (first, second) = tokio::join!(service.round1(auth, request(1)), service.round1(auth, request(1)));
+        assert_eq!(first.unwrap().commitment, second.unwrap().commitment);
+
+        // Expired sessions give their nonces back, so the session ID can be opened again
+        let se = software_share(&outputs, 1).await;
+        let (expiring, _) = service_on(&outputs, 1, DevicePolicy::default(), Box::new(se), 0);
+        let opened = expiring.round1(auth, request(2)).await.unwrap().commitment;
+        let reopened = expiring.round1(auth, request(2)).await.unwrap().commitment;
+        assert_ne!(opened, reopened);
+    }
+
+    #[test]
+    fn test_token_issuance_bounds() {
+        let outputs = group();
+        let (_, bearer_token) = bearer(&outputs, [1u8; 32]);
+        let ceiling = Capabilities {
+            policy: Some(Rule::TimeOfDay { start: 0, end: 3600 }),
+            ..signing_capabilities(true)
+        };
+        let issuance = TokenIssuance { capabilities: ceiling.clone(), max_lifetime_secs: 3600 };
+        let device = AuthorizedDevice {
+            device_id: [1u8; 32],
+            token_id: bearer_token.token_id,
+            token: bearer_token,
+            policy: DevicePolicy { token_issuance: Some(issuance), ..DevicePolicy::default() },
+        };
+        let token = |capabilities: Capabilities| SessionToken::new([1u8; 32], capabilities, Duration::from_secs(3600));
+        let check = |token: &SessionToken| ShareService::check_issuance(&device, &token.to_signing_data(), token.issued_at);
+
+        assert!(check(&token(ceiling.clone())).is_ok());
+        // Narrower tokens pass, as long as they keep the ceiling's policy
+        let narrower = Capabilities {
+            device_unlock: false,
+            policy: Some(Rule::All(vec![Rule::Region("CH".to_string()), ceiling.policy.clone().unwrap()])),
+            ..ceiling.clone()
+        };
+        assert!(check(&token(narrower)).is_ok());
+
+        let wider = [
+            Capabilities { policy: None, ..ceiling.clone() },
+            Capabilities { policy: Some(Rule::Always), ..ceiling.clone() },
+            Capabilities { code_signing: true, ..ceiling.clone() },
+            Capabilities { custom: vec!["ssh".to_string()], ..ceiling.clone() },
+            Capabilities { payment_limits: Capabilities::default().payment_limits, ..ceiling.clone() },
+        ];
+        for capabilities in wider {
+            assert!(matches!(check(&token(capabilities)), Err(ServerError::Forbidden(_))));
+        }
+
+        let mut keyless = token(ceiling.clone());
+        keyless.ephemeral_public = CompressedRistretto::default();
+        assert!(matches!(check(&keyless), Err(ServerError::BadRequest(_))));
+        let backdated = token(ceiling.clone());
+        assert!(ShareService::check_issuance(&device, &backdated.to_signing_data(), backdated.issued_at + 3600).is_err());
+        let mut long_lived = token(ceiling.clone());
+        long_lived.expires_at += 1;
+        assert!(matches!(check(&long_lived), Err(ServerError::Forbidden(_))));
+        let other_device = SessionToken::new([2u8; 32], ceiling.clone(), Duration::from_secs(60));
+        assert!(matches!(check(&other_device), Err(ServerError::Forbidden(_))));
+        assert!(matches!(ShareService::check_issuance(&device, b"not a token", 0), Err(ServerError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,164 @@
+//! Transparency log of signing requests
+//!
+//! Every round-1 and round-2 request is appended before it is answered; a
+//! request that cannot be logged is not answered. Leaves are hashed as in
+//! RFC 6962 (`frost_core::merkle::leaf_hash` over the JSON entry), so the
+//! file can be submitted to the transparency log and audited entry by
+//! entry.
+
+use crate::{ServerError, ServerResult};
+use frost_core::merkle::leaf_hash;
+use frost_core::SessionId;
+use serde::{Serialize, Deserialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Outcome of a logged request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoggedOutcome {
+    /// Commitment or partial signature issued
+    Answered,
+    /// Request refused, with the reason
+    Refused(String),
+}
+
+/// One signing request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningLogEntry {
+    /// Time of the request (Unix epoch seconds)
+    pub timestamp: u64,
+    /// Share that received it
+    pub participant_id: u32,
+    /// Signing round (1 or 2)
+    pub round: u8,
+    /// Signing session
+    pub session_id: SessionId,
+    /// Authorized device, if the token checked out
+    pub device_id: Option<[u8; 32]>,
+    /// Token the device presented, if it checked out
+    pub token_id: Option<[u8; 16]>,
+    /// SHA-256 of the message
+    pub message_hash: [u8; 32],
+    /// What the server answered
+    pub outcome: LoggedOutcome,
+}
+
+impl SigningLogEntry {
+    /// RFC 6962 leaf hash of the entry
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        leaf_hash(&serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+/// Append-only log of signing requests
+pub trait TransparencyLog: Send {
+    /// Durably append `entry`, returning its index
+    fn append(&mut self, entry: &SigningLogEntry) -> ServerResult<u64>;
+}
+
+/// One line of a `FileTransparencyLog`
+#[derive(Serialize, Deserialize)]
+struct LogLine {
+    index: u64,
+    leaf_hash: String,
+    entry: SigningLogEntry,
+}
+
+/// Log as a JSON-lines file, synced after every append
+pub struct FileTransparencyLog {
+    file: std::fs::File,
+    next_index: u64,
+}
+
+impl FileTransparencyLog {
+    /// Open or create the log at `path`
+    pub fn open(path: &Path) -> ServerResult<Self> {
+        let storage = |e: std::io::Error| ServerError::Storage(format!("{}: {}", path.display(), e));
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(storage)?;
+        let next_index = std::io::BufReader::new(&file).lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(storage)?
+            .len() as u64;
+        Ok(FileTransparencyLog { file, next_index })
+    }
+}
+
+impl TransparencyLog for FileTransparencyLog {
+    fn append(&mut self, entry: &SigningLogEntry) -> ServerResult<u64> {
+        let line = LogLine {
+            index: self.next_index,
+            leaf_hash: hex::encode(entry.leaf_hash()),
+            entry: entry.clone(),
+        };
+        let mut bytes = serde_json::to_vec(&line).map_err(|e| ServerError::Storage(e.to_string()))?;
+        bytes.push(b'\n');
+        self.file.write_all(&bytes)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| ServerError::Storage(e.to_string()))?;
+        self.next_index += 1;
+        Ok(line.index)
+    }
+}
+
+/// In-memory log for tests; clones share the entries
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTransparencyLog {
+    entries: Arc<Mutex<Vec<SigningLogEntry>>>,
+}
+
+impl MemoryTransparencyLog {
+    /// Entries appended so far
+    pub fn entries(&self) -> Vec<SigningLogEntry> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl TransparencyLog for MemoryTransparencyLog {
+    fn append(&mut self, entry: &SigningLogEntry) -> ServerResult<u64> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.push(entry.clone());
+        Ok(entries.len() as u64 - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_log_appends_across_reopen() {
+        let dir = std::env::temp_dir().join(format!("frost-share-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("signing.log");
+        let _ = std::fs::remove_file(&path);
+
+        let entry = SigningLogEntry {
+            timestamp: 1_700_000_000,
+            participant_id: 2,
+            round: 1,
+            session_id: [1u8; 32],
+            device_id: Some([2u8; 32]),
+            token_id: Some([3u8; 16]),
+            message_hash: [4u8; 32],
+            outcome: LoggedOutcome::Answered,
+        };
+        assert_eq!(FileTransparencyLog::open(&path).unwrap().append(&entry).unwrap(), 0);
+
+        let refused = SigningLogEntry { outcome: LoggedOutcome::Refused("Rate limit exceeded".to_string()), ..entry.clone() };
+        assert_eq!(FileTransparencyLog::open(&path).unwrap().append(&refused).unwrap(), 1);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<LogLine> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].entry, refused);
+        assert_eq!(lines[1].leaf_hash, hex::encode(refused.leaf_hash()));
+        assert_ne!(lines[0].leaf_hash, lines[1].leaf_hash);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! Log entry formats
+//!
+//! Derivation statements are logged in their canonical encoding, so the leaf
+//! hash is the one `DerivedDeviceKey::verify_derivation_proof` checks.
+//! Ceremony transcripts are JSON behind a prefix naming the transcript type.
+//! Anything else is opaque to the log.
+
+use crate::{LogError, LogResult};
+use frost_core::derived_key::DerivationStatement;
+use frost_core::transcript::{DkgTranscript, RotationTranscript};
+
+/// Prefix of every derivation statement
+pub const DERIVATION_PREFIX: &[u8] = b"FROST-DERIVATION-STATEMENT-v1";
+
+/// Prefix of a logged DKG transcript
+pub const DKG_TRANSCRIPT_PREFIX: &[u8] = b"FROST-LOG-DKG-TRANSCRIPT-v1\n";
+
+/// Prefix of a logged rotation transcript
+pub const ROTATION_TRANSCRIPT_PREFIX: &[u8] = b"FROST-LOG-ROTATION-TRANSCRIPT-v1\n";
+
+/// A log entry, by type
+#[derive(Debug, Clone)]
+pub enum LogEntry {
+    /// Device key derivation
+    Derivation(DerivationStatement),
+    /// Key generation ceremony
+    DkgTranscript(DkgTranscript),
+    /// Share rotation, chained to the previous transcript
+    RotationTranscript(RotationTranscript),
+    /// Entry of no known type
+    Other(Vec<u8>),
+}
+
+impl LogEntry {
+    /// Encoding appended to the log
+    pub fn to_bytes(&self) -> LogResult<Vec<u8>> {
+        let (prefix, json) = match self {
+            LogEntry::Derivation(statement) => return Ok(statement.to_bytes()),
+            LogEntry::Other(data) => return Ok(data.clone()),
+            LogEntry::DkgTranscript(transcript) => (DKG_TRANSCRIPT_PREFIX, serde_json::to_vec(transcript)),
+            LogEntry::RotationTranscript(transcript) => (ROTATION_TRANSCRIPT_PREFIX, serde_json::to_vec(transcript)),
+        };
+        let json = json.map_err(|e| LogError::Storage(e.to_string()))?;
+        Ok([prefix, &json].concat())
+    }
+
+    /// Parse an entry read from the log
+    ///
+    /// Fails only for entries with a known prefix that do not parse.
+    pub fn parse(data: &[u8]) -> LogResult<Self> {
+        let malformed = |what: &str, e: &dyn std::fmt::Display| LogError::Corrupt(format!("malformed {}: {}", what, e));
+        if data.starts_with(DERIVATION_PREFIX) {
+            DerivationStatement::from_bytes(data)
+                .map(LogEntry::Derivation)
+                .ok_or_else(|| malformed("derivation statement", &format!("{} bytes", data.len())))
+        } else if let Some(json) = data.strip_prefix(DKG_TRANSCRIPT_PREFIX) {
+            serde_json::from_slice(json)
+                .map(LogEntry::DkgTranscript)
+                .map_err(|e| malformed("DKG transcript", &e))
+        } else if let Some(json) = data.strip_prefix(ROTATION_TRANSCRIPT_PREFIX) {
+            serde_json::from_slice(json)
+                .map(LogEntry::RotationTranscript)
+                .map_err(|e| malformed("rotation transcript", &e))
+        } else {
+            Ok(LogEntry::Other(data.to_vec()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::ristretto::CompressedRistretto;
+
+    #[test]
+    fn test_entries_round_trip() {
+        let dkg = DkgTranscript::new(1_700_000_000, 2, 3, vec![serde_json::json!({ "participant": 1 })]);
+        let rotation = RotationTranscript::new(1_700_000_100, dkg.transcript_hash, vec![]);
+        let statement = DerivationStatement {
+            device_id: [1u8; 32],
+            public_key: CompressedRistretto([2u8; 32]),
+            version: 3,
+            firmware_hash: [4u8; 32],
+            dkg_transcript_hash: dkg.transcript_hash,
+        };
+
+        let parse = |entry: LogEntry| LogEntry::parse(&entry.to_bytes().unwrap()).unwrap();
+        assert!(matches!(parse(LogEntry::Derivation(statement.clone())), LogEntry::Derivation(s) if s == statement));
+        assert!(matches!(parse(LogEntry::DkgTranscript(dkg)), LogEntry::DkgTranscript(t) if t.verify()));
+        assert!(matches!(parse(LogEntry::RotationTranscript(rotation)), LogEntry::RotationTranscript(t) if t.verify()));
+        assert!(matches!(parse(LogEntry::Other(b"note".to_vec())), LogEntry::Other(data) if data == b"note"));
+
+        assert!(LogEntry::parse(&statement.to_bytes()[..100]).is_err());
+        assert!(LogEntry::parse(&[DKG_TRANSCRIPT_PREFIX, b"{"].concat()).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Transparency log for FROST RoT
+//!
+//! Append-only Merkle log with RFC 6962 / RFC 9162 hashing. Device key
+//! derivations, ceremonies and signing requests are logged here (formats in
+//! `entry`); anyone can check that an entry is included under a tree head,
+//! and that a later tree head extends an earlier one.
+//!
+//! ```text
+//! append ──► MerkleLog ──► TileStore (C2SP tlog-tiles layout, see `tile`)
+//!               │
+//!               ├──► inclusion / consistency proofs ──► frost_core::merkle verifiers
+//!               ├──► sign_tree_head ──► frost_coordinator signing ceremony
+//!               └──► cosign_tree_head ──► WitnessClient ──► Witness (see `witness`)
+//! ```
+//!
+//! Tree heads are `frost_core::merkle::SignedTreeHead`s signed by the FROST
+//! group key, the form `DerivedDeviceKey::verify_derivation_proof` expects.
+//! Since that key also issues device keys, independent witnesses check each
+//! tree head extends the last one they saw and countersign it; devices
+//! require k-of-m cosignatures. `SplitViewMonitor` collects tree heads from
+//! wherever they are seen and reports two that cannot both be honest.
+
+#![warn(missing_docs)]
+
+pub mod tile;
+pub mod entry;
+pub mod log;
+pub mod proof;
+pub mod tree_head;
+pub mod witness;
+pub mod split_view;
+
+pub use tile::{TileStore, FileTileStore, MemoryTileStore};
+pub use entry::LogEntry;
+pub use log::MerkleLog;
+pub use proof::{InclusionProof, ConsistencyProof};
+pub use witness::{AddCheckpoint, Witness, WitnessClient};
+pub use split_view::{SplitView, SplitViewMonitor, ConsistencyProver};
+pub use frost_core::merkle::{leaf_hash, node_hash, verify_inclusion, verify_consistency, SignedTreeHead};
+pub use frost_core::merkle::{Cosignature, WitnessKey, WitnessPolicy};
+
+use frost_coordinator::CoordinatorError;
+use thiserror::Error;
+
+/// Transparency log errors
+#[derive(Error, Debug)]
+pub enum LogError {
+    /// Tile store failure
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// Stored tiles or state do not fit together
+    #[error("Corrupt log: {0}")]
+    Corrupt(String),
+
+    /// Entries are limited to 65535 bytes by the bundle format
+    #[error("Entry too large: {0} bytes")]
+    EntryTooLarge(usize),
+
+    /// Index or tree size beyond the log
+    #[error("Index {index} out of range for tree size {tree_size}")]
+    OutOfRange {
+        /// Requested index or size
+        index: u64,
+        /// Size it was checked against
+        tree_size: u64,
+    },
+
+    /// Tree head could not be produced or does not match
+    #[error("Tree head rejected: {0}")]
+    TreeHead(String),
+
+    /// Signing ceremony for a tree head failed
+    #[error("Signing failed: {0}")]
+    Signing(#[from] CoordinatorError),
+
+    /// Witness does not follow this log
+    #[error("Unknown log: {0}")]
+    UnknownLog(String),
+
+    /// Witness has cosigned a different tree size than the request assumed
+    #[error("Witness is at tree size {witnessed_size}")]
+    Conflict {
+        /// Size of the last tree head the witness cosigned
+        witnessed_size: u64,
+    },
+
+    /// Witness could not be reached or answered badly
+    #[error("Witness error: {0}")]
+    Witness(String),
+}
+
+/// Result type for log operations
+pub type LogResult<T> = Result<T, LogError>;
@@ -0,0 +1,361 @@
+//! Append-only Merkle log over a tile store
+//!
+//! Appending writes the entry to its bundle and the leaf hash to its level-0
+//! tile; when a tile fills, its root becomes the next hash of the tile one
+//! level up. The tree size is stored last, so a crash mid-append leaves the
+//! log at its previous size and the half-written tiles are overwritten by
+//! the next append.
+
+use crate::entry::LogEntry;
+use crate::proof::{ConsistencyProof, InclusionProof};
+use crate::tile::{entries_path, tile_path, TileStore, TILE_HEIGHT, TILE_WIDTH};
+use crate::{LogError, LogResult};
+use frost_core::merkle::{leaf_hash, node_hash, SignedTreeHead};
+use frost_core::DerivedDeviceKey;
+
+const STATE_PATH: &str = "state";
+const CHECKPOINT_PATH: &str = "checkpoint";
+
+/// Append-only RFC 6962 Merkle log
+pub struct MerkleLog<S> {
+    pub(crate) store: S,
+    size: u64,
+}
+
+impl<S: TileStore> MerkleLog<S> {
+    /// Open the log kept in `store`, empty if nothing was stored yet
+    pub fn open(store: S) -> LogResult<Self> {
+        let size = match store.get(STATE_PATH)? {
+            Some(data) => u64::from_le_bytes(data.as_slice().try_into()
+                .map_err(|_| LogError::Corrupt("tree size is not 8 bytes".to_string()))?),
+            None => 0,
+        };
+        Ok(MerkleLog { store, size })
+    }
+
+    /// Number of entries
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Append `entry`, returning its index
+    pub fn append(&mut self, entry: &[u8]) -> LogResult<u64> {
+        if entry.len() > u16::MAX as usize {
+            return Err(LogError::EntryTooLarge(entry.len()));
+        }
+        let index = self.size;
+
+        let bundle = index / TILE_WIDTH;
+        let mut data = self.read_entries(bundle, index % TILE_WIDTH)?;
+        data.extend_from_slice(&(entry.len() as u16).to_be_bytes());
+        data.extend_from_slice(entry);
+        self.store.put(&entries_path(bundle, index % TILE_WIDTH + 1), &data)?;
+
+        let mut hash = leaf_hash(entry);
+        let mut position = index;
+        for level in 0.. {
+            let tile = position / TILE_WIDTH;
+            let mut hashes = self.read_tile(level, tile, position % TILE_WIDTH)?;
+            hashes.push(hash);
+            self.store.put(&tile_path(level, tile, hashes.len() as u64), &hashes.concat())?;
+            if hashes.len() < TILE_WIDTH as usize {
+                break;
+            }
+            hash = perfect_root(&hashes);
+            position = tile;
+        }
+
+        self.size = index + 1;
+        self.store.put(STATE_PATH, &self.size.to_le_bytes())?;
+        Ok(index)
+    }
+
+    /// Entry at `index`
+    pub fn entry(&self, index: u64) -> LogResult<Vec<u8>> {
+        self.check_index(index, self.size)?;
+        let bundle = index / TILE_WIDTH;
+        let width = (self.size - bundle * TILE_WIDTH).min(TILE_WIDTH);
+        let data = self.read_entries(bundle, width)?;
+
+        let mut offset = 0usize;
+        for position in 0..width {
+            let len = data.get(offset..offset + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .ok_or_else(|| LogError::Corrupt(format!("entry bundle {} is truncated", bundle)))?;
+            let entry = data.get(offset + 2..offset + 2 + len)
+                .ok_or_else(|| LogError::Corrupt(format!("entry bundle {} is truncated", bundle)))?;
+            if position == index % TILE_WIDTH {
+                return Ok(entry.to_vec());
+            }
+            offset += 2 + len;
+        }
+        Err(LogError::Corrupt(format!("entry bundle {} is truncated", bundle)))
+    }
+
+    /// Root hash of the first `tree_size` entries
+    pub fn root_hash(&self, tree_size: u64) -> LogResult<[u8; 32]> {
+        self.check_size(tree_size)?;
+        if tree_size == 0 {
+            return Ok(empty_root());
+        }
+        self.subtree_hash(0, tree_size)
+    }
+
+    /// Inclusion proof for entry `index` in the tree of size `tree_size`
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> LogResult<InclusionProof> {
+        self.check_size(tree_size)?;
+        self.check_index(index, tree_size)?;
+
+        // PATH(m, D[n]) from RFC 6962, section 2.1.1
+        let mut hashes = Vec::new();
+        let (mut start, mut end) = (0, tree_size);
+        let mut siblings = Vec::new();
+        while end - start > 1 {
+            let k = split(end - start);
+            if index < start + k {
+                siblings.push((start + k, end));
+                end = start + k;
+            } else {
+                siblings.push((start, start + k));
+                start += k;
+            }
+        }
+        for (start, end) in siblings.into_iter().rev() {
+            hashes.push(self.subtree_hash(start, end)?);
+        }
+
+        Ok(InclusionProof { leaf_index: index, tree_size, hashes })
+    }
+
+    /// Consistency proof that the tree of size `new_size` extends the one of size `old_size`
+    pub fn consistency_proof(&self, old_size: u64, new_size: u64) -> LogResult<ConsistencyProof> {
+        self.check_size(new_size)?;
+        if old_size > new_size {
+            return Err(LogError::OutOfRange { index: old_size, tree_size: new_size });
+        }
+
+        // PROOF(m, D[n]) from RFC 6962, section 2.1.2
+        let mut hashes = Vec::new();
+        if old_size > 0 && old_size < new_size {
+            let (mut start, mut end) = (0, new_size);
+            let mut m = old_size;
+            let mut complete = true;
+            let mut siblings = Vec::new();
+            while m != end - start {
+                let k = split(end - start);
+                if m <= k {
+                    siblings.push((start + k, end));
+                    end = start + k;
+                } else {
+                    siblings.push((start, start + k));
+                    start += k;
+                    m -= k;
+                    complete = false;
+                }
+            }
+            if !complete {
+                hashes.push(self.subtree_hash(start, end)?);
+            }
+            for (start, end) in siblings.into_iter().rev() {
+                hashes.push(self.subtree_hash(start, end)?);
+            }
+        }
+
+        Ok(ConsistencyProof { old_size, new_size, hashes })
+    }
+
+    /// Log the derivation statement of `key`, returning its index
+    pub fn append_derivation(&mut self, key: &DerivedDeviceKey) -> LogResult<u64> {
+        self.append(&key.derivation_statement().to_bytes())
+    }
+
+    /// Log a typed entry, such as a ceremony transcript, returning its index
+    pub fn append_entry(&mut self, entry: &LogEntry) -> LogResult<u64> {
+        self.append(&entry.to_bytes()?)
+    }
+
+    /// Attach the proof that `key` is at `log_index` under `tree_head`
+    ///
+    /// `DerivedDeviceKey::verify_derivation_proof` then accepts the key for a
+    /// policy carrying this tree head.
+    pub fn attach_inclusion_proof(
+        &self,
+        key: &mut DerivedDeviceKey,
+        log_index: u64,
+        tree_head: &SignedTreeHead,
+    ) -> LogResult<()> {
+        let proof = self.inclusion_proof(log_index, tree_head.tree_size)?;
+        if !proof.verify(&key.derivation_statement().leaf_hash(), tree_head) {
+            return Err(LogError::TreeHead("key is not at this index under the tree head".to_string()));
+        }
+        key.attach_inclusion_proof(log_index, proof.hashes);
+        Ok(())
+    }
+
+    /// Latest signed tree head, if one was stored
+    pub fn latest_tree_head(&self) -> LogResult<Option<SignedTreeHead>> {
+        self.store.get(CHECKPOINT_PATH)?
+            .map(|data| serde_json::from_slice(&data).map_err(|e| LogError::Corrupt(format!("checkpoint: {}", e))))
+            .transpose()
+    }
+
+    pub(crate) fn store_tree_head(&mut self, tree_head: &SignedTreeHead) -> LogResult<()> {
+        let data = serde_json::to_vec(tree_head).map_err(|e| LogError::Storage(e.to_string()))?;
+        self.store.put(CHECKPOINT_PATH, &data)
+    }
+
+    fn check_size(&self, tree_size: u64) -> LogResult<()> {
+        if tree_size > self.size {
+            return Err(LogError::OutOfRange { index: tree_size, tree_size: self.size });
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, index: u64, tree_size: u64) -> LogResult<()> {
+        if index >= tree_size {
+            return Err(LogError::OutOfRange { index, tree_size });
+        }
+        Ok(())
+    }
+
+    fn read_entries(&self, bundle: u64, width: u64) -> LogResult<Vec<u8>> {
+        if width == 0 {
+            return Ok(Vec::new());
+        }
+        self.store.get(&entries_path(bundle, width))?
+            .ok_or_else(|| LogError::Corrupt(format!("missing entry bundle {}", entries_path(bundle, width))))
+    }
+
+    fn read_tile(&self, level: u32, index: u64, width: u64) -> LogResult<Vec<[u8; 32]>> {
+        if width == 0 {
+            return Ok(Vec::new());
+        }
+        let path = tile_path(level, index, width);
+        let data = self.store.get(&path)?
+            .ok_or_else(|| LogError::Corrupt(format!("missing tile {}", path)))?;
+        if data.len() != width as usize * 32 {
+            return Err(LogError::Corrupt(format!("tile {} has the wrong length", path)));
+        }
+        Ok(data.chunks_exact(32).map(|c| c.try_into().expect("32-byte chunk")).collect())
+    }
+
+    /// Hash of the complete subtree at `level` and `index`, whose leaves all exist
+    fn node(&self, level: u32, index: u64) -> LogResult<[u8; 32]> {
+        let tile_level = level / TILE_HEIGHT;
+        let span = 1u64 << (level % TILE_HEIGHT);
+        let first = index * span;
+        let tile = first / TILE_WIDTH;
+        let available = self.size >> (tile_level * TILE_HEIGHT);
+        let width = (available - tile * TILE_WIDTH).min(TILE_WIDTH);
+
+        let hashes = self.read_tile(tile_level, tile, width)?;
+        let offset = (first % TILE_WIDTH) as usize;
+        let hashes = hashes.get(offset..offset + span as usize)
+            .ok_or_else(|| LogError::Corrupt(format!("tile {} is short", tile_path(tile_level, tile, width))))?;
+        Ok(perfect_root(hashes))
+    }
+
+    /// MTH of leaves `start..end`, where `start` is aligned to the subtree split
+    fn subtree_hash(&self, start: u64, end: u64) -> LogResult<[u8; 32]> {
+        let n = end - start;
+        if n.is_power_of_two() && start.is_multiple_of(n) {
+            return self.node(n.trailing_zeros(), start / n);
+        }
+        let k = split(n);
+        Ok(node_hash(&self.subtree_hash(start, start + k)?, &self.subtree_hash(start + k, end)?))
+    }
+}
+
+/// Largest power of two smaller than n (n > 1)
+fn split(n: u64) -> u64 {
+    1 << (63 - (n - 1).leading_zeros())
+}
+
+/// Root of a power-of-two number of hashes
+fn perfect_root(hashes: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = hashes.to_vec();
+    while level.len() > 1 {
+        level = level.chunks_exact(2).map(|pair| node_hash(&pair[0], &pair[1])).collect();
+    }
+    level[0]
+}
+
+/// MTH of the empty tree: SHA-256 of the empty string
+fn empty_root() -> [u8; 32] {
+    [
+        0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24,
+        0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::{FileTileStore, MemoryTileStore};
+    use frost_core::merkle::{verify_consistency, verify_inclusion};
+
+    /// MTH(D[n]) computed directly from the leaves
+    fn reference_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        match leaves.len() {
+            1 => leaves[0],
+            n => {
+                let k = split(n as u64) as usize;
+                node_hash(&reference_root(&leaves[..k]), &reference_root(&leaves[k..]))
+            }
+        }
+    }
+
+    #[test]
+    fn test_roots_and_proofs_across_tiles() {
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        assert_eq!(log.root_hash(0).unwrap(), empty_root());
+
+        let entries: Vec<_> = (0u32..530).map(|i| format!("entry {}", i).into_bytes()).collect();
+        let leaves: Vec<_> = entries.iter().map(|e| leaf_hash(e)).collect();
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(log.append(entry).unwrap(), i as u64);
+        }
+
+        for size in [1u64, 2, 7, 255, 256, 257, 511, 512, 513, 530] {
+            let root = log.root_hash(size).unwrap();
+            assert_eq!(root, reference_root(&leaves[..size as usize]));
+
+            for index in [0, size / 3, size - 1] {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                assert!(verify_inclusion(&leaves[index as usize], index, size, &proof.hashes, &root));
+            }
+            for old_size in [1, size / 2, size - 1, size] {
+                let proof = log.consistency_proof(old_size.max(1), size).unwrap();
+                let old_root = log.root_hash(old_size.max(1)).unwrap();
+                assert!(verify_consistency(old_size.max(1), size, &proof.hashes, &old_root, &root));
+            }
+        }
+
+        assert_eq!(log.entry(300).unwrap(), entries[300]);
+        assert!(log.inclusion_proof(530, 530).is_err());
+        assert!(log.root_hash(531).is_err());
+    }
+
+    #[test]
+    fn test_file_log_reopens() {
+        let dir = std::env::temp_dir().join(format!("frost-tlog-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let root = {
+            let mut log = MerkleLog::open(FileTileStore::new(dir.clone())).unwrap();
+            for i in 0u32..300 {
+                log.append(&i.to_le_bytes()).unwrap();
+            }
+            log.root_hash(300).unwrap()
+        };
+
+        let mut log = MerkleLog::open(FileTileStore::new(dir.clone())).unwrap();
+        assert_eq!(log.size(), 300);
+        assert_eq!(log.root_hash(300).unwrap(), root);
+        assert_eq!(log.append(b"next").unwrap(), 300);
+        assert_eq!(log.entry(299).unwrap(), 299u32.to_le_bytes());
+        assert!(dir.join("tile/0/001.p/45").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+//! Inclusion and consistency proofs
+//!
+//! Verification uses `frost_core::merkle`, the same code a device runs in
+//! `DerivedDeviceKey::verify_derivation_proof`, so a proof accepted here is
+//! accepted there.
+
+use frost_core::merkle::{verify_consistency, verify_inclusion, SignedTreeHead};
+use serde::{Deserialize, Serialize};
+
+/// Proof that an entry is in the tree of a given size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Index of the entry
+    pub leaf_index: u64,
+    /// Size of the tree the proof is for
+    pub tree_size: u64,
+    /// Sibling hashes from the leaf up to the root
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+    /// Verify that `leaf_hash` is included under `tree_head`
+    pub fn verify(&self, leaf_hash: &[u8; 32], tree_head: &SignedTreeHead) -> bool {
+        self.tree_size == tree_head.tree_size
+            && verify_inclusion(leaf_hash, self.leaf_index, self.tree_size, &self.hashes, &tree_head.root_hash)
+    }
+}
+
+/// Proof that a tree extends an earlier, smaller one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    /// Size of the earlier tree
+    pub old_size: u64,
+    /// Size of the later tree
+    pub new_size: u64,
+    /// Subtree hashes as in RFC 9162, section 2.1.4
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl ConsistencyProof {
+    /// Verify that `new` extends `old`
+    pub fn verify(&self, old: &SignedTreeHead, new: &SignedTreeHead) -> bool {
+        self.old_size == old.tree_size
+            && self.new_size == new.tree_size
+            && verify_consistency(self.old_size, self.new_size, &self.hashes, &old.root_hash, &new.root_hash)
+    }
+}
@@ -0,0 +1,143 @@
+//! Split-view detection
+//!
+//! Two validly signed tree heads of the same log that cannot both be
+//! prefixes of one append-only tree are proof the log showed different
+//! views to different parties. `SplitViewMonitor` collects tree heads from
+//! devices, witnesses and gossip, checks each against its neighbours by
+//! size, and keeps any such pair as evidence.
+
+use crate::log::MerkleLog;
+use crate::proof::ConsistencyProof;
+use crate::tile::TileStore;
+use crate::{LogError, LogResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::merkle::SignedTreeHead;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Two signed tree heads the log cannot show to be one tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitView {
+    /// Tree head seen earlier
+    pub first: SignedTreeHead,
+    /// Tree head that conflicts with it, outside the prover's view
+    pub second: SignedTreeHead,
+}
+
+/// Source of consistency proofs, such as a local mirror of the log's tiles
+pub trait ConsistencyProver {
+    /// Proof that the tree of `new_size` extends the tree of `old_size`
+    fn consistency_proof(&self, old_size: u64, new_size: u64) -> LogResult<ConsistencyProof>;
+}
+
+impl<S: TileStore> ConsistencyProver for MerkleLog<S> {
+    fn consistency_proof(&self, old_size: u64, new_size: u64) -> LogResult<ConsistencyProof> {
+        MerkleLog::consistency_proof(self, old_size, new_size)
+    }
+}
+
+/// Tree heads of one log seen so far, and the conflicts among them
+pub struct SplitViewMonitor {
+    log_public_key: CompressedRistretto,
+    tree_heads: BTreeMap<u64, SignedTreeHead>,
+    conflicts: Vec<SplitView>,
+}
+
+impl SplitViewMonitor {
+    /// Monitor for the log with `log_public_key`
+    pub fn new(log_public_key: CompressedRistretto) -> Self {
+        SplitViewMonitor { log_public_key, tree_heads: BTreeMap::new(), conflicts: Vec::new() }
+    }
+
+    /// Check `tree_head` against the tree heads seen so far
+    ///
+    /// Returns the conflict when it has one. Tree heads not signed by the
+    /// log are an error, not evidence. A proof from `prover` that fails to
+    /// verify is evidence, since a mirror of one tree proves every pair of
+    /// its own tree heads consistent.
+    pub fn observe(&mut self, tree_head: SignedTreeHead, prover: &dyn ConsistencyProver) -> LogResult<Option<SplitView>> {
+        if !tree_head.verify(&self.log_public_key) {
+            return Err(LogError::TreeHead(format!("tree head at size {} is not signed by the log", tree_head.tree_size)));
+        }
+
+        let size = tree_head.tree_size;
+        let conflict = if let Some(seen) = self.tree_heads.get(&size) {
+            (seen.root_hash != tree_head.root_hash).then(|| seen.clone())
+        } else {
+            // An off-view tree head may still extend a smaller seen one, so the
+            // larger neighbour is the better witness against it
+            let larger = self.tree_heads.range(size + 1..).next().map(|(_, t)| t);
+            let smaller = self.tree_heads.range(..size).next_back().map(|(_, t)| t);
+            let mut conflict = None;
+            if let Some(newer) = larger {
+                if !prover.consistency_proof(size, newer.tree_size)?.verify(&tree_head, newer) {
+                    conflict = Some(newer.clone());
+                }
+            }
+            if let (None, Some(older)) = (&conflict, smaller) {
+                if !prover.consistency_proof(older.tree_size, size)?.verify(older, &tree_head) {
+                    conflict = Some(older.clone());
+                }
+            }
+            conflict
+        };
+
+        match conflict {
+            Some(first) => {
+                log::warn!("Split view: tree heads at sizes {} and {} conflict", first.tree_size, size);
+                let split = SplitView { first, second: tree_head };
+                self.conflicts.push(split.clone());
+                Ok(Some(split))
+            }
+            None => {
+                self.tree_heads.entry(size).or_insert(tree_head);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Conflicts found so far
+    pub fn conflicts(&self) -> &[SplitView] {
+        &self.conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::MemoryTileStore;
+    use crate::tree_head::tests::log_signer;
+
+    #[tokio::test]
+    async fn test_monitor_flags_forked_tree_heads() {
+        let (mut coordinator, log_key) = log_signer();
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let mut fork = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let mut monitor = SplitViewMonitor::new(log_key.public_key);
+
+        log.append(b"shared").unwrap();
+        fork.append(b"shared").unwrap();
+        let shared = log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_000).await.unwrap();
+        for entry in [b"a", b"b", b"c"] {
+            log.append(entry).unwrap();
+        }
+        let honest = log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_060).await.unwrap();
+        fork.append(b"x").unwrap();
+        let forked = fork.sign_tree_head(&mut coordinator, &log_key, 1_700_000_060).await.unwrap();
+
+        assert!(monitor.observe(honest.clone(), &log).unwrap().is_none());
+        assert!(monitor.observe(shared, &log).unwrap().is_none());
+        assert!(monitor.observe(honest, &log).unwrap().is_none());
+
+        // Size 2 on the fork extends size 1 but not size 4
+        let split = monitor.observe(forked.clone(), &log).unwrap().unwrap();
+        assert_eq!((split.first.tree_size, split.second.tree_size), (4, 2));
+        assert_eq!(split.second.root_hash, forked.root_hash);
+        assert_eq!(monitor.conflicts().len(), 1);
+
+        // Tree heads not signed by the log are refused outright
+        let (_, other_key) = log_signer();
+        let mut other = SplitViewMonitor::new(other_key.public_key);
+        assert!(other.observe(log.latest_tree_head().unwrap().unwrap(), &log).is_err());
+    }
+}
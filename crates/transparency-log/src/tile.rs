@@ -0,0 +1,142 @@
+//! Tile storage layout
+//!
+//! Follows the C2SP tlog-tiles layout. Hashes are stored in tiles of height
+//! 8: tile `(L, N)` holds up to 256 consecutive node hashes from tree level
+//! 8L, starting at node 256N. Entries are stored in bundles of 256 with a
+//! 2-byte big-endian length prefix each. Paths are
+//!
+//! ```text
+//! tile/<L>/<N>            full hash tile
+//! tile/<L>/<N>.p/<W>      partial hash tile of width W
+//! tile/entries/<N>        full entry bundle (and .p/<W> when partial)
+//! state                   tree size, written after the tiles it covers
+//! checkpoint              latest signed tree head (JSON)
+//! ```
+//!
+//! N is written in groups of three digits, all but the last prefixed with
+//! `x`: tile 1234067 is `x001/x234/067`.
+
+use crate::{LogError, LogResult};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Tile height: each tile spans 8 tree levels
+pub const TILE_HEIGHT: u32 = 8;
+
+/// Hashes or entries per full tile
+pub const TILE_WIDTH: u64 = 1 << TILE_HEIGHT;
+
+/// Path of hash tile `index` at tile level `level`, holding `width` hashes
+pub fn tile_path(level: u32, index: u64, width: u64) -> String {
+    with_width(format!("tile/{}/{}", level, encode_index(index)), width)
+}
+
+/// Path of entry bundle `index`, holding `width` entries
+pub fn entries_path(index: u64, width: u64) -> String {
+    with_width(format!("tile/entries/{}", encode_index(index)), width)
+}
+
+fn with_width(path: String, width: u64) -> String {
+    if width == TILE_WIDTH {
+        path
+    } else {
+        format!("{}.p/{}", path, width)
+    }
+}
+
+fn encode_index(mut index: u64) -> String {
+    let mut groups = vec![format!("{:03}", index % 1000)];
+    index /= 1000;
+    while index > 0 {
+        groups.push(format!("x{:03}", index % 1000));
+        index /= 1000;
+    }
+    groups.reverse();
+    groups.join("/")
+}
+
+/// Storage for tiles, entry bundles and log state
+pub trait TileStore: Send {
+    /// Read the object at `path`, `None` if it was never written
+    fn get(&self, path: &str) -> LogResult<Option<Vec<u8>>>;
+
+    /// Durably write the object at `path`
+    fn put(&mut self, path: &str, data: &[u8]) -> LogResult<()>;
+}
+
+/// Tiles as files under a directory, each replaced atomically
+pub struct FileTileStore {
+    root: PathBuf,
+}
+
+impl FileTileStore {
+    /// Store rooted at `root`, created on first write
+    pub fn new(root: PathBuf) -> Self {
+        FileTileStore { root }
+    }
+}
+
+fn storage_error(path: &str, e: std::io::Error) -> LogError {
+    LogError::Storage(format!("{}: {}", path, e))
+}
+
+impl TileStore for FileTileStore {
+    fn get(&self, path: &str) -> LogResult<Option<Vec<u8>>> {
+        match std::fs::read(self.root.join(path)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(path, e)),
+        }
+    }
+
+    fn put(&mut self, path: &str, data: &[u8]) -> LogResult<()> {
+        let target = self.root.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| storage_error(path, e))?;
+        }
+
+        let tmp = target.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp).map_err(|e| storage_error(path, e))?;
+        file.write_all(data).map_err(|e| storage_error(path, e))?;
+        file.sync_all().map_err(|e| storage_error(path, e))?;
+        std::fs::rename(&tmp, &target).map_err(|e| storage_error(path, e))
+    }
+}
+
+/// Tiles held in memory, for tests and short-lived logs
+#[derive(Default)]
+pub struct MemoryTileStore {
+    objects: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryTileStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TileStore for MemoryTileStore {
+    fn get(&self, path: &str) -> LogResult<Option<Vec<u8>>> {
+        Ok(self.objects.get(path).cloned())
+    }
+
+    fn put(&mut self, path: &str, data: &[u8]) -> LogResult<()> {
+        self.objects.insert(path.to_string(), data.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_paths() {
+        assert_eq!(tile_path(0, 0, 256), "tile/0/000");
+        assert_eq!(tile_path(1, 1234067, 256), "tile/1/x001/x234/067");
+        assert_eq!(tile_path(0, 5, 17), "tile/0/005.p/17");
+        assert_eq!(entries_path(1000, 3), "tile/entries/x001/000.p/3");
+    }
+}
@@ -0,0 +1,147 @@
+//! Tree heads signed by the FROST group
+//!
+//! The log operator holds no signing key. Each tree head is signed by a
+//! signing ceremony over `SignedTreeHead::to_signing_data`, so publishing a
+//! tree head needs t share holders, just like signing anything else with
+//! the group key.
+
+use crate::log::MerkleLog;
+use crate::tile::TileStore;
+use crate::{LogError, LogResult};
+use frost_coordinator::{Coordinator, ParticipantLink};
+use frost_core::merkle::SignedTreeHead;
+use frost_core::GroupPublicKey;
+
+impl<S: TileStore> MerkleLog<S> {
+    /// Sign the current tree head through `coordinator` and store it as the checkpoint
+    ///
+    /// `timestamp` (Unix epoch seconds) may not go back from the stored
+    /// checkpoint's.
+    pub async fn sign_tree_head<L: ParticipantLink>(
+        &mut self,
+        coordinator: &mut Coordinator<L>,
+        group_public_key: &GroupPublicKey,
+        timestamp: u64,
+    ) -> LogResult<SignedTreeHead> {
+        if let Some(previous) = self.latest_tree_head()? {
+            if timestamp < previous.timestamp {
+                return Err(LogError::TreeHead(format!(
+                    "timestamp {} is before the checkpoint's {}",
+                    timestamp, previous.timestamp
+                )));
+            }
+        }
+
+        let tree_size = self.size();
+        let root_hash = self.root_hash(tree_size)?;
+        let data = SignedTreeHead::to_signing_data(tree_size, timestamp, &root_hash);
+        let outcome = coordinator.sign(group_public_key, &data).await?;
+
+        let tree_head = SignedTreeHead {
+            tree_size,
+            timestamp,
+            root_hash,
+            signature: outcome.signature,
+            cosignatures: Vec::new(),
+        };
+        if !tree_head.verify(&group_public_key.public_key) {
+            return Err(LogError::TreeHead("group signature does not verify".to_string()));
+        }
+
+        self.store_tree_head(&tree_head)?;
+        log::info!("Signed tree head at size {}", tree_size);
+        Ok(tree_head)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::tile::MemoryTileStore;
+    use crate::witness::{Witness, WitnessClient};
+    use crate::MerkleLog;
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, scalar::Scalar};
+    use frost_coordinator::{CeremonyParticipant, ChannelLink, Coordinator, CoordinatorConfig};
+    use frost_core::derived_key::DeviceAttestation;
+    use frost_core::dkg::DkgCoordinator;
+    use frost_core::merkle::{WitnessKey, WitnessPolicy};
+    use frost_core::{DerivationParticipant, DerivationPolicy, DerivationSession, DerivedDeviceKey, DkgOutput, GroupPublicKey};
+    use rand::rngs::OsRng;
+
+    /// Coordinator over a fresh 2-of-3 log key, and that key
+    pub(crate) fn log_signer() -> (Coordinator<ChannelLink>, GroupPublicKey) {
+        let log_outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let holders = log_outputs.iter()
+            .map(|o| CeremonyParticipant::with_key(o.secret_share.clone(), o.group_public_key.clone()))
+            .collect();
+        let (link, _handles) = ChannelLink::spawn(holders);
+        (Coordinator::new(link, CoordinatorConfig::default()), log_outputs[0].group_public_key.clone())
+    }
+
+    fn derived_key(dkg_outputs: &[DkgOutput], device_id: [u8; 32]) -> DerivedDeviceKey {
+        let group_public_key = &dkg_outputs[0].group_public_key;
+        let mut participants: Vec<_> = dkg_outputs.iter()
+            .map(|o| DerivationParticipant::new(o.secret_share.clone(), group_public_key, [0u8; 32]).unwrap())
+            .collect();
+        let attestation = DeviceAttestation {
+            firmware_hash: [0u8; 32],
+            hardware_id: device_id,
+            tamper_status: 0,
+            boot_measurements: vec![],
+        };
+
+        let session = DerivationSession::new(device_id, 1, attestation, &mut OsRng);
+        let request = session.request();
+        let partials: Vec<_> = participants[..2].iter_mut().map(|p| p.evaluate(&request, &mut OsRng).unwrap()).collect();
+        let mut key = session.combine(group_public_key, [0u8; 32], &partials).unwrap();
+
+        let statement = key.derivation_statement();
+        let proof = session.prove_possession(&key, &mut OsRng).unwrap();
+        let signatures = participants[..2].iter_mut()
+            .map(|p| p.sign_statement(&statement, &proof, &mut OsRng).unwrap())
+            .collect();
+        key.attach_participant_signatures(group_public_key, signatures).unwrap();
+        key
+    }
+
+    #[tokio::test]
+    async fn test_logged_key_passes_derivation_check() {
+        let ceremony_outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let (mut coordinator, log_key) = log_signer();
+        let witness = Witness::new(WitnessKey::generate(&mut OsRng), vec![log_key.public_key], MemoryTileStore::new());
+        let witness_key = witness.public_key();
+        let mut witnesses: Vec<Box<dyn WitnessClient>> = vec![Box::new(witness)];
+
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        log.append(b"earlier entry").unwrap();
+        let mut key = derived_key(&ceremony_outputs, [7u8; 32]);
+        let index = log.append_derivation(&key).unwrap();
+        log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_000).await.unwrap();
+        let first = log.cosign_tree_head(&log_key.public_key, &mut witnesses, 1).await.unwrap();
+        log.attach_inclusion_proof(&mut key, index, &first).unwrap();
+
+        let policy = DerivationPolicy {
+            ceremony_key: ceremony_outputs[0].group_public_key.clone(),
+            tree_head: first.clone(),
+            log_public_key: log_key.public_key,
+            witnesses: WitnessPolicy { witnesses: vec![witness_key], threshold: 1 },
+            allowed_firmware: vec![[0u8; 32]],
+        };
+        let verdict = key.verify_derivation_proof(&policy);
+        assert!(verdict.is_valid(), "{:?}", verdict.failures);
+        assert_eq!(log.latest_tree_head().unwrap().unwrap().root_hash, first.root_hash);
+
+        // Later tree heads extend the first and cannot go back in time
+        log.append(b"later entry").unwrap();
+        let second = log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_060).await.unwrap();
+        assert!(log.consistency_proof(first.tree_size, second.tree_size).unwrap().verify(&first, &second));
+        assert!(log.sign_tree_head(&mut coordinator, &log_key, 1_600_000_000).await.is_err());
+
+        // A tree head signed by anyone else, or without the witness, is rejected
+        let mut forged = policy.clone();
+        forged.log_public_key = (Scalar::from(7u8) * RISTRETTO_BASEPOINT_POINT).compress();
+        assert!(!key.verify_derivation_proof(&forged).is_valid());
+        let mut unwitnessed = policy;
+        unwitnessed.tree_head.cosignatures.clear();
+        assert!(!key.verify_derivation_proof(&unwitnessed).is_valid());
+    }
+}
@@ -0,0 +1,262 @@
+//! Witness cosigning
+//!
+//! Modelled on C2SP tlog-witness. The log submits each new tree head with a
+//! consistency proof from the size the witness last cosigned; the witness
+//! checks the log's signature and the proof, records the new size and
+//! countersigns. A witness never cosigns two trees that do not extend one
+//! another, so a log that shows different views to different devices cannot
+//! collect cosignatures for both from the same witness.
+//!
+//! When the log assumes the wrong old size, the witness answers
+//! `LogError::Conflict` with the size it is at, and the log retries from
+//! there.
+
+use crate::log::MerkleLog;
+use crate::tile::TileStore;
+use crate::{LogError, LogResult};
+use async_trait::async_trait;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::merkle::{verify_consistency, Cosignature, SignedTreeHead, WitnessKey};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tree head submitted to a witness
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddCheckpoint {
+    /// Group public key of the log
+    pub log_public_key: CompressedRistretto,
+    /// Tree size the log believes the witness last cosigned
+    pub old_size: u64,
+    /// Consistency proof from `old_size` to the tree head's size
+    pub consistency_proof: Vec<[u8; 32]>,
+    /// Tree head to cosign
+    pub tree_head: SignedTreeHead,
+}
+
+/// Last tree a witness cosigned for one log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WitnessedTree {
+    tree_size: u64,
+    root_hash: [u8; 32],
+}
+
+/// Witness for a set of logs, keeping what it cosigned in `store`
+pub struct Witness<S: TileStore> {
+    key: WitnessKey,
+    logs: Vec<CompressedRistretto>,
+    store: S,
+}
+
+impl<S: TileStore> Witness<S> {
+    /// Witness cosigning with `key` for the logs with public keys `logs`
+    pub fn new(key: WitnessKey, logs: Vec<CompressedRistretto>, store: S) -> Self {
+        Witness { key, logs, store }
+    }
+
+    /// Key verifiers list in their `WitnessPolicy`
+    pub fn public_key(&self) -> CompressedRistretto {
+        self.key.public_key()
+    }
+
+    /// Size of the last tree cosigned for `log_public_key`, 0 before the first
+    pub fn witnessed_size(&self, log_public_key: &CompressedRistretto) -> LogResult<u64> {
+        Ok(self.witnessed(log_public_key)?.map_or(0, |w| w.tree_size))
+    }
+
+    /// Check `request` extends the last cosigned tree and cosign it at `now`
+    pub fn add_checkpoint(&mut self, request: &AddCheckpoint, now: u64) -> LogResult<Cosignature> {
+        let log_public_key = &request.log_public_key;
+        if !self.logs.contains(log_public_key) {
+            return Err(LogError::UnknownLog(hex::encode(log_public_key.as_bytes())));
+        }
+        let tree_head = &request.tree_head;
+        if !tree_head.verify(log_public_key) {
+            return Err(LogError::TreeHead("log signature does not verify".to_string()));
+        }
+
+        let (old_size, old_root) = self.witnessed(log_public_key)?
+            .map_or((0, [0u8; 32]), |w| (w.tree_size, w.root_hash));
+        if request.old_size != old_size {
+            return Err(LogError::Conflict { witnessed_size: old_size });
+        }
+        if !verify_consistency(old_size, tree_head.tree_size, &request.consistency_proof, &old_root, &tree_head.root_hash) {
+            log::warn!(
+                "Log {} tree head at size {} does not extend the cosigned size {}",
+                hex::encode(log_public_key.as_bytes()), tree_head.tree_size, old_size
+            );
+            return Err(LogError::TreeHead("consistency proof does not verify".to_string()));
+        }
+
+        let witnessed = WitnessedTree { tree_size: tree_head.tree_size, root_hash: tree_head.root_hash };
+        let data = serde_json::to_vec(&witnessed).map_err(|e| LogError::Storage(e.to_string()))?;
+        self.store.put(&witnessed_path(log_public_key), &data)?;
+        Ok(self.key.cosign(log_public_key, tree_head, now, &mut OsRng))
+    }
+
+    fn witnessed(&self, log_public_key: &CompressedRistretto) -> LogResult<Option<WitnessedTree>> {
+        let path = witnessed_path(log_public_key);
+        self.store.get(&path)?
+            .map(|data| serde_json::from_slice(&data).map_err(|e| LogError::Corrupt(format!("{}: {}", path, e))))
+            .transpose()
+    }
+}
+
+fn witnessed_path(log_public_key: &CompressedRistretto) -> String {
+    format!("witness/{}", hex::encode(log_public_key.as_bytes()))
+}
+
+/// A witness as the log sees it, local or remote
+#[async_trait]
+pub trait WitnessClient: Send {
+    /// Key the witness cosigns with
+    fn public_key(&self) -> CompressedRistretto;
+
+    /// Submit a tree head for cosigning
+    async fn add_checkpoint(&mut self, request: &AddCheckpoint) -> LogResult<Cosignature>;
+}
+
+#[async_trait]
+impl<S: TileStore> WitnessClient for Witness<S> {
+    fn public_key(&self) -> CompressedRistretto {
+        Witness::public_key(self)
+    }
+
+    async fn add_checkpoint(&mut self, request: &AddCheckpoint) -> LogResult<Cosignature> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Witness::add_checkpoint(self, request, now)
+    }
+}
+
+impl<S: TileStore> MerkleLog<S> {
+    /// Collect cosignatures on the stored checkpoint and store them with it
+    ///
+    /// Witnesses that fail are logged and skipped; fails when fewer than
+    /// `required` cosign.
+    pub async fn cosign_tree_head(
+        &mut self,
+        log_public_key: &CompressedRistretto,
+        witnesses: &mut [Box<dyn WitnessClient>],
+        required: usize,
+    ) -> LogResult<SignedTreeHead> {
+        let mut tree_head = self.latest_tree_head()?
+            .ok_or_else(|| LogError::TreeHead("no signed tree head to cosign".to_string()))?;
+
+        let mut cosigned = 0;
+        for witness in witnesses.iter_mut() {
+            let witness_key = witness.public_key();
+            match self.request_cosignature(log_public_key, witness.as_mut(), &tree_head).await {
+                Ok(cosignature) => {
+                    tree_head.cosignatures.retain(|c| c.witness_key != witness_key);
+                    tree_head.cosignatures.push(cosignature);
+                    cosigned += 1;
+                }
+                Err(e) => log::warn!("Witness {} did not cosign: {}", hex::encode(witness_key.as_bytes()), e),
+            }
+        }
+
+        self.store_tree_head(&tree_head)?;
+        if cosigned < required {
+            return Err(LogError::TreeHead(format!("{} witnesses cosigned, need {}", cosigned, required)));
+        }
+        log::info!("Tree head at size {} cosigned by {} witnesses", tree_head.tree_size, cosigned);
+        Ok(tree_head)
+    }
+
+    async fn request_cosignature(
+        &mut self,
+        log_public_key: &CompressedRistretto,
+        witness: &mut dyn WitnessClient,
+        tree_head: &SignedTreeHead,
+    ) -> LogResult<Cosignature> {
+        let witness_key = witness.public_key();
+        let path = format!("witnessed/{}", hex::encode(witness_key.as_bytes()));
+        let mut old_size = match self.store.get(&path)? {
+            Some(data) => serde_json::from_slice(&data).map_err(|e| LogError::Corrupt(format!("{}: {}", path, e)))?,
+            None => 0,
+        };
+
+        // A witness that moved on without us (or lost our record) says where it is; retry once from there
+        let mut retried = false;
+        let cosignature = loop {
+            let request = AddCheckpoint {
+                log_public_key: *log_public_key,
+                old_size,
+                consistency_proof: self.consistency_proof(old_size, tree_head.tree_size)?.hashes,
+                tree_head: tree_head.clone(),
+            };
+            match witness.add_checkpoint(&request).await {
+                Err(LogError::Conflict { witnessed_size }) if !retried && witnessed_size <= tree_head.tree_size => {
+                    old_size = witnessed_size;
+                    retried = true;
+                }
+                result => break result?,
+            }
+        };
+
+        if cosignature.witness_key != witness_key || !cosignature.verify(log_public_key, tree_head) {
+            return Err(LogError::Witness("cosignature does not verify".to_string()));
+        }
+        let data = serde_json::to_vec(&tree_head.tree_size).map_err(|e| LogError::Storage(e.to_string()))?;
+        self.store.put(&path, &data)?;
+        Ok(cosignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::MemoryTileStore;
+    use crate::tree_head::tests::log_signer;
+
+    #[tokio::test]
+    async fn test_witness_cosigns_only_extensions() {
+        let (mut coordinator, log_key) = log_signer();
+        let log_public_key = log_key.public_key;
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let witnesses: Vec<Witness<MemoryTileStore>> = (0..2)
+            .map(|_| Witness::new(WitnessKey::generate(&mut OsRng), vec![log_public_key], MemoryTileStore::new()))
+            .collect();
+        let witness_keys: Vec<_> = witnesses.iter().map(|w| w.public_key()).collect();
+        let mut clients: Vec<Box<dyn WitnessClient>> = witnesses.into_iter()
+            .map(|w| Box::new(w) as Box<dyn WitnessClient>)
+            .collect();
+
+        log.append(b"first").unwrap();
+        log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_000).await.unwrap();
+        let first = log.cosign_tree_head(&log_public_key, &mut clients, 2).await.unwrap();
+        assert_eq!(first.cosignatures.len(), 2);
+        assert_eq!(log.latest_tree_head().unwrap().unwrap().cosignatures.len(), 2);
+
+        // The log forgot what the witnesses saw: they say where they are and it retries
+        log.store.put(&format!("witnessed/{}", hex::encode(witness_keys[0].as_bytes())), b"0").unwrap();
+        log.append(b"second").unwrap();
+        log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_060).await.unwrap();
+        let second = log.cosign_tree_head(&log_public_key, &mut clients, 2).await.unwrap();
+        assert!(second.cosignatures.iter().all(|c| c.verify(&log_public_key, &second)));
+
+        // A fork of the first tree is not an extension of the second
+        let mut fork = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        fork.append(b"first").unwrap();
+        fork.append(b"forked").unwrap();
+        let forked = fork.sign_tree_head(&mut coordinator, &log_key, 1_700_000_120).await.unwrap();
+        let request = AddCheckpoint {
+            log_public_key,
+            old_size: 2,
+            consistency_proof: Vec::new(),
+            tree_head: forked,
+        };
+        let mut witness = Witness::new(WitnessKey::generate(&mut OsRng), vec![log_public_key], MemoryTileStore::new());
+        assert!(witness.add_checkpoint(&AddCheckpoint { old_size: 0, tree_head: second.clone(), ..request.clone() }, 1).is_ok());
+        assert!(matches!(witness.add_checkpoint(&request, 2), Err(LogError::TreeHead(_))));
+        assert!(matches!(
+            witness.add_checkpoint(&AddCheckpoint { old_size: 1, ..request.clone() }, 2),
+            Err(LogError::Conflict { witnessed_size: 2 })
+        ));
+
+        // Unknown logs and too few witnesses are refused
+        let mut stranger = Witness::new(WitnessKey::generate(&mut OsRng), Vec::new(), MemoryTileStore::new());
+        assert!(matches!(stranger.add_checkpoint(&request, 2), Err(LogError::UnknownLog(_))));
+        assert!(log.cosign_tree_head(&log_public_key, &mut [], 1).await.is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Log-side client for a remote witness
+
+use crate::http::{ErrorBody, ADD_CHECKPOINT_PATH, MAX_BODY_BYTES};
+use crate::{WitnessError, WitnessResult};
+use async_trait::async_trait;
+use bytes::Bytes;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::merkle::Cosignature;
+use frost_transparency_log::{AddCheckpoint, LogError, LogResult, WitnessClient};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{Method, Request, StatusCode, Uri};
+use hyper_util::rt::TokioIo;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// Default time allowed for one checkpoint
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Witness reached over HTTP
+pub struct HttpWitnessClient {
+    authority: String,
+    public_key: CompressedRistretto,
+    timeout: Duration,
+}
+
+impl HttpWitnessClient {
+    /// Client for the witness at `url` (`http://host:port`) cosigning with `public_key`
+    pub fn new(url: &str, public_key: CompressedRistretto) -> WitnessResult<Self> {
+        let uri: Uri = url.parse()
+            .map_err(|e| WitnessError::Config(format!("{}: {}", url, e)))?;
+        let authority = match (uri.scheme_str(), uri.authority()) {
+            (Some("http"), Some(authority)) => authority.to_string(),
+            _ => return Err(WitnessError::Config(format!("{}: expected http://host:port", url))),
+        };
+        Ok(HttpWitnessClient { authority, public_key, timeout: DEFAULT_TIMEOUT })
+    }
+
+    /// Set the time allowed for one checkpoint
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn post(&self, body: Vec<u8>) -> LogResult<(StatusCode, Bytes)> {
+        let transport = |e: &dyn std::fmt::Display| LogError::Witness(format!("{}: {}", self.authority, e));
+
+        let tcp = TcpStream::connect(&self.authority).await.map_err(|e| transport(&e))?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(tcp)).await
+            .map_err(|e| transport(&e))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::debug!("Witness connection closed: {}", e);
+            }
+        });
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(ADD_CHECKPOINT_PATH)
+            .header(hyper::header::HOST, &self.authority)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| transport(&e))?;
+        let response = sender.send_request(request).await.map_err(|e| transport(&e))?;
+        let status = response.status();
+        let body = Limited::new(response.into_body(), MAX_BODY_BYTES).collect().await
+            .map_err(|e| transport(&e))?
+            .to_bytes();
+        Ok((status, body))
+    }
+}
+
+#[async_trait]
+impl WitnessClient for HttpWitnessClient {
+    fn public_key(&self) -> CompressedRistretto {
+        self.public_key
+    }
+
+    async fn add_checkpoint(&mut self, request: &AddCheckpoint) -> LogResult<Cosignature> {
+        let body = serde_json::to_vec(request).map_err(|e| LogError::Witness(e.to_string()))?;
+        let (status, body) = tokio::time::timeout(self.timeout, self.post(body)).await
+            .map_err(|_| LogError::Witness(format!("{}: timed out", self.authority)))??;
+
+        if status == StatusCode::OK {
+            return serde_json::from_slice(&body)
+                .map_err(|e| LogError::Witness(format!("{}: {}", self.authority, e)));
+        }
+        let refusal: ErrorBody = serde_json::from_slice(&body).unwrap_or_else(|_| ErrorBody {
+            error: String::from_utf8_lossy(&body).into_owned(),
+            witnessed_size: None,
+        });
+        Err(match (status, refusal.witnessed_size) {
+            (StatusCode::CONFLICT, Some(witnessed_size)) => LogError::Conflict { witnessed_size },
+            (StatusCode::NOT_FOUND, _) => LogError::UnknownLog(refusal.error),
+            (StatusCode::UNPROCESSABLE_ENTITY, _) => LogError::TreeHead(refusal.error),
+            _ => LogError::Witness(format!("{}: {} {}", self.authority, status, refusal.error)),
+        })
+    }
+}
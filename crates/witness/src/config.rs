@@ -0,0 +1,128 @@
+//! Witness configuration
+//!
+//! ```json
+//! {
+//!   "key_file": "/etc/frost/witness.key",
+//!   "logs": ["<hex log group public key>"],
+//!   "listen": "0.0.0.0:7380",
+//!   "state_dir": "/var/lib/frost-witness"
+//! }
+//! ```
+//!
+//! The key file holds the 32-byte witness scalar in hex. The state
+//! directory keeps the last tree cosigned per log; losing it lets the
+//! witness be walked back to an older view, so it belongs on durable
+//! storage.
+
+use crate::{WitnessError, WitnessResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::merkle::WitnessKey;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Default configuration file
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/frost/witness.conf";
+
+/// Contents of `witness.conf`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WitnessConfig {
+    /// File holding the witness key (hex)
+    pub key_file: PathBuf,
+    /// Group public keys of the logs to witness (hex)
+    pub logs: Vec<String>,
+    /// Listen address
+    pub listen: String,
+    /// Directory for witness state
+    pub state_dir: PathBuf,
+}
+
+impl WitnessConfig {
+    /// Read and validate the configuration at `path`
+    pub fn load(path: &Path) -> WitnessResult<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| WitnessError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// Parse and validate configuration text
+    pub fn parse(text: &str) -> WitnessResult<Self> {
+        let config: WitnessConfig = serde_json::from_str(text)
+            .map_err(|e| WitnessError::Config(e.to_string()))?;
+        config.log_keys()?;
+        config.listen_addr()?;
+        Ok(config)
+    }
+
+    /// Public keys of the witnessed logs
+    pub fn log_keys(&self) -> WitnessResult<Vec<CompressedRistretto>> {
+        if self.logs.is_empty() {
+            return Err(WitnessError::Config("No logs to witness".to_string()));
+        }
+        self.logs.iter().map(|log| parse_point(log)).collect()
+    }
+
+    /// Address to listen on
+    pub fn listen_addr(&self) -> WitnessResult<SocketAddr> {
+        self.listen.parse()
+            .map_err(|e| WitnessError::Config(format!("listen {:?}: {}", self.listen, e)))
+    }
+
+    /// Read the witness key from `key_file`
+    pub fn key(&self) -> WitnessResult<WitnessKey> {
+        let text = std::fs::read_to_string(&self.key_file)
+            .map_err(|e| WitnessError::Config(format!("{}: {}", self.key_file.display(), e)))?;
+        WitnessKey::from_bytes(&parse_bytes32(text.trim())?)
+            .map_err(|e| WitnessError::Config(format!("{}: {}", self.key_file.display(), e)))
+    }
+}
+
+/// Decode hex, with or without a `0x` prefix
+pub fn parse_hex(text: &str) -> WitnessResult<Vec<u8>> {
+    hex::decode(text.strip_prefix("0x").unwrap_or(text))
+        .map_err(|e| WitnessError::Config(format!("Invalid hex {:?}: {}", text, e)))
+}
+
+fn parse_bytes32(text: &str) -> WitnessResult<[u8; 32]> {
+    parse_hex(text)?.try_into()
+        .map_err(|_| WitnessError::Config(format!("Expected 32 bytes: {:?}", text)))
+}
+
+/// Decode a hex public key, checking it is a valid point
+pub fn parse_point(text: &str) -> WitnessResult<CompressedRistretto> {
+    let point = CompressedRistretto(parse_bytes32(text)?);
+    point.decompress()
+        .ok_or_else(|| WitnessError::Config(format!("Not a public key: {:?}", text)))?;
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_parse_config_and_key() {
+        let log_key = WitnessKey::generate(&mut OsRng).public_key();
+        let dir = std::env::temp_dir().join(format!("frost-witness-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("witness.key");
+        std::fs::write(&key_file, format!("{}\n", hex::encode([3u8; 32]))).unwrap();
+
+        let text = serde_json::json!({
+            "key_file": key_file,
+            "logs": [hex::encode(log_key.as_bytes())],
+            "listen": "127.0.0.1:7380",
+            "state_dir": dir.join("state"),
+        }).to_string();
+        let config = WitnessConfig::parse(&text).unwrap();
+        assert_eq!(config.log_keys().unwrap(), vec![log_key]);
+        assert_eq!(config.key().unwrap().public_key(), WitnessKey::from_bytes(&[3u8; 32]).unwrap().public_key());
+
+        assert!(WitnessConfig::parse(&text.replace("127.0.0.1:7380", "nowhere")).is_err());
+        assert!(WitnessConfig::parse(&text.replace(&hex::encode(log_key.as_bytes()), &"ff".repeat(32))).is_err());
+        std::fs::write(&key_file, "00".repeat(32)).unwrap();
+        assert!(config.key().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! HTTP/1.1 in front of `Witness`
+//!
+//! One endpoint, after C2SP tlog-witness: `POST /add-checkpoint` with an
+//! `AddCheckpoint` as JSON, answered with the `Cosignature`. Refusals carry
+//! an `ErrorBody` and the status from `status`; a 409 names the size the
+//! witness is at so the log can resend from there.
+
+use crate::{WitnessError, WitnessResult};
+use bytes::Bytes;
+use frost_transparency_log::{AddCheckpoint, LogError, TileStore, Witness};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+/// Path tree heads are submitted to
+pub const ADD_CHECKPOINT_PATH: &str = "/add-checkpoint";
+
+/// Largest request body accepted
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Body of a refused request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// What went wrong
+    pub error: String,
+    /// Tree size the witness is at, on 409
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witnessed_size: Option<u64>,
+}
+
+/// HTTP status for a refused checkpoint
+pub fn status(error: &LogError) -> StatusCode {
+    match error {
+        LogError::UnknownLog(_) => StatusCode::NOT_FOUND,
+        LogError::Conflict { .. } => StatusCode::CONFLICT,
+        LogError::TreeHead(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Full<Bytes>> {
+    let body = serde_json::to_vec(body).expect("response serializes");
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(hyper::header::CONTENT_TYPE, hyper::header::HeaderValue::from_static("application/json"));
+    response
+}
+
+fn refuse(status: StatusCode, error: String, witnessed_size: Option<u64>) -> Response<Full<Bytes>> {
+    json_response(status, &ErrorBody { error, witnessed_size })
+}
+
+/// Route one request to the witness
+pub async fn handle<S: TileStore>(witness: &Mutex<Witness<S>>, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let (parts, body) = request.into_parts();
+    if (&parts.method, parts.uri.path()) != (&Method::POST, ADD_CHECKPOINT_PATH) {
+        return refuse(StatusCode::NOT_FOUND, parts.uri.path().to_string(), None);
+    }
+
+    let bytes = match Limited::new(body, MAX_BODY_BYTES).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => return refuse(StatusCode::BAD_REQUEST, e.to_string(), None),
+    };
+    let checkpoint: AddCheckpoint = match serde_json::from_slice(&bytes) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => return refuse(StatusCode::BAD_REQUEST, e.to_string(), None),
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    match witness.lock().await.add_checkpoint(&checkpoint, now) {
+        Ok(cosignature) => {
+            log::info!("Cosigned tree head at size {}", checkpoint.tree_head.tree_size);
+            json_response(StatusCode::OK, &cosignature)
+        }
+        Err(e) => {
+            let status = status(&e);
+            if status.is_server_error() {
+                log::error!("Checkpoint failed: {}", e);
+            } else {
+                log::info!("Checkpoint at size {} refused: {}", checkpoint.tree_head.tree_size, e);
+            }
+            let witnessed_size = match e {
+                LogError::Conflict { witnessed_size } => Some(witnessed_size),
+                _ => None,
+            };
+            refuse(status, e.to_string(), witnessed_size)
+        }
+    }
+}
+
+/// Accept connections until `shutdown` completes
+pub async fn serve<S: TileStore + 'static>(
+    listener: TcpListener,
+    witness: Arc<Mutex<Witness<S>>>,
+    shutdown: impl Future<Output = ()>,
+) -> WitnessResult<()> {
+    tokio::pin!(shutdown);
+
+    loop {
+        let (tcp, peer) = tokio::select! {
+            accepted = listener.accept() => accepted.map_err(|e| WitnessError::Config(e.to_string()))?,
+            _ = &mut shutdown => return Ok(()),
+        };
+        let witness = witness.clone();
+        tokio::spawn(async move {
+            let handler = service_fn(move |request| {
+                let witness = witness.clone();
+                async move { Ok::<_, std::convert::Infallible>(handle(&witness, request).await) }
+            });
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(tcp), handler)
+                .await
+            {
+                log::debug!("Connection from {} closed: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::client::HttpWitnessClient;
+    use frost_coordinator::{CeremonyParticipant, ChannelLink, Coordinator, CoordinatorConfig};
+    use frost_core::dkg::DkgCoordinator;
+    use frost_core::merkle::WitnessKey;
+    use frost_core::GroupPublicKey;
+    use frost_transparency_log::{MemoryTileStore, MerkleLog, WitnessClient};
+    use rand::rngs::OsRng;
+
+    /// Coordinator over a fresh 2-of-3 log key, and that key
+    pub(crate) fn log_signer() -> (Coordinator<ChannelLink>, GroupPublicKey) {
+        let outputs = DkgCoordinator::new(2, 3).unwrap().run_dkg(&mut OsRng).unwrap();
+        let holders = outputs.iter()
+            .map(|o| CeremonyParticipant::with_key(o.secret_share.clone(), o.group_public_key.clone()))
+            .collect();
+        let (link, _handles) = ChannelLink::spawn(holders);
+        (Coordinator::new(link, CoordinatorConfig::default()), outputs[0].group_public_key.clone())
+    }
+
+    #[tokio::test]
+    async fn test_log_collects_cosignatures_over_http() {
+        let (mut coordinator, log_key) = log_signer();
+        let log_public_key = log_key.public_key;
+
+        let mut clients: Vec<Box<dyn WitnessClient>> = Vec::new();
+        for _ in 0..2 {
+            let witness = Witness::new(WitnessKey::generate(&mut OsRng), vec![log_public_key], MemoryTileStore::new());
+            let public_key = witness.public_key();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(serve(listener, Arc::new(Mutex::new(witness)), std::future::pending()));
+            clients.push(Box::new(HttpWitnessClient::new(&url, public_key).unwrap()));
+        }
+
+        let mut log = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        log.append(b"first").unwrap();
+        log.sign_tree_head(&mut coordinator, &log_key, 1_700_000_000).await.unwrap();
+        log.cosign_tree_head(&log_public_key, &mut clients, 2).await.unwrap();
+
+        // A second log instance that never talked to the witnesses learns their size from the 409
+        let mut replica = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        for entry in [&b"first"[..], b"second"] {
+            replica.append(entry).unwrap();
+        }
+        replica.sign_tree_head(&mut coordinator, &log_key, 1_700_000_060).await.unwrap();
+        let tree_head = replica.cosign_tree_head(&log_public_key, &mut clients, 2).await.unwrap();
+        assert_eq!(tree_head.cosignatures.len(), 2);
+        assert!(tree_head.cosignatures.iter().all(|c| c.verify(&log_public_key, &tree_head)));
+
+        // A fork at the same size is refused by both
+        let mut fork = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        for entry in [&b"first"[..], b"forked"] {
+            fork.append(entry).unwrap();
+        }
+        fork.sign_tree_head(&mut coordinator, &log_key, 1_700_000_120).await.unwrap();
+        assert!(fork.cosign_tree_head(&log_public_key, &mut clients, 1).await.is_err());
+        assert!(fork.latest_tree_head().unwrap().unwrap().cosignatures.is_empty());
+    }
+}
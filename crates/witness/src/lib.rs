@@ -0,0 +1,42 @@
+//! Transparency log witness for FROST RoT
+//!
+//! An independent operator runs a witness for each log it follows. The log
+//! submits every tree head with a consistency proof; the witness cosigns
+//! only tree heads that extend the last one it cosigned, so a log cannot
+//! get the same witness to vouch for two views:
+//!
+//! ```text
+//! MerkleLog::cosign_tree_head ──► HttpWitnessClient ──HTTP/1.1 JSON──► http ──► Witness ──► FileTileStore
+//! ```
+//!
+//! Requests and cosignatures are signed, so the transport needs no TLS.
+//! `monitor` checks collected checkpoints against a local mirror of the
+//! log's tiles and reports split views.
+
+#![warn(missing_docs)]
+
+pub mod config;
+pub mod http;
+pub mod client;
+pub mod monitor;
+
+pub use config::WitnessConfig;
+pub use client::HttpWitnessClient;
+
+use frost_transparency_log::LogError;
+use thiserror::Error;
+
+/// Witness daemon and monitor errors
+#[derive(Error, Debug)]
+pub enum WitnessError {
+    /// Configuration file missing or invalid
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// Log, tile store or witness state failure
+    #[error(transparent)]
+    Log(#[from] LogError),
+}
+
+/// Result type for witness operations
+pub type WitnessResult<T> = Result<T, WitnessError>;
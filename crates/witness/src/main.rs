@@ -0,0 +1,90 @@
+//! frost-witness: cosign tree heads, or check checkpoints for split views
+//!
+//! ```text
+//! frost-witness serve [--config /etc/frost/witness.conf]
+//! frost-witness monitor --log-key HEX --mirror DIR CHECKPOINT...
+//! ```
+//!
+//! `monitor` prints each conflict as a JSON line and exits with status 2
+//! when it found any.
+
+use frost_transparency_log::{FileTileStore, MerkleLog, Witness};
+use frost_witness::config::{parse_point, DEFAULT_CONFIG_PATH};
+use frost_witness::{http, monitor, WitnessConfig, WitnessError, WitnessResult};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+const USAGE: &str = "usage: frost-witness serve [--config PATH]\n       \
+                     frost-witness monitor --log-key HEX --mirror DIR CHECKPOINT...";
+
+async fn serve(config_path: PathBuf) -> WitnessResult<()> {
+    let config = WitnessConfig::load(&config_path)?;
+    let key = config.key()?;
+    let public_key = key.public_key();
+    let logs = config.log_keys()?;
+    let witness = Witness::new(key, logs.clone(), FileTileStore::new(config.state_dir.clone()));
+
+    let addr = config.listen_addr()?;
+    let listener = TcpListener::bind(addr).await
+        .map_err(|e| WitnessError::Config(format!("{}: {}", addr, e)))?;
+    log::info!("Witness {} for {} logs on {}", hex::encode(public_key.as_bytes()), logs.len(), addr);
+
+    http::serve(listener, Arc::new(Mutex::new(witness)), async {
+        let _ = tokio::signal::ctrl_c().await;
+        log::info!("Shutting down");
+    }).await
+}
+
+fn run_monitor(args: &[String]) -> WitnessResult<bool> {
+    let (mut log_key, mut mirror, mut checkpoints) = (None, None, Vec::new());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.clone().next()) {
+            ("--log-key", Some(value)) => log_key = Some(parse_point(value)?),
+            ("--mirror", Some(value)) => mirror = Some(PathBuf::from(value)),
+            (flag, _) if flag.starts_with("--") => return Err(WitnessError::Config(USAGE.to_string())),
+            (path, _) => {
+                checkpoints.push(PathBuf::from(path));
+                continue;
+            }
+        }
+        args.next();
+    }
+    let (Some(log_key), Some(mirror)) = (log_key, mirror) else {
+        return Err(WitnessError::Config(USAGE.to_string()));
+    };
+
+    let mirror = MerkleLog::open(FileTileStore::new(mirror))?;
+    let conflicts = monitor::scan(log_key, &checkpoints, &mirror);
+    for conflict in &conflicts {
+        println!("{}", serde_json::to_string(conflict).expect("conflict serializes"));
+    }
+    log::info!("Checked {} checkpoints, {} conflicts", checkpoints.len(), conflicts.len());
+    Ok(conflicts.is_empty())
+}
+
+async fn run() -> WitnessResult<bool> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["serve"] => serve(PathBuf::from(DEFAULT_CONFIG_PATH)).await.map(|_| true),
+        ["serve", "--config", path] => serve(PathBuf::from(path)).await.map(|_| true),
+        ["monitor", ..] => run_monitor(&args[1..]),
+        _ => Err(WitnessError::Config(USAGE.to_string())),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    match run().await {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(2),
+        Err(e) => {
+            log::error!("{}", e);
+            eprintln!("frost-witness: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Local split-view monitor
+//!
+//! Checks checkpoints gathered from devices, witnesses or other monitors
+//! against a local mirror of the log's tiles. Checkpoints are
+//! `SignedTreeHead` JSON, as the log stores them.
+
+use crate::{WitnessError, WitnessResult};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use frost_core::merkle::SignedTreeHead;
+use frost_transparency_log::{ConsistencyProver, SplitView, SplitViewMonitor};
+use std::path::{Path, PathBuf};
+
+/// Read one checkpoint file
+pub fn read_checkpoint(path: &Path) -> WitnessResult<SignedTreeHead> {
+    let data = std::fs::read(path)
+        .map_err(|e| WitnessError::Config(format!("{}: {}", path.display(), e)))?;
+    serde_json::from_slice(&data)
+        .map_err(|e| WitnessError::Config(format!("{}: {}", path.display(), e)))
+}
+
+/// Check the checkpoints at `paths` against each other and `mirror`
+///
+/// Checkpoints that are unreadable, not signed by the log or beyond the
+/// mirror are logged and skipped. Returns the conflicts found.
+pub fn scan(
+    log_public_key: CompressedRistretto,
+    paths: &[PathBuf],
+    mirror: &dyn ConsistencyProver,
+) -> Vec<SplitView> {
+    let mut monitor = SplitViewMonitor::new(log_public_key);
+    for path in paths {
+        let result = read_checkpoint(path)
+            .and_then(|tree_head| monitor.observe(tree_head, mirror).map_err(WitnessError::from));
+        match result {
+            Ok(Some(split)) => log::warn!(
+                "{}: size {} conflicts with size {}",
+                path.display(), split.second.tree_size, split.first.tree_size
+            ),
+            Ok(None) => {}
+            Err(e) => log::warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+    monitor.conflicts().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::log_signer;
+    use frost_transparency_log::{MemoryTileStore, MerkleLog};
+
+    #[tokio::test]
+    async fn test_scan_reports_forked_checkpoint() {
+        let (mut coordinator, log_key) = log_signer();
+        let mut mirror = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let mut fork = MerkleLog::open(MemoryTileStore::new()).unwrap();
+        let dir = std::env::temp_dir().join(format!("frost-witness-monitor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut paths = Vec::new();
+        let mut save = |name: &str, tree_head: &SignedTreeHead| {
+            let path = dir.join(name);
+            std::fs::write(&path, serde_json::to_vec(tree_head).unwrap()).unwrap();
+            paths.push(path);
+        };
+        for (i, entry) in [b"a", b"b", b"c"].into_iter().enumerate() {
+            mirror.append(entry).unwrap();
+            let tree_head = mirror.sign_tree_head(&mut coordinator, &log_key, 1_700_000_000 + i as u64).await.unwrap();
+            save(&format!("honest-{}.json", i), &tree_head);
+        }
+        fork.append(b"a").unwrap();
+        fork.append(b"x").unwrap();
+        save("forked.json", &fork.sign_tree_head(&mut coordinator, &log_key, 1_700_000_010).await.unwrap());
+        std::fs::write(dir.join("garbage.json"), b"{}").unwrap();
+        paths.push(dir.join("garbage.json"));
+
+        let conflicts = scan(log_key.public_key, &paths, &mirror);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].first.tree_size, conflicts[0].second.tree_size), (2, 2));
+        assert!(scan(log_key.public_key, &paths[..3], &mirror).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}